@@ -4,8 +4,8 @@ use core::mem;
 use crate::step::{In, Out, Over};
 use crate::then::{Then, Cont, Done, Fail};
 use crate::input::{Input, AsInput};
-use crate::output::{Output, IntoOutput};
-use crate::decoder::Decoder;
+use crate::output::{Output, IntoOutput, SliceOutput};
+use crate::decoder::{Decoder, DecodedLen, DecoderContext, OutputLen, PartialDecoder};
 use crate::encoder::Encoder;
 ///
 /// Base64 is used for encoding and decoding small image file
@@ -50,6 +50,9 @@ impl Base64Alphabet {
 pub enum Base64Error {
     Unexpected,
     Unpadded,
+    /// In strict mode, the final digit of a padded or truncated quantum has
+    /// non-zero bits beyond the end of the decoded bytes.
+    NonCanonical,
 }
 
 pub struct Base64Decoder<I: Input<Token=char>, O: Output<Token=u8>> {
@@ -58,6 +61,9 @@ pub struct Base64Decoder<I: Input<Token=char>, O: Output<Token=u8>> {
     q: u8,
     r: u8,
     padded: bool,
+    constant_time: bool,
+    strict: bool,
+    invalid: u8,
     state: u32,
     input: PhantomData<I>,
 }
@@ -81,6 +87,9 @@ impl<I, O> Base64Decoder<I, O> where I: Input<Token=char>, O: Output<Token=u8> {
             q: 0,
             r: 0,
             padded: true,
+            constant_time: false,
+            strict: false,
+            invalid: 0,
             state: 1,
             input: PhantomData,
         }
@@ -91,6 +100,25 @@ impl<I, O> Base64Decoder<I, O> where I: Input<Token=char>, O: Output<Token=u8> {
         self
     }
 
+    /// Decodes base64 digits with branchless arithmetic, instead of range
+    /// checks, so that decode time doesn't depend on the values of the input
+    /// characters. Every character other than `=` is consumed as a digit;
+    /// invalid digits fail the decode only once the input is exhausted.
+    pub fn constant_time(mut self, constant_time: bool) -> Self {
+        self.constant_time = constant_time;
+        self
+    }
+
+    /// Rejects encodings that aren't the unique canonical encoding of their
+    /// decoded bytes: a final quantum whose unused trailing bits aren't zero
+    /// fails with `NonCanonical`, and padding followed by further digits or
+    /// padding fails with `Unexpected`. Without strict mode, distinct inputs,
+    /// such as `AA==` and `AB==`, can decode to the same bytes.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
     pub fn consume(mut self, input: &mut I) -> Result<O::Out, Base64Error> where O::Err: fmt::Debug {
         loop {
             match self.decode(input) {
@@ -107,82 +135,90 @@ impl<I, O> Base64Decoder<I, O> where I: Input<Token=char>, O: Output<Token=u8> {
             }
         }
     }
-}
-
-impl<I, O> Decoder for Base64Decoder<I, O>
-    where I: Input<Token=char>,
-          O: Output<Token=u8>,
-          O::Err: fmt::Debug {
-
-    type Input = I;
-    type Output = O::Out;
-    type Error = Base64Error;
 
-    fn decode(mut self, input: &mut I) -> Then<Self, O::Out, Base64Error> {
+    /// Advances the decode state machine, leaving the decoder in place.
+    fn run(&mut self, input: &mut I) -> Then<(), (), Base64Error> {
         loop {
+            if self.output.is_blocked() {
+                return Cont(());
+            }
             match self.state {
                 1 => {
                     match input.head() {
-                        In(c) if is_base64_char(c) => {
+                        In(c) if self.is_digit(c) => {
                             input.step();
-                            self.p = decode_base64_char(c);
+                            self.p = self.decode_digit(c);
                             self.state = 2;
                         },
-                        In(_) | Over => return Done(self.output.take_out().unwrap()),
-                        Out => return Cont(self),
+                        In('=') if self.strict => return Fail(Base64Error::Unexpected),
+                        In(_) | Over => return Done(()),
+                        Out => return Cont(()),
                     };
                 },
                 2 => {
                     match input.head() {
-                        In(c) if is_base64_char(c) => {
+                        In(c) if self.is_digit(c) => {
                             input.step();
-                            self.q = decode_base64_char(c);
+                            self.q = self.decode_digit(c);
                             self.state = 3;
                         },
                         In(_) | Over => return Fail(Base64Error::Unexpected),
-                        Out => return Cont(self),
+                        Out => return Cont(()),
                     };
                 },
                 3 => {
                     match input.head() {
-                        In(c) if is_base64_char(c) || c == '=' => {
+                        In(c) if self.is_digit(c) || c == '=' => {
                             input.step();
-                            self.r = decode_base64_char(c);
+                            self.r = self.decode_digit(c);
                             if c != '=' {
                                 self.state = 4;
+                            } else if self.strict && self.q & 0x0F != 0 {
+                                return Fail(Base64Error::NonCanonical);
                             } else {
                                 self.state = 5;
                             }
                         },
                         In(_) | Over if !self.padded => {
+                            if self.strict && self.q & 0x0F != 0 {
+                                return Fail(Base64Error::NonCanonical);
+                            }
                             decode_base64_quantum(self.p, self.q, 255, 255, &mut self.output);
-                            return Done(self.output.take_out().unwrap());
+                            self.state = 7;
                         },
                         In(_) | Over => return Fail(Base64Error::Unpadded),
-                        Out => return Cont(self),
+                        Out => return Cont(()),
                     };
                 },
                 4 => {
                     match input.head() {
-                        In(c) if is_base64_char(c) || c == '=' => {
+                        In(c) if self.is_digit(c) || c == '=' => {
                             input.step();
-                            let s = decode_base64_char(c);
+                            let s = self.decode_digit(c);
+                            if c == '=' && self.strict && self.r & 0x03 != 0 {
+                                return Fail(Base64Error::NonCanonical);
+                            }
                             decode_base64_quantum(self.p, self.q, self.r, s, &mut self.output);
                             self.r = 0;
                             self.q = 0;
                             self.p = 0;
                             if c != '=' {
                                 self.state = 1;
+                            } else if self.strict {
+                                self.state = 6;
                             } else {
-                                return Done(self.output.take_out().unwrap());
+                                self.state = 7;
                             }
                         },
                         In(_) | Over if !self.padded => {
+                            if self.strict && self.r & 0x03 != 0 {
+                                return Fail(Base64Error::NonCanonical);
+                            }
                             decode_base64_quantum(self.p, self.q, self.r, 255, &mut self.output);
-                            return Done(self.output.take_out().unwrap());
+                            self.state = 7;
                         }
                         In(_) | Over => return Fail(Base64Error::Unpadded),
-                        Out => return Cont(self),
+                        Out => return Cont(()),
                     };
                 },
                 5 => {
@@ -193,16 +229,128 @@ impl<I, O> Decoder for Base64Decoder<I, O>
                             self.r = 0;
                             self.q = 0;
                             self.p = 0;
-                            return Done(self.output.take_out().unwrap());
+                            if self.strict {
+                                self.state = 6;
+                            } else {
+                                self.state = 7;
+                            }
                         },
                         In(_) | Over => return Fail(Base64Error::Unpadded),
-                        Out => return Cont(self),
+                        Out => return Cont(()),
                     }
                 },
+                6 => {
+                    // Strict mode: padding must end the encoding.
+                    match input.head() {
+                        In(c) if self.is_digit(c) || c == '=' => return Fail(Base64Error::Unexpected),
+                        In(_) | Over => return Done(()),
+                        Out => return Cont(()),
+                    }
+                },
+                7 => {
+                    // Completes once the final quantum has drained.
+                    return Done(());
+                },
                 _ => unreachable!(),
             };
         }
     }
+
+    #[inline]
+    fn is_digit(&self, c: char) -> bool {
+        if !self.constant_time {
+            is_base64_char(c)
+        } else {
+            c != '='
+        }
+    }
+
+    #[inline]
+    fn decode_digit(&mut self, c: char) -> u8 {
+        if !self.constant_time || c == '=' {
+            decode_base64_char(c)
+        } else {
+            let x = decode_base64_char_ct(c);
+            // Accumulate invalid digits without branching on them.
+            self.invalid |= (x >> 8) as u8;
+            (x & 0x3F) as u8
+        }
+    }
+
+    #[inline]
+    fn done(self) -> Then<Self, O::Out, Base64Error> where O::Err: fmt::Debug {
+        if self.invalid == 0 {
+            Done(self.output.take_out().unwrap())
+        } else {
+            Fail(Base64Error::Unexpected)
+        }
+    }
+}
+
+impl<'c, I> Base64Decoder<I, SliceOutput<'c, u8>> where I: Input<Token=char> {
+    /// Returns a new decoder that decodes into the scratch of a shared
+    /// `DecoderContext`, resetting the context first. The decoded bytes
+    /// must fit in the context's capacity.
+    pub fn with_context(context: &'c mut DecoderContext) -> Self {
+        Self::new(SliceOutput::new(context.lend()))
+    }
+}
+
+impl<I, O> Decoder for Base64Decoder<I, O>
+    where I: Input<Token=char>,
+          O: Output<Token=u8>,
+          O::Err: fmt::Debug {
+
+    type Input = I;
+    type Output = O::Out;
+    type Error = Base64Error;
+
+    fn decode(mut self, input: &mut I) -> Then<Self, O::Out, Base64Error> {
+        match self.run(input) {
+            Cont(()) => Cont(self),
+            Done(()) => self.done(),
+            Fail(error) => Fail(error),
+        }
+    }
+
+    fn reset(&mut self) {
+        self.p = 0;
+        self.q = 0;
+        self.r = 0;
+        self.invalid = 0;
+        self.state = 1;
+    }
+}
+
+impl<I, O> DecodedLen for Base64Decoder<I, O>
+    where I: Input<Token=char>,
+          O: Output<Token=u8> + OutputLen {
+    #[inline]
+    fn decoded_len(&self) -> usize {
+        self.output.output_len()
+    }
+}
+
+impl<I, O> PartialDecoder for Base64Decoder<I, O>
+    where I: Input<Token=char>,
+          O: Output<Token=u8>,
+          O::Err: fmt::Debug {
+
+    /// Decodes like `decode`, but on failure surrenders the bytes of all
+    /// complete quanta decoded before the failure. In constant time mode,
+    /// the surrendered bytes may include quanta with invalid digits.
+    fn decode_partial(mut self, input: &mut I) -> Then<Self, O::Out, (O::Out, Base64Error)> {
+        match self.run(input) {
+            Cont(()) => Cont(self),
+            Done(()) if self.invalid == 0 => Done(self.output.take_out().unwrap()),
+            Done(()) => Fail((self.output.take_out().unwrap(), Base64Error::Unexpected)),
+            Fail(error) => Fail((self.output.take_out().unwrap(), error)),
+        }
+    }
+
+    fn take_partial(self) -> O::Out {
+        self.output.take_out().unwrap()
+    }
 }
 
 impl<I, O> Base64Encoder<I, O> where I: Input<Token=u8>, O: Output<Token=char> {
@@ -351,6 +499,26 @@ fn decode_base64_char(c: char) -> u8 {
     }
 }
 
+/// Decodes a base64 digit without branches or table lookups, returning `-1`
+/// if `c` isn't a valid digit of either alphabet.
+#[inline]
+fn decode_base64_char_ct(c: char) -> i32 {
+    let c = c as i32;
+    // All bits set if `c` lies outside the 8-bit range checked below.
+    let wide = (0xFF - c) >> 31;
+    let c = c & 0xFF;
+    // Each `(lo - 1 - c) & (c - (hi + 1))` is negative iff `lo <= c <= hi`.
+    let mut x = -1;
+    x += (((0x40 - c) & (c - 0x5B)) >> 8) & (c - 64); // 'A'..='Z'
+    x += (((0x60 - c) & (c - 0x7B)) >> 8) & (c - 70); // 'a'..='z'
+    x += (((0x2F - c) & (c - 0x3A)) >> 8) & (c + 5); // '0'..='9'
+    x += (((0x2A - c) & (c - 0x2C)) >> 8) & 63; // '+'
+    x += (((0x2C - c) & (c - 0x2E)) >> 8) & 63; // '-'
+    x += (((0x2E - c) & (c - 0x30)) >> 8) & 64; // '/'
+    x += (((0x5E - c) & (c - 0x60)) >> 8) & 64; // '_'
+    x | wide
+}
+
 fn decode_base64_quantum<O>(p: u8, q: u8, r: u8, s: u8, output: &mut O)
     where O: Output<Token=u8> {
     if r < 64 {
@@ -370,7 +538,14 @@ fn decode_base64_quantum<O>(p: u8, q: u8, r: u8, s: u8, output: &mut O)
 
 #[cfg(test)]
 mod tests {
+    use crate::input::{BytesInput, Utf8Input, OffsetInput};
     use crate::output::{SliceOutput, StrOutput};
+    use crate::decoder::DecodeError;
+    use tg_mem::block::Block;
+    use tg_mem::alloc::Pack;
+    use tg_mem::lease::{MutString, RefBuf};
+    #[cfg(feature = "trace")]
+    use tg_mem::alloc::HoldTrace;
     use super::*;
 
     fn assert_transcodes(encoded: &str, decoded: &[u8]) {
@@ -382,6 +557,257 @@ mod tests {
         assert_eq!(encoder.produce(StrOutput::new(&mut buffer)).unwrap(), encoded);
     }
 
+    #[test]
+    fn test_base64_decode_all_partial() {
+        let mut buffer = [0u8; 16];
+        let mut input = "QUJDRGFi".as_input();
+        let decoder = Base64Decoder::new(SliceOutput::new(&mut buffer));
+        assert_eq!(decoder.decode_all(&mut input).unwrap(), b"ABCDab");
+
+        let mut buffer = [0u8; 16];
+        let mut input = "QUJDRGFiY!QUJD".as_input();
+        let decoder = Base64Decoder::new(SliceOutput::new(&mut buffer));
+        match decoder.decode_all(&mut input) {
+            Err((output, error)) => {
+                assert_eq!(output, b"ABCDab");
+                assert_eq!(error, DecodeError::Decode(Base64Error::Unexpected));
+            },
+            Ok(_) => panic!(),
+        }
+        assert_eq!(input.offset(), 9);
+    }
+
+    #[test]
+    fn test_base64_decode_chunked_bytes() {
+        let stored = *b"ABCDabcd12/+";
+        let mut input = Utf8Input::new(BytesInput::chunked(&stored[..], 5));
+        let mut buffer = [0u8; 16];
+        let mut decoder = Base64Decoder::new(SliceOutput::new(&mut buffer));
+        let decoded = loop {
+            match decoder.decode(&mut input) {
+                Cont(next) => {
+                    decoder = next;
+                    if !input.input_mut().more() {
+                        input.over();
+                    }
+                },
+                Done(output) => break output,
+                Fail(error) => panic!("{:?}", error),
+            }
+        };
+        assert_eq!(decoded, &[0, 16, 131, 105, 183, 29, 215, 111, 254]);
+    }
+
+    #[test]
+    fn test_base64_decode_utf8_invalid() {
+        // A sequence cut short by a digit ends the decode at a replacement
+        // char, leaving the digit for the next decode.
+        let mut input = Utf8Input::new(BytesInput::new(b"QUJD\xE2\x82QUJD"));
+        let mut buffer = [0u8; 16];
+        let decoder = Base64Decoder::new(SliceOutput::new(&mut buffer));
+        assert_eq!(decoder.consume(&mut input).unwrap(), b"ABC");
+        assert_eq!(input.head(), In('\u{FFFD}'));
+        assert!(input.is_invalid());
+        input.step();
+        let mut buffer = [0u8; 16];
+        let decoder = Base64Decoder::new(SliceOutput::new(&mut buffer));
+        assert_eq!(decoder.consume(&mut input).unwrap(), b"ABC");
+
+        // An encoded surrogate is ill-formed; each of its bytes decodes as a
+        // replacement char, and the digit after it is left unconsumed.
+        let mut input = Utf8Input::new(BytesInput::new(b"QU\xED\xA0\x80JD"));
+        let mut buffer = [0u8; 16];
+        let decoder = Base64Decoder::new(SliceOutput::new(&mut buffer));
+        assert_eq!(decoder.consume(&mut input), Err(Base64Error::Unpadded));
+        for _ in 0..3 {
+            assert!(input.is_invalid());
+            input.step();
+        }
+        assert_eq!(input.head(), In('J'));
+    }
+
+    /// Byte output that appends decoded ASCII text to a `MutString`.
+    struct MutStringOutput<'a> {
+        string: MutString<'a>,
+    }
+
+    impl<'a> Output for MutStringOutput<'a> {
+        type Token = u8;
+        type Out = MutString<'a>;
+        type Err = ();
+
+        fn is_full(&self) -> bool {
+            false
+        }
+
+        fn push(&mut self, b: u8) {
+            debug_assert!(b < 0x80);
+            self.string.push(b as char);
+        }
+
+        fn take_out(self) -> Result<MutString<'a>, ()> {
+            Ok(self.string)
+        }
+    }
+
+    #[test]
+    fn test_base64_decode_ref_buf_into_mut_string() {
+        static mut TEST_AREA: [u8; 4096] = [0; 4096];
+        let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+
+        let stored = RefBuf::<u8>::hold_copy(pack, &b"aGVsbG8sIHdvcmxkIQ=="[..]);
+        for &chunk in [1, 3, stored.len()].iter() {
+            // The input borrows the stored bytes for the duration of the decode.
+            let mut input = Utf8Input::new(BytesInput::chunked(&stored[..], chunk));
+            let output = MutStringOutput { string: MutString::hold_cap(pack, 16) };
+            let mut decoder = Base64Decoder::new(output);
+            let decoded = loop {
+                match decoder.decode(&mut input) {
+                    Cont(next) => {
+                        decoder = next;
+                        if !input.input_mut().more() {
+                            input.over();
+                        }
+                    },
+                    Done(output) => break output,
+                    Fail(error) => panic!("{:?}", error),
+                }
+            };
+            assert_eq!(&decoded[..], "hello, world!");
+        }
+    }
+
+    #[test]
+    fn test_base64_decoder_reset() {
+        let mut buffer = [0u8; 16];
+        let decoder = Base64Decoder::new(SliceOutput::new(&mut buffer));
+        let mut decoder = match decoder.decode(&mut "QU".as_input()) {
+            Cont(next) => next,
+            _ => panic!(),
+        };
+        decoder.reset();
+        assert_eq!(decoder.consume(&mut "QUJD".as_input()).unwrap(), &b"ABC"[..]);
+    }
+
+    #[test]
+    fn test_base64_decode_shared_context() {
+        static mut TEST_AREA: [u8; 4096] = [0; 4096];
+        let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+
+        let mut context = DecoderContext::hold_cap(pack, 16);
+        let live = pack.live();
+        for encoded in ["QUJDRGFi", "QUI=", ""].iter() {
+            let decoder = Base64Decoder::with_context(&mut context);
+            let decoded = decoder.consume(&mut encoded.as_input()).unwrap().len();
+            assert_eq!(decoded, encoded.len() / 4 * 3 - encoded.matches('=').count());
+        }
+        // The previous message's bytes were cleared by the last lend.
+        assert!(context.lend().iter().all(|&b| b == 0));
+        assert_eq!(context.capacity(), 16);
+        assert_eq!(pack.live(), live);
+    }
+
+    #[cfg(feature = "trace")]
+    #[test]
+    fn test_base64_decode_shared_context_allocs() {
+        static mut TEST_AREA: [u8; 4096] = [0; 4096];
+        let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+
+        let start = HoldTrace::get();
+        let mut context = DecoderContext::hold_cap(pack, 64);
+        for i in 0..1000 {
+            let encoded = if i % 2 == 0 { "aGVsbG8sIHdvcmxkIQ==" } else { "QUJD" };
+            let decoder = Base64Decoder::with_context(&mut context);
+            let decoded = decoder.consume(&mut encoded.as_input()).unwrap();
+            assert_eq!(decoded, if i % 2 == 0 { &b"hello, world!"[..] } else { &b"ABC"[..] });
+        }
+        // Only the context's scratch was ever allocated.
+        let trace = HoldTrace::since(start);
+        assert_eq!(trace.allocs, 1);
+        assert_eq!(trace.deallocs, 0);
+        drop(context);
+        assert_eq!(HoldTrace::since(start).deallocs, 1);
+    }
+
+    #[test]
+    fn test_base64_decode_constant_time() {
+        let valid = ["", "AA==", "AAA=", "++8=", "ABCDabcd12/+", "-_-_", "QUJD", "QUI"];
+        for encoded in valid.iter() {
+            let mut buffer0 = [0u8; 64];
+            let decoder = Base64Decoder::new(SliceOutput::new(&mut buffer0)).padded(false);
+            let expected = decoder.consume(&mut encoded.as_input()).unwrap();
+            let mut buffer1 = [0u8; 64];
+            let decoder = Base64Decoder::new(SliceOutput::new(&mut buffer1)).padded(false).constant_time(true);
+            assert_eq!(decoder.consume(&mut encoded.as_input()).unwrap(), expected);
+        }
+        for c in 0u32..0x100 {
+            let c = core::char::from_u32(c).unwrap();
+            if is_base64_char(c) {
+                assert_eq!(decode_base64_char_ct(c), decode_base64_char(c) as i32);
+            } else {
+                assert_eq!(decode_base64_char_ct(c), -1);
+            }
+        }
+        assert_eq!(decode_base64_char_ct('\u{141}'), -1);
+    }
+
+    #[test]
+    fn test_base64_decode_constant_time_invalid() {
+        let mut input = "QU!DQUJD".as_input();
+        let mut buffer = [0u8; 16];
+        let decoder = Base64Decoder::new(SliceOutput::new(&mut buffer)).constant_time(true);
+        let decoder = match decoder.decode(&mut input) {
+            Cont(next) => next,
+            _ => panic!(),
+        };
+        // The invalid digit didn't cut the decode short.
+        assert_eq!(input.offset(), 8);
+        input.over();
+        match decoder.decode(&mut input) {
+            Fail(error) => assert_eq!(error, Base64Error::Unexpected),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_base64_decode_strict() {
+        let canonical = ["", "AA==", "AAA=", "+w==", "++8=", "QUJD", "QUJD!"];
+        for encoded in canonical.iter() {
+            let mut buffer0 = [0u8; 16];
+            let decoder = Base64Decoder::new(SliceOutput::new(&mut buffer0));
+            let expected = decoder.consume(&mut encoded.as_input()).unwrap();
+            let mut buffer1 = [0u8; 16];
+            let decoder = Base64Decoder::new(SliceOutput::new(&mut buffer1)).strict(true);
+            assert_eq!(decoder.consume(&mut encoded.as_input()).unwrap(), expected);
+        }
+
+        let non_canonical = [("AB==", &[0][..]), ("AP==", &[0][..]), ("AAB=", &[0, 0][..]), ("++9=", &[251, 239][..])];
+        for &(encoded, decoded) in non_canonical.iter() {
+            let mut buffer = [0u8; 16];
+            let decoder = Base64Decoder::new(SliceOutput::new(&mut buffer));
+            assert_eq!(decoder.consume(&mut encoded.as_input()).unwrap(), decoded);
+            let mut buffer = [0u8; 16];
+            let decoder = Base64Decoder::new(SliceOutput::new(&mut buffer)).strict(true);
+            assert_eq!(decoder.consume(&mut encoded.as_input()), Err(Base64Error::NonCanonical));
+        }
+
+        let mut buffer = [0u8; 16];
+        let decoder = Base64Decoder::new(SliceOutput::new(&mut buffer)).padded(false).strict(true);
+        assert_eq!(decoder.consume(&mut "AB".as_input()), Err(Base64Error::NonCanonical));
+        let mut buffer = [0u8; 16];
+        let decoder = Base64Decoder::new(SliceOutput::new(&mut buffer)).padded(false).strict(true);
+        assert_eq!(decoder.consume(&mut "AAA".as_input()).unwrap(), &[0, 0][..]);
+
+        for encoded in ["AA==AA==", "AAA==", "AA===", "QUJD="].iter() {
+            let mut buffer = [0u8; 16];
+            let decoder = Base64Decoder::new(SliceOutput::new(&mut buffer)).strict(true);
+            assert_eq!(decoder.consume(&mut encoded.as_input()), Err(Base64Error::Unexpected));
+        }
+        let mut buffer = [0u8; 16];
+        let decoder = Base64Decoder::new(SliceOutput::new(&mut buffer));
+        assert_eq!(decoder.consume(&mut "AA==AA==".as_input()).unwrap(), &[0][..]);
+    }
+
     #[test]
     fn test_base64_transcode() {
         assert_transcodes("AA==", &[0]);
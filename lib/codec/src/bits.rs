@@ -0,0 +1,318 @@
+use crate::step::{Step, In, Out, Over};
+use crate::input::Input;
+use crate::output::Output;
+
+/// Order in which the bits of a field are packed into successive bytes.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BitOrder {
+    /// Fields fill each byte starting from its most significant bit, with
+    /// the most significant bit of each field first.
+    MsbFirst,
+    /// Fields fill each byte starting from its least significant bit, with
+    /// the least significant bit of each field first, as in DEFLATE.
+    LsbFirst,
+}
+
+/// Incremental reader of arbitrary width bit fields from a byte input.
+/// Buffers whole input bytes in a bit accumulator, carrying any partially
+/// consumed byte, and any bits buffered ahead of a suspended read, across
+/// input chunks. Fields are at most 32 bits wide.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BitReader {
+    order: BitOrder,
+    bits: u64,
+    nbits: u32,
+}
+
+/// Incremental writer of arbitrary width bit fields to a byte output.
+/// Packs fields into a bit accumulator, and emits each completed byte as
+/// the output has room, carrying any partial byte, and any bytes pending a
+/// full output, across calls. Fields are at most 32 bits wide.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BitWriter {
+    order: BitOrder,
+    bits: u64,
+    nbits: u32,
+}
+
+impl BitReader {
+    pub fn new(order: BitOrder) -> Self {
+        BitReader {
+            order: order,
+            bits: 0,
+            nbits: 0,
+        }
+    }
+
+    pub fn order(&self) -> BitOrder {
+        self.order
+    }
+
+    /// Returns the number of bits buffered ahead of the next field.
+    #[inline]
+    pub fn buffered(&self) -> u32 {
+        self.nbits
+    }
+
+    /// Buffers input bytes until at least `n` bits are available; returns
+    /// `Out` if the input runs out first, retaining the bits buffered so
+    /// far, and `Over` if the input ends first.
+    pub fn fill<I: Input<Token=u8>>(&mut self, input: &mut I, n: u32) -> Step<()> {
+        debug_assert!(n <= 32);
+        while self.nbits < n {
+            match input.head() {
+                In(b) => {
+                    input.step();
+                    self.bits = match self.order {
+                        BitOrder::MsbFirst => self.bits << 8 | b as u64,
+                        BitOrder::LsbFirst => self.bits | (b as u64) << self.nbits,
+                    };
+                    self.nbits += 8;
+                },
+                Out => return Out,
+                Over => return Over,
+            }
+        }
+        In(())
+    }
+
+    /// Returns the next `n` buffered bits as a field, without consuming them.
+    /// At least `n` bits must be buffered.
+    #[inline]
+    pub fn peek(&self, n: u32) -> u32 {
+        debug_assert!(n <= self.nbits && n <= 32);
+        match self.order {
+            BitOrder::MsbFirst => ((self.bits >> (self.nbits - n)) & mask(n)) as u32,
+            BitOrder::LsbFirst => (self.bits & mask(n)) as u32,
+        }
+    }
+
+    /// Discards the next `n` buffered bits.
+    #[inline]
+    pub fn skip(&mut self, n: u32) {
+        debug_assert!(n <= self.nbits);
+        self.nbits -= n;
+        match self.order {
+            BitOrder::MsbFirst => self.bits &= mask(self.nbits),
+            BitOrder::LsbFirst => self.bits >>= n,
+        }
+    }
+
+    /// Reads the next `n` bit field; returns `Out` if the input runs out
+    /// before the field completes, in which case the read should be repeated
+    /// once more input is available.
+    pub fn read<I: Input<Token=u8>>(&mut self, input: &mut I, n: u32) -> Step<u32> {
+        match self.fill(input, n) {
+            In(()) => {
+                let field = self.peek(n);
+                self.skip(n);
+                In(field)
+            },
+            Out => Out,
+            Over => Over,
+        }
+    }
+
+    /// Discards any remaining bits of a partially consumed byte.
+    #[inline]
+    pub fn align(&mut self) {
+        let n = self.nbits & 7;
+        self.skip(n);
+    }
+
+    pub fn reset(&mut self) {
+        self.bits = 0;
+        self.nbits = 0;
+    }
+}
+
+impl BitWriter {
+    pub fn new(order: BitOrder) -> Self {
+        BitWriter {
+            order: order,
+            bits: 0,
+            nbits: 0,
+        }
+    }
+
+    pub fn order(&self) -> BitOrder {
+        self.order
+    }
+
+    /// Returns the number of bits written but not yet emitted.
+    #[inline]
+    pub fn pending(&self) -> u32 {
+        self.nbits
+    }
+
+    /// Writes the low `n` bits of `value` as the next field. Returns `false`,
+    /// without writing the field, if the output is too full to make room for
+    /// it, in which case the write should be repeated once the output has
+    /// room.
+    pub fn write<O: Output<Token=u8>>(&mut self, output: &mut O, value: u32, n: u32) -> bool {
+        debug_assert!(n <= 32);
+        self.flush(output);
+        if self.nbits + n > 64 {
+            return false;
+        }
+        let value = value as u64 & mask(n);
+        self.bits = match self.order {
+            BitOrder::MsbFirst => self.bits << n | value,
+            BitOrder::LsbFirst => self.bits | value << self.nbits,
+        };
+        self.nbits += n;
+        self.flush(output);
+        true
+    }
+
+    /// Emits buffered whole bytes while the output has room; returns `true`
+    /// if only a partial byte remains buffered.
+    pub fn flush<O: Output<Token=u8>>(&mut self, output: &mut O) -> bool {
+        while self.nbits >= 8 {
+            if output.is_full() {
+                return false;
+            }
+            self.nbits -= 8;
+            let b = match self.order {
+                BitOrder::MsbFirst => {
+                    let b = (self.bits >> self.nbits) as u8;
+                    self.bits &= mask(self.nbits);
+                    b
+                },
+                BitOrder::LsbFirst => {
+                    let b = self.bits as u8;
+                    self.bits >>= 8;
+                    b
+                },
+            };
+            output.push(b);
+        }
+        true
+    }
+
+    /// Pads any partial byte with zero bits, and emits all buffered bytes
+    /// while the output has room; returns `true` once every written bit has
+    /// been emitted.
+    pub fn finish<O: Output<Token=u8>>(&mut self, output: &mut O) -> bool {
+        let pad = 8u32.wrapping_sub(self.nbits) & 7;
+        if let BitOrder::MsbFirst = self.order {
+            self.bits <<= pad;
+        }
+        self.nbits += pad;
+        self.flush(output)
+    }
+
+    pub fn reset(&mut self) {
+        self.bits = 0;
+        self.nbits = 0;
+    }
+}
+
+#[inline]
+fn mask(n: u32) -> u64 {
+    if n < 64 { (1u64 << n) - 1 } else { !0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::input::BytesInput;
+    use crate::output::SliceOutput;
+    use super::*;
+
+    const FIELDS: [u32; 8] = [1, 2, 3, 4, 5, 6, 7, 8];
+
+    fn read_fields(bytes: &[u8], chunk: usize, order: BitOrder, fields: &mut [u32]) -> usize {
+        let mut input = BytesInput::chunked(bytes, chunk);
+        let mut reader = BitReader::new(order);
+        let mut count = 0;
+        while count < fields.len() {
+            match reader.read(&mut input, 5) {
+                In(field) => {
+                    fields[count] = field;
+                    count += 1;
+                },
+                Out => if !input.more() {
+                    input.over();
+                },
+                Over => break,
+            }
+        }
+        count
+    }
+
+    #[test]
+    fn test_bit_reader_straddles_chunks() {
+        let msb = [0x08, 0x86, 0x42, 0x98, 0xE8];
+        let lsb = [0x41, 0x0C, 0x52, 0xCC, 0x41];
+        // 5 bit fields straddle byte boundaries; chunks of 1 to 3 bytes
+        // split fields between reads.
+        for &chunk in [1, 2, 3, 5].iter() {
+            let mut fields = [0u32; 8];
+            assert_eq!(read_fields(&msb, chunk, BitOrder::MsbFirst, &mut fields), 8);
+            assert_eq!(fields, FIELDS);
+            let mut fields = [0u32; 8];
+            assert_eq!(read_fields(&lsb, chunk, BitOrder::LsbFirst, &mut fields), 8);
+            assert_eq!(fields, FIELDS);
+        }
+    }
+
+    #[test]
+    fn test_bit_reader_truncated() {
+        // Two bytes hold three whole 5 bit fields, and one leftover bit.
+        let mut fields = [0u32; 8];
+        assert_eq!(read_fields(&[0x08, 0x86], 1, BitOrder::MsbFirst, &mut fields), 3);
+        assert_eq!(&fields[..3], &FIELDS[..3]);
+
+        let mut input = BytesInput::new(&[0xA5u8, 0xFF][..]);
+        let mut reader = BitReader::new(BitOrder::MsbFirst);
+        assert_eq!(reader.read(&mut input, 3), In(0b101));
+        reader.align();
+        assert_eq!(reader.buffered(), 0);
+        assert_eq!(reader.read(&mut input, 8), In(0xFF));
+    }
+
+    #[test]
+    fn test_bit_writer_round_trip() {
+        for &order in [BitOrder::MsbFirst, BitOrder::LsbFirst].iter() {
+            let mut buffer = [0u8; 8];
+            let mut output = SliceOutput::new(&mut buffer);
+            let mut writer = BitWriter::new(order);
+            for &field in FIELDS.iter() {
+                assert!(writer.write(&mut output, field, 5));
+            }
+            // A 3 bit field leaves a partial byte, padded on finish.
+            assert!(writer.write(&mut output, 0b110, 3));
+            assert_eq!(writer.pending(), 3);
+            assert!(writer.finish(&mut output));
+            assert_eq!(writer.pending(), 0);
+            let bytes = output.take_out().unwrap();
+            assert_eq!(bytes.len(), 6);
+            match order {
+                BitOrder::MsbFirst => assert_eq!(&bytes[..], &[0x08, 0x86, 0x42, 0x98, 0xE8, 0xC0]),
+                BitOrder::LsbFirst => assert_eq!(&bytes[..], &[0x41, 0x0C, 0x52, 0xCC, 0x41, 0x06]),
+            }
+            for &chunk in [1, 2, 4].iter() {
+                let mut fields = [0u32; 8];
+                assert_eq!(read_fields(bytes, chunk, order, &mut fields), 8);
+                assert_eq!(fields, FIELDS);
+            }
+        }
+    }
+
+    #[test]
+    fn test_bit_writer_full_output() {
+        let mut buffer = [0u8; 1];
+        let mut output = SliceOutput::new(&mut buffer);
+        let mut writer = BitWriter::new(BitOrder::MsbFirst);
+        assert!(writer.write(&mut output, 0xABCD, 16));
+        // The first byte fits; the second remains pending.
+        assert_eq!(writer.pending(), 8);
+        assert!(!writer.finish(&mut output));
+        assert_eq!(output.take_out().unwrap(), &[0xAB]);
+
+        let mut buffer = [0u8; 1];
+        let mut output = SliceOutput::new(&mut buffer);
+        assert!(writer.finish(&mut output));
+        assert_eq!(output.take_out().unwrap(), &[0xCD]);
+    }
+}
@@ -0,0 +1,238 @@
+use core::fmt;
+use core::marker::PhantomData;
+use crate::step::{In, Out, Over};
+use crate::then::{Then, Cont, Done, Fail};
+use crate::input::Input;
+use crate::output::Output;
+use crate::decoder::Decoder;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ChunkedError {
+    /// Encountered a byte that doesn't belong at its position in the body,
+    /// such as a non-hex digit in a chunk size, or a missing `\r\n`.
+    Unexpected,
+    /// Input ended before the terminating zero-size chunk and trailers.
+    Truncated,
+    /// A chunk size overflows `usize`, or a chunk exceeds the capacity of
+    /// the output.
+    Oversized,
+}
+
+/// Incremental decoder for an HTTP/1.1 chunked transfer-encoded body.
+/// Decodes the payload of each chunk into a caller-provided output,
+/// suspending at any byte of the chunk size lines, payloads, and `\r\n`
+/// delimiters. Chunk extensions and trailer fields are skipped. Completes
+/// after the blank line that follows the zero-size last chunk.
+pub struct ChunkedDecoder<I: Input<Token=u8>, O: Output<Token=u8>> {
+    pub output: O,
+    /// Size of the current chunk while parsing its size line; remaining
+    /// payload bytes of the current chunk while decoding its payload.
+    size: usize,
+    /// Number of hex digits in the current chunk size.
+    digits: u32,
+    state: u32,
+    input: PhantomData<I>,
+}
+
+impl<I, O> ChunkedDecoder<I, O> where I: Input<Token=u8>, O: Output<Token=u8> {
+    pub fn new(output: O) -> Self {
+        Self {
+            output: output,
+            size: 0,
+            digits: 0,
+            state: 1,
+            input: PhantomData,
+        }
+    }
+
+    pub fn consume(mut self, input: &mut I) -> Result<O::Out, ChunkedError> where O::Err: fmt::Debug {
+        loop {
+            match self.decode(input) {
+                Done(output) => return Ok(output),
+                Fail(error) => return Err(error),
+                Cont(next) => {
+                    input.over();
+                    self = next;
+                },
+            }
+        }
+    }
+}
+
+impl<I, O> Decoder for ChunkedDecoder<I, O>
+    where I: Input<Token=u8>,
+          O: Output<Token=u8>,
+          O::Err: fmt::Debug {
+
+    type Input = I;
+    type Output = O::Out;
+    type Error = ChunkedError;
+
+    fn decode(mut self, input: &mut I) -> Then<Self, O::Out, ChunkedError> {
+        loop {
+            if self.output.is_blocked() {
+                return Cont(self);
+            }
+            let head = match input.head() {
+                In(b) => b,
+                Over => return Fail(ChunkedError::Truncated),
+                Out => return Cont(self),
+            };
+            match self.state {
+                1 => { // chunk size
+                    match decode_hex_digit(head) {
+                        Some(digit) => {
+                            self.size = match self.size.checked_mul(16) {
+                                Some(size) => size | digit as usize,
+                                None => return Fail(ChunkedError::Oversized),
+                            };
+                            self.digits += 1;
+                        },
+                        None if self.digits == 0 => return Fail(ChunkedError::Unexpected),
+                        None if head == b';' => self.state = 2,
+                        None if head == b'\r' => self.state = 3,
+                        None => return Fail(ChunkedError::Unexpected),
+                    }
+                },
+                2 => { // chunk extension
+                    if head == b'\r' {
+                        self.state = 3;
+                    }
+                },
+                3 => { // chunk size line feed
+                    if head != b'\n' {
+                        return Fail(ChunkedError::Unexpected);
+                    }
+                    self.digits = 0;
+                    self.state = if self.size != 0 { 4 } else { 7 };
+                },
+                4 => { // chunk payload
+                    if self.output.is_full() {
+                        return Fail(ChunkedError::Oversized);
+                    }
+                    self.output.push(head);
+                    self.size -= 1;
+                    if self.size == 0 {
+                        self.state = 5;
+                    }
+                },
+                5 => { // chunk payload carriage return
+                    if head != b'\r' {
+                        return Fail(ChunkedError::Unexpected);
+                    }
+                    self.state = 6;
+                },
+                6 => { // chunk payload line feed
+                    if head != b'\n' {
+                        return Fail(ChunkedError::Unexpected);
+                    }
+                    self.state = 1;
+                },
+                7 => { // trailer field or final carriage return
+                    self.state = if head == b'\r' { 10 } else { 8 };
+                },
+                8 => { // trailer field
+                    if head == b'\r' {
+                        self.state = 9;
+                    }
+                },
+                9 => { // trailer field line feed
+                    if head != b'\n' {
+                        return Fail(ChunkedError::Unexpected);
+                    }
+                    self.state = 7;
+                },
+                10 => { // final line feed
+                    if head != b'\n' {
+                        return Fail(ChunkedError::Unexpected);
+                    }
+                    input.step();
+                    return Done(self.output.take_out().unwrap());
+                },
+                _ => unreachable!(),
+            };
+            input.step();
+        }
+    }
+
+    fn reset(&mut self) {
+        self.size = 0;
+        self.digits = 0;
+        self.state = 1;
+    }
+}
+
+#[inline]
+fn decode_hex_digit(b: u8) -> Option<u8> {
+    match b {
+        b'0'..=b'9' => Some(b - b'0'),
+        b'A'..=b'F' => Some(10 + (b - b'A')),
+        b'a'..=b'f' => Some(10 + (b - b'a')),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::input::{AsInput, BytesInput};
+    use crate::output::SliceOutput;
+    use super::*;
+
+    fn decode_chunked<'b>(input: &mut BytesInput, buffer: &'b mut [u8]) -> Result<&'b mut [u8], ChunkedError> {
+        let mut decoder = ChunkedDecoder::new(SliceOutput::new(buffer));
+        loop {
+            match decoder.decode(input) {
+                Cont(next) => {
+                    decoder = next;
+                    if !input.more() {
+                        input.over();
+                    }
+                },
+                Done(output) => return Ok(output),
+                Fail(error) => return Err(error),
+            }
+        }
+    }
+
+    #[test]
+    fn test_chunked_decode() {
+        let body = b"4\r\nWiki\r\nA;name=value\r\npedia, the\r\n0\r\n\r\n";
+        // Reads of 5 and 7 bytes straddle chunk size lines and payloads.
+        for &chunk in [1, 5, 7, body.len()].iter() {
+            let mut input = BytesInput::chunked(&body[..], chunk);
+            let mut buffer = [0u8; 32];
+            assert_eq!(decode_chunked(&mut input, &mut buffer).unwrap(), b"Wikipedia, the");
+            assert!(input.is_out());
+        }
+    }
+
+    #[test]
+    fn test_chunked_decode_trailers() {
+        let body = b"3\r\nabc\r\n0\r\nExpires: never\r\nX-Count: 1\r\n\r\nnext";
+        let mut input = (&body[..]).as_input();
+        let mut buffer = [0u8; 32];
+        let decoder = ChunkedDecoder::new(SliceOutput::new(&mut buffer));
+        assert_eq!(decoder.consume(&mut input).unwrap(), b"abc");
+        assert_eq!(input.head(), In(b'n'));
+    }
+
+    #[test]
+    fn test_chunked_decode_errors() {
+        let bodies: [(&[u8], ChunkedError); 5] = [
+            (b"4\r\nWiki\r\n", ChunkedError::Truncated),
+            (b"4\r\nWikipedia\r\n0\r\n\r\n", ChunkedError::Unexpected),
+            (b"g\r\n\r\n", ChunkedError::Unexpected),
+            (b"\r\n\r\n", ChunkedError::Unexpected),
+            (b"100000000000000000\r\n", ChunkedError::Oversized),
+        ];
+        for &(body, error) in bodies.iter() {
+            let mut buffer = [0u8; 32];
+            let decoder = ChunkedDecoder::new(SliceOutput::new(&mut buffer));
+            assert_eq!(decoder.consume(&mut body.as_input()), Err(error));
+        }
+
+        let mut buffer = [0u8; 2];
+        let decoder = ChunkedDecoder::new(SliceOutput::new(&mut buffer));
+        assert_eq!(decoder.consume(&mut (&b"4\r\nWiki\r\n0\r\n\r\n"[..]).as_input()), Err(ChunkedError::Oversized));
+    }
+}
@@ -0,0 +1,153 @@
+use core::fmt;
+use core::marker::PhantomData;
+use crate::step::{In, Out, Over};
+use crate::then::{Then, Cont, Done, Fail};
+use crate::input::{Input, Utf8Input};
+use crate::output::Output;
+use crate::decoder::Decoder;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CStrError {
+    /// A string exceeds the maximum string length, or the capacity of the
+    /// output, before its null terminator.
+    TooLong,
+    /// Encountered a byte sequence that isn't well-formed UTF-8.
+    InvalidUtf8,
+    /// Input ended before the null terminator.
+    Truncated,
+}
+
+/// Incremental null-terminated string decoder. Decodes the UTF-8 bytes
+/// preceding a null terminator into a caller-provided output of chars,
+/// reading them through a `Utf8Input`, which reassembles multi-byte
+/// sequences across input chunks, and never consumes a null byte as part of
+/// one. Consumes the null terminator, excluding it from the output, and
+/// completes just after it.
+pub struct CStrDecoder<I: Input<Token=u8>, O: Output<Token=char>> {
+    pub output: O,
+    /// Number of string bytes consumed, excluding the null terminator.
+    len: usize,
+    max_len: usize,
+    input: PhantomData<I>,
+}
+
+impl<I, O> CStrDecoder<I, O> where I: Input<Token=u8>, O: Output<Token=char> {
+    pub fn new(output: O) -> Self {
+        Self {
+            output: output,
+            len: 0,
+            max_len: usize::MAX,
+            input: PhantomData,
+        }
+    }
+
+    /// Fails strings longer than `max_len` bytes, excluding the null
+    /// terminator, with `CStrError::TooLong`. Bounds the input consumed
+    /// while searching for a null terminator that may never arrive.
+    pub fn max_len(mut self, max_len: usize) -> Self {
+        self.max_len = max_len;
+        self
+    }
+
+    pub fn consume(mut self, input: &mut Utf8Input<I>) -> Result<O::Out, CStrError> where O::Err: fmt::Debug {
+        loop {
+            match self.decode(input) {
+                Done(output) => return Ok(output),
+                Fail(error) => return Err(error),
+                Cont(next) => {
+                    input.over();
+                    self = next;
+                },
+            }
+        }
+    }
+}
+
+impl<I, O> Decoder for CStrDecoder<I, O>
+    where I: Input<Token=u8>,
+          O: Output<Token=char>,
+          O::Err: fmt::Debug {
+
+    type Input = Utf8Input<I>;
+    type Output = O::Out;
+    type Error = CStrError;
+
+    fn decode(mut self, input: &mut Utf8Input<I>) -> Then<Self, O::Out, CStrError> {
+        loop {
+            match input.head() {
+                In('\0') => {
+                    input.step();
+                    return Done(self.output.take_out().unwrap());
+                },
+                In(_) if input.is_invalid() => return Fail(CStrError::InvalidUtf8),
+                In(c) => {
+                    if c.len_utf8() > self.max_len - self.len || self.output.is_full() {
+                        return Fail(CStrError::TooLong);
+                    }
+                    input.step();
+                    self.output.push(c);
+                    self.len += c.len_utf8();
+                },
+                Over => return Fail(CStrError::Truncated),
+                Out => return Cont(self),
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.len = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::input::{AsInput, BytesInput};
+    use crate::output::StrOutput;
+    use super::*;
+
+    #[test]
+    fn test_cstr_decode_chunked() {
+        let encoded = "caf\u{E9} \u{1F600}\0rest".as_bytes();
+        for &chunk in [1, 3, 5].iter() {
+            let mut input = Utf8Input::new(BytesInput::chunked(encoded, chunk));
+            let mut buffer = [0u8; 16];
+            let mut decoder = CStrDecoder::new(StrOutput::new(&mut buffer));
+            let decoded = loop {
+                match decoder.decode(&mut input) {
+                    Cont(next) => {
+                        decoder = next;
+                        assert!(input.input_mut().more());
+                    },
+                    Done(decoded) => break decoded,
+                    Fail(error) => panic!("{:?}", error),
+                }
+            };
+            assert_eq!(decoded, "caf\u{E9} \u{1F600}");
+        }
+    }
+
+    #[test]
+    fn test_cstr_decode_too_long() {
+        let mut buffer = [0u8; 16];
+        let decoder = CStrDecoder::new(StrOutput::new(&mut buffer)).max_len(4);
+        assert_eq!(decoder.consume(&mut Utf8Input::new((&b"abcd\0"[..]).as_input())).unwrap(), "abcd");
+
+        let mut buffer = [0u8; 16];
+        let decoder = CStrDecoder::new(StrOutput::new(&mut buffer)).max_len(4);
+        assert_eq!(decoder.consume(&mut Utf8Input::new((&b"abcdefgh"[..]).as_input())), Err(CStrError::TooLong));
+
+        let mut buffer = [0u8; 16];
+        let decoder = CStrDecoder::new(StrOutput::new(&mut buffer));
+        assert_eq!(decoder.consume(&mut Utf8Input::new((&b"abcd"[..]).as_input())), Err(CStrError::Truncated));
+    }
+
+    #[test]
+    fn test_cstr_decode_invalid_utf8() {
+        let invalid: [&[u8]; 4] = [b"\xC0\x80\0", b"\xED\xA0\x80\0", b"\xF4\x90\x80\x80\0", b"\xE2\x82\0"];
+        for &encoded in invalid.iter() {
+            let mut buffer = [0u8; 16];
+            let decoder = CStrDecoder::new(StrOutput::new(&mut buffer));
+            assert_eq!(decoder.consume(&mut Utf8Input::new(encoded.as_input())), Err(CStrError::InvalidUtf8));
+        }
+    }
+}
@@ -1,4 +1,8 @@
-use crate::then::Then;
+use tg_mem::alloc::{Hold, HoldError};
+use tg_mem::lease::RawBuf;
+use crate::input::{Input, OffsetInput, RawByteInput};
+use crate::io::{ByteSink, DecodeIter, FallibleInput, Tee};
+use crate::then::{Then, Cont, Done, Fail};
 
 pub trait Decoder: Sized {
     type Input;
@@ -6,4 +10,828 @@ pub trait Decoder: Sized {
     type Error;
 
     fn decode(self, input: &mut Self::Input) -> Then<Self, Self::Output, Self::Error>;
+
+    /// Clears any residual decode state, returning this decoder to its
+    /// initial state, while retaining its output and configuration. The
+    /// default implementation does nothing, which suits decoders that carry
+    /// no state between calls to `decode`.
+    fn reset(&mut self) {}
+
+    /// Decodes once, as `decode` does, consuming as much of the available
+    /// `input` as the decoder can before it completes, fails, or runs out of
+    /// input. Returns the outcome of the decode, along with the number of
+    /// input tokens consumed, measured by the change in the input's offset.
+    /// Reports zero tokens consumed if the input ends up marked over, which
+    /// leaves it without a meaningful offset.
+    fn step(self, input: &mut Self::Input) -> StepResult<Self, Self::Output, Self::Error>
+        where Self::Input: OffsetInput {
+        let start = input.offset();
+        let then = self.decode(input);
+        let end = input.offset();
+        StepResult {
+            consumed: if end >= start && end != usize::MAX { end - start } else { 0 },
+            then: then,
+        }
+    }
+
+    /// Decodes exactly one item spanning the entire remaining `input`,
+    /// marking the input as over once it runs out. Fails with
+    /// `DecodeExactError::TrailingBytes` if input tokens remain after the item
+    /// completes, which are consumed to count them, with
+    /// `DecodeExactError::Incomplete` if the input ends before the item
+    /// completes, and with `DecodeExactError::Stalled` if the decoder stops
+    /// consuming input before it runs out.
+    fn decode_exact(self, input: &mut Self::Input) -> Result<Self::Output, DecodeExactError<Self::Error>>
+        where Self::Input: Input {
+        let mut decoder = self;
+        loop {
+            match decoder.decode(input) {
+                Done(output) => {
+                    let mut count = 0;
+                    while input.is_in() {
+                        input.step();
+                        count += 1;
+                    }
+                    if count != 0 {
+                        return Err(DecodeExactError::TrailingBytes { count: count });
+                    }
+                    return Ok(output);
+                },
+                Fail(error) => return Err(DecodeExactError::Decode(error)),
+                Cont(next) => {
+                    if input.is_out() {
+                        input.over();
+                        decoder = next;
+                    } else if input.is_over() {
+                        return Err(DecodeExactError::Incomplete);
+                    } else {
+                        return Err(DecodeExactError::Stalled);
+                    }
+                },
+            }
+        }
+    }
+
+    /// Returns a decoder that fails with `DecodeError::OutputLimitExceeded`
+    /// as soon as its decoded output exceeds `max_output` tokens, checking
+    /// the output decoded so far after every input chunk, rather than only
+    /// once decoding completes. Guards against decompression bombs in
+    /// untrusted input.
+    fn limited(self, max_output: usize) -> Limited<Self> where Self: DecodedLen, Self::Output: OutputLen {
+        Limited::new(self, max_output)
+    }
+
+    /// Returns a decoder that decodes a tag with this decoder, then passes
+    /// the tag to `select` to choose a payload decoder, and runs it over the
+    /// rest of the input, completing with the payload's output. The tag and
+    /// payload may span any number of input chunks. Payload decoders for
+    /// different formats share a single type, such as an enum of decoders.
+    /// Fails with `DispatchError::UnknownTag` if `select` returns `None`.
+    fn dispatch<D, F>(self, select: F) -> Dispatch<Self, D, F>
+        where Self: Clone, D: Decoder<Input=Self::Input>, F: FnMut(Self::Output) -> Option<D> {
+        Dispatch::new(self, select)
+    }
+
+    /// Returns a decoder that forwards the exact raw bytes consumed by this
+    /// decoder to a `side` sink, such as a log or a hasher, as decoding
+    /// progresses. Completes with both the decoded output and the side sink.
+    fn tee<S: ByteSink>(self, side: S) -> Tee<Self, S> where Self::Input: RawByteInput {
+        Tee::new(self, side)
+    }
+
+    /// Returns an iterator that pulls successive items from a fallible
+    /// `input`, such as a `SourceInput`, decoding each item with a fresh
+    /// clone of this decoder. The iterator ends when the input is exhausted
+    /// at an item boundary, and after yielding any error.
+    fn into_item_iter(self, input: Self::Input) -> DecodeIter<Self>
+        where Self: Clone, Self::Input: FallibleInput {
+        DecodeIter::new(self, input)
+    }
+}
+
+/// A decoder that can surrender its partially decoded output, for lenient
+/// parsing, and for inspecting the progress of failed decodes.
+pub trait PartialDecoder: Decoder {
+    /// Decodes like `decode`, but on failure returns the output decoded
+    /// before the failure, containing exactly the items completed before the
+    /// failing input token, along with the error.
+    fn decode_partial(self, input: &mut Self::Input)
+        -> Then<Self, Self::Output, (Self::Output, Self::Error)>;
+
+    /// Consumes this decoder, returning the output decoded so far.
+    fn take_partial(self) -> Self::Output;
+
+    /// Decodes the entire remaining `input`, marking the input as over once
+    /// it runs out. On failure, returns the partially decoded output along
+    /// with the error; the input is left positioned at the failing token.
+    /// Fails with `DecodeError::Stalled` if the decoder stops consuming input
+    /// before it runs out.
+    fn decode_all(mut self, input: &mut Self::Input)
+        -> Result<Self::Output, (Self::Output, DecodeError<Self::Error>)>
+        where Self::Input: Input {
+        loop {
+            match self.decode_partial(input) {
+                Done(output) => return Ok(output),
+                Fail((output, error)) => return Err((output, DecodeError::Decode(error))),
+                Cont(next) => {
+                    if input.is_out() {
+                        input.over();
+                        self = next;
+                    } else {
+                        return Err((next.take_partial(), DecodeError::Stalled));
+                    }
+                },
+            }
+        }
+    }
+}
+
+/// Error produced by a `Limited` decoder, and by `decode_all`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DecodeError<E> {
+    /// The wrapped decoder failed.
+    Decode(E),
+    /// The decoded output exceeds the output budget.
+    OutputLimitExceeded,
+    /// The decoder stopped consuming input before the input ran out, such as
+    /// when its output is blocked.
+    Stalled,
+}
+
+/// Error produced by `decode_exact`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DecodeExactError<E> {
+    /// The decoder failed.
+    Decode(E),
+    /// Input tokens remain after the decoded item completed.
+    TrailingBytes { count: usize },
+    /// The input ended before the decoded item completed.
+    Incomplete,
+    /// The decoder stopped consuming input before the input ran out.
+    Stalled,
+}
+
+/// Decoded output whose size, in tokens, can be checked against a budget.
+pub trait OutputLen {
+    fn output_len(&self) -> usize;
+}
+
+impl<'a, T> OutputLen for &'a [T] {
+    #[inline]
+    fn output_len(&self) -> usize {
+        self.len()
+    }
+}
+
+impl<'a, T> OutputLen for &'a mut [T] {
+    #[inline]
+    fn output_len(&self) -> usize {
+        self.len()
+    }
+}
+
+impl<'a> OutputLen for &'a str {
+    #[inline]
+    fn output_len(&self) -> usize {
+        self.len()
+    }
+}
+
+impl<T: OutputLen> OutputLen for Option<T> {
+    #[inline]
+    fn output_len(&self) -> usize {
+        match self {
+            Some(output) => output.output_len(),
+            None => 0,
+        }
+    }
+}
+
+/// Decoder that can report how many output tokens it has decoded so far,
+/// so that its output can be checked against a budget between input chunks.
+pub trait DecodedLen {
+    fn decoded_len(&self) -> usize;
+}
+
+/// Decoder combinator that enforces a maximum output size budget on a wrapped
+/// decoder. Returned by `Decoder::limited`.
+pub struct Limited<D> {
+    decoder: D,
+    max_output: usize,
+}
+
+impl<D: Decoder> Limited<D> where D: DecodedLen, D::Output: OutputLen {
+    pub fn new(decoder: D, max_output: usize) -> Self {
+        Self {
+            decoder: decoder,
+            max_output: max_output,
+        }
+    }
+
+    /// Returns the maximum number of output tokens this decoder will produce.
+    pub fn max_output(&self) -> usize {
+        self.max_output
+    }
+
+    /// Returns a reference to the wrapped decoder.
+    pub fn decoder(&self) -> &D {
+        &self.decoder
+    }
+
+    /// Returns a mutable reference to the wrapped decoder.
+    pub fn decoder_mut(&mut self) -> &mut D {
+        &mut self.decoder
+    }
+
+    /// Unwraps this combinator, returning the wrapped decoder.
+    pub fn into_decoder(self) -> D {
+        self.decoder
+    }
+}
+
+impl<D: Decoder> Decoder for Limited<D> where D: DecodedLen, D::Output: OutputLen {
+    type Input = D::Input;
+    type Output = D::Output;
+    type Error = DecodeError<D::Error>;
+
+    fn decode(mut self, input: &mut D::Input) -> Then<Self, D::Output, DecodeError<D::Error>> {
+        match self.decoder.decode(input) {
+            Cont(next) if next.decoded_len() > self.max_output => {
+                Fail(DecodeError::OutputLimitExceeded)
+            },
+            Cont(next) => {
+                self.decoder = next;
+                Cont(self)
+            },
+            Done(output) if output.output_len() > self.max_output => {
+                Fail(DecodeError::OutputLimitExceeded)
+            },
+            Done(output) => Done(output),
+            Fail(error) => Fail(DecodeError::Decode(error)),
+        }
+    }
+
+    fn reset(&mut self) {
+        self.decoder.reset();
+    }
+}
+
+/// Error produced by a `Dispatch` decoder.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DispatchError<T, P> {
+    /// The tag decoder failed.
+    Tag(T),
+    /// No payload decoder handles the decoded tag.
+    UnknownTag,
+    /// The payload decoder failed.
+    Payload(P),
+}
+
+enum DispatchState<T, D> {
+    Tag(T),
+    Payload(D),
+}
+
+/// Decoder combinator that decodes a tag, then a payload with a decoder
+/// selected by the tag. Returned by `Decoder::dispatch`.
+pub struct Dispatch<T, D, F> {
+    /// Tag decoder in its initial state, restored on reset.
+    tag: T,
+    state: DispatchState<T, D>,
+    select: F,
+}
+
+impl<T, D, F> Dispatch<T, D, F>
+    where T: Decoder + Clone,
+          D: Decoder<Input=T::Input>,
+          F: FnMut(T::Output) -> Option<D> {
+
+    pub fn new(tag: T, select: F) -> Self {
+        Self {
+            state: DispatchState::Tag(tag.clone()),
+            tag: tag,
+            select: select,
+        }
+    }
+
+    /// Returns `true` if the tag has been decoded, and the payload decoder
+    /// selected.
+    pub fn is_payload(&self) -> bool {
+        match self.state {
+            DispatchState::Tag(_) => false,
+            DispatchState::Payload(_) => true,
+        }
+    }
+}
+
+impl<T, D, F> Decoder for Dispatch<T, D, F>
+    where T: Decoder + Clone,
+          D: Decoder<Input=T::Input>,
+          F: FnMut(T::Output) -> Option<D> {
+
+    type Input = T::Input;
+    type Output = D::Output;
+    type Error = DispatchError<T::Error, D::Error>;
+
+    fn decode(self, input: &mut T::Input) -> Then<Self, D::Output, DispatchError<T::Error, D::Error>> {
+        let Dispatch { tag, state, mut select } = self;
+        let payload = match state {
+            DispatchState::Tag(decoder) => match decoder.decode(input) {
+                Cont(next) => {
+                    return Cont(Dispatch { tag: tag, state: DispatchState::Tag(next), select: select });
+                },
+                // Hand the rest of the input to the selected payload decoder.
+                Done(value) => match select(value) {
+                    Some(payload) => payload,
+                    None => return Fail(DispatchError::UnknownTag),
+                },
+                Fail(error) => return Fail(DispatchError::Tag(error)),
+            },
+            DispatchState::Payload(payload) => payload,
+        };
+        match payload.decode(input) {
+            Cont(next) => Cont(Dispatch { tag: tag, state: DispatchState::Payload(next), select: select }),
+            Done(output) => Done(output),
+            Fail(error) => Fail(DispatchError::Payload(error)),
+        }
+    }
+
+    fn reset(&mut self) {
+        self.state = DispatchState::Tag(self.tag.clone());
+    }
+}
+
+/// Outcome of a single decode step, without the decoder, output, or error
+/// that the step produced.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StepState {
+    /// The decoder needs more input to make progress.
+    Continue,
+    /// The decoder completed, producing its output.
+    Done,
+    /// The decoder failed.
+    Error,
+}
+
+/// The outcome of a single decode step, along with the number of input
+/// tokens the decoder consumed during the step.
+#[derive(Clone, Debug)]
+pub struct StepResult<C, D, E> {
+    pub consumed: usize,
+    pub then: Then<C, D, E>,
+}
+
+impl<C, D, E> StepResult<C, D, E> {
+    /// Returns whether the step continued, completed, or failed.
+    pub fn state(&self) -> StepState {
+        match self.then {
+            Cont(_) => StepState::Continue,
+            Done(_) => StepState::Done,
+            Fail(_) => StepState::Error,
+        }
+    }
+
+    /// Returns the decoded output if the step completed, discarding the
+    /// decoder of a continued step, and the error of a failed one.
+    pub fn output(self) -> Option<D> {
+        match self.then {
+            Done(output) => Some(output),
+            _ => None,
+        }
+    }
+}
+
+/// Reusable scratch memory for decoders that retain state between input
+/// chunks, such as a DEFLATE back-reference window, or that decode small
+/// messages into a staging buffer. The scratch is allocated once, in a given
+/// hold; lending the same context to each successive decoder avoids
+/// allocating new scratch per decode.
+pub struct DecoderContext<'a> {
+    scratch: RawBuf<'a, u8>,
+}
+
+impl<'a> DecoderContext<'a> {
+    /// Allocates a new context in `hold` with `cap` bytes of zeroed scratch.
+    pub fn try_hold_cap(hold: &dyn Hold<'a>, cap: usize) -> Result<DecoderContext<'a>, HoldError> {
+        Ok(DecoderContext {
+            scratch: RawBuf::try_hold_cap_zeroed(hold, cap)?,
+        })
+    }
+
+    #[inline]
+    pub fn hold_cap(hold: &dyn Hold<'a>, cap: usize) -> DecoderContext<'a> {
+        DecoderContext::try_hold_cap(hold, cap).unwrap()
+    }
+
+    /// Returns the size of this context's scratch memory, in bytes.
+    pub fn capacity(&self) -> usize {
+        self.scratch.len()
+    }
+
+    /// Zeroes this context's scratch, so that no state from a previous
+    /// decode is visible to the next borrowing decoder.
+    pub fn reset(&mut self) {
+        for b in self.scratch.as_mut_slice() {
+            *b = 0;
+        }
+    }
+
+    /// Resets this context, and lends its scratch to a new decoder.
+    pub fn lend(&mut self) -> &mut [u8] {
+        self.reset();
+        self.scratch.as_mut_slice()
+    }
+}
+
+/// Decoded output that borrows its input when decoding made no changes, and
+/// owns a separately produced output only when decoding transformed the input.
+#[derive(Debug)]
+pub enum CowOutput<'i, T: 'i, O> {
+    Borrowed(&'i [T]),
+    Owned(O),
+}
+
+impl<'i, T, O> CowOutput<'i, T, O> {
+    pub fn is_borrowed(&self) -> bool {
+        match self {
+            CowOutput::Borrowed(_) => true,
+            CowOutput::Owned(_) => false,
+        }
+    }
+
+    pub fn is_owned(&self) -> bool {
+        !self.is_borrowed()
+    }
+}
+
+impl<'i, T, O: AsRef<[T]>> AsRef<[T]> for CowOutput<'i, T, O> {
+    fn as_ref(&self) -> &[T] {
+        match self {
+            CowOutput::Borrowed(input) => input,
+            CowOutput::Owned(output) => output.as_ref(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::marker::PhantomData;
+    use crate::input::{Input, AsInput, BytesInput, SliceInput};
+    use crate::output::{Output, SliceOutput};
+    use crate::step::{In, Out, Over};
+    use super::*;
+
+    struct TakeDecoder<'a> {
+        count: usize,
+        remaining: usize,
+        input: PhantomData<&'a [u8]>,
+    }
+
+    impl<'a> Decoder for TakeDecoder<'a> {
+        type Input = SliceInput<'a, u8>;
+        type Output = ();
+        type Error = ();
+
+        fn decode(mut self, input: &mut Self::Input) -> Then<Self, (), ()> {
+            while self.remaining != 0 {
+                match input.head() {
+                    In(_) => {
+                        input.step();
+                        self.remaining -= 1;
+                    },
+                    _ => return Cont(self),
+                }
+            }
+            Done(())
+        }
+
+        fn reset(&mut self) {
+            self.remaining = self.count;
+        }
+    }
+
+    #[test]
+    fn test_step_consumed() {
+        let chunk = [0u8; 10];
+        let mut input = (&chunk[..]).as_input();
+        let decoder = TakeDecoder { count: 6, remaining: 6, input: PhantomData };
+        let result = decoder.step(&mut input);
+        assert_eq!(result.consumed, 6);
+        assert_eq!(result.state(), StepState::Done);
+        assert_eq!(chunk.len() - result.consumed, 4);
+        assert_eq!(input.offset(), 6);
+    }
+
+    #[test]
+    fn test_decode_exact() {
+        let chunk = [0u8; 10];
+        let decoder = TakeDecoder { count: 6, remaining: 6, input: PhantomData };
+        assert_eq!(decoder.decode_exact(&mut (&chunk[..6]).as_input()), Ok(()));
+
+        let decoder = TakeDecoder { count: 6, remaining: 6, input: PhantomData };
+        let mut input = (&chunk[..]).as_input();
+        assert_eq!(decoder.decode_exact(&mut input), Err(DecodeExactError::TrailingBytes { count: 4 }));
+        assert_eq!(input.offset(), 10);
+
+        let decoder = TakeDecoder { count: 6, remaining: 6, input: PhantomData };
+        assert_eq!(decoder.decode_exact(&mut (&chunk[..4]).as_input()), Err(DecodeExactError::Incomplete));
+
+        let mut buffer = [0u8; 4];
+        let decoder = CopyDecoder { output: SliceOutput::new(&mut buffer), input: PhantomData };
+        assert_eq!(decoder.decode_exact(&mut (&chunk[..]).as_input()).unwrap_err(), DecodeExactError::Stalled);
+    }
+
+    /// Copies bytes to its output, suspending while the output is full.
+    struct CopyDecoder<'a, 'b> {
+        output: SliceOutput<'b, u8>,
+        input: PhantomData<&'a [u8]>,
+    }
+
+    impl<'a, 'b> Decoder for CopyDecoder<'a, 'b> {
+        type Input = SliceInput<'a, u8>;
+        type Output = &'b mut [u8];
+        type Error = ();
+
+        fn decode(mut self, input: &mut Self::Input) -> Then<Self, &'b mut [u8], ()> {
+            while !self.output.is_full() {
+                match input.head() {
+                    In(b) => {
+                        input.step();
+                        self.output.push(b);
+                    },
+                    Over => return Done(self.output.take_out().unwrap()),
+                    Out => break,
+                }
+            }
+            Cont(self)
+        }
+    }
+
+    impl<'a, 'b> PartialDecoder for CopyDecoder<'a, 'b> {
+        fn decode_partial(self, input: &mut Self::Input) -> Then<Self, &'b mut [u8], (&'b mut [u8], ())> {
+            match self.decode(input) {
+                Cont(next) => Cont(next),
+                Done(output) => Done(output),
+                Fail(()) => unreachable!(),
+            }
+        }
+
+        fn take_partial(self) -> &'b mut [u8] {
+            self.output.take_out().unwrap()
+        }
+    }
+
+    #[test]
+    fn test_decode_all_stalled() {
+        let chunk = [1u8, 2, 3, 4, 5, 6];
+        let mut buffer = [0u8; 8];
+        let decoder = CopyDecoder { output: SliceOutput::new(&mut buffer), input: PhantomData };
+        assert_eq!(decoder.decode_all(&mut (&chunk[..]).as_input()).unwrap(), &chunk[..]);
+
+        // The decoder stops consuming input once its output fills up.
+        let mut buffer = [0u8; 4];
+        let decoder = CopyDecoder { output: SliceOutput::new(&mut buffer), input: PhantomData };
+        let mut input = (&chunk[..]).as_input();
+        match decoder.decode_all(&mut input) {
+            Err((output, error)) => {
+                assert_eq!(output, &chunk[..4]);
+                assert_eq!(error, DecodeError::Stalled);
+            },
+            Ok(_) => panic!(),
+        }
+        assert_eq!(input.offset(), 4);
+    }
+
+    /// Decodes a single byte.
+    #[derive(Clone)]
+    struct ByteDecoder<'a> {
+        input: PhantomData<&'a [u8]>,
+    }
+
+    impl<'a> Decoder for ByteDecoder<'a> {
+        type Input = BytesInput<'a>;
+        type Output = u8;
+        type Error = ();
+
+        fn decode(self, input: &mut BytesInput<'a>) -> Then<Self, u8, ()> {
+            match input.head() {
+                In(b) => {
+                    input.step();
+                    Done(b)
+                },
+                Over => Fail(()),
+                Out => Cont(self),
+            }
+        }
+
+        fn reset(&mut self) {
+        }
+    }
+
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    enum Record {
+        /// Big-endian `u32`, tagged `I`.
+        Int(u32),
+        /// Length-prefixed byte string, tagged `S`, summed.
+        Sum(u32),
+    }
+
+    /// Decodes the payload of either record format.
+    enum RecordDecoder<'a> {
+        Int { value: u32, count: usize, input: PhantomData<&'a [u8]> },
+        Sum { sum: u32, remaining: Option<u8>, input: PhantomData<&'a [u8]> },
+    }
+
+    impl<'a> Decoder for RecordDecoder<'a> {
+        type Input = BytesInput<'a>;
+        type Output = Record;
+        type Error = ();
+
+        fn decode(mut self, input: &mut BytesInput<'a>) -> Then<Self, Record, ()> {
+            loop {
+                let b = match input.head() {
+                    In(b) => b,
+                    Over => return Fail(()),
+                    Out => return Cont(self),
+                };
+                match self {
+                    RecordDecoder::Int { ref mut value, ref mut count, .. } => {
+                        input.step();
+                        *value = *value << 8 | b as u32;
+                        *count += 1;
+                        if *count == 4 {
+                            return Done(Record::Int(*value));
+                        }
+                    },
+                    RecordDecoder::Sum { ref mut sum, ref mut remaining, .. } => {
+                        input.step();
+                        match *remaining {
+                            None => *remaining = Some(b),
+                            Some(n) => {
+                                *sum += b as u32;
+                                *remaining = Some(n - 1);
+                            },
+                        }
+                        if *remaining == Some(0) {
+                            return Done(Record::Sum(*sum));
+                        }
+                    },
+                }
+            }
+        }
+
+        fn reset(&mut self) {
+        }
+    }
+
+    fn record_decoder<'a>() -> Dispatch<ByteDecoder<'a>, RecordDecoder<'a>, fn(u8) -> Option<RecordDecoder<'a>>> {
+        fn select<'a>(tag: u8) -> Option<RecordDecoder<'a>> {
+            match tag {
+                b'I' => Some(RecordDecoder::Int { value: 0, count: 0, input: PhantomData }),
+                b'S' => Some(RecordDecoder::Sum { sum: 0, remaining: None, input: PhantomData }),
+                _ => None,
+            }
+        }
+        ByteDecoder { input: PhantomData }.dispatch(select as fn(u8) -> Option<RecordDecoder<'a>>)
+    }
+
+    #[test]
+    fn test_dispatch_tagged_records() {
+        let stream = [b'I', 0, 0, 1, 2, b'S', 3, 10, 20, 30, b'I', 0xDE, 0xAD, 0xBE, 0xEF, b'S', 0];
+        let expected = [Record::Int(258), Record::Sum(60), Record::Int(0xDEADBEEF), Record::Sum(0)];
+        // Chunks of every size put a chunk boundary between some tag and its payload.
+        for chunk in 1..=stream.len() {
+            let mut input = BytesInput::chunked(&stream[..], chunk);
+            for &record in expected.iter() {
+                let mut decoder = record_decoder();
+                let decoded = loop {
+                    match decoder.decode(&mut input) {
+                        Cont(next) => {
+                            decoder = next;
+                            assert!(input.more());
+                        },
+                        Done(decoded) => break decoded,
+                        Fail(error) => panic!("{:?}", error),
+                    }
+                };
+                assert_eq!(decoded, record, "chunk {}", chunk);
+            }
+        }
+    }
+
+    #[test]
+    fn test_dispatch_suspends_between_tag_and_payload() {
+        let stream = [b'S', 2, 5, 6];
+        let mut input = BytesInput::chunked(&stream[..], 1);
+        let decoder = match record_decoder().decode(&mut input) {
+            Cont(next) => next,
+            _ => panic!(),
+        };
+        assert!(decoder.is_payload());
+        assert!(input.more());
+
+        let mut input = BytesInput::new(&b"X"[..]);
+        match record_decoder().decode(&mut input) {
+            Fail(error) => assert_eq!(error, DispatchError::UnknownTag),
+            _ => panic!(),
+        }
+    }
+
+    /// Decodes run-length encoded `(count, byte)` pairs.
+    struct RunLengthDecoder<'a, 'b> {
+        output: SliceOutput<'b, u8>,
+        count: Option<u8>,
+        input: PhantomData<&'a [u8]>,
+    }
+
+    impl<'a, 'b> Decoder for RunLengthDecoder<'a, 'b> {
+        type Input = BytesInput<'a>;
+        type Output = &'b mut [u8];
+        type Error = ();
+
+        fn decode(mut self, input: &mut BytesInput<'a>) -> Then<Self, &'b mut [u8], ()> {
+            loop {
+                match (self.count, input.head()) {
+                    (None, In(count)) => self.count = Some(count),
+                    (Some(count), In(b)) => {
+                        for _ in 0..count {
+                            if self.output.is_full() {
+                                return Fail(());
+                            }
+                            self.output.push(b);
+                        }
+                        self.count = None;
+                    },
+                    (None, Over) => return Done(self.output.take_out().unwrap()),
+                    (Some(_), Over) => return Fail(()),
+                    (_, Out) => return Cont(self),
+                }
+                input.step();
+            }
+        }
+    }
+
+    impl<'a, 'b> DecodedLen for RunLengthDecoder<'a, 'b> {
+        fn decoded_len(&self) -> usize {
+            self.output.output_len()
+        }
+    }
+
+    /// Three runs of 200 bytes each, in two byte chunks.
+    const RUNS: [u8; 6] = [200, b'a', 200, b'b', 200, b'c'];
+
+    fn run_length_limited(max_output: usize, buffer: &mut [u8]) -> Result<usize, (DecodeError<()>, usize)> {
+        let mut input = BytesInput::chunked(&RUNS[..], 2);
+        let output = SliceOutput::new(buffer);
+        let mut decoder = RunLengthDecoder { output: output, count: None, input: PhantomData }.limited(max_output);
+        loop {
+            match decoder.decode(&mut input) {
+                Cont(next) => {
+                    decoder = next;
+                    if !input.more() {
+                        input.over();
+                    }
+                },
+                Done(output) => return Ok(output.len()),
+                // Report how much of the stream was consumed before failing.
+                Fail(error) => return Err((error, input.offset())),
+            }
+        }
+    }
+
+    #[test]
+    fn test_limited_output() {
+        let mut buffer = [0u8; 1024];
+        // Streams within budget decode fully.
+        assert_eq!(run_length_limited(600, &mut buffer), Ok(600));
+        assert!(buffer[400..600].iter().all(|&b| b == b'c'));
+        assert_eq!(run_length_limited(1024, &mut buffer), Ok(600));
+        // The decoder fails at the first chunk that takes the output over
+        // budget, without decoding the rest of the stream.
+        assert_eq!(run_length_limited(599, &mut buffer), Err((DecodeError::OutputLimitExceeded, 6)));
+        assert_eq!(run_length_limited(399, &mut buffer), Err((DecodeError::OutputLimitExceeded, 4)));
+        assert_eq!(run_length_limited(100, &mut buffer), Err((DecodeError::OutputLimitExceeded, 2)));
+        assert_eq!(run_length_limited(0, &mut buffer), Err((DecodeError::OutputLimitExceeded, 2)));
+    }
+
+    #[test]
+    fn test_limited_accessors() {
+        let mut buffer = [0u8; 1024];
+        let mut input = BytesInput::chunked(&RUNS[..], 2);
+        let output = SliceOutput::new(&mut buffer);
+        let decoder = RunLengthDecoder { output: output, count: None, input: PhantomData }.limited(500);
+        assert_eq!(decoder.max_output(), 500);
+        assert_eq!(decoder.decoder().decoded_len(), 0);
+        let mut decoder = match decoder.decode(&mut input) {
+            Cont(next) => next,
+            _ => panic!(),
+        };
+        // The budget is checked against the wrapped decoder's progress.
+        assert_eq!(decoder.decoder().decoded_len(), 200);
+        // A run count set through the mutable reference survives unwrapping.
+        decoder.decoder_mut().count = Some(3);
+        let decoder = decoder.into_decoder();
+        assert_eq!(decoder.count, Some(3));
+        assert_eq!(decoder.decoded_len(), 200);
+    }
 }
@@ -0,0 +1,392 @@
+use core::fmt;
+use core::marker::PhantomData;
+use crate::step::{Step, In, Out, Over};
+use crate::then::{Then, Cont, Done, Fail};
+use crate::input::Input;
+use crate::output::Output;
+use crate::decoder::{Decoder, DecodedLen, DecoderContext, OutputLen};
+use crate::bits::{BitOrder, BitReader};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DeflateError {
+    InvalidBlockType,
+    InvalidStoredLength,
+    InvalidSymbol,
+    InvalidDistance,
+    Unsupported,
+    Truncated,
+}
+
+/// Incremental DEFLATE decoder supporting stored and fixed Huffman blocks.
+///
+/// Decoded bytes are retained in a caller-provided `window` ring buffer to
+/// resolve LZ77 back-references; the window should be 32 KiB to decode
+/// arbitrary streams, but may be smaller when the encoder is known to use
+/// shorter distances.
+pub struct DeflateDecoder<'w, I: Input<Token=u8>, O: Output<Token=u8>> {
+    pub output: O,
+    window: &'w mut [u8],
+    head: usize,
+    fill: usize,
+    bits: BitReader,
+    last: bool,
+    state: u32,
+    symbol: u32,
+    length: u32,
+    distance: u32,
+    input: PhantomData<I>,
+}
+
+impl<'w, I, O> DeflateDecoder<'w, I, O> where I: Input<Token=u8>, O: Output<Token=u8> {
+    pub fn new(output: O, window: &'w mut [u8]) -> Self {
+        Self {
+            output: output,
+            window: window,
+            head: 0,
+            fill: 0,
+            bits: BitReader::new(BitOrder::LsbFirst),
+            last: false,
+            state: 1,
+            symbol: 0,
+            length: 0,
+            distance: 0,
+            input: PhantomData,
+        }
+    }
+
+    /// Returns a new decoder that borrows its back-reference window from
+    /// the scratch of a shared `DecoderContext`.
+    pub fn with_context(output: O, context: &'w mut DecoderContext) -> Self {
+        Self::new(output, context.lend())
+    }
+
+    pub fn consume(mut self, input: &mut I) -> Result<O::Out, DeflateError> where O::Err: fmt::Debug {
+        loop {
+            match self.decode(input) {
+                Done(output) => return Ok(output),
+                Fail(error) => return Err(error),
+                Cont(next) => {
+                    if input.is_out() {
+                        input.over();
+                        self = next;
+                    } else {
+                        return Err(DeflateError::Truncated);
+                    }
+                },
+            }
+        }
+    }
+
+    /// Appends a decoded byte to the output and the back-reference window.
+    #[inline]
+    fn emit(&mut self, b: u8) {
+        self.output.push(b);
+        let size = self.window.len();
+        if size != 0 {
+            self.window[self.head] = b;
+            self.head = if self.head + 1 < size { self.head + 1 } else { 0 };
+            if self.fill < size {
+                self.fill += 1;
+            }
+        }
+    }
+
+    /// Decodes the next fixed Huffman literal/length symbol.
+    fn fixed_symbol(&mut self, input: &mut I) -> Step<u32> {
+        // Buffer enough bits for the longest code, if available.
+        let step = self.bits.fill(input, 9);
+        if self.bits.buffered() >= 7 {
+            let code = reverse_bits(self.bits.peek(7), 7);
+            if code <= 0x17 {
+                self.bits.skip(7);
+                return In(256 + code);
+            }
+        }
+        if self.bits.buffered() >= 8 {
+            let code = reverse_bits(self.bits.peek(8), 8);
+            if code >= 0x30 && code <= 0xBF {
+                self.bits.skip(8);
+                return In(code - 0x30);
+            } else if code >= 0xC0 && code <= 0xC7 {
+                self.bits.skip(8);
+                return In(280 + code - 0xC0);
+            }
+        }
+        if self.bits.buffered() >= 9 {
+            let code = reverse_bits(self.bits.peek(9), 9);
+            self.bits.skip(9);
+            return In(144 + code - 0x190);
+        }
+        match step {
+            Out => Out,
+            _ => Over,
+        }
+    }
+}
+
+impl<'w, I, O> Decoder for DeflateDecoder<'w, I, O>
+    where I: Input<Token=u8>,
+          O: Output<Token=u8>,
+          O::Err: fmt::Debug {
+
+    type Input = I;
+    type Output = O::Out;
+    type Error = DeflateError;
+
+    fn decode(mut self, input: &mut I) -> Then<Self, O::Out, DeflateError> {
+        loop {
+            match self.state {
+                1 => { // block header
+                    match self.bits.fill(input, 3) {
+                        In(()) => {
+                            self.last = self.bits.peek(1) != 0;
+                            let kind = self.bits.peek(3) >> 1;
+                            self.bits.skip(3);
+                            match kind {
+                                0 => {
+                                    // Discard bits up to the next byte boundary.
+                                    self.bits.align();
+                                    self.state = 2;
+                                },
+                                1 => self.state = 4,
+                                2 => return Fail(DeflateError::Unsupported),
+                                _ => return Fail(DeflateError::InvalidBlockType),
+                            }
+                        },
+                        Out => return Cont(self),
+                        Over => return Fail(DeflateError::Truncated),
+                    };
+                },
+                2 => { // stored block length
+                    match self.bits.fill(input, 32) {
+                        In(()) => {
+                            let len = self.bits.peek(16);
+                            self.bits.skip(16);
+                            let nlen = self.bits.peek(16);
+                            self.bits.skip(16);
+                            if len != !nlen & 0xFFFF {
+                                return Fail(DeflateError::InvalidStoredLength);
+                            }
+                            self.length = len;
+                            self.state = 3;
+                        },
+                        Out => return Cont(self),
+                        Over => return Fail(DeflateError::Truncated),
+                    };
+                },
+                3 => { // stored block data
+                    while self.length != 0 {
+                        match self.bits.fill(input, 8) {
+                            In(()) => {
+                                let b = self.bits.peek(8) as u8;
+                                self.bits.skip(8);
+                                self.emit(b);
+                                self.length -= 1;
+                            },
+                            Out => return Cont(self),
+                            Over => return Fail(DeflateError::Truncated),
+                        };
+                    }
+                    self.state = 9;
+                },
+                4 => { // literal/length symbol
+                    match self.fixed_symbol(input) {
+                        In(symbol) if symbol < 256 => self.emit(symbol as u8),
+                        In(256) => self.state = 9,
+                        In(symbol) if symbol <= 285 => {
+                            self.symbol = symbol - 257;
+                            self.state = 5;
+                        },
+                        In(_) => return Fail(DeflateError::InvalidSymbol),
+                        Out => return Cont(self),
+                        Over => return Fail(DeflateError::Truncated),
+                    };
+                },
+                5 => { // length extra bits
+                    let extra = LENGTH_EXTRA[self.symbol as usize];
+                    match self.bits.fill(input, extra) {
+                        In(()) => {
+                            self.length = LENGTH_BASE[self.symbol as usize] + self.bits.peek(extra);
+                            self.bits.skip(extra);
+                            self.state = 6;
+                        },
+                        Out => return Cont(self),
+                        Over => return Fail(DeflateError::Truncated),
+                    };
+                },
+                6 => { // distance code
+                    match self.bits.fill(input, 5) {
+                        In(()) => {
+                            let code = reverse_bits(self.bits.peek(5), 5);
+                            self.bits.skip(5);
+                            if code >= 30 {
+                                return Fail(DeflateError::InvalidDistance);
+                            }
+                            self.symbol = code;
+                            self.state = 7;
+                        },
+                        Out => return Cont(self),
+                        Over => return Fail(DeflateError::Truncated),
+                    };
+                },
+                7 => { // distance extra bits
+                    let extra = DISTANCE_EXTRA[self.symbol as usize];
+                    match self.bits.fill(input, extra) {
+                        In(()) => {
+                            self.distance = DISTANCE_BASE[self.symbol as usize] + self.bits.peek(extra);
+                            self.bits.skip(extra);
+                            if self.distance as usize > self.fill {
+                                return Fail(DeflateError::InvalidDistance);
+                            }
+                            self.state = 8;
+                        },
+                        Out => return Cont(self),
+                        Over => return Fail(DeflateError::Truncated),
+                    };
+                },
+                8 => { // back-reference copy
+                    let size = self.window.len();
+                    while self.length != 0 {
+                        let index = (self.head + size - self.distance as usize) % size;
+                        let b = self.window[index];
+                        self.emit(b);
+                        self.length -= 1;
+                    }
+                    self.state = 4;
+                },
+                9 => { // end of block
+                    if self.last {
+                        return Done(self.output.take_out().unwrap());
+                    }
+                    self.state = 1;
+                },
+                _ => unreachable!(),
+            };
+        }
+    }
+
+    fn reset(&mut self) {
+        self.head = 0;
+        self.fill = 0;
+        self.bits.reset();
+        self.last = false;
+        self.state = 1;
+        self.symbol = 0;
+        self.length = 0;
+        self.distance = 0;
+    }
+}
+
+impl<'w, I, O> DecodedLen for DeflateDecoder<'w, I, O>
+    where I: Input<Token=u8>,
+          O: Output<Token=u8> + OutputLen {
+    #[inline]
+    fn decoded_len(&self) -> usize {
+        self.output.output_len()
+    }
+}
+
+#[inline]
+fn reverse_bits(code: u32, n: u32) -> u32 {
+    code.reverse_bits() >> (32 - n)
+}
+
+const LENGTH_BASE: [u32; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31,
+    35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258,
+];
+
+const LENGTH_EXTRA: [u32; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2,
+    3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+
+const DISTANCE_BASE: [u32; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193,
+    257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+
+const DISTANCE_EXTRA: [u32; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6,
+    7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13,
+];
+
+#[cfg(test)]
+mod tests {
+    use crate::input::{AsInput, BytesInput};
+    use crate::output::SliceOutput;
+    use tg_mem::block::Block;
+    use tg_mem::alloc::Pack;
+    use super::*;
+
+    fn assert_inflates_bytewise(encoded: &[u8], decoded: &[u8]) {
+        let mut window = [0u8; 1024];
+        let mut buffer = [0u8; 1024];
+        let mut input = BytesInput::chunked(encoded, 1);
+        let mut decoder = DeflateDecoder::new(SliceOutput::new(&mut buffer), &mut window);
+        let output = loop {
+            match decoder.decode(&mut input) {
+                Cont(next) => {
+                    decoder = next;
+                    if !input.more() {
+                        input.over();
+                    }
+                },
+                Done(output) => break output,
+                Fail(error) => panic!("{:?}", error),
+            }
+        };
+        assert_eq!(output, decoded);
+    }
+
+    #[test]
+    fn test_deflate_stored() {
+        let encoded = [0x01, 0x05, 0x00, 0xFA, 0xFF, b'h', b'e', b'l', b'l', b'o'];
+        assert_inflates_bytewise(&encoded, b"hello");
+        let mut window = [0u8; 16];
+        let mut buffer = [0u8; 16];
+        let decoder = DeflateDecoder::new(SliceOutput::new(&mut buffer), &mut window);
+        assert_eq!(decoder.consume(&mut (&encoded[..]).as_input()).unwrap(), b"hello");
+    }
+
+    #[test]
+    fn test_deflate_fixed_huffman() {
+        assert_inflates_bytewise(&[203, 72, 205, 201, 201, 87, 200, 64, 144, 0],
+                                 b"hello hello hello");
+        assert_inflates_bytewise(&[115, 73, 77, 203, 73, 44, 73, 85, 0, 17, 0],
+                                 b"Deflate late");
+    }
+
+    #[test]
+    fn test_deflate_shared_context() {
+        static mut TEST_AREA: [u8; 4096] = [0; 4096];
+        let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+
+        let encoded = [203, 72, 205, 201, 201, 87, 200, 64, 144, 0];
+        let mut context = DecoderContext::hold_cap(pack, 64);
+        let scratch_ptr = context.lend().as_ptr();
+        let live = pack.live();
+        for _ in 0..1000 {
+            let mut buffer = [0u8; 32];
+            let decoder = DeflateDecoder::with_context(SliceOutput::new(&mut buffer), &mut context);
+            // Every decoder reuses the context's scratch as its window, with
+            // the previous decode's back-references cleared.
+            assert_eq!(decoder.window.as_ptr(), scratch_ptr);
+            assert!(decoder.window.iter().all(|&b| b == 0));
+            assert_eq!(decoder.consume(&mut (&encoded[..]).as_input()).unwrap(),
+                       b"hello hello hello");
+        }
+        assert_eq!(context.capacity(), 64);
+        assert_eq!(pack.live(), live);
+    }
+
+    #[test]
+    fn test_deflate_invalid_stored_length() {
+        let encoded = [0x01, 0x05, 0x00, 0x00, 0x00];
+        let mut window = [0u8; 16];
+        let mut buffer = [0u8; 16];
+        let decoder = DeflateDecoder::new(SliceOutput::new(&mut buffer), &mut window);
+        assert_eq!(decoder.consume(&mut (&encoded[..]).as_input()).unwrap_err(),
+                   DeflateError::InvalidStoredLength);
+    }
+}
@@ -0,0 +1,250 @@
+use core::fmt;
+use core::marker::PhantomData;
+use crate::step::{In, Out, Over};
+use crate::then::{Then, Cont, Done, Fail};
+use crate::input::Input;
+use crate::output::Output;
+use crate::decoder::Decoder;
+use crate::encoder::Encoder;
+
+/// Lowercase hex digit alphabet.
+const HEX_LOWER: &'static [u8; 16] = b"0123456789abcdef";
+
+/// Uppercase hex digit alphabet.
+const HEX_UPPER: &'static [u8; 16] = b"0123456789ABCDEF";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum HexError {
+    /// Input ended, or a non-digit followed, after the first digit of a pair.
+    Unpaired,
+}
+
+/// Incremental hex decoder. Decodes each pair of hex digits, of either
+/// case, into a byte, carrying a lone first digit across input chunks.
+/// Completes at the first non-digit, or at the end of input, following a
+/// complete pair.
+pub struct HexDecoder<I: Input<Token=char>, O: Output<Token=u8>> {
+    pub output: O,
+    /// Value of the first digit of the current pair.
+    high: u8,
+    /// Whether the first digit of the current pair has been consumed.
+    half: bool,
+    input: PhantomData<I>,
+}
+
+/// Incremental hex encoder. Encodes each byte into a pair of hex digits,
+/// carrying the second digit of a pair across full outputs.
+pub struct HexEncoder<I: Input<Token=u8>, O: Output<Token=char>> {
+    alphabet: &'static [u8; 16],
+    pub input: I,
+    /// Second digit of the last encoded byte, if `pending` is set.
+    low: u8,
+    pending: bool,
+    output: PhantomData<O>,
+}
+
+impl<I, O> HexDecoder<I, O> where I: Input<Token=char>, O: Output<Token=u8> {
+    pub fn new(output: O) -> Self {
+        Self {
+            output: output,
+            high: 0,
+            half: false,
+            input: PhantomData,
+        }
+    }
+
+    pub fn consume(mut self, input: &mut I) -> Result<O::Out, HexError> where O::Err: fmt::Debug {
+        loop {
+            match self.decode(input) {
+                Done(output) => return Ok(output),
+                Fail(error) => return Err(error),
+                Cont(next) => {
+                    if input.is_out() {
+                        input.over();
+                        self = next;
+                    } else {
+                        return Err(HexError::Unpaired);
+                    }
+                },
+            }
+        }
+    }
+}
+
+impl<I, O> Decoder for HexDecoder<I, O>
+    where I: Input<Token=char>,
+          O: Output<Token=u8>,
+          O::Err: fmt::Debug {
+
+    type Input = I;
+    type Output = O::Out;
+    type Error = HexError;
+
+    fn decode(mut self, input: &mut I) -> Then<Self, O::Out, HexError> {
+        loop {
+            if self.output.is_blocked() {
+                return Cont(self);
+            }
+            match input.head() {
+                In(c) => match decode_hex_char(c) {
+                    Some(digit) => {
+                        input.step();
+                        if self.half {
+                            self.output.push(self.high << 4 | digit);
+                            self.half = false;
+                        } else {
+                            self.high = digit;
+                            self.half = true;
+                        }
+                    },
+                    None if !self.half => return Done(self.output.take_out().unwrap()),
+                    None => return Fail(HexError::Unpaired),
+                },
+                Over if !self.half => return Done(self.output.take_out().unwrap()),
+                Over => return Fail(HexError::Unpaired),
+                Out => return Cont(self),
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.high = 0;
+        self.half = false;
+    }
+}
+
+impl<I, O> HexEncoder<I, O> where I: Input<Token=u8>, O: Output<Token=char> {
+    pub fn new(input: I) -> Self {
+        Self {
+            alphabet: HEX_LOWER,
+            input: input,
+            low: 0,
+            pending: false,
+            output: PhantomData,
+        }
+    }
+
+    /// Encodes with uppercase, rather than lowercase, digits.
+    pub fn uppercase(mut self, uppercase: bool) -> Self {
+        self.alphabet = if uppercase { HEX_UPPER } else { HEX_LOWER };
+        self
+    }
+
+    pub fn produce(mut self, mut output: O) -> Result<O::Out, HexError> where O::Err: fmt::Debug {
+        loop {
+            match self.encode(&mut output) {
+                Done(_) => return Ok(output.take_out().unwrap()),
+                Fail(error) => return Err(error),
+                Cont(next) => {
+                    self = next;
+                    self.input.over();
+                }
+            }
+        }
+    }
+}
+
+impl<I, O> Encoder for HexEncoder<I, O> where I: Input<Token=u8>, O: Output<Token=char> {
+    type Input = I;
+    type Output = O;
+    type Error = HexError;
+
+    fn encode(mut self, output: &mut O) -> Then<Self, I, HexError> {
+        loop {
+            // Flush the second digit of the last encoded byte.
+            if self.pending {
+                if output.is_full() {
+                    return Cont(self);
+                }
+                output.push(self.alphabet[self.low as usize] as char);
+                self.pending = false;
+            }
+            match self.input.head() {
+                In(b) => {
+                    if output.is_full() {
+                        return Cont(self);
+                    }
+                    self.input.step();
+                    output.push(self.alphabet[(b >> 4) as usize] as char);
+                    self.low = b & 0x0F;
+                    self.pending = true;
+                },
+                Over => return Done(self.input),
+                Out => return Cont(self),
+            }
+        }
+    }
+}
+
+#[inline]
+fn decode_hex_char(c: char) -> Option<u8> {
+    match c {
+        '0'..='9' => Some(c as u8 - b'0'),
+        'a'..='f' => Some(c as u8 - b'a' + 10),
+        'A'..='F' => Some(c as u8 - b'A' + 10),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::input::{AsInput, BytesInput, Utf8Input};
+    use crate::output::{SliceOutput, StrOutput};
+    use super::*;
+
+    const BYTES: [u8; 8] = [0x00, 0x01, 0x7F, 0x80, 0xAB, 0xCD, 0xEF, 0xFF];
+
+    #[test]
+    fn test_hex_transcode() {
+        let mut buffer = [0u8; 16];
+        let encoder = HexEncoder::new((&BYTES[..]).as_input());
+        assert_eq!(encoder.produce(StrOutput::new(&mut buffer)).unwrap(), "00017f80abcdefff");
+
+        let mut buffer = [0u8; 16];
+        let encoder = HexEncoder::new((&BYTES[..]).as_input()).uppercase(true);
+        assert_eq!(encoder.produce(StrOutput::new(&mut buffer)).unwrap(), "00017F80ABCDEFFF");
+
+        let mut buffer = [0u8; 16];
+        let decoder = HexDecoder::new(SliceOutput::new(&mut buffer));
+        assert_eq!(decoder.consume(&mut "00017f80ABCDefFF".as_input()).unwrap(), &BYTES[..]);
+    }
+
+    #[test]
+    fn test_hex_decode_chunked() {
+        let encoded = *b"00017f80abcdefff";
+        let mut input = Utf8Input::new(BytesInput::chunked(&encoded[..], 3));
+        let mut buffer = [0u8; 16];
+        let mut decoder = HexDecoder::new(SliceOutput::new(&mut buffer));
+        let decoded = loop {
+            match decoder.decode(&mut input) {
+                Cont(next) => {
+                    decoder = next;
+                    if !input.input_mut().more() {
+                        input.over();
+                    }
+                },
+                Done(output) => break output,
+                Fail(error) => panic!("{:?}", error),
+            }
+        };
+        assert_eq!(decoded, &BYTES[..]);
+    }
+
+    #[test]
+    fn test_hex_unpaired() {
+        let mut buffer = [0u8; 16];
+        let decoder = HexDecoder::new(SliceOutput::new(&mut buffer));
+        assert_eq!(decoder.consume(&mut "abc".as_input()), Err(HexError::Unpaired));
+
+        let mut buffer = [0u8; 16];
+        let decoder = HexDecoder::new(SliceOutput::new(&mut buffer));
+        assert_eq!(decoder.consume(&mut "abc!".as_input()), Err(HexError::Unpaired));
+
+        // A non-digit following a complete pair ends the decode.
+        let mut input = "ab!cd".as_input();
+        let mut buffer = [0u8; 16];
+        let decoder = HexDecoder::new(SliceOutput::new(&mut buffer));
+        assert_eq!(decoder.consume(&mut input).unwrap(), &[0xAB]);
+        assert_eq!(input.head(), In('!'));
+    }
+}
@@ -42,6 +42,14 @@ pub trait OffsetInput: Input {
     fn offset(&self) -> usize;
 }
 
+/// An `OffsetInput` whose offsets index into a retained buffer of raw bytes,
+/// such that the bytes consumed between two offsets can be recovered.
+pub trait RawByteInput: OffsetInput {
+    /// Returns the raw bytes between byte offsets `start` and `end`, neither
+    /// of which may exceed the current offset of this input.
+    fn raw_bytes(&self, start: usize, end: usize) -> &[u8];
+}
+
 pub trait AsInput {
     type Token;
 
@@ -113,6 +121,12 @@ impl<'a, T: 'a + Clone> OffsetInput for SliceInput<'a, T> {
     }
 }
 
+impl<'a> RawByteInput for SliceInput<'a, u8> {
+    fn raw_bytes(&self, start: usize, end: usize) -> &[u8] {
+        &self.slice[start..end]
+    }
+}
+
 impl<'a, T: 'a + Clone> AsInput for &'a [T] {
     type Token = T;
     type AsIn = SliceInput<'a, T>;
@@ -125,6 +139,104 @@ impl<'a, T: 'a + Clone> AsInput for &'a [T] {
     }
 }
 
+/// Byte input over the contents of a borrowed byte container, such as a
+/// leased `Buf<u8>` or `String`. The container remains immutably borrowed
+/// for the lifetime of the input. A chunked `BytesInput` exposes its bytes
+/// incrementally, reporting `Out` at the end of each chunk until `more` is
+/// called to make the next chunk available.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BytesInput<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+    limit: usize,
+    chunk: usize,
+}
+
+impl<'a> BytesInput<'a> {
+    /// Returns an input that exposes all of the given `bytes` as one chunk.
+    pub fn new<B: AsRef<[u8]> + ?Sized>(bytes: &'a B) -> Self {
+        let bytes = bytes.as_ref();
+        BytesInput {
+            bytes: bytes,
+            offset: 0,
+            limit: bytes.len(),
+            chunk: bytes.len(),
+        }
+    }
+
+    /// Returns an input that exposes the given `bytes` in chunks of at most
+    /// `chunk` bytes at a time.
+    pub fn chunked<B: AsRef<[u8]> + ?Sized>(bytes: &'a B, chunk: usize) -> Self {
+        let bytes = bytes.as_ref();
+        let chunk = if chunk != 0 { chunk } else { 1 };
+        BytesInput {
+            bytes: bytes,
+            offset: 0,
+            limit: if chunk < bytes.len() { chunk } else { bytes.len() },
+            chunk: chunk,
+        }
+    }
+
+    /// Returns `true` if bytes remain beyond the currently exposed chunk.
+    pub fn has_more(&self) -> bool {
+        self.limit < self.bytes.len()
+    }
+
+    /// Exposes the next chunk of bytes; returns `false` if no bytes remain.
+    pub fn more(&mut self) -> bool {
+        if self.limit < self.bytes.len() {
+            let remaining = self.bytes.len() - self.limit;
+            self.limit += if self.chunk < remaining { self.chunk } else { remaining };
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl<'a> Input for BytesInput<'a> {
+    type Token = u8;
+
+    fn head(&mut self) -> Step<u8> {
+        if self.offset < self.limit {
+            In(unsafe { *self.bytes.get_unchecked(self.offset) })
+        } else if self.offset < usize::MAX {
+            Out
+        } else {
+            Over
+        }
+    }
+
+    fn step(&mut self) {
+        if self.offset < self.limit {
+            self.offset += 1;
+        }
+    }
+
+    fn over(&mut self) {
+        self.offset = usize::MAX;
+    }
+}
+
+impl<'a> OffsetInput for BytesInput<'a> {
+    fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl<'a> RawByteInput for BytesInput<'a> {
+    fn raw_bytes(&self, start: usize, end: usize) -> &[u8] {
+        &self.bytes[start..end]
+    }
+}
+
+/// Code point that stands in for an ill-formed byte sequence; `Utf8Input`
+/// reports it as U+FFFD.
+const INVALID: u32 = 0x110000;
+
+/// Input of the chars of UTF-8 encoded bytes. Each byte that can't start a
+/// sequence, and each maximal prefix of a well-formed sequence that's cut
+/// short, decodes as a single U+FFFD replacement char.
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub struct Utf8Input<I: Input<Token=u8>> {
     input: I,
@@ -151,6 +263,16 @@ impl<I: Input<Token=u8>> Utf8Input<I> {
         self.input = input;
     }
 
+    pub fn input_mut(&mut self) -> &mut I {
+        &mut self.input
+    }
+
+    /// Returns `true` if the head char is a U+FFFD that replaces an
+    /// ill-formed byte sequence, rather than an encoded U+FFFD.
+    pub fn is_invalid(&mut self) -> bool {
+        self.head().is_in() && self.head == INVALID
+    }
+
     #[inline]
     fn byte1(&mut self) -> Step<u32> {
         if self.have >= 1 {
@@ -165,110 +287,70 @@ impl<I: Input<Token=u8>> Utf8Input<I> {
         }
     }
 
+    /// Returns the `n`th byte of the current sequence, consuming it only if
+    /// it lies between `lower` and `upper`. Returns `INVALID` if the byte is
+    /// out of range, leaving it to start the next char, or if the input has
+    /// ended. No byte outside the continuation range, and in particular no
+    /// null byte, is ever consumed as part of a multi-byte sequence.
     #[inline]
-    fn byte2(&mut self) -> Step<u32> {
-        if self.have >= 2 {
-            In(self.b2 as u32)
-        } else {
-            self.input.head().map(|c| {
-                self.input.step();
-                self.have = 2;
-                self.b2 = c;
-                c as u32
-            })
+    fn byte(&mut self, n: u8, lower: u8, upper: u8) -> Step<u32> {
+        if self.have >= n {
+            return In(if n == 2 { self.b2 } else { self.b3 } as u32);
         }
-    }
-
-    #[inline]
-    fn byte3(&mut self) -> Step<u32> {
-        if self.have >= 3 {
-            In(self.b3 as u32)
-        } else {
-            self.input.head().map(|c| {
+        match self.input.head() {
+            In(c) if c >= lower && c <= upper => {
                 self.input.step();
-                self.have = 3;
-                self.b3 = c;
-                c as u32
-            })
+                self.have = n;
+                if n == 2 {
+                    self.b2 = c;
+                } else if n == 3 {
+                    self.b3 = c;
+                }
+                In(c as u32)
+            },
+            In(_) | Over => In(INVALID),
+            Out => Out,
         }
     }
 
-    #[inline]
-    fn byte4(&mut self) -> Step<u32> {
-        debug_assert_eq!(self.have, 3);
-        self.input.head().map(|c| {
-            self.input.step();
-            self.have = 4;
-            c as u32
-        })
-    }
-
     #[inline]
     fn next(&mut self) -> Step<u32> {
-        self.byte1().and_then(|b1| {
-            self.next1(b1)
-        })
-    }
-
-    #[inline]
-    fn next1(&mut self, b1: u32) -> Step<u32> {
-        if b1 <= 0x7F { // U+0000..U+007F
-            In(b1)
-        } else if b1 >= 0xC2 && b1 <= 0xF4 {
-            self.byte2().and_then(|b2| {
-                self.next2(b1, b2)
-            })
-        } else {
-            In(0xFFFD)
+        let b1 = match self.byte1() {
+            In(b1) => b1,
+            step => return step,
+        };
+        // Bound the second byte to exclude overlong encodings, surrogates,
+        // and code points beyond U+10FFFF.
+        let (len, lower, upper) = match b1 {
+            0x00..=0x7F => return In(b1), // U+0000..U+007F
+            0xC2..=0xDF => (2, 0x80, 0xBF), // U+0080..U+07FF
+            0xE0 => (3, 0xA0, 0xBF), // U+0800..U+0FFF
+            0xE1..=0xEC | 0xEE..=0xEF => (3, 0x80, 0xBF), // U+1000..U+CFFF, U+E000..U+FFFF
+            0xED => (3, 0x80, 0x9F), // U+D000..U+D7FF
+            0xF0 => (4, 0x90, 0xBF), // U+10000..U+3FFFF
+            0xF1..=0xF3 => (4, 0x80, 0xBF), // U+40000..U+FFFFF
+            0xF4 => (4, 0x80, 0x8F), // U+100000..U+10FFFF
+            _ => return In(INVALID),
+        };
+        let b2 = match self.byte(2, lower, upper) {
+            In(b2) if b2 != INVALID => b2,
+            step => return step,
+        };
+        if len == 2 {
+            return In((b1 & 0x1F) << 6 | b2 & 0x3F);
         }
-    }
-
-    #[inline]
-    fn next2(&mut self, b1: u32, b2: u32) -> Step<u32> {
-        if b1 <= 0xDF {
-            if b2 >= 0x80 && b2 <= 0xBF { // U+0080..U+07FF
-                In((b1 & 0x1F) << 6 | b2 & 0x3F)
-            } else {
-                In(0xFFFD)
-            }
-        } else {
-            self.byte3().and_then(|b3| {
-                self.next3(b1, b2, b3)
-            })
-        }
-    }
-
-    #[inline]
-    fn next3(&mut self, b1: u32, b2: u32, b3: u32) -> Step<u32> {
-        if b1 == 0xE0 && b2 >= 0xA0 && b2 <= 0xBF ||
-           b1 == 0xED && b2 >= 0x80 && b2 <= 0x9F ||
-           b1 >= 0xE1 && b1 <= 0xEF && b2 >= 0x80 && b2 <= 0xBF {
-            if b3 >= 0x80 && b3 <= 0xBF { // U+0800..U+FFFF
-                In((b1 & 0x0F) << 12 | (b2 & 0x3F) << 6 | b3 & 0x3F)
-            } else {
-                In(0xFFFD)
-            }
-        } else {
-            self.byte4().and_then(|b4| {
-                self.next4(b1, b2, b3, b4)
-            })
-        }
-    }
-
-    #[inline]
-    fn next4(&mut self, b1: u32, b2: u32, b3: u32, b4: u32) -> Step<u32> {
-        if (b1 == 0xF0 && b2 >= 0x90 && b2 <= 0xBF ||
-            b1 >= 0xF1 && b1 <= 0xF3 && b2 >= 0x80 && b2 <= 0xBF ||
-            b1 == 0xF4 && b2 >= 0x80 && b2 <= 0x8F) &&
-           b3 >= 0x80 && b3 <= 0xBF {
-            if b4 >= 0x80 && b4 <= 0xBF { // U+10000..U+10FFFF
-                In((b1 & 0x07) << 18 | (b2 & 0x3F) << 12 | (b3 & 0x3F) << 6 | b4 & 0x3F)
-            } else {
-                In(0xFFFD)
-            }
-        } else {
-            In(0xFFFD)
+        let b3 = match self.byte(3, 0x80, 0xBF) {
+            In(b3) if b3 != INVALID => b3,
+            step => return step,
+        };
+        if len == 3 {
+            return In((b1 & 0x0F) << 12 | (b2 & 0x3F) << 6 | b3 & 0x3F);
         }
+        let b4 = match self.byte(4, 0x80, 0xBF) {
+            In(b4) if b4 != INVALID => b4,
+            step => return step,
+        };
+        In((b1 & 0x07) << 18 | (b2 & 0x3F) << 12 | (b3 & 0x3F) << 6 | b4 & 0x3F)
     }
 }
 
@@ -285,7 +367,7 @@ impl<I: Input<Token=u8>> Input for Utf8Input<I> {
                 Over => return Over,
             };
         }
-        In(unsafe { char::from_u32_unchecked(self.head) })
+        In(unsafe { char::from_u32_unchecked(if self.head != INVALID { self.head } else { 0xFFFD }) })
     }
 
     fn step(&mut self) {
@@ -300,7 +382,18 @@ impl<I: Input<Token=u8>> Input for Utf8Input<I> {
 
 impl<I: OffsetInput<Token=u8>> OffsetInput for Utf8Input<I> {
     fn offset(&self) -> usize {
-        self.input.offset() - self.have as usize
+        let offset = self.input.offset();
+        if offset != usize::MAX {
+            offset - self.have as usize
+        } else {
+            usize::MAX
+        }
+    }
+}
+
+impl<I: RawByteInput<Token=u8>> RawByteInput for Utf8Input<I> {
+    fn raw_bytes(&self, start: usize, end: usize) -> &[u8] {
+        self.input.raw_bytes(start, end)
     }
 }
 
@@ -333,6 +426,24 @@ mod tests {
         assert_eq!(input.head(), Out);
     }
 
+    #[test]
+    fn test_bytes_input() {
+        let bytes = [1u8, 2, 3];
+        let mut input = BytesInput::chunked(&bytes[..], 2);
+        assert_eq!(input.head(), In(1));
+        input.step();
+        assert_eq!(input.head(), In(2));
+        input.step();
+        assert_eq!(input.head(), Out);
+        assert!(input.more());
+        assert_eq!(input.head(), In(3));
+        input.step();
+        assert_eq!(input.head(), Out);
+        assert!(!input.more());
+        input.over();
+        assert_eq!(input.head(), Over);
+    }
+
     #[test]
     fn test_utf8_input() {
         let mut input = "\0ÀÖØöø˿ͰͽͿ῿⁰↏Ⰰ⿯、퟿豈﷏ﷰ𐀀󯿿".as_input();
@@ -382,4 +493,33 @@ mod tests {
         input.step();
         assert_eq!(input.head(), Out);
     }
+
+    #[test]
+    fn test_utf8_input_invalid() {
+        let bytes = b"\xE2\x82\0\xEF\xBF\xBD\xED\xA0\x80\xC0a";
+        let mut input = Utf8Input::new((&bytes[..]).as_input());
+        // A truncated sequence doesn't consume the null byte that cuts it short.
+        assert_eq!(input.head(), In('\u{FFFD}'));
+        assert!(input.is_invalid());
+        input.step();
+        assert_eq!(input.head(), In('\0'));
+        assert!(!input.is_invalid());
+        input.step();
+        // An encoded replacement char is valid.
+        assert_eq!(input.head(), In('\u{FFFD}'));
+        assert!(!input.is_invalid());
+        input.step();
+        // Encoded surrogates are ill-formed, as are lone continuation bytes,
+        // and overlong leading bytes.
+        for _ in 0..4 {
+            assert_eq!(input.head(), In('\u{FFFD}'));
+            assert!(input.is_invalid());
+            input.step();
+        }
+        assert_eq!(input.head(), In('a'));
+        input.step();
+        assert_eq!(input.head(), Out);
+        input.over();
+        assert_eq!(input.head(), Over);
+    }
 }
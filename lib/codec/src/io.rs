@@ -0,0 +1,853 @@
+use crate::step::{Step, In, Out, Over};
+use crate::then::{Then, Cont, Done, Fail};
+use crate::input::{Input, OffsetInput, RawByteInput, Utf8Input};
+use crate::output::Output;
+use crate::decoder::Decoder;
+
+/// A `std::io`-free source of bytes, analogous to `Read`.
+pub trait ByteSource {
+    type Error;
+
+    /// Copies up to `buf.len()` bytes from this source into `buf`, returning
+    /// the number of bytes read. Returns `Ok(0)` once the source is exhausted.
+    /// May return fewer bytes than requested before the source is exhausted.
+    fn fill(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error>;
+}
+
+/// A `std::io`-free sink of bytes, analogous to `Write`.
+pub trait ByteSink {
+    type Error;
+
+    /// Writes up to `buf.len()` bytes from `buf` into this sink, returning
+    /// the number of bytes written. Returns `Ok(0)` for a non-empty `buf`
+    /// if the sink can accept no more bytes.
+    fn drain(&mut self, buf: &[u8]) -> Result<usize, Self::Error>;
+}
+
+/// Error produced when draining bytes into a `ByteSink`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SinkError<E> {
+    /// The sink failed.
+    Drain(E),
+    /// The sink accepted no bytes.
+    WriteZero,
+}
+
+/// Error produced by `pump`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PumpError<S, D> {
+    /// The byte source failed.
+    Source(S),
+    /// The decoder failed.
+    Decode(D),
+    /// The decoder stopped consuming input before completing.
+    Stalled,
+}
+
+/// Error produced by a `Tee` decoder.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TeeError<D, S> {
+    /// The wrapped decoder failed.
+    Decode(D),
+    /// The side sink failed.
+    Side(SinkError<S>),
+}
+
+/// Drains all of `buf` into `sink`, retrying partial drains.
+pub fn drain_all<S: ByteSink>(sink: &mut S, mut buf: &[u8]) -> Result<(), SinkError<S::Error>> {
+    while !buf.is_empty() {
+        match sink.drain(buf) {
+            Ok(0) => return Err(SinkError::WriteZero),
+            Ok(n) => buf = &buf[n..],
+            Err(error) => return Err(SinkError::Drain(error)),
+        }
+    }
+    Ok(())
+}
+
+impl<'a> ByteSource for &'a [u8] {
+    type Error = ();
+
+    fn fill(&mut self, buf: &mut [u8]) -> Result<usize, ()> {
+        let n = if buf.len() < self.len() { buf.len() } else { self.len() };
+        buf[..n].copy_from_slice(&self[..n]);
+        *self = &self[n..];
+        Ok(n)
+    }
+}
+
+/// Byte sink that appends drained bytes to any `Extend<u8>` container,
+/// such as a `MutBuf<u8>`.
+pub struct ExtendSink<E> {
+    pub inner: E,
+}
+
+impl<E: Extend<u8>> ExtendSink<E> {
+    pub fn new(inner: E) -> Self {
+        Self {
+            inner: inner,
+        }
+    }
+}
+
+impl<E: Extend<u8>> ByteSink for ExtendSink<E> {
+    type Error = ();
+
+    fn drain(&mut self, buf: &[u8]) -> Result<usize, ()> {
+        self.inner.extend(buf.iter().cloned());
+        Ok(buf.len())
+    }
+}
+
+/// An `Input` that reports errors encountered while pulling its tokens.
+pub trait FallibleInput: Input {
+    type Error;
+
+    /// Returns, and clears, the error that stalled this input, if any.
+    fn take_error(&mut self) -> Option<Self::Error>;
+}
+
+/// Byte input that pulls from a `ByteSource` into a caller-provided buffer,
+/// refilling the buffer on demand. Reports `Over` once the source is
+/// exhausted, and `Out` if the source fails; the source error can then be
+/// retrieved with `take_error`.
+pub struct SourceInput<'b, S: ByteSource> {
+    pub source: S,
+    buf: &'b mut [u8],
+    offset: usize,
+    len: usize,
+    over: bool,
+    error: Option<S::Error>,
+}
+
+impl<'b, S: ByteSource> SourceInput<'b, S> {
+    pub fn new(source: S, buf: &'b mut [u8]) -> Self {
+        debug_assert!(!buf.is_empty());
+        Self {
+            source: source,
+            buf: buf,
+            offset: 0,
+            len: 0,
+            over: false,
+            error: None,
+        }
+    }
+}
+
+impl<'b, S: ByteSource> Input for SourceInput<'b, S> {
+    type Token = u8;
+
+    fn head(&mut self) -> Step<u8> {
+        if self.offset < self.len {
+            return In(unsafe { *self.buf.get_unchecked(self.offset) });
+        } else if self.over {
+            return Over;
+        } else if self.error.is_some() {
+            return Out;
+        }
+        match self.source.fill(self.buf) {
+            Ok(0) => {
+                self.over = true;
+                Over
+            },
+            Ok(n) => {
+                self.offset = 0;
+                self.len = n;
+                In(self.buf[0])
+            },
+            Err(error) => {
+                self.error = Some(error);
+                Out
+            },
+        }
+    }
+
+    fn step(&mut self) {
+        if self.offset < self.len {
+            self.offset += 1;
+        }
+    }
+
+    fn over(&mut self) {
+        self.offset = 0;
+        self.len = 0;
+        self.over = true;
+    }
+}
+
+impl<'b, S: ByteSource> FallibleInput for SourceInput<'b, S> {
+    type Error = S::Error;
+
+    fn take_error(&mut self) -> Option<S::Error> {
+        self.error.take()
+    }
+}
+
+impl<I: FallibleInput<Token=u8>> FallibleInput for Utf8Input<I> {
+    type Error = I::Error;
+
+    fn take_error(&mut self) -> Option<I::Error> {
+        self.input_mut().take_error()
+    }
+}
+
+/// Byte output that buffers pushed bytes in a caller-provided buffer, and
+/// drains them into a `ByteSink` whenever the buffer fills, and once more
+/// when the output is taken. Taking the output always succeeds, yielding
+/// either the sink, or the error that stopped the draining. Bytes pushed
+/// after a sink error are discarded.
+pub struct SinkOutput<'b, S: ByteSink> {
+    sink: S,
+    buf: &'b mut [u8],
+    len: usize,
+    error: Option<SinkError<S::Error>>,
+}
+
+impl<'b, S: ByteSink> SinkOutput<'b, S> {
+    pub fn new(sink: S, buf: &'b mut [u8]) -> Self {
+        debug_assert!(!buf.is_empty());
+        Self {
+            sink: sink,
+            buf: buf,
+            len: 0,
+            error: None,
+        }
+    }
+
+    /// Drains all buffered bytes into the sink, retrying partial drains.
+    fn flush(&mut self) {
+        let mut start = 0;
+        while start < self.len && self.error.is_none() {
+            match self.sink.drain(&self.buf[start..self.len]) {
+                Ok(0) => self.error = Some(SinkError::WriteZero),
+                Ok(n) => start += n,
+                Err(error) => self.error = Some(SinkError::Drain(error)),
+            }
+        }
+        self.len = 0;
+    }
+}
+
+impl<'b, S: ByteSink> Output for SinkOutput<'b, S> {
+    type Token = u8;
+    type Out = Result<S, SinkError<S::Error>>;
+    type Err = ();
+
+    fn is_full(&self) -> bool {
+        self.error.is_some()
+    }
+
+    fn push(&mut self, b: u8) {
+        if self.error.is_some() {
+            return;
+        }
+        self.buf[self.len] = b;
+        self.len += 1;
+        if self.len == self.buf.len() {
+            self.flush();
+        }
+    }
+
+    fn take_out(mut self) -> Result<Result<S, SinkError<S::Error>>, ()> {
+        self.flush();
+        Ok(match self.error {
+            None => Ok(self.sink),
+            Some(error) => Err(error),
+        })
+    }
+}
+
+/// Byte output that applies backpressure from a `ByteSink` that can fill
+/// up, such as a bounded buffer drained by another task. Pushed bytes are
+/// staged in a caller-provided buffer, which must have room for the most
+/// bytes a decoder pushes per input token, and are drained into the sink
+/// whenever a decoder checks whether the output is blocked. The output is
+/// blocked while the sink, by returning `Ok(0)`, refuses staged bytes;
+/// decoders that support backpressure then suspend, and resume with no
+/// loss of output once the caller has made room in the sink. Taking the
+/// output yields the sink, or `SinkError::WriteZero` if staged bytes remain
+/// that the sink still refuses. The base64, percent, and chunked decoders
+/// support backpressure. The output is full while the staging buffer is
+/// full; a decoder that pushes to a full output anyway fails the output
+/// with `SinkError::WriteZero`, rather than overrunning the buffer.
+pub struct BackpressureOutput<'b, S: ByteSink> {
+    sink: S,
+    buf: &'b mut [u8],
+    /// Offset of the first staged byte not yet drained into the sink.
+    start: usize,
+    len: usize,
+    error: Option<SinkError<S::Error>>,
+}
+
+impl<'b, S: ByteSink> BackpressureOutput<'b, S> {
+    pub fn new(sink: S, buf: &'b mut [u8]) -> Self {
+        debug_assert!(!buf.is_empty());
+        Self {
+            sink: sink,
+            buf: buf,
+            start: 0,
+            len: 0,
+            error: None,
+        }
+    }
+
+    /// Returns the number of staged bytes not yet accepted by the sink.
+    pub fn pending(&self) -> usize {
+        self.len - self.start
+    }
+
+    pub fn sink(&self) -> &S {
+        &self.sink
+    }
+
+    /// Returns a mutable reference to the sink, through which the caller
+    /// can make room in the sink while the output is blocked.
+    pub fn sink_mut(&mut self) -> &mut S {
+        &mut self.sink
+    }
+
+    /// Drains as many staged bytes as the sink accepts; returns `true` if
+    /// all staged bytes have been drained.
+    fn drain(&mut self) -> bool {
+        while self.start < self.len && self.error.is_none() {
+            match self.sink.drain(&self.buf[self.start..self.len]) {
+                Ok(0) => return false,
+                Ok(n) => self.start += n,
+                Err(error) => self.error = Some(SinkError::Drain(error)),
+            }
+        }
+        self.start = 0;
+        self.len = 0;
+        true
+    }
+
+    /// Drains staged bytes into the sink, and moves any the sink refuses to
+    /// the front of the staging buffer; returns `true` if there's room to
+    /// stage another byte.
+    fn make_room(&mut self) -> bool {
+        if !self.drain() && self.start > 0 {
+            self.buf.copy_within(self.start..self.len, 0);
+            self.len -= self.start;
+            self.start = 0;
+        }
+        self.len < self.buf.len()
+    }
+}
+
+impl<'b, S: ByteSink> Output for BackpressureOutput<'b, S> {
+    type Token = u8;
+    type Out = Result<S, SinkError<S::Error>>;
+    type Err = ();
+
+    fn is_full(&self) -> bool {
+        self.error.is_some() || self.len == self.buf.len()
+    }
+
+    fn is_blocked(&mut self) -> bool {
+        !self.drain()
+    }
+
+    fn push(&mut self, b: u8) {
+        if self.error.is_some() {
+            return;
+        }
+        if self.len == self.buf.len() && !self.make_room() {
+            // The decoder ignored a full output; refuse the byte.
+            self.error = Some(SinkError::WriteZero);
+            return;
+        }
+        self.buf[self.len] = b;
+        self.len += 1;
+        if self.len == self.buf.len() {
+            self.make_room();
+        }
+    }
+
+    fn take_out(mut self) -> Result<Result<S, SinkError<S::Error>>, ()> {
+        if !self.drain() {
+            self.error = Some(SinkError::WriteZero);
+        }
+        Ok(match self.error {
+            None => Ok(self.sink),
+            Some(error) => Err(error),
+        })
+    }
+}
+
+/// Decoder combinator that forwards the raw bytes consumed by a wrapped
+/// decoder to a side sink. Only the bytes actually consumed by each decode
+/// step are forwarded, even if the decoder suspends mid-chunk. The bytes of
+/// a step are known only once the wrapped decoder returns, so they reach
+/// the side sink before the step's outcome is processed: before decoded
+/// output is returned, and before a decode error is reported. Returned by
+/// `Decoder::tee`.
+pub struct Tee<D, S> {
+    pub decoder: D,
+    pub side: S,
+}
+
+impl<D: Decoder, S: ByteSink> Tee<D, S> where D::Input: RawByteInput {
+    pub fn new(decoder: D, side: S) -> Self {
+        Self {
+            decoder: decoder,
+            side: side,
+        }
+    }
+}
+
+impl<D: Decoder, S: ByteSink> Decoder for Tee<D, S> where D::Input: RawByteInput {
+    type Input = D::Input;
+    type Output = (D::Output, S);
+    type Error = TeeError<D::Error, S::Error>;
+
+    fn decode(mut self, input: &mut D::Input) -> Then<Self, (D::Output, S), TeeError<D::Error, S::Error>> {
+        let start = input.offset();
+        let then = self.decoder.decode(input);
+        let end = input.offset();
+        // Inputs that are over report an offset of `usize::MAX`, which isn't
+        // a position in their raw bytes. Only the caller marks an input as
+        // over, between steps, once its bytes have run out; so a step that
+        // ends on an input that's over started on one, and consumed nothing.
+        if end > start && end != usize::MAX {
+            if let Err(error) = drain_all(&mut self.side, input.raw_bytes(start, end)) {
+                return Fail(TeeError::Side(error));
+            }
+        }
+        // Process the step only after its bytes have been forwarded.
+        match then {
+            Cont(next) => {
+                self.decoder = next;
+                Cont(self)
+            },
+            Done(output) => Done((output, self.side)),
+            Fail(error) => Fail(TeeError::Decode(error)),
+        }
+    }
+
+    fn reset(&mut self) {
+        self.decoder.reset();
+    }
+}
+
+/// Runs `decoder` to completion, pulling its entire input from a fallible
+/// input, such as a `SourceInput`.
+pub fn pump<D>(mut decoder: D, input: &mut D::Input)
+    -> Result<D::Output, PumpError<<D::Input as FallibleInput>::Error, D::Error>>
+    where D: Decoder, D::Input: FallibleInput {
+    loop {
+        match decoder.decode(input) {
+            Done(output) => return Ok(output),
+            Fail(error) => return Err(PumpError::Decode(error)),
+            Cont(next) => {
+                if let Some(error) = input.take_error() {
+                    return Err(PumpError::Source(error));
+                }
+                if input.is_in() {
+                    return Err(PumpError::Stalled);
+                }
+                decoder = next;
+            },
+        }
+    }
+}
+
+/// Iterator that decodes successive items from a fallible input, pulling
+/// more input as needed. Returned by `Decoder::into_item_iter`.
+pub struct DecodeIter<D: Decoder> where D::Input: FallibleInput {
+    decoder: D,
+    pub input: D::Input,
+    done: bool,
+}
+
+impl<D: Decoder + Clone> DecodeIter<D> where D::Input: FallibleInput {
+    pub fn new(decoder: D, input: D::Input) -> Self {
+        Self {
+            decoder: decoder,
+            input: input,
+            done: false,
+        }
+    }
+
+    fn fail(&mut self, error: PumpError<<D::Input as FallibleInput>::Error, D::Error>)
+        -> Option<Result<D::Output, PumpError<<D::Input as FallibleInput>::Error, D::Error>>> {
+        self.done = true;
+        Some(Err(error))
+    }
+}
+
+impl<D: Decoder + Clone> Iterator for DecodeIter<D> where D::Input: FallibleInput {
+    type Item = Result<D::Output, PumpError<<D::Input as FallibleInput>::Error, D::Error>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        // Check for the end of input at the item boundary.
+        if !self.input.is_in() {
+            if let Some(error) = self.input.take_error() {
+                return self.fail(PumpError::Source(error));
+            } else if self.input.is_over() {
+                self.done = true;
+                return None;
+            }
+        }
+        let mut decoder = self.decoder.clone();
+        loop {
+            match decoder.decode(&mut self.input) {
+                Done(item) => return Some(Ok(item)),
+                Fail(error) => return self.fail(PumpError::Decode(error)),
+                Cont(next) => {
+                    if let Some(error) = self.input.take_error() {
+                        return self.fail(PumpError::Source(error));
+                    }
+                    if self.input.is_in() {
+                        return self.fail(PumpError::Stalled);
+                    }
+                    decoder = next;
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::base64::{Base64Decoder, Base64Error};
+    use crate::percent::PercentDecoder;
+    use crate::deflate::DeflateDecoder;
+    use crate::input::{AsInput, BytesInput};
+    use crate::output::SliceOutput;
+    use tg_mem::block::Block;
+    use tg_mem::alloc::Pack;
+    use tg_mem::lease::MutBuf;
+    use super::*;
+
+    /// Source that returns at most `chunk` bytes per fill.
+    struct TrickleSource<'a> {
+        bytes: &'a [u8],
+        chunk: usize,
+    }
+
+    impl<'a> ByteSource for TrickleSource<'a> {
+        type Error = ();
+
+        fn fill(&mut self, buf: &mut [u8]) -> Result<usize, ()> {
+            let n = if self.chunk < buf.len() { self.chunk } else { buf.len() };
+            (&mut self.bytes).fill(&mut buf[..n])
+        }
+    }
+
+    /// Fixed capacity byte container.
+    struct Bytes {
+        array: [u8; 64],
+        len: usize,
+    }
+
+    impl Extend<u8> for Bytes {
+        fn extend<I: IntoIterator<Item=u8>>(&mut self, iter: I) {
+            for b in iter {
+                self.array[self.len] = b;
+                self.len += 1;
+            }
+        }
+    }
+
+    /// Sink that accepts a single byte per drain.
+    struct ByteAtATime<'a> {
+        bytes: &'a mut Bytes,
+    }
+
+    impl<'a> ByteSink for ByteAtATime<'a> {
+        type Error = ();
+
+        fn drain(&mut self, buf: &[u8]) -> Result<usize, ()> {
+            if self.bytes.len < 4 {
+                self.bytes.extend(buf[..1].iter().cloned());
+                Ok(1)
+            } else {
+                Ok(0)
+            }
+        }
+    }
+
+    #[test]
+    fn test_pump_base64_source_to_sink() {
+        static mut TEST_AREA: [u8; 4096] = [0; 4096];
+        let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+
+        let source = TrickleSource { bytes: b"QUJDRGFiY2QxMi8r", chunk: 3 };
+        let mut input_buf = [0u8; 8];
+        let mut input = Utf8Input::new(SourceInput::new(source, &mut input_buf));
+        let mut output_buf = [0u8; 5];
+        let sink = ExtendSink::new(MutBuf::<u8>::hold_cap(pack, 16));
+        let decoder = Base64Decoder::new(SinkOutput::new(sink, &mut output_buf));
+        let sink = pump(decoder, &mut input).unwrap().unwrap();
+        assert_eq!(&sink.inner[..], b"ABCDabcd12/+");
+    }
+
+    #[test]
+    fn test_pump_errors() {
+        let mut input_buf = [0u8; 8];
+        let mut input = Utf8Input::new(SourceInput::new(&b"QUJ"[..], &mut input_buf));
+        let mut output_buf = [0u8; 8];
+        let sink = ExtendSink::new(Bytes { array: [0; 64], len: 0 });
+        let decoder = Base64Decoder::new(SinkOutput::new(sink, &mut output_buf));
+        match pump(decoder, &mut input) {
+            Err(PumpError::Decode(error)) => assert_eq!(error, Base64Error::Unpadded),
+            _ => panic!(),
+        }
+
+        let mut input_buf = [0u8; 8];
+        let mut input = Utf8Input::new(SourceInput::new(&b"QUJDRGFi"[..], &mut input_buf));
+        let mut bytes = Bytes { array: [0; 64], len: 0 };
+        let mut output_buf = [0u8; 2];
+        let decoder = Base64Decoder::new(SinkOutput::new(ByteAtATime { bytes: &mut bytes }, &mut output_buf));
+        match pump(decoder, &mut input).unwrap() {
+            Err(error) => assert_eq!(error, SinkError::WriteZero),
+            _ => panic!(),
+        }
+        assert_eq!(&bytes.array[..bytes.len], b"ABCD");
+    }
+
+    #[test]
+    fn test_pump_utf8_invalid() {
+        static mut TEST_AREA: [u8; 4096] = [0; 4096];
+        let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+
+        // The truncated sequence straddles two reads, and is cut short by a digit.
+        let source = TrickleSource { bytes: b"QUJD\xE2\x82RGFi", chunk: 5 };
+        let mut input_buf = [0u8; 8];
+        let mut input = Utf8Input::new(SourceInput::new(source, &mut input_buf));
+        let mut output_buf = [0u8; 5];
+        let sink = ExtendSink::new(MutBuf::<u8>::hold_cap(pack, 16));
+        let decoder = Base64Decoder::new(SinkOutput::new(sink, &mut output_buf));
+        let sink = pump(decoder, &mut input).unwrap().unwrap();
+        assert_eq!(&sink.inner[..], b"ABC");
+
+        // The decoder stops at the replacement char, which doesn't swallow
+        // the digit that follows it.
+        assert_eq!(input.head(), In('\u{FFFD}'));
+        assert!(input.is_invalid());
+        input.step();
+        let sink = ExtendSink::new(MutBuf::<u8>::hold_cap(pack, 16));
+        let decoder = Base64Decoder::new(SinkOutput::new(sink, &mut output_buf));
+        let sink = pump(decoder, &mut input).unwrap().unwrap();
+        assert_eq!(&sink.inner[..], b"Dab");
+    }
+
+    /// Sink that accepts at most `quota` bytes before it's full, until the
+    /// caller grants it more quota.
+    struct QuotaSink<'a> {
+        bytes: &'a mut Bytes,
+        quota: usize,
+    }
+
+    impl<'a> ByteSink for QuotaSink<'a> {
+        type Error = ();
+
+        fn drain(&mut self, buf: &[u8]) -> Result<usize, ()> {
+            let n = if self.quota < buf.len() { self.quota } else { buf.len() };
+            self.bytes.extend(buf[..n].iter().cloned());
+            self.quota -= n;
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn test_backpressure_base64_resumes() {
+        let encoded = b"QUJDRGFiY2QxMi8rAA==";
+        for &quota in [1, 2, 5].iter() {
+            let mut bytes = Bytes { array: [0; 64], len: 0 };
+            let mut input = Utf8Input::new(BytesInput::new(&encoded[..]));
+            let mut output_buf = [0u8; 3];
+            let sink = QuotaSink { bytes: &mut bytes, quota: quota };
+            let mut decoder = Base64Decoder::new(BackpressureOutput::new(sink, &mut output_buf));
+            let mut rounds = 0;
+            let sink = loop {
+                match decoder.decode(&mut input) {
+                    Cont(next) => {
+                        decoder = next;
+                        if decoder.output.sink().quota == 0 {
+                            // Suspended with the sink full; make room.
+                            decoder.output.sink_mut().quota = quota;
+                            rounds += 1;
+                        } else {
+                            assert!(!input.input_mut().more());
+                            input.over();
+                        }
+                    },
+                    Done(sink) => break sink.unwrap(),
+                    Fail(error) => panic!("{:?}", error),
+                }
+            };
+            assert_eq!(&sink.bytes.array[..sink.bytes.len], b"ABCDabcd12/+\0");
+            // The sink fills once per quota of bytes, except after the last.
+            assert_eq!(rounds, (13 - 1) / quota);
+        }
+    }
+
+    #[test]
+    fn test_backpressure_percent_chunked_input() {
+        let encoded = b"a%20b%2Fc%41%42d";
+        let mut bytes = Bytes { array: [0; 64], len: 0 };
+        let mut input = BytesInput::chunked(&encoded[..], 3);
+        let mut output_buf = [0u8; 1];
+        let sink = QuotaSink { bytes: &mut bytes, quota: 2 };
+        let mut decoder = PercentDecoder::new(BackpressureOutput::new(sink, &mut output_buf));
+        let sink = loop {
+            match decoder.decode(&mut input) {
+                Cont(next) => {
+                    decoder = next;
+                    if decoder.output.sink().quota == 0 {
+                        decoder.output.sink_mut().quota = 2;
+                    } else if !input.more() {
+                        input.over();
+                    }
+                },
+                Done(sink) => break sink.unwrap(),
+                Fail(error) => panic!("{:?}", error),
+            }
+        };
+        assert_eq!(&sink.bytes.array[..sink.bytes.len], b"a b/cABd");
+    }
+
+    #[test]
+    fn test_backpressure_take_out_blocked() {
+        let mut bytes = Bytes { array: [0; 64], len: 0 };
+        let mut output_buf = [0u8; 4];
+        let mut output = BackpressureOutput::new(QuotaSink { bytes: &mut bytes, quota: 1 }, &mut output_buf);
+        output.push(b'x');
+        output.push(b'y');
+        assert!(output.is_blocked());
+        assert_eq!(output.pending(), 1);
+        output.sink_mut().quota = 1;
+        assert!(!output.is_blocked());
+        output.push(b'z');
+        match output.take_out().unwrap() {
+            Err(error) => assert_eq!(error, SinkError::WriteZero),
+            Ok(_) => panic!(),
+        }
+        assert_eq!(&bytes.array[..bytes.len], b"xy");
+    }
+
+    #[test]
+    fn test_backpressure_unsupported_decoder() {
+        // DEFLATE ignores backpressure, and pushes a stored block's bytes
+        // without checking whether the output is blocked.
+        let encoded = [0x01, 0x05, 0x00, 0xFA, 0xFF, b'h', b'e', b'l', b'l', b'o'];
+        let mut window = [0u8; 16];
+        let mut bytes = Bytes { array: [0; 64], len: 0 };
+        let mut output_buf = [0u8; 2];
+        // Staged bytes are flushed whenever the staging buffer fills.
+        let output = BackpressureOutput::new(QuotaSink { bytes: &mut bytes, quota: 64 }, &mut output_buf);
+        let decoder = DeflateDecoder::new(output, &mut window);
+        assert!(decoder.consume(&mut (&encoded[..]).as_input()).unwrap().is_ok());
+        assert_eq!(&bytes.array[..bytes.len], b"hello");
+
+        // Bytes pushed while the sink refuses them fail the output.
+        let mut bytes = Bytes { array: [0; 64], len: 0 };
+        let mut output = BackpressureOutput::new(QuotaSink { bytes: &mut bytes, quota: 2 }, &mut output_buf);
+        for &b in b"hel".iter() {
+            output.push(b);
+        }
+        assert!(!output.is_full());
+        output.push(b'l');
+        assert!(output.is_full());
+        output.push(b'o');
+        match output.take_out().unwrap() {
+            Err(error) => assert_eq!(error, SinkError::WriteZero),
+            Ok(_) => panic!(),
+        }
+        assert_eq!(&bytes.array[..bytes.len], b"he");
+    }
+
+    #[test]
+    fn test_tee_base64_consumed_bytes() {
+        static mut TEST_AREA: [u8; 4096] = [0; 4096];
+        let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+
+        let encoded = b"QUJDRGFiY2QxMi8r";
+        let mut input = Utf8Input::new(BytesInput::chunked(&encoded[..], 5));
+        let mut output = [0u8; 16];
+        let side = ExtendSink::new(MutBuf::<u8>::hold_cap(pack, 16));
+        let mut decoder = Base64Decoder::new(SliceOutput::new(&mut output)).tee(side);
+        let (decoded, side) = loop {
+            match decoder.decode(&mut input) {
+                Cont(next) => {
+                    decoder = next;
+                    let consumed = input.offset();
+                    assert_eq!(&decoder.side.inner[..], &encoded[..consumed]);
+                    if !input.input_mut().more() {
+                        input.over();
+                    }
+                },
+                Done(done) => break done,
+                Fail(_) => panic!(),
+            }
+        };
+        assert_eq!(&decoded[..], &b"ABCDabcd12/+"[..]);
+        assert_eq!(&side.inner[..], &encoded[..]);
+    }
+
+    /// Decodes a single big-endian `u32`.
+    struct U32Decoder<I> {
+        value: u32,
+        count: usize,
+        input: core::marker::PhantomData<I>,
+    }
+
+    impl<I> Clone for U32Decoder<I> {
+        fn clone(&self) -> Self {
+            U32Decoder { value: self.value, count: self.count, input: core::marker::PhantomData }
+        }
+    }
+
+    impl<I: Input<Token=u8>> Decoder for U32Decoder<I> {
+        type Input = I;
+        type Output = u32;
+        type Error = ();
+
+        fn decode(mut self, input: &mut I) -> Then<Self, u32, ()> {
+            while self.count < 4 {
+                match input.head() {
+                    In(b) => {
+                        input.step();
+                        self.value = self.value << 8 | b as u32;
+                        self.count += 1;
+                    },
+                    Over => return Fail(()),
+                    Out => return Cont(self),
+                }
+            }
+            Done(self.value)
+        }
+
+        fn reset(&mut self) {
+            self.value = 0;
+            self.count = 0;
+        }
+    }
+
+    #[test]
+    fn test_decode_item_iter() {
+        let bytes = [0, 0, 0, 1, 0, 0, 1, 0, 0xDE, 0xAD, 0xBE, 0xEF];
+        let source = TrickleSource { bytes: &bytes, chunk: 3 };
+        let mut input_buf = [0u8; 8];
+        let input = SourceInput::new(source, &mut input_buf);
+        let decoder = U32Decoder { value: 0, count: 0, input: core::marker::PhantomData };
+        let mut items = decoder.into_item_iter(input);
+        assert_eq!(items.next(), Some(Ok(1)));
+        assert_eq!(items.next(), Some(Ok(256)));
+        assert_eq!(items.next(), Some(Ok(0xDEADBEEF)));
+        assert_eq!(items.next(), None);
+        assert_eq!(items.next(), None);
+
+        let mut input_buf = [0u8; 8];
+        let input = SourceInput::new(&bytes[..6], &mut input_buf);
+        let decoder = U32Decoder { value: 0, count: 0, input: core::marker::PhantomData };
+        let mut items = decoder.into_item_iter(input);
+        assert_eq!(items.next(), Some(Ok(1)));
+        assert_eq!(items.next(), Some(Err(PumpError::Decode(()))));
+        assert_eq!(items.next(), None);
+    }
+}
@@ -4,10 +4,24 @@
 
 #![feature(const_fn)]
 
+extern crate tg_mem;
+
 pub mod step;
 pub mod then;
 pub mod input;
 pub mod output;
 pub mod decoder;
 pub mod encoder;
+pub mod bits;
 pub mod base64;
+pub mod deflate;
+pub mod percent;
+pub mod lines;
+pub mod io;
+pub mod msgpack;
+pub mod z85;
+pub mod hex;
+pub mod utf16;
+pub mod chunked;
+pub mod cstr;
+pub mod transcode;
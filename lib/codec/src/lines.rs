@@ -0,0 +1,176 @@
+use core::fmt;
+use core::marker::PhantomData;
+use crate::step::{In, Out, Over};
+use crate::then::{Then, Cont, Done, Fail};
+use crate::input::Input;
+use crate::output::Output;
+use crate::decoder::Decoder;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LineError {
+    /// A line exceeds the maximum line length, or the capacity of the output.
+    TooLong,
+}
+
+/// Incremental line decoder. Decodes a single line, excluding its `\n`
+/// terminator, into a caller-provided output, buffering partial lines across
+/// input chunks. Completes with `None` once the input is exhausted, and with
+/// the final unterminated line, if non-empty, just before that. Decode each
+/// successive line with a new decoder, reusing the same line buffer.
+pub struct LineDecoder<I: Input<Token=u8>, O: Output<Token=u8>> {
+    pub output: O,
+    len: usize,
+    max_len: usize,
+    strip_cr: bool,
+    state: u32,
+    input: PhantomData<I>,
+}
+
+impl<I, O> LineDecoder<I, O> where I: Input<Token=u8>, O: Output<Token=u8> {
+    pub fn new(output: O) -> Self {
+        Self {
+            output: output,
+            len: 0,
+            max_len: usize::MAX,
+            strip_cr: true,
+            state: 1,
+            input: PhantomData,
+        }
+    }
+
+    /// Fails lines longer than `max_len` bytes with `LineError::TooLong`.
+    pub fn max_len(mut self, max_len: usize) -> Self {
+        self.max_len = max_len;
+        self
+    }
+
+    /// Determines whether or not a `\r` preceding a `\n` is stripped from
+    /// the decoded line; enabled by default.
+    pub fn strip_cr(mut self, strip_cr: bool) -> Self {
+        self.strip_cr = strip_cr;
+        self
+    }
+
+    /// Appends a byte to the current line; returns `false` if the line is
+    /// too long.
+    #[inline]
+    fn push(&mut self, b: u8) -> bool {
+        if self.len < self.max_len && !self.output.is_full() {
+            self.output.push(b);
+            self.len += 1;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl<I, O> Decoder for LineDecoder<I, O>
+    where I: Input<Token=u8>,
+          O: Output<Token=u8>,
+          O::Err: fmt::Debug {
+
+    type Input = I;
+    type Output = Option<O::Out>;
+    type Error = LineError;
+
+    fn decode(mut self, input: &mut I) -> Then<Self, Option<O::Out>, LineError> {
+        loop {
+            match self.state {
+                1 => { // line bytes
+                    match input.head() {
+                        In(b'\n') => {
+                            input.step();
+                            return Done(Some(self.output.take_out().unwrap()));
+                        },
+                        In(b'\r') if self.strip_cr => {
+                            input.step();
+                            self.state = 2;
+                        },
+                        In(b) => {
+                            input.step();
+                            if !self.push(b) {
+                                return Fail(LineError::TooLong);
+                            }
+                        },
+                        Over if self.len == 0 => return Done(None),
+                        Over => return Done(Some(self.output.take_out().unwrap())),
+                        Out => return Cont(self),
+                    };
+                },
+                2 => { // carriage return
+                    match input.head() {
+                        In(b'\n') => {
+                            input.step();
+                            return Done(Some(self.output.take_out().unwrap()));
+                        },
+                        In(_) | Over => {
+                            // Not a line terminator; keep the carriage return.
+                            if !self.push(b'\r') {
+                                return Fail(LineError::TooLong);
+                            }
+                            self.state = 1;
+                        },
+                        Out => return Cont(self),
+                    };
+                },
+                _ => unreachable!(),
+            };
+        }
+    }
+
+    fn reset(&mut self) {
+        self.len = 0;
+        self.state = 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::input::{AsInput, BytesInput};
+    use crate::output::SliceOutput;
+    use super::*;
+
+    fn decode_line<'a, 'b>(input: &mut BytesInput<'a>, buffer: &'b mut [u8])
+        -> Result<Option<&'b mut [u8]>, LineError> {
+        let mut decoder = LineDecoder::new(SliceOutput::new(buffer)).max_len(16);
+        loop {
+            match decoder.decode(input) {
+                Cont(next) => {
+                    decoder = next;
+                    if !input.more() {
+                        input.over();
+                    }
+                },
+                Done(line) => return Ok(line),
+                Fail(error) => return Err(error),
+            }
+        }
+    }
+
+    #[test]
+    fn test_lines_split_across_chunks() {
+        // The first line spans three chunks, with its `\r` and `\n` in different chunks.
+        let mut input = BytesInput::chunked("abcdefg\r\nhi\n\nj\rk\nlast", 4);
+        let mut buffer = [0u8; 16];
+        assert_eq!(decode_line(&mut input, &mut buffer).unwrap().unwrap(), b"abcdefg");
+        assert_eq!(decode_line(&mut input, &mut buffer).unwrap().unwrap(), b"hi");
+        assert_eq!(decode_line(&mut input, &mut buffer).unwrap().unwrap(), b"");
+        assert_eq!(decode_line(&mut input, &mut buffer).unwrap().unwrap(), b"j\rk");
+        assert_eq!(decode_line(&mut input, &mut buffer).unwrap().unwrap(), b"last");
+        assert_eq!(decode_line(&mut input, &mut buffer).unwrap(), None);
+    }
+
+    #[test]
+    fn test_lines_too_long() {
+        let mut input = BytesInput::chunked("0123456789abcdefg\n", 5);
+        let mut buffer = [0u8; 32];
+        assert_eq!(decode_line(&mut input, &mut buffer).unwrap_err(), LineError::TooLong);
+        let mut buffer = [0u8; 4];
+        let decoder = LineDecoder::new(SliceOutput::new(&mut buffer));
+        match decoder.decode(&mut (&b"01234\n"[..]).as_input()) {
+            Fail(error) => assert_eq!(error, LineError::TooLong),
+            _ => panic!(),
+        }
+    }
+}
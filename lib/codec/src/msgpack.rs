@@ -0,0 +1,492 @@
+use core::fmt;
+use core::marker::PhantomData;
+use crate::step::{In, Out, Over};
+use crate::then::{Then, Cont, Done, Fail};
+use crate::input::Input;
+use crate::output::Output;
+use crate::decoder::Decoder;
+use crate::encoder::Encoder;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MsgPackError {
+    /// An unsupported type byte, such as an extension or timestamp type.
+    Unsupported(u8),
+    /// A str or bin payload exceeds the capacity of the output.
+    TooLong,
+    /// The input ends in the middle of an item.
+    Truncated,
+}
+
+/// A single MessagePack item. Arrays and maps are represented by headers
+/// carrying their lengths; their elements, or key/value pairs, follow as
+/// subsequent items. Str and bin items carry their payload as `S`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MsgPackItem<S> {
+    Nil,
+    Bool(bool),
+    /// A signed integer. The variant follows the wire type: negative
+    /// fixints, and int 8 through int 64 items, decode as `Int`, even when
+    /// their values are non-negative. The encoder writes non-negative `Int`s
+    /// as unsigned integers.
+    Int(i64),
+    /// An unsigned integer, decoded from a positive fixint, or from a uint 8
+    /// through uint 64 item.
+    UInt(u64),
+    F32(f32),
+    F64(f64),
+    /// UTF-8 string payload; not validated.
+    Str(S),
+    Bin(S),
+    /// Array header, followed by the given number of items.
+    Array(u32),
+    /// Map header, followed by the given number of key/value item pairs.
+    Map(u32),
+}
+
+/// Incremental MessagePack decoder for the primitive types: nil, bool,
+/// integers, floats, str, bin, arrays, and maps. Decodes a single item,
+/// streaming any str or bin payload into a caller-provided output, such as
+/// a `SinkOutput` draining into a leased `Buf` or `String`. Multi-byte
+/// integers, floats, and length prefixes may be split across input chunks.
+/// Completes with `None` if the input is exhausted before the next item.
+/// Decode each successive item with a new decoder.
+pub struct MsgPackDecoder<I: Input<Token=u8>, O: Output<Token=u8>> {
+    pub output: O,
+    /// Type byte of the item being decoded.
+    tag: u8,
+    /// Big-endian accumulator of the item's fixed-width field.
+    acc: u64,
+    /// Number of field or payload bytes that remain to be decoded.
+    need: u64,
+    state: u32,
+    input: PhantomData<I>,
+}
+
+/// Incremental MessagePack encoder for a single item. Encodes each item in
+/// its most compact representation.
+pub struct MsgPackEncoder<'s, O: Output<Token=u8>> {
+    /// Encoded type byte and fixed-width field.
+    head: [u8; 9],
+    head_len: u8,
+    head_pos: u8,
+    /// Str or bin payload.
+    payload: &'s [u8],
+    offset: usize,
+    output: PhantomData<O>,
+}
+
+impl<I, O> MsgPackDecoder<I, O> where I: Input<Token=u8>, O: Output<Token=u8> {
+    pub fn new(output: O) -> Self {
+        Self {
+            output: output,
+            tag: 0,
+            acc: 0,
+            need: 0,
+            state: 1,
+            input: PhantomData,
+        }
+    }
+
+    /// Completes an item whose fixed-width field has been accumulated.
+    #[inline]
+    fn field(mut self) -> Then<Self, Option<MsgPackItem<O::Out>>, MsgPackError> where O::Err: fmt::Debug {
+        let acc = self.acc;
+        let item = match self.tag {
+            0xCC..=0xCF => MsgPackItem::UInt(acc),
+            0xD0 => MsgPackItem::Int(acc as u8 as i8 as i64),
+            0xD1 => MsgPackItem::Int(acc as u16 as i16 as i64),
+            0xD2 => MsgPackItem::Int(acc as u32 as i32 as i64),
+            0xD3 => MsgPackItem::Int(acc as i64),
+            0xCA => MsgPackItem::F32(f32::from_bits(acc as u32)),
+            0xCB => MsgPackItem::F64(f64::from_bits(acc)),
+            0xDC | 0xDD => MsgPackItem::Array(acc as u32),
+            0xDE | 0xDF => MsgPackItem::Map(acc as u32),
+            _ => { // str or bin length prefix
+                self.need = acc;
+                self.state = 3;
+                return Cont(self);
+            },
+        };
+        Done(Some(item))
+    }
+
+    /// Completes a str or bin item whose payload has been streamed out.
+    #[inline]
+    fn payload(self) -> Then<Self, Option<MsgPackItem<O::Out>>, MsgPackError> where O::Err: fmt::Debug {
+        let payload = self.output.take_out().unwrap();
+        match self.tag {
+            0xA0..=0xBF | 0xD9..=0xDB => Done(Some(MsgPackItem::Str(payload))),
+            _ => Done(Some(MsgPackItem::Bin(payload))),
+        }
+    }
+}
+
+impl<I, O> Decoder for MsgPackDecoder<I, O>
+    where I: Input<Token=u8>,
+          O: Output<Token=u8>,
+          O::Err: fmt::Debug {
+
+    type Input = I;
+    type Output = Option<MsgPackItem<O::Out>>;
+    type Error = MsgPackError;
+
+    fn decode(mut self, input: &mut I) -> Then<Self, Option<MsgPackItem<O::Out>>, MsgPackError> {
+        loop {
+            match self.state {
+                1 => { // type byte
+                    let b = match input.head() {
+                        In(b) => b,
+                        Over => return Done(None),
+                        Out => return Cont(self),
+                    };
+                    input.step();
+                    self.tag = b;
+                    self.acc = 0;
+                    let item = match b {
+                        0x00..=0x7F => MsgPackItem::UInt(b as u64),
+                        0xE0..=0xFF => MsgPackItem::Int(b as i8 as i64),
+                        0x80..=0x8F => MsgPackItem::Map((b & 0x0F) as u32),
+                        0x90..=0x9F => MsgPackItem::Array((b & 0x0F) as u32),
+                        0xC0 => MsgPackItem::Nil,
+                        0xC2 => MsgPackItem::Bool(false),
+                        0xC3 => MsgPackItem::Bool(true),
+                        0xA0..=0xBF => {
+                            self.need = (b & 0x1F) as u64;
+                            self.state = 3;
+                            continue;
+                        },
+                        0xCC..=0xCF | 0xD0..=0xD3 | 0xCA | 0xCB |
+                        0xC4..=0xC6 | 0xD9..=0xDB | 0xDC..=0xDF => {
+                            // Get the width of the fixed-width field.
+                            self.need = match b {
+                                0xCC..=0xCF => 1 << (b - 0xCC),
+                                0xD0..=0xD3 => 1 << (b - 0xD0),
+                                0xCA => 4,
+                                0xCB => 8,
+                                0xC4 | 0xD9 => 1,
+                                0xC5 | 0xDA | 0xDC | 0xDE => 2,
+                                _ => 4,
+                            };
+                            self.state = 2;
+                            continue;
+                        },
+                        _ => return Fail(MsgPackError::Unsupported(b)),
+                    };
+                    return Done(Some(item));
+                },
+                2 => { // fixed-width field
+                    while self.need != 0 {
+                        match input.head() {
+                            In(b) => {
+                                input.step();
+                                self.acc = (self.acc << 8) | b as u64;
+                                self.need -= 1;
+                            },
+                            Over => return Fail(MsgPackError::Truncated),
+                            Out => return Cont(self),
+                        };
+                    }
+                    match self.field() {
+                        Cont(next) => self = next,
+                        then => return then,
+                    };
+                },
+                3 => { // str or bin payload
+                    while self.need != 0 {
+                        match input.head() {
+                            In(b) => {
+                                if self.output.is_full() {
+                                    return Fail(MsgPackError::TooLong);
+                                }
+                                input.step();
+                                self.output.push(b);
+                                self.need -= 1;
+                            },
+                            Over => return Fail(MsgPackError::Truncated),
+                            Out => return Cont(self),
+                        };
+                    }
+                    return self.payload();
+                },
+                _ => unreachable!(),
+            };
+        }
+    }
+
+    fn reset(&mut self) {
+        self.tag = 0;
+        self.acc = 0;
+        self.need = 0;
+        self.state = 1;
+    }
+}
+
+impl<'s, O> MsgPackEncoder<'s, O> where O: Output<Token=u8> {
+    pub fn new(item: MsgPackItem<&'s [u8]>) -> Self {
+        let mut encoder = Self {
+            head: [0; 9],
+            head_len: 0,
+            head_pos: 0,
+            payload: &[],
+            offset: 0,
+            output: PhantomData,
+        };
+        match item {
+            MsgPackItem::Nil => encoder.put_tag(0xC0),
+            MsgPackItem::Bool(false) => encoder.put_tag(0xC2),
+            MsgPackItem::Bool(true) => encoder.put_tag(0xC3),
+            MsgPackItem::Int(n) if n >= 0 => encoder.put_uint(n as u64),
+            MsgPackItem::Int(n) => {
+                if n >= -32 {
+                    encoder.put_tag(n as u8);
+                } else if n >= i8::MIN as i64 {
+                    encoder.put_field(0xD0, n as u64, 1);
+                } else if n >= i16::MIN as i64 {
+                    encoder.put_field(0xD1, n as u64, 2);
+                } else if n >= i32::MIN as i64 {
+                    encoder.put_field(0xD2, n as u64, 4);
+                } else {
+                    encoder.put_field(0xD3, n as u64, 8);
+                }
+            },
+            MsgPackItem::UInt(n) => encoder.put_uint(n),
+            MsgPackItem::F32(x) => encoder.put_field(0xCA, x.to_bits() as u64, 4),
+            MsgPackItem::F64(x) => encoder.put_field(0xCB, x.to_bits(), 8),
+            MsgPackItem::Str(s) => {
+                let n = s.len() as u64;
+                if n <= 31 {
+                    encoder.put_tag(0xA0 | n as u8);
+                } else {
+                    encoder.put_len(0xD9, n);
+                }
+                encoder.payload = s;
+            },
+            MsgPackItem::Bin(s) => {
+                encoder.put_len(0xC4, s.len() as u64);
+                encoder.payload = s;
+            },
+            MsgPackItem::Array(n) => {
+                if n <= 15 {
+                    encoder.put_tag(0x90 | n as u8);
+                } else if n <= 0xFFFF {
+                    encoder.put_field(0xDC, n as u64, 2);
+                } else {
+                    encoder.put_field(0xDD, n as u64, 4);
+                }
+            },
+            MsgPackItem::Map(n) => {
+                if n <= 15 {
+                    encoder.put_tag(0x80 | n as u8);
+                } else if n <= 0xFFFF {
+                    encoder.put_field(0xDE, n as u64, 2);
+                } else {
+                    encoder.put_field(0xDF, n as u64, 4);
+                }
+            },
+        };
+        encoder
+    }
+
+    /// Encodes the item into `output`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the output fills before the item is fully encoded.
+    pub fn produce(self, mut output: O) -> Result<O::Out, O::Err> {
+        match self.encode(&mut output) {
+            Done(_) => output.take_out(),
+            Fail(_) => unreachable!(),
+            Cont(_) => panic!("output full"),
+        }
+    }
+
+    #[inline]
+    fn put_tag(&mut self, tag: u8) {
+        self.head[0] = tag;
+        self.head_len = 1;
+    }
+
+    #[inline]
+    fn put_field(&mut self, tag: u8, n: u64, width: u8) {
+        self.head[0] = tag;
+        let mut i = 0;
+        while i < width {
+            self.head[(1 + i) as usize] = (n >> (8 * (width - 1 - i) as u32)) as u8;
+            i += 1;
+        }
+        self.head_len = 1 + width;
+    }
+
+    #[inline]
+    fn put_uint(&mut self, n: u64) {
+        if n <= 0x7F {
+            self.put_tag(n as u8);
+        } else if n <= 0xFF {
+            self.put_field(0xCC, n, 1);
+        } else if n <= 0xFFFF {
+            self.put_field(0xCD, n, 2);
+        } else if n <= 0xFFFF_FFFF {
+            self.put_field(0xCE, n, 4);
+        } else {
+            self.put_field(0xCF, n, 8);
+        }
+    }
+
+    /// Puts the smallest of the three length-prefixed variants of a str or
+    /// bin type, whose 8-bit length variant has the given `tag`.
+    #[inline]
+    fn put_len(&mut self, tag: u8, n: u64) {
+        if n <= 0xFF {
+            self.put_field(tag, n, 1);
+        } else if n <= 0xFFFF {
+            self.put_field(tag + 1, n, 2);
+        } else {
+            self.put_field(tag + 2, n, 4);
+        }
+    }
+}
+
+impl<'s, O> Encoder for MsgPackEncoder<'s, O> where O: Output<Token=u8> {
+    type Input = ();
+    type Output = O;
+    type Error = ();
+
+    fn encode(mut self, output: &mut O) -> Then<Self, (), ()> {
+        loop {
+            if self.head_pos < self.head_len {
+                if output.is_full() {
+                    return Cont(self);
+                }
+                output.push(self.head[self.head_pos as usize]);
+                self.head_pos += 1;
+            } else if self.offset < self.payload.len() {
+                if output.is_full() {
+                    return Cont(self);
+                }
+                output.push(self.payload[self.offset]);
+                self.offset += 1;
+            } else {
+                return Done(());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::input::BytesInput;
+    use crate::output::SliceOutput;
+    use super::*;
+
+    fn encode_items(items: &[MsgPackItem<&[u8]>], buffer: &mut [u8]) -> usize {
+        let mut len = 0;
+        for item in items.iter() {
+            let encoded = MsgPackEncoder::new(*item).produce(SliceOutput::new(&mut buffer[len..])).unwrap();
+            len += encoded.len();
+        }
+        len
+    }
+
+    fn decode_item<'a, 'b>(input: &mut BytesInput<'a>, buffer: &'b mut [u8])
+        -> Result<Option<MsgPackItem<&'b mut [u8]>>, MsgPackError> {
+        let mut decoder = MsgPackDecoder::new(SliceOutput::new(buffer));
+        loop {
+            match decoder.decode(input) {
+                Cont(next) => {
+                    decoder = next;
+                    if !input.more() {
+                        input.over();
+                    }
+                },
+                Done(item) => return Ok(item),
+                Fail(error) => return Err(error),
+            }
+        }
+    }
+
+    fn assert_str(item: Option<MsgPackItem<&mut [u8]>>, expected: &str) {
+        match item {
+            Some(MsgPackItem::Str(s)) => assert_eq!(s, expected.as_bytes()),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_msgpack_map_roundtrip_bytewise() {
+        let long_key = "a key that is longer than thirty one bytes";
+        let items = [
+            MsgPackItem::Map(4),
+            MsgPackItem::Str(&b"id"[..]), MsgPackItem::UInt(7),
+            MsgPackItem::Str(&b"weight"[..]), MsgPackItem::UInt(70000),
+            MsgPackItem::Str(&b"delta"[..]), MsgPackItem::Int(-300),
+            MsgPackItem::Str(long_key.as_bytes()), MsgPackItem::UInt(1 << 40),
+        ];
+        let mut encoded = [0u8; 128];
+        let len = encode_items(&items, &mut encoded);
+        assert_eq!(&encoded[..4], &[0x84, 0xA2, b'i', b'd']);
+
+        let mut input = BytesInput::chunked(&encoded[..len], 1);
+        let mut buffer = [0u8; 64];
+        assert_eq!(decode_item(&mut input, &mut buffer).unwrap(), Some(MsgPackItem::Map(4)));
+        assert_str(decode_item(&mut input, &mut buffer).unwrap(), "id");
+        assert_eq!(decode_item(&mut input, &mut buffer).unwrap(), Some(MsgPackItem::UInt(7)));
+        assert_str(decode_item(&mut input, &mut buffer).unwrap(), "weight");
+        assert_eq!(decode_item(&mut input, &mut buffer).unwrap(), Some(MsgPackItem::UInt(70000)));
+        assert_str(decode_item(&mut input, &mut buffer).unwrap(), "delta");
+        assert_eq!(decode_item(&mut input, &mut buffer).unwrap(), Some(MsgPackItem::Int(-300)));
+        assert_str(decode_item(&mut input, &mut buffer).unwrap(), long_key);
+        assert_eq!(decode_item(&mut input, &mut buffer).unwrap(), Some(MsgPackItem::UInt(1 << 40)));
+        assert_eq!(decode_item(&mut input, &mut buffer).unwrap(), None);
+    }
+
+    #[test]
+    fn test_msgpack_int_variant_follows_wire_type() {
+        let encoded = [0x05, 0xCC, 0x05, 0xD0, 0x05, 0xD1, 0x00, 0x05, 0xD3, 0, 0, 0, 0, 0, 0, 0, 0, 0xFB];
+        let mut input = BytesInput::new(&encoded[..]);
+        let mut buffer = [0u8; 8];
+        assert_eq!(decode_item(&mut input, &mut buffer).unwrap(), Some(MsgPackItem::UInt(5)));
+        assert_eq!(decode_item(&mut input, &mut buffer).unwrap(), Some(MsgPackItem::UInt(5)));
+        // Signed wire types decode as `Int`, whatever the sign of their value.
+        assert_eq!(decode_item(&mut input, &mut buffer).unwrap(), Some(MsgPackItem::Int(5)));
+        assert_eq!(decode_item(&mut input, &mut buffer).unwrap(), Some(MsgPackItem::Int(5)));
+        assert_eq!(decode_item(&mut input, &mut buffer).unwrap(), Some(MsgPackItem::Int(0)));
+        assert_eq!(decode_item(&mut input, &mut buffer).unwrap(), Some(MsgPackItem::Int(-5)));
+        assert_eq!(decode_item(&mut input, &mut buffer).unwrap(), None);
+
+        // Non-negative `Int`s encode as unsigned integers.
+        let mut encoded = [0u8; 8];
+        let len = encode_items(&[MsgPackItem::Int(5)], &mut encoded);
+        assert_eq!(&encoded[..len], &[0x05]);
+    }
+
+    #[test]
+    fn test_msgpack_scalars() {
+        let items = [
+            MsgPackItem::Array(5),
+            MsgPackItem::Nil, MsgPackItem::Bool(true),
+            MsgPackItem::F64(1.5), MsgPackItem::Int(-5),
+            MsgPackItem::Bin(&[1, 2, 3][..]),
+        ];
+        let mut encoded = [0u8; 64];
+        let len = encode_items(&items, &mut encoded);
+        let mut input = BytesInput::chunked(&encoded[..len], 3);
+        let mut buffer = [0u8; 64];
+        assert_eq!(decode_item(&mut input, &mut buffer).unwrap(), Some(MsgPackItem::Array(5)));
+        assert_eq!(decode_item(&mut input, &mut buffer).unwrap(), Some(MsgPackItem::Nil));
+        assert_eq!(decode_item(&mut input, &mut buffer).unwrap(), Some(MsgPackItem::Bool(true)));
+        assert_eq!(decode_item(&mut input, &mut buffer).unwrap(), Some(MsgPackItem::F64(1.5)));
+        assert_eq!(decode_item(&mut input, &mut buffer).unwrap(), Some(MsgPackItem::Int(-5)));
+        match decode_item(&mut input, &mut buffer).unwrap() {
+            Some(MsgPackItem::Bin(b)) => assert_eq!(b, &[1, 2, 3]),
+            _ => panic!(),
+        }
+
+        let mut buffer = [0u8; 2];
+        let mut input = BytesInput::new(&[0xC4, 3, 1, 2, 3][..]);
+        assert_eq!(decode_item(&mut input, &mut buffer).unwrap_err(), MsgPackError::TooLong);
+        let mut input = BytesInput::new(&[0xCD, 1][..]);
+        assert_eq!(decode_item(&mut input, &mut buffer).unwrap_err(), MsgPackError::Truncated);
+        let mut input = BytesInput::new(&[0xD4, 0, 0][..]);
+        assert_eq!(decode_item(&mut input, &mut buffer).unwrap_err(), MsgPackError::Unsupported(0xD4));
+    }
+}
@@ -1,4 +1,5 @@
 use core::str;
+use crate::decoder::OutputLen;
 
 pub trait Output {
     type Token;
@@ -11,6 +12,17 @@ pub trait Output {
 
     fn push(&mut self, token: Self::Token);
 
+    /// Returns `true` if this output can't accept more tokens until its
+    /// consumer makes room. Decoders that support backpressure check for a
+    /// blocked output before consuming each input token, and before
+    /// completing, and suspend with their state intact while the output
+    /// remains blocked; the caller resumes them by decoding again once it
+    /// has made room. Outputs that never block always return `false`.
+    #[inline]
+    fn is_blocked(&mut self) -> bool {
+        false
+    }
+
     fn take_out(self) -> Result<Self::Out, Self::Err>;
 }
 
@@ -64,6 +76,13 @@ impl<'a, T: 'a> Output for SliceOutput<'a, T> {
     }
 }
 
+impl<'a, T: 'a> OutputLen for SliceOutput<'a, T> {
+    #[inline]
+    fn output_len(&self) -> usize {
+        self.offset
+    }
+}
+
 impl<'a, T: 'a> IntoOutput for &'a mut [T] {
     type Token = T;
     type IntoOut = SliceOutput<'a, T>;
@@ -145,6 +164,11 @@ impl<O: Output<Token=u8>> Output for Utf8Output<O> {
         self.output.is_full()
     }
 
+    #[inline]
+    fn is_blocked(&mut self) -> bool {
+        self.output.is_blocked()
+    }
+
     fn push(&mut self, c: char) {
         self.flush();
         let c = c as u32;
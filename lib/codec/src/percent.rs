@@ -0,0 +1,356 @@
+use core::fmt;
+use core::marker::PhantomData;
+use crate::step::{In, Out, Over};
+use crate::then::{Then, Cont, Done, Fail};
+use crate::input::{AsInput, Input, SliceInput};
+use crate::output::Output;
+use crate::decoder::{CowOutput, Decoder};
+use crate::encoder::Encoder;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PercentError {
+    /// A `%` at the given byte offset isn't followed by two hex digits.
+    InvalidEscape(usize),
+    Truncated,
+}
+
+/// Incremental percent (URL) decoder. Escapes split across input chunks are
+/// buffered until both hex digits arrive.
+pub struct PercentDecoder<I: Input<Token=u8>, O: Output<Token=u8>> {
+    pub output: O,
+    offset: usize,
+    escape: usize,
+    hi: u8,
+    state: u32,
+    input: PhantomData<I>,
+}
+
+/// Incremental percent (URL) encoder. Escapes every byte other than the
+/// RFC 3986 unreserved characters, and any additional ASCII `safe` bytes, as
+/// `%XX`.
+pub struct PercentEncoder<'s, I: Input<Token=u8>, O: Output<Token=char>> {
+    pub input: I,
+    safe: &'s [u8],
+    b: u8,
+    state: u32,
+    output: PhantomData<O>,
+}
+
+impl<I, O> PercentDecoder<I, O> where I: Input<Token=u8>, O: Output<Token=u8> {
+    pub fn new(output: O) -> Self {
+        Self {
+            output: output,
+            offset: 0,
+            escape: 0,
+            hi: 0,
+            state: 1,
+            input: PhantomData,
+        }
+    }
+
+    pub fn consume(mut self, input: &mut I) -> Result<O::Out, PercentError> where O::Err: fmt::Debug {
+        loop {
+            match self.decode(input) {
+                Done(output) => return Ok(output),
+                Fail(error) => return Err(error),
+                Cont(next) => {
+                    if input.is_out() {
+                        input.over();
+                        self = next;
+                    } else {
+                        return Err(PercentError::Truncated);
+                    }
+                },
+            }
+        }
+    }
+}
+
+impl<'i, O> PercentDecoder<SliceInput<'i, u8>, O> where O: Output<Token=u8>, O::Err: fmt::Debug {
+    /// Decodes the complete `input`; returns a borrow of the `input` itself
+    /// if it contains no escapes, leaving the output untouched, and returns
+    /// the decoded output only if unescaping changed some bytes.
+    pub fn decode_cow(self, input: &'i [u8]) -> Result<CowOutput<'i, u8, O::Out>, PercentError> {
+        if !input.contains(&b'%') {
+            return Ok(CowOutput::Borrowed(input));
+        }
+        self.consume(&mut input.as_input()).map(CowOutput::Owned)
+    }
+}
+
+impl<I, O> Decoder for PercentDecoder<I, O>
+    where I: Input<Token=u8>,
+          O: Output<Token=u8>,
+          O::Err: fmt::Debug {
+
+    type Input = I;
+    type Output = O::Out;
+    type Error = PercentError;
+
+    fn decode(mut self, input: &mut I) -> Then<Self, O::Out, PercentError> {
+        loop {
+            if self.output.is_blocked() {
+                return Cont(self);
+            }
+            match self.state {
+                1 => {
+                    match input.head() {
+                        In(b'%') => {
+                            input.step();
+                            self.escape = self.offset;
+                            self.offset += 1;
+                            self.state = 2;
+                        },
+                        In(b) => {
+                            input.step();
+                            self.offset += 1;
+                            self.output.push(b);
+                        },
+                        Over => return Done(self.output.take_out().unwrap()),
+                        Out => return Cont(self),
+                    };
+                },
+                2 => {
+                    match input.head() {
+                        In(b) if is_hex_digit(b) => {
+                            input.step();
+                            self.offset += 1;
+                            self.hi = decode_hex_digit(b);
+                            self.state = 3;
+                        },
+                        In(_) | Over => return Fail(PercentError::InvalidEscape(self.escape)),
+                        Out => return Cont(self),
+                    };
+                },
+                3 => {
+                    match input.head() {
+                        In(b) if is_hex_digit(b) => {
+                            input.step();
+                            self.offset += 1;
+                            self.output.push(self.hi << 4 | decode_hex_digit(b));
+                            self.hi = 0;
+                            self.state = 1;
+                        },
+                        In(_) | Over => return Fail(PercentError::InvalidEscape(self.escape)),
+                        Out => return Cont(self),
+                    };
+                },
+                _ => unreachable!(),
+            };
+        }
+    }
+
+    fn reset(&mut self) {
+        self.offset = 0;
+        self.escape = 0;
+        self.hi = 0;
+        self.state = 1;
+    }
+}
+
+impl<'s, I, O> PercentEncoder<'s, I, O> where I: Input<Token=u8>, O: Output<Token=char> {
+    pub fn new(input: I) -> Self {
+        Self {
+            input: input,
+            safe: &[],
+            b: 0,
+            state: 1,
+            output: PhantomData,
+        }
+    }
+
+    /// Leaves the given `safe` bytes unescaped, in addition to the unreserved
+    /// characters. Only ASCII bytes can be safe; bytes at or above `0x80`
+    /// are always escaped, since they can't be output as chars by themselves.
+    pub fn safe(mut self, safe: &'s [u8]) -> Self {
+        self.safe = safe;
+        self
+    }
+
+    pub fn produce(mut self, mut output: O) -> Result<O::Out, O::Err> {
+        loop {
+            match self.encode(&mut output) {
+                Done(_) => return output.take_out(),
+                Fail(_) => unreachable!(),
+                Cont(next) => {
+                    self = next;
+                    self.input.over();
+                }
+            }
+        }
+    }
+
+    #[inline]
+    fn is_safe(&self, b: u8) -> bool {
+        is_unreserved(b) || b < 0x80 && self.safe.contains(&b)
+    }
+}
+
+impl<'s, I, O> Encoder for PercentEncoder<'s, I, O> where I: Input<Token=u8>, O: Output<Token=char> {
+    type Input = I;
+    type Output = O;
+    type Error = ();
+
+    fn encode(mut self, output: &mut O) -> Then<Self, I, ()> {
+        while !output.is_full() {
+            match self.state {
+                1 => {
+                    match self.input.head() {
+                        In(b) if self.is_safe(b) => {
+                            self.input.step();
+                            output.push(b as char);
+                        },
+                        In(b) => {
+                            self.input.step();
+                            self.b = b;
+                            self.state = 2;
+                            output.push('%');
+                        },
+                        Over => return Done(self.input),
+                        Out => break,
+                    };
+                },
+                2 => {
+                    output.push(encode_hex_digit(self.b >> 4));
+                    self.state = 3;
+                },
+                3 => {
+                    output.push(encode_hex_digit(self.b & 0x0F));
+                    self.b = 0;
+                    self.state = 1;
+                },
+                _ => unreachable!(),
+            };
+        }
+        return Cont(self);
+    }
+}
+
+#[inline]
+fn is_unreserved(b: u8) -> bool {
+    b >= b'0' && b <= b'9' ||
+    b >= b'A' && b <= b'Z' ||
+    b >= b'a' && b <= b'z' ||
+    b == b'-' || b == b'.' ||
+    b == b'_' || b == b'~'
+}
+
+#[inline]
+fn is_hex_digit(b: u8) -> bool {
+    b >= b'0' && b <= b'9' ||
+    b >= b'A' && b <= b'F' ||
+    b >= b'a' && b <= b'f'
+}
+
+#[inline]
+fn decode_hex_digit(b: u8) -> u8 {
+    if b >= b'0' && b <= b'9' {
+        b - b'0'
+    } else if b >= b'A' && b <= b'F' {
+        10 + (b - b'A')
+    } else if b >= b'a' && b <= b'f' {
+        10 + (b - b'a')
+    } else {
+        unreachable!()
+    }
+}
+
+#[inline]
+fn encode_hex_digit(x: u8) -> char {
+    debug_assert!(x < 16);
+    if x < 10 {
+        (b'0' + x) as char
+    } else {
+        (b'A' + (x - 10)) as char
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::input::BytesInput;
+    use crate::output::{SliceOutput, StrOutput};
+    use super::*;
+
+    fn assert_decodes_chunked(encoded: &str, chunk: usize, decoded: &[u8]) {
+        let mut input = BytesInput::chunked(encoded, chunk);
+        let mut buffer = [0u8; 64];
+        let mut decoder = PercentDecoder::new(SliceOutput::new(&mut buffer));
+        let output = loop {
+            match decoder.decode(&mut input) {
+                Cont(next) => {
+                    decoder = next;
+                    if !input.more() {
+                        input.over();
+                    }
+                },
+                Done(output) => break output,
+                Fail(error) => panic!("{:?}", error),
+            }
+        };
+        assert_eq!(output, decoded);
+    }
+
+    #[test]
+    fn test_percent_encode() {
+        let mut buffer = [0u8; 64];
+        let encoder = PercentEncoder::new("a b\u{2713}".as_bytes().as_input());
+        assert_eq!(encoder.produce(StrOutput::new(&mut buffer)).unwrap(), "a%20b%E2%9C%93");
+        let mut buffer = [0u8; 64];
+        let encoder = PercentEncoder::new("/path to/x~y".as_bytes().as_input()).safe(b"/");
+        assert_eq!(encoder.produce(StrOutput::new(&mut buffer)).unwrap(), "/path%20to/x~y");
+    }
+
+    #[test]
+    fn test_percent_encode_non_ascii_safe() {
+        // Non-ASCII safe bytes are escaped anyway, so the bytes of a
+        // multi-byte char can't each turn into a char of their own.
+        let safe = "/\u{E9}".as_bytes();
+        let mut buffer = [0u8; 64];
+        let encoder = PercentEncoder::new("/caf\u{E9}\u{FF}".as_bytes().as_input()).safe(safe);
+        assert_eq!(encoder.produce(StrOutput::new(&mut buffer)).unwrap(), "/caf%C3%A9%C3%BF");
+        let mut buffer = [0u8; 64];
+        let encoder = PercentEncoder::new((&[0x80u8, 0xFF][..]).as_input()).safe(&[0x80, 0xFF]);
+        assert_eq!(encoder.produce(StrOutput::new(&mut buffer)).unwrap(), "%80%FF");
+    }
+
+    #[test]
+    fn test_percent_decode_split_escape() {
+        // Chunk boundaries fall after a `%`, and between two hex digits.
+        assert_decodes_chunked("%E2%9C%93", 4, "\u{2713}".as_bytes());
+        assert_decodes_chunked("%E2%9C%93", 2, "\u{2713}".as_bytes());
+        assert_decodes_chunked("a%20b", 1, b"a b");
+    }
+
+    #[test]
+    fn test_percent_decode_invalid_escape() {
+        let mut buffer = [0u8; 64];
+        let decoder = PercentDecoder::new(SliceOutput::new(&mut buffer));
+        assert_eq!(decoder.consume(&mut (&b"ab%2G"[..]).as_input()).unwrap_err(),
+                   PercentError::InvalidEscape(2));
+        let mut buffer = [0u8; 64];
+        let decoder = PercentDecoder::new(SliceOutput::new(&mut buffer));
+        assert_eq!(decoder.consume(&mut (&b"abc%"[..]).as_input()).unwrap_err(),
+                   PercentError::InvalidEscape(3));
+    }
+
+    #[test]
+    fn test_percent_decode_cow() {
+        let plain = b"plain-path/segment";
+        let mut buffer = [0u8; 64];
+        let decoder = PercentDecoder::new(SliceOutput::new(&mut buffer));
+        let decoded = decoder.decode_cow(&plain[..]).unwrap();
+        assert!(decoded.is_borrowed());
+        assert_eq!(decoded.as_ref().as_ptr(), plain.as_ptr());
+        assert_eq!(decoded.as_ref(), &plain[..]);
+
+        let mut buffer = [0u8; 64];
+        let decoder = PercentDecoder::new(SliceOutput::new(&mut buffer));
+        let decoded = decoder.decode_cow(&b"a%20b"[..]).unwrap();
+        assert!(decoded.is_owned());
+        assert_eq!(decoded.as_ref(), b"a b");
+
+        let mut buffer = [0u8; 64];
+        let decoder = PercentDecoder::new(SliceOutput::new(&mut buffer));
+        assert_eq!(decoder.decode_cow(&b"a%2"[..]).unwrap_err(), PercentError::InvalidEscape(1));
+    }
+}
@@ -0,0 +1,418 @@
+use core::cell::Cell;
+use core::fmt;
+use crate::step::{Step, In, Out, Over};
+use crate::then::{Then, Cont, Done, Fail};
+use crate::input::Input;
+use crate::output::Output;
+use crate::decoder::Decoder;
+use crate::encoder::Encoder;
+
+/// Fixed capacity byte queue that connects the output of a decoder to the
+/// input of an encoder. The decoder pushes bytes through a `PipeOutput`,
+/// which blocks while the pipe is full; the encoder pulls bytes through a
+/// `PipeInput`, which runs out while the pipe is empty, and ends once the
+/// decoder takes out its output. Bytes pushed into a full pipe are dropped,
+/// and mark the pipe as overflowed.
+pub struct Pipe<'b> {
+    buf: &'b [Cell<u8>],
+    head: Cell<usize>,
+    len: Cell<usize>,
+    closed: Cell<bool>,
+    overflowed: Cell<bool>,
+}
+
+/// Decoder side of a `Pipe`.
+pub struct PipeOutput<'p> {
+    pipe: &'p Pipe<'p>,
+}
+
+/// Encoder side of a `Pipe`.
+pub struct PipeInput<'p> {
+    pipe: &'p Pipe<'p>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TranscodeError<D, E> {
+    /// The decoder failed.
+    Decode(D),
+    /// The encoder failed.
+    Encode(E),
+    /// The decoder pushed a byte into a full pipe, instead of suspending
+    /// while its output was blocked.
+    Overflow,
+}
+
+/// Decoder combinator that re-encodes the bytes decoded by a wrapped
+/// decoder with a wrapped encoder, as decoding progresses. The decoder
+/// writes into a `PipeOutput`, and the encoder reads from a `PipeInput`,
+/// of the same `Pipe`; each decode step runs the two stages in turn until
+/// the decoder needs more input, so every input chunk yields output without
+/// buffering the whole stream. The decoder must suspend while its output is
+/// blocked; transcoding fails with `TranscodeError::Overflow` if it pushes
+/// into a full pipe. Completes with the encoded output, once the encoder
+/// has encoded the last decoded byte. Returned by `transcode`.
+pub struct Transcode<'p, D, E: Encoder> {
+    pipe: &'p Pipe<'p>,
+    decoder: Option<D>,
+    encoder: Option<E>,
+    pub output: E::Output,
+}
+
+impl<'b> Pipe<'b> {
+    pub fn new(buf: &'b mut [u8]) -> Self {
+        Pipe {
+            buf: Cell::from_mut(buf).as_slice_of_cells(),
+            head: Cell::new(0),
+            len: Cell::new(0),
+            closed: Cell::new(false),
+            overflowed: Cell::new(false),
+        }
+    }
+
+    /// Returns the output through which a decoder pushes bytes into this pipe.
+    pub fn output<'p>(&'p self) -> PipeOutput<'p> where 'b: 'p {
+        PipeOutput { pipe: self }
+    }
+
+    /// Returns the input through which an encoder pulls bytes out of this pipe.
+    pub fn input<'p>(&'p self) -> PipeInput<'p> where 'b: 'p {
+        PipeInput { pipe: self }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Returns the number of queued bytes.
+    pub fn len(&self) -> usize {
+        self.len.get()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len.get() == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len.get() >= self.buf.len()
+    }
+
+    /// Returns `true` once no more bytes will be pushed into this pipe.
+    pub fn is_closed(&self) -> bool {
+        self.closed.get()
+    }
+
+    /// Returns `true` if a byte was pushed into this pipe while it was full.
+    pub fn is_overflowed(&self) -> bool {
+        self.overflowed.get()
+    }
+
+    fn push(&self, b: u8) {
+        let len = self.len.get();
+        let cap = self.buf.len();
+        if len >= cap {
+            self.overflowed.set(true);
+            return;
+        }
+        let tail = self.head.get() + len;
+        self.buf[if tail < cap { tail } else { tail - cap }].set(b);
+        self.len.set(len + 1);
+    }
+
+    fn head(&self) -> Step<u8> {
+        if self.len.get() != 0 {
+            In(self.buf[self.head.get()].get())
+        } else if !self.closed.get() {
+            Out
+        } else {
+            Over
+        }
+    }
+
+    fn step(&self) {
+        let len = self.len.get();
+        if len != 0 {
+            let head = self.head.get() + 1;
+            self.head.set(if head < self.buf.len() { head } else { 0 });
+            self.len.set(len - 1);
+        }
+    }
+}
+
+impl<'p> Output for PipeOutput<'p> {
+    type Token = u8;
+    type Out = ();
+    type Err = ();
+
+    #[inline]
+    fn is_full(&self) -> bool {
+        self.pipe.is_full()
+    }
+
+    /// Drops the byte, and marks the pipe as overflowed, if the pipe is full.
+    #[inline]
+    fn push(&mut self, b: u8) {
+        self.pipe.push(b);
+    }
+
+    #[inline]
+    fn is_blocked(&mut self) -> bool {
+        self.pipe.is_full()
+    }
+
+    /// Closes the pipe, ending the encoder's input once it drains.
+    fn take_out(self) -> Result<(), ()> {
+        self.pipe.closed.set(true);
+        Ok(())
+    }
+}
+
+impl<'p> Input for PipeInput<'p> {
+    type Token = u8;
+
+    #[inline]
+    fn head(&mut self) -> Step<u8> {
+        self.pipe.head()
+    }
+
+    #[inline]
+    fn step(&mut self) {
+        self.pipe.step();
+    }
+
+    fn over(&mut self) {
+        self.pipe.closed.set(true);
+    }
+}
+
+/// Returns a decoder that decodes its input with `decoder`, and re-encodes
+/// the decoded bytes with `encoder` into `output`, streaming the decoded
+/// bytes through `pipe`, which `decoder` outputs to, and `encoder` inputs
+/// from.
+pub fn transcode<'p, D, E>(pipe: &'p Pipe<'p>, decoder: D, encoder: E, output: E::Output) -> Transcode<'p, D, E>
+    where D: Decoder, E: Encoder {
+    Transcode {
+        pipe: pipe,
+        decoder: Some(decoder),
+        encoder: Some(encoder),
+        output: output,
+    }
+}
+
+impl<'p, D, E> Decoder for Transcode<'p, D, E>
+    where D: Decoder,
+          D::Input: Input,
+          E: Encoder,
+          E::Output: Output,
+          <E::Output as Output>::Err: fmt::Debug {
+
+    type Input = D::Input;
+    type Output = <E::Output as Output>::Out;
+    type Error = TranscodeError<D::Error, E::Error>;
+
+    fn decode(mut self, input: &mut D::Input) -> Then<Self, Self::Output, Self::Error> {
+        loop {
+            // Decode input into the pipe, until the decoder runs out of input,
+            // or blocks on a full pipe.
+            if let Some(decoder) = self.decoder.take() {
+                match decoder.decode(input) {
+                    Cont(next) => self.decoder = Some(next),
+                    // The decoder closed the pipe when it took out its output.
+                    Done(_) => (),
+                    Fail(error) => return Fail(TranscodeError::Decode(error)),
+                }
+                if self.pipe.is_overflowed() {
+                    return Fail(TranscodeError::Overflow);
+                }
+            }
+            // Encode the piped bytes, until the pipe runs out, or the output fills.
+            match self.encoder.take().unwrap().encode(&mut self.output) {
+                Cont(next) => self.encoder = Some(next),
+                Done(_) => return Done(self.output.take_out().unwrap()),
+                Fail(error) => return Fail(TranscodeError::Encode(error)),
+            }
+            // Suspend if the output is full, or if the decoder finished
+            // without closing the pipe, or if the decoder needs more input;
+            // otherwise the decoder blocked, and the encoder made room.
+            if self.output.is_full() || self.decoder.is_none() || !input.is_in() {
+                return Cont(self);
+            }
+        }
+    }
+
+    /// Does nothing: transcoding can't be reset. The `Encoder` trait has no
+    /// means to reset the encoder, and resetting the decoder alone would
+    /// desynchronize it from the bytes queued in the pipe, and from the
+    /// encoder's residual state. Construct a new transcoder instead.
+    fn reset(&mut self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use core::marker::PhantomData;
+    use crate::base64::{Base64, Base64Encoder};
+    use crate::deflate::DeflateDecoder;
+    use crate::percent::PercentDecoder;
+    use crate::input::{AsInput, BytesInput};
+    use crate::output::{SliceOutput, StrOutput};
+    use super::*;
+
+    /// Decodes pairs of hex digits into bytes, suspending while its output
+    /// is blocked.
+    struct HexDecoder<I, O> {
+        output: O,
+        hi: u8,
+        state: u32,
+        input: PhantomData<I>,
+    }
+
+    impl<I: Input<Token=u8>, O: Output<Token=u8>> HexDecoder<I, O> {
+        fn new(output: O) -> Self {
+            Self {
+                output: output,
+                hi: 0,
+                state: 1,
+                input: PhantomData,
+            }
+        }
+    }
+
+    impl<I: Input<Token=u8>, O: Output<Token=u8>> Decoder for HexDecoder<I, O> where O::Err: fmt::Debug {
+        type Input = I;
+        type Output = O::Out;
+        type Error = ();
+
+        fn decode(mut self, input: &mut I) -> Then<Self, O::Out, ()> {
+            loop {
+                if self.output.is_blocked() {
+                    return Cont(self);
+                }
+                match input.head() {
+                    In(b) => {
+                        let digit = match (b as char).to_digit(16) {
+                            Some(digit) => digit as u8,
+                            None => return Fail(()),
+                        };
+                        input.step();
+                        if self.state == 1 {
+                            self.hi = digit;
+                            self.state = 2;
+                        } else {
+                            self.output.push(self.hi << 4 | digit);
+                            self.state = 1;
+                        }
+                    },
+                    Out => return Cont(self),
+                    Over if self.state == 1 => return Done(self.output.take_out().unwrap()),
+                    Over => return Fail(()),
+                }
+            }
+        }
+
+        fn reset(&mut self) {
+            self.hi = 0;
+            self.state = 1;
+        }
+    }
+
+    fn transcode_all<'i, 'p, D, E>(mut transcoder: Transcode<'p, D, E>, input: &mut BytesInput<'i>)
+        -> Result<<E::Output as Output>::Out, TranscodeError<D::Error, E::Error>>
+        where D: Decoder<Input=BytesInput<'i>>,
+              E: Encoder,
+              E::Output: Output,
+              <E::Output as Output>::Err: fmt::Debug
+    {
+        loop {
+            match transcoder.decode(input) {
+                Cont(next) => {
+                    transcoder = next;
+                    if !input.more() {
+                        input.over();
+                    }
+                },
+                Done(output) => return Ok(output),
+                Fail(error) => return Err(error),
+            }
+        }
+    }
+
+    fn transcode_chunked<'o>(hex: &[u8], chunk: usize, scratch: &mut [u8], buffer: &'o mut [u8])
+        -> Result<&'o str, TranscodeError<(), ()>>
+    {
+        let pipe = Pipe::new(scratch);
+        let decoder = HexDecoder::new(pipe.output());
+        let encoder = Base64Encoder::new(pipe.input(), Base64);
+        let transcoder = transcode(&pipe, decoder, encoder, StrOutput::new(buffer));
+        transcode_all(transcoder, &mut BytesInput::chunked(hex, chunk))
+    }
+
+    #[test]
+    fn test_transcode_hex_to_base64() {
+        let hex = b"00010203deadbeefCAFEBABE0badf00d7f80ff";
+        let mut bytes = [0u8; 19];
+        let decoded = HexDecoder::new(SliceOutput::new(&mut bytes)).decode_exact(&mut (&hex[..]).as_input()).unwrap();
+        let mut expected = [0u8; 32];
+        let expected = Base64Encoder::new((&decoded[..]).as_input(), Base64)
+            .produce(StrOutput::new(&mut expected)).unwrap();
+        assert_eq!(expected, "AAECA96tvu/K/rq+C63wDX+A/w==");
+
+        // One byte chunks split hex pairs and base64 groups; a pipe smaller
+        // than a base64 group forces the decoder to block.
+        for &chunk in [1, 2, 3, hex.len()].iter() {
+            for &size in [1, 2, 4, 64].iter() {
+                let mut scratch = [0u8; 64];
+                let mut buffer = [0u8; 32];
+                let output = transcode_chunked(hex, chunk, &mut scratch[..size], &mut buffer).unwrap();
+                assert_eq!(output, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_transcode_decode_error() {
+        let mut scratch = [0u8; 4];
+        let mut buffer = [0u8; 32];
+        assert_eq!(transcode_chunked(b"00zz", 1, &mut scratch, &mut buffer), Err(TranscodeError::Decode(())));
+    }
+
+    #[test]
+    fn test_transcode_percent_to_base64() {
+        let encoded = b"a%20b%2Fc%41%42d";
+        // The percent decoder suspends while the pipe is full.
+        for &chunk in [1, 3, encoded.len()].iter() {
+            for &size in [1, 2, 64].iter() {
+                let mut scratch = [0u8; 64];
+                let pipe = Pipe::new(&mut scratch[..size]);
+                let decoder = PercentDecoder::new(pipe.output());
+                let encoder = Base64Encoder::new(pipe.input(), Base64);
+                let mut buffer = [0u8; 32];
+                let transcoder = transcode(&pipe, decoder, encoder, StrOutput::new(&mut buffer));
+                let output = transcode_all(transcoder, &mut BytesInput::chunked(&encoded[..], chunk)).unwrap();
+                // Base64 of "a b/cABd".
+                assert_eq!(output, "YSBiL2NBQmQ=");
+            }
+        }
+    }
+
+    #[test]
+    fn test_transcode_pipe_overflow() {
+        // The DEFLATE decoder pushes a stored block's bytes without checking
+        // whether its output is blocked.
+        let encoded = [0x01, 0x05, 0x00, 0xFA, 0xFF, b'h', b'e', b'l', b'l', b'o'];
+        for &size in [2, 5].iter() {
+            let mut window = [0u8; 16];
+            let mut scratch = [0u8; 8];
+            let pipe = Pipe::new(&mut scratch[..size]);
+            let decoder = DeflateDecoder::new(pipe.output(), &mut window);
+            let encoder = Base64Encoder::new(pipe.input(), Base64);
+            let mut buffer = [0u8; 32];
+            let transcoder = transcode(&pipe, decoder, encoder, StrOutput::new(&mut buffer));
+            let result = transcode_all(transcoder, &mut BytesInput::new(&encoded[..]));
+            if size < 5 {
+                assert_eq!(result, Err(TranscodeError::Overflow));
+                assert!(pipe.is_overflowed());
+            } else {
+                assert_eq!(result, Ok("aGVsbG8="));
+            }
+        }
+    }
+}
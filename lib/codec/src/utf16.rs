@@ -0,0 +1,309 @@
+use core::char;
+use core::fmt;
+use core::marker::PhantomData;
+use crate::step::{In, Out, Over};
+use crate::then::{Then, Cont, Done, Fail};
+use crate::input::Input;
+use crate::output::Output;
+use crate::decoder::Decoder;
+
+/// Byte order of UTF-16 code units.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Utf16Endian {
+    /// Most significant byte first.
+    Big,
+    /// Least significant byte first.
+    Little,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Utf16Error {
+    /// Encountered a high surrogate not followed by a low surrogate, or a
+    /// low surrogate not preceded by a high surrogate.
+    UnpairedSurrogate,
+    /// Input ended in the middle of a 2 byte code unit.
+    Truncated,
+    /// The output filled up before the input was decoded.
+    OutputFull,
+}
+
+/// Incremental UTF-16 decoder. Reassembles 2 byte code units, and surrogate
+/// pairs of code units, across input chunks. Completes at the end of input,
+/// following a complete character.
+pub struct Utf16Decoder<I: Input<Token=u8>, O: Output<Token=char>> {
+    pub output: O,
+    /// Byte order of the code units.
+    endian: Utf16Endian,
+    /// `true` if a leading byte order mark may still select the byte order.
+    detect: bool,
+    /// Byte order the decoder was constructed with, or `None` if it detects
+    /// the byte order from a byte order mark; restored by `reset`.
+    initial: Option<Utf16Endian>,
+    /// Bytes of the partially received code unit.
+    bytes: [u8; 2],
+    /// Number of received bytes of the current code unit.
+    have: u8,
+    /// Pending high surrogate, or `0` if none.
+    high: u16,
+    input: PhantomData<I>,
+}
+
+impl<I, O> Utf16Decoder<I, O> where I: Input<Token=u8>, O: Output<Token=char> {
+    /// Returns a decoder that selects its byte order from a leading byte
+    /// order mark, which it strips from the output. Input without a byte
+    /// order mark decodes as big-endian.
+    pub fn new(output: O) -> Self {
+        Self {
+            output: output,
+            endian: Utf16Endian::Big,
+            detect: true,
+            initial: None,
+            bytes: [0; 2],
+            have: 0,
+            high: 0,
+            input: PhantomData,
+        }
+    }
+
+    /// Returns a decoder for input of a known byte order. A leading
+    /// U+FEFF decodes as a character, rather than as a byte order mark.
+    pub fn with_endian(output: O, endian: Utf16Endian) -> Self {
+        Self {
+            output: output,
+            endian: endian,
+            detect: false,
+            initial: Some(endian),
+            bytes: [0; 2],
+            have: 0,
+            high: 0,
+            input: PhantomData,
+        }
+    }
+
+    /// Returns the byte order used to decode code units; only final once
+    /// the first code unit has been decoded.
+    pub fn endian(&self) -> Utf16Endian {
+        self.endian
+    }
+
+    pub fn consume(mut self, input: &mut I) -> Result<O::Out, Utf16Error> where O::Err: fmt::Debug {
+        loop {
+            match self.decode(input) {
+                Done(output) => return Ok(output),
+                Fail(error) => return Err(error),
+                Cont(next) => {
+                    if input.is_out() {
+                        input.over();
+                        self = next;
+                    } else {
+                        return Err(Utf16Error::OutputFull);
+                    }
+                },
+            }
+        }
+    }
+}
+
+impl<I, O> Decoder for Utf16Decoder<I, O>
+    where I: Input<Token=u8>,
+          O: Output<Token=char>,
+          O::Err: fmt::Debug {
+
+    type Input = I;
+    type Output = O::Out;
+    type Error = Utf16Error;
+
+    fn decode(mut self, input: &mut I) -> Then<Self, O::Out, Utf16Error> {
+        loop {
+            match input.head() {
+                In(b) => {
+                    // Suspend before completing a code unit whose character
+                    // the output may not have room for.
+                    if self.have == 1 && self.output.is_full() {
+                        return Cont(self);
+                    }
+                    input.step();
+                    self.bytes[self.have as usize] = b;
+                    self.have += 1;
+                    if self.have < 2 {
+                        continue;
+                    }
+                    self.have = 0;
+                    // Strip a leading byte order mark, adopting its byte order.
+                    if self.detect {
+                        self.detect = false;
+                        match self.bytes {
+                            [0xFE, 0xFF] => {
+                                self.endian = Utf16Endian::Big;
+                                continue;
+                            },
+                            [0xFF, 0xFE] => {
+                                self.endian = Utf16Endian::Little;
+                                continue;
+                            },
+                            _ => (),
+                        }
+                    }
+                    let unit = match self.endian {
+                        Utf16Endian::Big => (self.bytes[0] as u16) << 8 | self.bytes[1] as u16,
+                        Utf16Endian::Little => (self.bytes[1] as u16) << 8 | self.bytes[0] as u16,
+                    };
+                    if self.high != 0 {
+                        if unit < 0xDC00 || unit > 0xDFFF {
+                            return Fail(Utf16Error::UnpairedSurrogate);
+                        }
+                        let c = 0x10000 + ((self.high as u32 - 0xD800) << 10 | (unit as u32 - 0xDC00));
+                        self.output.push(unsafe { char::from_u32_unchecked(c) });
+                        self.high = 0;
+                    } else if unit >= 0xD800 && unit <= 0xDBFF {
+                        self.high = unit;
+                    } else if unit >= 0xDC00 && unit <= 0xDFFF {
+                        return Fail(Utf16Error::UnpairedSurrogate);
+                    } else {
+                        self.output.push(unsafe { char::from_u32_unchecked(unit as u32) });
+                    }
+                },
+                Over if self.have != 0 => return Fail(Utf16Error::Truncated),
+                Over if self.high != 0 => return Fail(Utf16Error::UnpairedSurrogate),
+                Over => return Done(self.output.take_out().unwrap()),
+                Out => return Cont(self),
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.endian = self.initial.unwrap_or(Utf16Endian::Big);
+        self.detect = self.initial.is_none();
+        self.have = 0;
+        self.high = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::input::{AsInput, BytesInput, OffsetInput};
+    use crate::output::StrOutput;
+    use super::*;
+
+    #[test]
+    fn test_utf16_decode_bom() {
+        let mut buffer = [0u8; 16];
+        let decoder = Utf16Decoder::new(StrOutput::new(&mut buffer));
+        let encoded = [0xFF, 0xFE, b'h', 0, b'i', 0];
+        assert_eq!(decoder.consume(&mut (&encoded[..]).as_input()).unwrap(), "hi");
+
+        let mut buffer = [0u8; 16];
+        let decoder = Utf16Decoder::new(StrOutput::new(&mut buffer));
+        let encoded = [0xFE, 0xFF, 0, b'h', 0, b'i'];
+        assert_eq!(decoder.consume(&mut (&encoded[..]).as_input()).unwrap(), "hi");
+
+        let mut buffer = [0u8; 16];
+        let decoder = Utf16Decoder::new(StrOutput::new(&mut buffer));
+        let encoded = [0, b'h', 0, b'i'];
+        assert_eq!(decoder.consume(&mut (&encoded[..]).as_input()).unwrap(), "hi");
+
+        let mut buffer = [0u8; 16];
+        let decoder = Utf16Decoder::with_endian(StrOutput::new(&mut buffer), Utf16Endian::Little);
+        let encoded = [0xFF, 0xFE, b'h', 0];
+        assert_eq!(decoder.consume(&mut (&encoded[..]).as_input()).unwrap(), "\u{FEFF}h");
+    }
+
+    #[test]
+    fn test_utf16_reset_restores_byte_order() {
+        // A detected byte order is forgotten, and detection re-armed.
+        let mut buffer = [0u8; 16];
+        let decoder = Utf16Decoder::new(StrOutput::new(&mut buffer));
+        let mut decoder = match decoder.decode(&mut BytesInput::new(&[0xFF, 0xFE, b'h'])) {
+            Cont(next) => next,
+            _ => panic!(),
+        };
+        assert_eq!(decoder.endian(), Utf16Endian::Little);
+        decoder.reset();
+        assert_eq!(decoder.endian(), Utf16Endian::Big);
+        let encoded = [0xFE, 0xFF, 0, b'i'];
+        assert_eq!(decoder.consume(&mut BytesInput::new(&encoded)).unwrap(), "i");
+
+        // A configured byte order is kept, without detection.
+        let mut buffer = [0u8; 16];
+        let decoder = Utf16Decoder::with_endian(StrOutput::new(&mut buffer), Utf16Endian::Little);
+        let mut decoder = match decoder.decode(&mut BytesInput::new(&[b'h'])) {
+            Cont(next) => next,
+            _ => panic!(),
+        };
+        decoder.reset();
+        let encoded = [0xFF, 0xFE, b'i', 0];
+        assert_eq!(decoder.consume(&mut BytesInput::new(&encoded)).unwrap(), "\u{FEFF}i");
+    }
+
+    #[test]
+    fn test_utf16_decode_full_output() {
+        let encoded = [b'h', 0, b'i', 0, b'j', 0];
+        let mut buffer = [0u8; 2];
+        let decoder = Utf16Decoder::with_endian(StrOutput::new(&mut buffer), Utf16Endian::Little);
+        let mut input = (&encoded[..]).as_input();
+        match decoder.decode(&mut input) {
+            Cont(_) => (),
+            _ => panic!(),
+        }
+        // The last code unit wasn't completed.
+        assert_eq!(input.offset(), 5);
+
+        let mut buffer = [0u8; 2];
+        let decoder = Utf16Decoder::with_endian(StrOutput::new(&mut buffer), Utf16Endian::Little);
+        assert_eq!(decoder.consume(&mut (&encoded[..]).as_input()), Err(Utf16Error::OutputFull));
+        let mut buffer = [0u8; 2];
+        let decoder = Utf16Decoder::with_endian(StrOutput::new(&mut buffer), Utf16Endian::Little);
+        assert_eq!(decoder.consume(&mut (&encoded[..4]).as_input()).unwrap(), "hi");
+    }
+
+    #[test]
+    fn test_utf16_decode_chunked_surrogate_pair() {
+        // U+1F600 encodes as the surrogate pair D83D DE00, split here
+        // across the chunks [3D] [D8 00] [DE 21 00].
+        let chunks: [&[u8]; 3] = [&[0x3D], &[0xD8, 0x00], &[0xDE, b'!', 0]];
+        let mut buffer = [0u8; 16];
+        let mut decoder = Utf16Decoder::with_endian(StrOutput::new(&mut buffer), Utf16Endian::Little);
+        for chunk in &chunks[..2] {
+            decoder = match decoder.decode(&mut BytesInput::new(chunk)) {
+                Cont(next) => next,
+                Done(_) => panic!("done"),
+                Fail(error) => panic!("{:?}", error),
+            };
+        }
+        let mut input = BytesInput::new(chunks[2]);
+        let decoder = match decoder.decode(&mut input) {
+            Cont(next) => next,
+            Done(_) => panic!("done"),
+            Fail(error) => panic!("{:?}", error),
+        };
+        input.over();
+        match decoder.decode(&mut input) {
+            Done(decoded) => assert_eq!(decoded, "\u{1F600}!"),
+            Cont(_) => panic!("cont"),
+            Fail(error) => panic!("{:?}", error),
+        }
+    }
+
+    #[test]
+    fn test_utf16_unpaired_surrogate() {
+        let mut buffer = [0u8; 16];
+        let decoder = Utf16Decoder::with_endian(StrOutput::new(&mut buffer), Utf16Endian::Big);
+        let encoded = [0xD8, 0x3D, 0, b'h'];
+        assert_eq!(decoder.consume(&mut (&encoded[..]).as_input()), Err(Utf16Error::UnpairedSurrogate));
+
+        let mut buffer = [0u8; 16];
+        let decoder = Utf16Decoder::with_endian(StrOutput::new(&mut buffer), Utf16Endian::Big);
+        let encoded = [0xDE, 0x00];
+        assert_eq!(decoder.consume(&mut (&encoded[..]).as_input()), Err(Utf16Error::UnpairedSurrogate));
+
+        let mut buffer = [0u8; 16];
+        let decoder = Utf16Decoder::with_endian(StrOutput::new(&mut buffer), Utf16Endian::Big);
+        let encoded = [0xD8, 0x3D];
+        assert_eq!(decoder.consume(&mut (&encoded[..]).as_input()), Err(Utf16Error::UnpairedSurrogate));
+
+        let mut buffer = [0u8; 16];
+        let decoder = Utf16Decoder::with_endian(StrOutput::new(&mut buffer), Utf16Endian::Big);
+        let encoded = [0, b'h', 0];
+        assert_eq!(decoder.consume(&mut (&encoded[..]).as_input()), Err(Utf16Error::Truncated));
+    }
+}
@@ -0,0 +1,298 @@
+use core::fmt;
+use core::marker::PhantomData;
+use crate::step::{In, Out, Over};
+use crate::then::{Then, Cont, Done, Fail};
+use crate::input::Input;
+use crate::output::Output;
+use crate::decoder::Decoder;
+use crate::encoder::Encoder;
+
+/// Z85 digit alphabet, as defined by ZeroMQ RFC 32.
+const Z85_CHARS: &'static [u8; 85] =
+    b"0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ.-:+=^!/*?&<>()[]{}@%$#";
+
+/// Z85 digit values of the printable ASCII characters `' '..='\x7F'`;
+/// `0xFF` for characters outside the alphabet.
+const Z85_DIGITS: [u8; 96] = [
+    0xFF, 0x44, 0xFF, 0x54, 0x53, 0x52, 0x48, 0xFF, 0x4B, 0x4C, 0x46, 0x41, 0xFF, 0x3F, 0x3E, 0x45,
+    0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x40, 0xFF, 0x49, 0x42, 0x4A, 0x47,
+    0x51, 0x24, 0x25, 0x26, 0x27, 0x28, 0x29, 0x2A, 0x2B, 0x2C, 0x2D, 0x2E, 0x2F, 0x30, 0x31, 0x32,
+    0x33, 0x34, 0x35, 0x36, 0x37, 0x38, 0x39, 0x3A, 0x3B, 0x3C, 0x3D, 0x4D, 0xFF, 0x4E, 0x43, 0xFF,
+    0xFF, 0x0A, 0x0B, 0x0C, 0x0D, 0x0E, 0x0F, 0x10, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17, 0x18,
+    0x19, 0x1A, 0x1B, 0x1C, 0x1D, 0x1E, 0x1F, 0x20, 0x21, 0x22, 0x23, 0x4F, 0xFF, 0x50, 0xFF, 0xFF,
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Z85Error {
+    /// Encountered a non-digit in the middle of a 5 digit group.
+    Unexpected,
+    /// Input ended in the middle of a group; Z85 encodes only multiples of
+    /// 4 bytes, as multiples of 5 digits.
+    Unaligned,
+    /// A 5 digit group exceeds the range of a 4 byte group.
+    Overflow,
+}
+
+/// Incremental Z85 decoder. Decodes each group of 5 digits into 4 bytes,
+/// carrying partial groups across input chunks. Completes at the first
+/// non-digit, or at the end of input, following a complete group.
+pub struct Z85Decoder<I: Input<Token=char>, O: Output<Token=u8>> {
+    pub output: O,
+    value: u64,
+    count: u8,
+    input: PhantomData<I>,
+}
+
+/// Incremental Z85 encoder. Encodes each group of 4 bytes into 5 digits,
+/// carrying partial groups across input chunks, and pending digits across
+/// full outputs. Fails if the input length isn't a multiple of 4.
+pub struct Z85Encoder<I: Input<Token=u8>, O: Output<Token=char>> {
+    pub input: I,
+    value: u32,
+    count: u8,
+    digits: [u8; 5],
+    emit: u8,
+    output: PhantomData<O>,
+}
+
+impl<I, O> Z85Decoder<I, O> where I: Input<Token=char>, O: Output<Token=u8> {
+    pub fn new(output: O) -> Self {
+        Self {
+            output: output,
+            value: 0,
+            count: 0,
+            input: PhantomData,
+        }
+    }
+
+    pub fn consume(mut self, input: &mut I) -> Result<O::Out, Z85Error> where O::Err: fmt::Debug {
+        loop {
+            match self.decode(input) {
+                Done(output) => return Ok(output),
+                Fail(error) => return Err(error),
+                Cont(next) => {
+                    if input.is_out() {
+                        input.over();
+                        self = next;
+                    } else {
+                        return Err(Z85Error::Unexpected);
+                    }
+                },
+            }
+        }
+    }
+}
+
+impl<I, O> Decoder for Z85Decoder<I, O>
+    where I: Input<Token=char>,
+          O: Output<Token=u8>,
+          O::Err: fmt::Debug {
+
+    type Input = I;
+    type Output = O::Out;
+    type Error = Z85Error;
+
+    fn decode(mut self, input: &mut I) -> Then<Self, O::Out, Z85Error> {
+        loop {
+            match input.head() {
+                In(c) => match decode_z85_char(c) {
+                    Some(digit) => {
+                        input.step();
+                        self.value = self.value * 85 + digit as u64;
+                        self.count += 1;
+                        if self.count == 5 {
+                            if self.value > u32::MAX as u64 {
+                                return Fail(Z85Error::Overflow);
+                            }
+                            let value = self.value as u32;
+                            self.output.push((value >> 24) as u8);
+                            self.output.push((value >> 16) as u8);
+                            self.output.push((value >> 8) as u8);
+                            self.output.push(value as u8);
+                            self.value = 0;
+                            self.count = 0;
+                        }
+                    },
+                    None if self.count == 0 => return Done(self.output.take_out().unwrap()),
+                    None => return Fail(Z85Error::Unexpected),
+                },
+                Over if self.count == 0 => return Done(self.output.take_out().unwrap()),
+                Over => return Fail(Z85Error::Unaligned),
+                Out => return Cont(self),
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.value = 0;
+        self.count = 0;
+    }
+}
+
+impl<I, O> Z85Encoder<I, O> where I: Input<Token=u8>, O: Output<Token=char> {
+    pub fn new(input: I) -> Self {
+        Self {
+            input: input,
+            value: 0,
+            count: 0,
+            digits: [0; 5],
+            emit: 5,
+            output: PhantomData,
+        }
+    }
+
+    pub fn produce(mut self, mut output: O) -> Result<O::Out, Z85Error> where O::Err: fmt::Debug {
+        loop {
+            match self.encode(&mut output) {
+                Done(_) => return Ok(output.take_out().unwrap()),
+                Fail(error) => return Err(error),
+                Cont(next) => {
+                    self = next;
+                    self.input.over();
+                }
+            }
+        }
+    }
+}
+
+impl<I, O> Encoder for Z85Encoder<I, O> where I: Input<Token=u8>, O: Output<Token=char> {
+    type Input = I;
+    type Output = O;
+    type Error = Z85Error;
+
+    fn encode(mut self, output: &mut O) -> Then<Self, I, Z85Error> {
+        loop {
+            // Flush the digits of the last complete group.
+            while self.emit < 5 {
+                if output.is_full() {
+                    return Cont(self);
+                }
+                output.push(Z85_CHARS[self.digits[self.emit as usize] as usize] as char);
+                self.emit += 1;
+            }
+            match self.input.head() {
+                In(b) => {
+                    self.input.step();
+                    self.value = self.value << 8 | b as u32;
+                    self.count += 1;
+                    if self.count == 4 {
+                        let mut value = self.value;
+                        for i in (0..5).rev() {
+                            self.digits[i] = (value % 85) as u8;
+                            value /= 85;
+                        }
+                        self.emit = 0;
+                        self.value = 0;
+                        self.count = 0;
+                    }
+                },
+                Over if self.count == 0 => return Done(self.input),
+                Over => return Fail(Z85Error::Unaligned),
+                Out => return Cont(self),
+            }
+        }
+    }
+}
+
+#[inline]
+fn decode_z85_char(c: char) -> Option<u8> {
+    let c = c as u32;
+    if c >= 0x20 && c < 0x80 {
+        let digit = Z85_DIGITS[(c - 0x20) as usize];
+        if digit != 0xFF {
+            return Some(digit);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::input::{AsInput, BytesInput, Utf8Input};
+    use crate::output::{SliceOutput, StrOutput};
+    use super::*;
+
+    const HELLO: [u8; 8] = [0x86, 0x4F, 0xD2, 0x6F, 0xB5, 0x59, 0xF7, 0x5B];
+
+    #[test]
+    fn test_z85_transcode() {
+        let mut buffer = [0u8; 16];
+        let encoder = Z85Encoder::new((&HELLO[..]).as_input());
+        assert_eq!(encoder.produce(StrOutput::new(&mut buffer)).unwrap(), "HelloWorld");
+
+        let mut buffer = [0u8; 16];
+        let decoder = Z85Decoder::new(SliceOutput::new(&mut buffer));
+        assert_eq!(decoder.consume(&mut "HelloWorld".as_input()).unwrap(), &HELLO[..]);
+
+        for c in 0u32..0x80 {
+            let c = core::char::from_u32(c).unwrap();
+            match Z85_CHARS.iter().position(|&x| x as char == c) {
+                Some(digit) => assert_eq!(decode_z85_char(c), Some(digit as u8)),
+                None => assert_eq!(decode_z85_char(c), None),
+            }
+        }
+    }
+
+    #[test]
+    fn test_z85_decode_chunked() {
+        let encoded = *b"HelloWorld";
+        let mut input = Utf8Input::new(BytesInput::chunked(&encoded[..], 3));
+        let mut buffer = [0u8; 16];
+        let mut decoder = Z85Decoder::new(SliceOutput::new(&mut buffer));
+        let decoded = loop {
+            match decoder.decode(&mut input) {
+                Cont(next) => {
+                    decoder = next;
+                    if !input.input_mut().more() {
+                        input.over();
+                    }
+                },
+                Done(output) => break output,
+                Fail(error) => panic!("{:?}", error),
+            }
+        };
+        assert_eq!(decoded, &HELLO[..]);
+    }
+
+    #[test]
+    fn test_z85_decode_utf8_invalid() {
+        // A stray lead byte ends the first group's decode without consuming
+        // the digit after it.
+        let mut input = Utf8Input::new(BytesInput::new(b"Hello\xC3World"));
+        let mut buffer = [0u8; 16];
+        let decoder = Z85Decoder::new(SliceOutput::new(&mut buffer));
+        assert_eq!(decoder.consume(&mut input).unwrap(), &HELLO[..4]);
+        assert!(input.is_invalid());
+        input.step();
+        let mut buffer = [0u8; 16];
+        let decoder = Z85Decoder::new(SliceOutput::new(&mut buffer));
+        assert_eq!(decoder.consume(&mut input).unwrap(), &HELLO[4..]);
+
+        // A sequence truncated by the end of input is a non-digit, rather
+        // than the end of the group.
+        let mut input = Utf8Input::new(BytesInput::new(b"Hel\xF0\x9F"));
+        let mut buffer = [0u8; 16];
+        let decoder = Z85Decoder::new(SliceOutput::new(&mut buffer));
+        assert_eq!(decoder.consume(&mut input), Err(Z85Error::Unexpected));
+        assert!(input.is_invalid());
+    }
+
+    #[test]
+    fn test_z85_unaligned() {
+        let mut buffer = [0u8; 16];
+        let decoder = Z85Decoder::new(SliceOutput::new(&mut buffer));
+        assert_eq!(decoder.consume(&mut "HelloWor".as_input()), Err(Z85Error::Unaligned));
+
+        let mut buffer = [0u8; 16];
+        let decoder = Z85Decoder::new(SliceOutput::new(&mut buffer));
+        assert_eq!(decoder.consume(&mut "Hel\"oWorld".as_input()), Err(Z85Error::Unexpected));
+
+        let mut buffer = [0u8; 16];
+        let decoder = Z85Decoder::new(SliceOutput::new(&mut buffer));
+        assert_eq!(decoder.consume(&mut "#####".as_input()), Err(Z85Error::Overflow));
+
+        let mut buffer = [0u8; 16];
+        let encoder = Z85Encoder::new((&HELLO[..7]).as_input());
+        assert_eq!(encoder.produce(StrOutput::new(&mut buffer)), Err(Z85Error::Unaligned));
+    }
+}
@@ -0,0 +1,174 @@
+//! Roundtrip test harness. Checks that every registered codec decodes its
+//! own encodings back to the original bytes, for a battery of inputs, with
+//! the encoded input split into two chunks at every possible boundary.
+
+extern crate tg_codec;
+
+use core::fmt;
+use tg_codec::then::{Cont, Done, Fail};
+use tg_codec::input::{AsInput, Input, BytesInput, Utf8Input};
+use tg_codec::output::{SliceOutput, StrOutput};
+use tg_codec::decoder::Decoder;
+use tg_codec::base64::{Base64Alphabet, Base64, Base64Url, Base64Decoder, Base64Encoder};
+use tg_codec::hex::{HexDecoder, HexEncoder};
+use tg_codec::percent::{PercentDecoder, PercentEncoder};
+
+/// An encoder, decoder pair registered with the roundtrip harness.
+trait RoundtripCodec: Sync {
+    fn name(&self) -> &'static str;
+
+    /// Encodes all of `data` into `buffer`; returns the encoded bytes.
+    fn encode<'b>(&self, data: &[u8], buffer: &'b mut [u8]) -> &'b [u8];
+
+    /// Decodes the concatenation of `pieces` into `buffer`, presenting each
+    /// piece to the decoder as a separate input chunk.
+    fn decode<'b>(&self, pieces: &[&[u8]], buffer: &'b mut [u8]) -> &'b [u8];
+}
+
+/// Drives `decoder` over each of `pieces` in turn, converting each piece
+/// to the decoder's input type with `as_input`, then over the end of input.
+/// Panics if the decoder fails, or completes before the end of input.
+fn decode_pieces<'p, D, F>(mut decoder: D, pieces: &[&'p [u8]], as_input: F) -> D::Output
+    where D: Decoder,
+          D::Input: Input,
+          D::Error: fmt::Debug,
+          F: Fn(&'p [u8]) -> D::Input,
+{
+    for piece in pieces {
+        decoder = match decoder.decode(&mut as_input(piece)) {
+            Cont(next) => next,
+            Done(_) => panic!("completed before end of input"),
+            Fail(error) => panic!("{:?}", error),
+        };
+    }
+    let mut input = as_input(&[]);
+    input.over();
+    match decoder.decode(&mut input) {
+        Done(output) => output,
+        Cont(_) => panic!("incomplete at end of input"),
+        Fail(error) => panic!("{:?}", error),
+    }
+}
+
+struct Base64Codec {
+    name: &'static str,
+    alphabet: Base64Alphabet,
+    padded: bool,
+}
+
+impl RoundtripCodec for Base64Codec {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn encode<'b>(&self, data: &[u8], buffer: &'b mut [u8]) -> &'b [u8] {
+        let encoder = Base64Encoder::new(data.as_input(), self.alphabet).padded(self.padded);
+        encoder.produce(StrOutput::new(buffer)).unwrap().as_bytes()
+    }
+
+    fn decode<'b>(&self, pieces: &[&[u8]], buffer: &'b mut [u8]) -> &'b [u8] {
+        let decoder = Base64Decoder::new(SliceOutput::new(buffer)).padded(self.padded).strict(true);
+        decode_pieces(decoder, pieces, |piece| Utf8Input::new(BytesInput::new(piece)))
+    }
+}
+
+struct HexCodec {
+    name: &'static str,
+    uppercase: bool,
+}
+
+impl RoundtripCodec for HexCodec {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn encode<'b>(&self, data: &[u8], buffer: &'b mut [u8]) -> &'b [u8] {
+        let encoder = HexEncoder::new(data.as_input()).uppercase(self.uppercase);
+        encoder.produce(StrOutput::new(buffer)).unwrap().as_bytes()
+    }
+
+    fn decode<'b>(&self, pieces: &[&[u8]], buffer: &'b mut [u8]) -> &'b [u8] {
+        let decoder = HexDecoder::new(SliceOutput::new(buffer));
+        decode_pieces(decoder, pieces, |piece| Utf8Input::new(BytesInput::new(piece)))
+    }
+}
+
+struct PercentCodec {
+    name: &'static str,
+    safe: &'static [u8],
+}
+
+impl RoundtripCodec for PercentCodec {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn encode<'b>(&self, data: &[u8], buffer: &'b mut [u8]) -> &'b [u8] {
+        let encoder = PercentEncoder::new(data.as_input()).safe(self.safe);
+        encoder.produce(StrOutput::new(buffer)).unwrap().as_bytes()
+    }
+
+    fn decode<'b>(&self, pieces: &[&[u8]], buffer: &'b mut [u8]) -> &'b [u8] {
+        let decoder = PercentDecoder::new(SliceOutput::new(buffer));
+        decode_pieces(decoder, pieces, |piece| BytesInput::new(piece))
+    }
+}
+
+/// Codecs checked by every roundtrip test, one entry per configuration.
+static CODECS: [&dyn RoundtripCodec; 7] = [
+    &Base64Codec { name: "base64", alphabet: Base64, padded: true },
+    &Base64Codec { name: "base64 unpadded", alphabet: Base64, padded: false },
+    &Base64Codec { name: "base64url", alphabet: Base64Url, padded: true },
+    &HexCodec { name: "hex", uppercase: false },
+    &HexCodec { name: "hex uppercase", uppercase: true },
+    &PercentCodec { name: "percent", safe: &[] },
+    &PercentCodec { name: "percent path", safe: b"/:@" },
+];
+
+/// Asserts that every registered codec roundtrips `data`, decoding its
+/// encoding whole, and split into two chunks at every boundary.
+fn check_roundtrip(data: &[u8]) {
+    for codec in CODECS.iter() {
+        let mut encoded = [0u8; 1024];
+        let encoded = codec.encode(data, &mut encoded);
+        for split in 0..=encoded.len() {
+            let mut decoded = [0u8; 256];
+            let (head, tail) = encoded.split_at(split);
+            assert_eq!(codec.decode(&[head, tail], &mut decoded), data,
+                       "{} split at {} of {:?}", codec.name(), split, encoded);
+        }
+        let mut decoded = [0u8; 256];
+        assert_eq!(codec.decode(&[encoded], &mut decoded), data, "{} whole", codec.name());
+    }
+}
+
+#[test]
+fn test_roundtrip_empty() {
+    check_roundtrip(&[]);
+}
+
+#[test]
+fn test_roundtrip_single_bytes() {
+    for b in 0..=255u8 {
+        check_roundtrip(&[b]);
+    }
+}
+
+#[test]
+fn test_roundtrip_all_bytes() {
+    let mut data = [0u8; 256];
+    for (i, b) in data.iter_mut().enumerate() {
+        *b = i as u8;
+    }
+    check_roundtrip(&data);
+    data.reverse();
+    check_roundtrip(&data);
+}
+
+#[test]
+fn test_roundtrip_partial_quanta() {
+    let data = b"roundtrip";
+    for len in 0..=data.len() {
+        check_roundtrip(&data[..len]);
+    }
+}
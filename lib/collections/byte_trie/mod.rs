@@ -0,0 +1,466 @@
+use core::fmt;
+use core::iter::FusedIterator;
+use core::mem;
+use tg_mem::alloc::{Hold, Holder, HoldError};
+use tg_mem::lease::RawBuf;
+
+/// Radix trie map keyed by byte sequences. Unlike a `HashTrieMap`, which
+/// distributes keys by hash, a `ByteTrieMap` arranges keys by their bytes,
+/// so that all entries sharing a common prefix occupy a common subtree.
+/// Runs of bytes without branches collapse into single edges.
+pub struct ByteTrieMap<'a, V> {
+    /// Root node, whose edge label is always empty.
+    root: ByteTrieNode<'a, V>,
+    /// Number of entries in the trie.
+    len: usize,
+}
+
+/// Radix trie node, reached by a labeled edge from its parent.
+struct ByteTrieNode<'a, V> {
+    /// Bytes of the edge leading into this node.
+    label: RawBuf<'a, u8>,
+    /// Value associated with the key that ends at this node, if any.
+    value: Option<V>,
+    /// Child nodes, ordered by the first byte of their edge labels.
+    children: RawBuf<'a, ByteTrieNode<'a, V>>,
+}
+
+/// Iterator over the entries of a `ByteTrieMap` whose keys start with a
+/// given prefix, in lexicographic key order. Yields a copy of each key,
+/// allocated in the map's `Hold`. Yields a `HoldError`, and then ends, if
+/// the map's `Hold` fails to allocate a key, or to extend the traversal
+/// path.
+pub struct ByteTrieMapPrefixIter<'m, 'a: 'm, V: 'm> {
+    /// Path of nodes from the prefix node to the current node.
+    stack: RawBuf<'a, ByteTrieFrame<'m, 'a, V>>,
+    /// Concatenated edge labels of the current path.
+    key: RawBuf<'a, u8>,
+}
+
+/// Iterator over the entries of a `ByteTrieMap` whose keys start with a
+/// given prefix, in lexicographic key order. Yields a copy of each key,
+/// allocated in the map's `Hold`. Panics if the map's `Hold` fails to
+/// allocate a key, or to extend the traversal path.
+pub struct ByteTrieMapIter<'m, 'a: 'm, V: 'm> {
+    /// Fallible iterator whose errors panic.
+    iter: ByteTrieMapPrefixIter<'m, 'a, V>,
+}
+
+/// Traversal state of one node on the path of a `ByteTrieMapPrefixIter`.
+struct ByteTrieFrame<'m, 'a: 'm, V: 'm> {
+    node: &'m ByteTrieNode<'a, V>,
+    /// Number of key bytes to truncate when leaving the node.
+    label_len: usize,
+    /// Index of the next child to visit.
+    next: usize,
+    /// `true` once the node's own value has been yielded.
+    visited: bool,
+}
+
+impl<V> ByteTrieMap<'static, V> {
+    /// Constructs a new `ByteTrieMap` that will allocate its data in the
+    /// global `Hold`.
+    #[inline]
+    pub fn new() -> ByteTrieMap<'static, V> {
+        ByteTrieMap::hold_new(Hold::global())
+    }
+}
+
+impl<'a, V> ByteTrieMap<'a, V> {
+    /// Constructs a new `ByteTrieMap` that will allocate its data in `Hold`.
+    #[inline]
+    pub fn hold_new(hold: &dyn Hold<'a>) -> ByteTrieMap<'a, V> {
+        ByteTrieMap {
+            root: ByteTrieNode::hold_empty(hold),
+            len: 0,
+        }
+    }
+
+    /// Returns `true` if this `ByteTrieMap` contains no entries.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the number of entries in this `ByteTrieMap`.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if this `ByteTrieMap` contains the given `key`.
+    pub fn contains_key(&self, key: &[u8]) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Returns the value associated with the given `key`, or `None` if no
+    /// association exists.
+    pub fn get(&self, key: &[u8]) -> Option<&V> {
+        let mut node = &self.root;
+        let mut key = key;
+        loop {
+            if key.is_empty() {
+                return node.value.as_ref();
+            }
+            node = match node.find_child(key[0]) {
+                Ok(i) => &node.children[i],
+                Err(_) => return None,
+            };
+            if !key.starts_with(&node.label) {
+                return None;
+            }
+            key = &key[node.label.len()..];
+        }
+    }
+
+    /// Returns a mutable reference to the value associated with the given
+    /// `key`, or `None` if no association exists.
+    pub fn get_mut(&mut self, key: &[u8]) -> Option<&mut V> {
+        let mut node = &mut self.root;
+        let mut key = key;
+        loop {
+            if key.is_empty() {
+                return node.value.as_mut();
+            }
+            node = match node.find_child(key[0]) {
+                Ok(i) => &mut node.children[i],
+                Err(_) => return None,
+            };
+            if !key.starts_with(&node.label) {
+                return None;
+            }
+            key = &key[node.label.len()..];
+        }
+    }
+
+    /// Associates a new `value` with the given `key`; returns the previous
+    /// value associated with the `key`, if defined. If the map's `Hold` fails
+    /// to allocate any required new memory, returns the `value`, along with
+    /// a `HoldError`, and leaves the map in its original state.
+    pub fn insert(&mut self, key: &[u8], value: V) -> Result<Option<V>, (V, HoldError)> {
+        let hold = self.holder();
+        let old_value = self.root.insert(hold, key, value)?;
+        if old_value.is_none() {
+            self.len += 1;
+        }
+        Ok(old_value)
+    }
+
+    /// Disassociates the given `key`; returns the previous value associated
+    /// with the `key`, if any. Nodes left without a value or children get
+    /// pruned, and nodes left with a single child merge with that child.
+    /// A merge that fails to allocate leaves the nodes unmerged, which
+    /// affects only the compactness of the trie, not its contents.
+    pub fn remove(&mut self, key: &[u8]) -> Option<V> {
+        let value = self.root.remove(key);
+        if value.is_some() {
+            self.len -= 1;
+        }
+        value
+    }
+
+    /// Returns an iterator over all entries whose keys start with `prefix`,
+    /// in lexicographic key order. Returns a `HoldError` if the map's `Hold`
+    /// fails to allocate iteration state.
+    pub fn try_iter_prefix<'m>(&'m self, prefix: &[u8]) -> Result<ByteTrieMapPrefixIter<'m, 'a, V>, HoldError> {
+        let hold = self.holder();
+        let mut stack = RawBuf::try_hold_empty(hold)?;
+        let mut key = RawBuf::try_hold_empty(hold)?;
+        let mut node = &self.root;
+        let mut rest = prefix;
+        loop {
+            if rest.is_empty() {
+                stack.try_push(ByteTrieFrame {
+                    node: node,
+                    label_len: 0,
+                    next: 0,
+                    visited: false,
+                })?;
+                break;
+            }
+            node = match node.find_child(rest[0]) {
+                Ok(i) => &node.children[i],
+                Err(_) => break,
+            };
+            let common = common_prefix_len(&node.label, rest);
+            if common < node.label.len() && common < rest.len() {
+                // The prefix diverges within the edge label.
+                break;
+            }
+            key.try_extend_from_slice(&node.label)?;
+            rest = &rest[common..];
+        }
+        Ok(ByteTrieMapPrefixIter {
+            stack: stack,
+            key: key,
+        })
+    }
+
+    /// Returns an iterator over all entries in lexicographic key order.
+    /// Returns a `HoldError` if the map's `Hold` fails to allocate iteration
+    /// state.
+    pub fn try_iter<'m>(&'m self) -> Result<ByteTrieMapPrefixIter<'m, 'a, V>, HoldError> {
+        self.try_iter_prefix(&[])
+    }
+
+    /// Returns an iterator over all entries whose keys start with `prefix`,
+    /// in lexicographic key order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the map's `Hold` fails to allocate iteration state, or a
+    /// copy of a key.
+    pub fn iter_prefix<'m>(&'m self, prefix: &[u8]) -> ByteTrieMapIter<'m, 'a, V> {
+        ByteTrieMapIter { iter: self.try_iter_prefix(prefix).unwrap() }
+    }
+
+    /// Returns an iterator over all entries in lexicographic key order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the map's `Hold` fails to allocate iteration state, or a
+    /// copy of a key.
+    pub fn iter<'m>(&'m self) -> ByteTrieMapIter<'m, 'a, V> {
+        self.iter_prefix(&[])
+    }
+}
+
+impl<'a, V> ByteTrieNode<'a, V> {
+    fn hold_empty(hold: &dyn Hold<'a>) -> ByteTrieNode<'a, V> {
+        ByteTrieNode {
+            label: RawBuf::hold_empty(hold),
+            value: None,
+            children: RawBuf::hold_empty(hold),
+        }
+    }
+
+    /// Returns `Ok` with the index of the child whose label starts with `b`,
+    /// or `Err` with the index at which to insert such a child.
+    fn find_child(&self, b: u8) -> Result<usize, usize> {
+        self.children.binary_search_by(|child| child.label[0].cmp(&b))
+    }
+
+    fn insert(&mut self, hold: &'a dyn Hold<'a>, key: &[u8], value: V) -> Result<Option<V>, (V, HoldError)> {
+        if key.is_empty() {
+            return Ok(mem::replace(&mut self.value, Some(value)));
+        }
+        let i = match self.find_child(key[0]) {
+            Ok(i) => i,
+            Err(i) => {
+                // No edge shares the key's first byte; add a new leaf.
+                let label = match RawBuf::try_hold_copy(hold, key) {
+                    Ok(label) => label,
+                    Err(error) => return Err((value, error)),
+                };
+                let children = match RawBuf::try_hold_empty(hold) {
+                    Ok(children) => children,
+                    Err(error) => return Err((value, error)),
+                };
+                if let Err(error) = self.children.try_reserve(1) {
+                    return Err((value, error));
+                }
+                // Can't reallocate, because the slot was reserved above.
+                self.children.insert(i, ByteTrieNode {
+                    label: label,
+                    value: Some(value),
+                    children: children,
+                });
+                return Ok(None);
+            },
+        };
+        let child = &mut self.children[i];
+        let common = common_prefix_len(&child.label, key);
+        if common == child.label.len() {
+            return child.insert(hold, &key[common..], value);
+        }
+        // The key diverges within the child's edge; split the edge at the
+        // divergence point. Allocate everything up front, so that failure
+        // leaves the trie unchanged.
+        let head = match RawBuf::try_hold_copy(hold, &child.label[..common]) {
+            Ok(head) => head,
+            Err(error) => return Err((value, error)),
+        };
+        let tail = match RawBuf::try_hold_copy(hold, &child.label[common..]) {
+            Ok(tail) => tail,
+            Err(error) => return Err((value, error)),
+        };
+        let mut children = match RawBuf::try_hold_cap(hold, 2) {
+            Ok(children) => children,
+            Err(error) => return Err((value, error)),
+        };
+        let split_children = match RawBuf::try_hold_empty(hold) {
+            Ok(split_children) => split_children,
+            Err(error) => return Err((value, error)),
+        };
+        let leaf = if common < key.len() {
+            let leaf_label = match RawBuf::try_hold_copy(hold, &key[common..]) {
+                Ok(leaf_label) => leaf_label,
+                Err(error) => return Err((value, error)),
+            };
+            let leaf_children = match RawBuf::try_hold_empty(hold) {
+                Ok(leaf_children) => leaf_children,
+                Err(error) => return Err((value, error)),
+            };
+            Some((leaf_label, leaf_children))
+        } else {
+            None
+        };
+        // Can't fail to allocate past this point.
+        let mut split = mem::replace(child, ByteTrieNode {
+            label: head,
+            value: None,
+            children: split_children,
+        });
+        split.label = tail;
+        match leaf {
+            Some((leaf_label, leaf_children)) => {
+                let leaf = ByteTrieNode {
+                    label: leaf_label,
+                    value: Some(value),
+                    children: leaf_children,
+                };
+                if leaf.label[0] < split.label[0] {
+                    children.push(leaf);
+                    children.push(split);
+                } else {
+                    children.push(split);
+                    children.push(leaf);
+                }
+            },
+            None => {
+                children.push(split);
+                child.value = Some(value);
+            },
+        }
+        child.children = children;
+        Ok(None)
+    }
+
+    fn remove(&mut self, key: &[u8]) -> Option<V> {
+        if key.is_empty() {
+            return self.value.take();
+        }
+        let i = match self.find_child(key[0]) {
+            Ok(i) => i,
+            Err(_) => return None,
+        };
+        let value = {
+            let child = &mut self.children[i];
+            if !key.starts_with(&child.label) {
+                return None;
+            }
+            child.remove(&key[child.label.len()..])?
+        };
+        // Collapse the child, if it's left without a value.
+        let child = &mut self.children[i];
+        if child.value.is_none() {
+            match child.children.len() {
+                0 => { self.children.remove(i); },
+                1 => child.merge_only_child(),
+                _ => (),
+            }
+        }
+        Some(value)
+    }
+
+    /// Merges this valueless node with its only child, concatenating their
+    /// edge labels. Leaves both nodes unchanged if the merged label fails
+    /// to allocate.
+    fn merge_only_child(&mut self) {
+        let only = self.children.pop().unwrap();
+        if self.label.try_extend_from_slice(&only.label).is_err() {
+            self.children.push(only);
+            return;
+        }
+        let ByteTrieNode { label: _, value, children } = only;
+        self.value = value;
+        self.children = children;
+    }
+}
+
+impl<'a, V> Holder<'a> for ByteTrieMap<'a, V> {
+    #[inline]
+    fn holder(&self) -> &'a dyn Hold<'a> {
+        self.root.children.holder()
+    }
+}
+
+impl<'a, V: fmt::Debug> fmt::Debug for ByteTrieMap<'a, V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut map = f.debug_map();
+        for entry in self.try_iter().map_err(|_| fmt::Error)? {
+            let (key, value) = entry.map_err(|_| fmt::Error)?;
+            map.entry(&&key[..], value);
+        }
+        map.finish()
+    }
+}
+
+impl<'m, 'a: 'm, V: 'm> Iterator for ByteTrieMapPrefixIter<'m, 'a, V> {
+    type Item = Result<(RawBuf<'a, u8>, &'m V), HoldError>;
+
+    fn next(&mut self) -> Option<Result<(RawBuf<'a, u8>, &'m V), HoldError>> {
+        loop {
+            let frame = self.stack.last_mut()?;
+            let node = frame.node;
+            let result = if !frame.visited {
+                frame.visited = true;
+                match node.value {
+                    Some(ref value) => match RawBuf::try_hold_copy(self.key.holder(), &self.key[..]) {
+                        Ok(key) => return Some(Ok((key, value))),
+                        Err(error) => Err(error),
+                    },
+                    None => Ok(()),
+                }
+            } else if frame.next < node.children.len() {
+                // Descend into the next child.
+                let child = &node.children[frame.next];
+                frame.next += 1;
+                match self.key.try_extend_from_slice(&child.label) {
+                    Ok(()) => self.stack.try_push(ByteTrieFrame {
+                        node: child,
+                        label_len: child.label.len(),
+                        next: 0,
+                        visited: false,
+                    }),
+                    err @ Err(_) => err,
+                }
+            } else {
+                // Ascend out of the exhausted node.
+                let label_len = frame.label_len;
+                self.stack.pop();
+                let key_len = self.key.len();
+                self.key.truncate(key_len - label_len);
+                Ok(())
+            };
+            if let Err(error) = result {
+                // End the iteration.
+                self.stack.clear();
+                return Some(Err(error));
+            }
+        }
+    }
+}
+
+impl<'m, 'a: 'm, V: 'm> FusedIterator for ByteTrieMapPrefixIter<'m, 'a, V> {
+}
+
+impl<'m, 'a: 'm, V: 'm> Iterator for ByteTrieMapIter<'m, 'a, V> {
+    type Item = (RawBuf<'a, u8>, &'m V);
+
+    #[inline]
+    fn next(&mut self) -> Option<(RawBuf<'a, u8>, &'m V)> {
+        match self.iter.next() {
+            Some(entry) => Some(entry.unwrap()),
+            None => None,
+        }
+    }
+}
+
+impl<'m, 'a: 'm, V: 'm> FusedIterator for ByteTrieMapIter<'m, 'a, V> {
+}
+
+#[inline]
+fn common_prefix_len(xs: &[u8], ys: &[u8]) -> usize {
+    xs.iter().zip(ys.iter()).take_while(|&(x, y)| x == y).count()
+}
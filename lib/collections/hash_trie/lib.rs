@@ -12,3 +12,4 @@ extern crate tg_core;
 extern crate tg_mem;
 
 pub mod hash_trie;
+pub mod byte_trie;
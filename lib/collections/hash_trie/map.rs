@@ -1,10 +1,13 @@
 use core::borrow::Borrow;
 use core::fmt;
-use core::hash::{BuildHasher, Hash};
+use core::hash::{BuildHasher, Hash, Hasher};
 use core::iter::{ExactSizeIterator, FusedIterator, TrustedLen};
+use core::marker::PhantomData;
+use core::mem;
 use tg_core::murmur3::Murmur3;
-use tg_mem::alloc::{Hold, Holder, HoldError, Stow, TryClone, CloneIntoHold};
-use crate::hash_trie::{HashTrie, HashTrieIter};
+use tg_mem::alloc::{Hold, Holder, HoldError, Stow, StowFrom, TryClone, CloneIntoHold};
+use tg_mem::lease::RawBuf;
+use crate::hash_trie::{HashTrie, HashTrieIter, HashTrieSet, hash_key};
 
 /// Hash array mapped trie map.
 pub struct HashTrieMap<'a, K, V, H = Murmur3> {
@@ -16,6 +19,13 @@ pub struct HashTrieMapIter<'a, K: 'a, V: 'a> {
     iter: HashTrieIter<'a, K, V>
 }
 
+/// Iterator over the leafs of a `HashTrieMap`, along with the hash code of
+/// each leaf's key.
+pub struct HashTrieMapHashIter<'m, 'a: 'm, K: 'a, V: 'a, H: 'm> {
+    iter: HashTrieIter<'a, K, V>,
+    hasher: &'m H,
+}
+
 /// Mutable iterator over the leafs of a `HashTrieMap`.
 pub struct HashTrieMapIterMut<'a, K: 'a, V: 'a> {
     iter: HashTrieIter<'a, K, V>
@@ -36,6 +46,56 @@ pub struct HashTrieMapValsMut<'a, K: 'a, V: 'a> {
     iter: HashTrieIter<'a, K, V>
 }
 
+/// Builder for looking up leafs of a `HashTrieMap` by precomputed hash code.
+pub struct HashTrieMapRawEntryBuilder<'m, 'a: 'm, K: 'm, V: 'm, H: 'm> {
+    map: &'m HashTrieMap<'a, K, V, H>,
+}
+
+/// Builder for mutably looking up leafs of a `HashTrieMap` by precomputed
+/// hash code.
+pub struct HashTrieMapRawEntryBuilderMut<'m, 'a: 'm, K: 'm, V: 'm, H: 'm> {
+    map: &'m mut HashTrieMap<'a, K, V, H>,
+}
+
+/// View into a single leaf of a `HashTrieMap`, which may be either occupied
+/// or vacant.
+pub enum HashTrieMapRawEntryMut<'m, 'a: 'm, K: 'm, V: 'm, H: 'm> {
+    Occupied(HashTrieMapRawOccupiedEntryMut<'m, K, V>),
+    Vacant(HashTrieMapRawVacantEntryMut<'m, 'a, K, V, H>),
+}
+
+/// View into an occupied leaf of a `HashTrieMap`.
+pub struct HashTrieMapRawOccupiedEntryMut<'m, K: 'm, V: 'm> {
+    leaf: *mut (K, V),
+    marker: PhantomData<&'m mut (K, V)>,
+}
+
+/// View into a vacant leaf of a `HashTrieMap`.
+pub struct HashTrieMapRawVacantEntryMut<'m, 'a: 'm, K: 'm, V: 'm, H: 'm> {
+    map: &'m mut HashTrieMap<'a, K, V, H>,
+    hash: u64,
+}
+
+/// Error returned by `HashTrieMap::try_insert` when the key is already
+/// present, or when the trie's `Hold` fails to allocate.
+pub enum HashTrieMapTryInsertError<'m, K: 'm, V: 'm> {
+    /// The key is already present; the map is unchanged.
+    Occupied(HashTrieMapOccupiedError<'m, K, V>),
+    /// Allocation failed; returns the rejected key and value.
+    Hold(K, V, HoldError),
+}
+
+/// The rejected key and value of a `HashTrieMap::try_insert` whose key is
+/// already present, along with the occupied entry holding the existing value.
+pub struct HashTrieMapOccupiedError<'m, K: 'm, V: 'm> {
+    /// The entry occupied by the existing key and value.
+    pub entry: HashTrieMapRawOccupiedEntryMut<'m, K, V>,
+    /// The key that was not inserted.
+    pub key: K,
+    /// The value that was not inserted.
+    pub value: V,
+}
+
 impl<K, V> HashTrieMap<'static, K, V> {
     /// Constructs a new `HashTrieMap` that will allocate its data in the
     /// global `Hold`.
@@ -86,6 +146,45 @@ impl<'a, K, V, H> HashTrieMap<'a, K, V, H> {
         self.trie.len()
     }
 
+    /// Returns the number of bytes this `HashTrieMap` has allocated for its trie
+    /// nodes and collision buckets, including the inline storage of its
+    /// leafs, but excluding any memory owned by the leafs themselves.
+    pub fn memory_usage(&self) -> usize {
+        self.trie.memory_usage()
+    }
+
+    /// Returns the number of collision buckets in this `HashTrieMap`. Keys whose
+    /// 64-bit hashes fully collide share a bucket, which is searched linearly;
+    /// a non-zero count for keys expected to hash uniquely indicates a poor
+    /// quality hasher. Buckets formed by `insert_bounded_depth` for partial
+    /// hash collisions are counted too.
+    pub fn collision_count(&self) -> usize {
+        self.trie.knot_stats().0
+    }
+
+    /// Returns the number of keys in the largest collision bucket of this
+    /// `HashTrieMap`, or `0` if no keys collide.
+    pub fn max_collision_len(&self) -> usize {
+        self.trie.knot_stats().1
+    }
+
+    /// Folds all values of this `HashTrieMap` with the associative function
+    /// `f`, starting from `identity`, which must satisfy `f(identity, v) == v`.
+    /// Values combine in an unspecified grouping: each of the 32 top-level
+    /// hash buckets reduces independently before the bucket results combine,
+    /// so that buckets may later be reduced in parallel.
+    pub fn reduce_values<F: Fn(&V, &V) -> V>(&self, identity: V, f: F) -> V where V: Clone {
+        self.trie.reduce_values(identity, &f)
+    }
+
+    /// Applies `f` in place to every value of this `HashTrieMap`, along with
+    /// its key, visiting each leaf exactly once. Neither allocates nor
+    /// restructures the trie, making it far cheaper than rebuilding the map
+    /// to reset or transform all of its values.
+    pub fn set_all_values<F: FnMut(&K, &mut V)>(&mut self, mut f: F) {
+        self.trie.set_all_values(&mut f)
+    }
+
     /// Returns an iterator over the leafs of this `HashTrieMap`.
     pub fn iter(&self) -> HashTrieMapIter<'a, K, V> {
         HashTrieMapIter { iter: self.trie.iterator() }
@@ -110,9 +209,117 @@ impl<'a, K, V, H> HashTrieMap<'a, K, V, H> {
     pub fn values_mut(&mut self) -> HashTrieMapValsMut<'a, K, V> {
         HashTrieMapValsMut { iter: self.trie.iterator() }
     }
+
+    /// Returns the first leaf of this `HashTrieMap` in iteration order,
+    /// or `None` if the map is empty.
+    pub fn first(&self) -> Option<(&K, &V)> {
+        unsafe {
+            match self.trie.iterator().next() {
+                Some(leaf) => {
+                    let leaf = &*leaf.as_ptr();
+                    Some((&leaf.0, &leaf.1))
+                },
+                None => None,
+            }
+        }
+    }
+
+    /// Returns the last leaf of this `HashTrieMap` in iteration order,
+    /// or `None` if the map is empty.
+    pub fn last(&self) -> Option<(&K, &V)> {
+        unsafe {
+            match self.trie.last_leaf() {
+                Some(leaf) => {
+                    let leaf = &*leaf.as_ptr();
+                    Some((&leaf.0, &leaf.1))
+                },
+                None => None,
+            }
+        }
+    }
+
+    /// Returns a reference to the hasher used to hash the keys of this
+    /// `HashTrieMap`.
+    #[inline]
+    pub fn hasher(&self) -> &H {
+        self.trie.hasher()
+    }
+
+    /// Returns the number of bytes of trie structure this map shares with
+    /// `other`, counting the nodes and knots that are the same allocation in
+    /// both maps. Deep clones share no structure. The walk is read-only, and
+    /// only descends into sub-trees present in both maps.
+    pub fn shared_bytes<G>(&self, other: &HashTrieMap<'a, K, V, G>) -> usize {
+        self.trie.shared_bytes(&other.trie)
+    }
+
+    /// Makes `dst` equal to this `HashTrieMap`, reusing the nodes and knots
+    /// of `dst` whose shape matches their counterparts in this map, and
+    /// cloning leafs in place over the leafs they replace. Repeatedly
+    /// refreshing a map from a similar source thereby avoids most of the
+    /// allocations of a fresh clone. Returns a `HoldError`, and leaves `dst`
+    /// empty, if the `Hold` of `dst` fails to allocate any required memory.
+    pub fn clone_into(&self, dst: &mut HashTrieMap<'a, K, V, H>) -> Result<(), HoldError>
+        where K: Clone,
+              V: Clone,
+              H: Clone,
+    {
+        self.trie.clone_into(&mut dst.trie)
+    }
+
+    /// Returns a builder for looking up leafs by precomputed hash code and
+    /// custom key equality, without requiring an owned key.
+    #[inline]
+    pub fn raw_entry(&self) -> HashTrieMapRawEntryBuilder<'_, 'a, K, V, H> {
+        HashTrieMapRawEntryBuilder { map: self }
+    }
+
+    /// Returns a builder for looking up, updating, or inserting leafs by
+    /// precomputed hash code and custom key equality.
+    #[inline]
+    pub fn raw_entry_mut(&mut self) -> HashTrieMapRawEntryBuilderMut<'_, 'a, K, V, H> {
+        HashTrieMapRawEntryBuilderMut { map: self }
+    }
+
+    /// Returns a new buffer, allocated in `hold`, containing clones of all
+    /// leafs of this `HashTrieMap`, in iteration order. The buffer is sized
+    /// to the length of the map up front, so only a single allocation is made.
+    /// Any pairs already cloned are dropped if a subsequent clone panics.
+    pub fn to_pairs_buf<'b>(&self, hold: &dyn Hold<'b>) -> Result<RawBuf<'b, (K, V)>, HoldError>
+        where K: Clone,
+              V: Clone,
+    {
+        let mut buf = RawBuf::try_hold_cap(hold, self.len())?;
+        for (key, value) in self.iter() {
+            // Can't reallocate, because the buffer has capacity for every leaf.
+            buf.push((key.clone(), value.clone()));
+        }
+        Ok(buf)
+    }
+
+    /// Moves this `HashTrieMap`, and all of its leafs, into `hold`. If the
+    /// `hold` fails to allocate any required new memory, returns this map,
+    /// unchanged, along with a `HoldError`.
+    pub fn into_hold<'b>(self, hold: &dyn Hold<'b>)
+        -> Result<HashTrieMap<'b, K, V, H>, (HashTrieMap<'a, K, V, H>, HoldError)>
+        where K: Stow<'b>,
+              V: Stow<'b>,
+              H: Stow<'b>,
+    {
+        StowFrom::try_stow_from(self, hold)
+    }
 }
 
 impl<'a, K: Eq + Hash, V, H: BuildHasher> HashTrieMap<'a, K, V, H> {
+    /// Returns an iterator over the leafs of this `HashTrieMap` that also
+    /// yields the 64-bit hash code the trie used to place each leaf. A leaf's
+    /// position in the trie only determines the low order bits of its hash,
+    /// so the hash code is recomputed from the key with the map's hasher.
+    /// Keys whose hashes fully collide yield equal hash codes.
+    pub fn iter_with_hash(&self) -> HashTrieMapHashIter<'_, 'a, K, V, H> {
+        HashTrieMapHashIter { iter: self.trie.iterator(), hasher: self.hasher() }
+    }
+
     /// Returns `true` if this `HashTrieMap` contains the given `key`.
     pub fn contains_key<J: Borrow<K> + ?Sized>(&self, key: &J) -> bool {
         self.trie.contains_key(key)
@@ -124,6 +331,20 @@ impl<'a, K: Eq + Hash, V, H: BuildHasher> HashTrieMap<'a, K, V, H> {
         self.trie.get(key)
     }
 
+    /// Returns a buffer, allocated in `hold`, with the value associated with
+    /// each of the given `keys`, in order, or `None` for each key that has
+    /// no association. Returns a `HoldError` if the buffer can't be allocated.
+    pub fn get_many<'b, 'm, J: Borrow<K> + ?Sized>(&'m self, keys: &[&J], hold: &dyn Hold<'b>)
+        -> Result<RawBuf<'b, Option<&'m V>>, HoldError>
+    {
+        let mut buf = RawBuf::try_hold_cap(hold, keys.len())?;
+        for key in keys {
+            // Can't reallocate, because the buffer has capacity for every key.
+            buf.push(self.trie.get(*key));
+        }
+        Ok(buf)
+    }
+
     /// Associates a new `value` with the given `key`; returns the previous
     /// value associated with the `key`, if defined. If the trie's `Hold` fails
     /// to allocate any required new memory, returns the `key` and `value`,
@@ -132,6 +353,62 @@ impl<'a, K: Eq + Hash, V, H: BuildHasher> HashTrieMap<'a, K, V, H> {
         self.trie.insert(key, value)
     }
 
+    /// Associates a new `value` with the given `key`, storing keys whose hash
+    /// codes share a prefix spanning more than `max_depth` trie levels in a
+    /// collision knot, rather than in a chain of single-branch nodes. Bounds
+    /// the worst-case depth, and memory overhead, of maps with low-entropy
+    /// or adversarial keys. The bound applies to all subsequent inserts, and
+    /// can only be lowered by later calls. Otherwise behaves like `insert`.
+    pub fn insert_bounded_depth(&mut self, key: K, value: V, max_depth: u32)
+        -> Result<Option<V>, (K, V, HoldError)>
+    {
+        self.trie.insert_bounded_depth(key, value, max_depth)
+    }
+
+    /// Associates a new `value` with the given `key`, only if the `key` is
+    /// absent; returns a mutable reference to the inserted value. Returns an
+    /// `Occupied` error carrying the rejected `key` and `value`, and the entry
+    /// holding the existing value, if the `key` is already present. If the
+    /// trie's `Hold` fails to allocate any required new memory, returns the
+    /// `key` and `value`, along with a `HoldError`, and leaves the trie in its
+    /// original state.
+    pub fn try_insert(&mut self, key: K, value: V) -> Result<&mut V, HashTrieMapTryInsertError<'_, K, V>> {
+        // Hash the insert key.
+        let hash = hash_key(self.trie.hasher(), &key);
+        // Check if the key is already present.
+        let leaf = self.trie.find_raw(hash, |k| *k == key);
+        if !leaf.is_null() {
+            return Err(HashTrieMapTryInsertError::Occupied(HashTrieMapOccupiedError {
+                entry: HashTrieMapRawOccupiedEntryMut {
+                    leaf: leaf,
+                    marker: PhantomData,
+                },
+                key: key,
+                value: value,
+            }));
+        }
+        match self.trie.insert_hashed_leaf(hash, key, value, true) {
+            Ok((_, leaf)) => Ok(unsafe { &mut (*leaf).1 }),
+            Err((key, value, error)) => Err(HashTrieMapTryInsertError::Hold(key, value, error)),
+        }
+    }
+
+    /// Returns a mutable reference to the value associated with the given
+    /// `key`, first associating `V::default()` with the `key` if it's absent.
+    /// Hashes the `key` once, and descends the trie once. If the trie's `Hold`
+    /// fails to allocate any required new memory, returns the `key`, along
+    /// with a `HoldError`, and leaves the trie in its original state.
+    pub fn entry_or_default(&mut self, key: K) -> Result<&mut V, (K, HoldError)> where V: Default {
+        // Hash the key.
+        let hash = hash_key(self.trie.hasher(), &key);
+        // Insert the default value, unless the key is already present,
+        // getting the leaf holding the key from the same descent.
+        match self.trie.insert_hashed_leaf(hash, key, V::default(), false) {
+            Ok((_, leaf)) => Ok(unsafe { &mut (*leaf).1 }),
+            Err((key, _, error)) => Err((key, error)),
+        }
+    }
+
     /// Disassociates the given `key`; returns the previous value associated
     /// with the `key`, if any. Returns a `HoldError`, and leaves the trie in
     /// its original state, if the trie's `Hold` fails to allocate any required
@@ -139,6 +416,232 @@ impl<'a, K: Eq + Hash, V, H: BuildHasher> HashTrieMap<'a, K, V, H> {
     pub fn remove<J: Borrow<K> + ?Sized>(&mut self, key: &J) -> Result<Option<V>, HoldError> {
         self.trie.remove(key)
     }
+
+    /// Removes and returns the first leaf in iteration order, or `None` if
+    /// the map is empty. Repeated calls drain the map in hash order. Returns
+    /// a `HoldError`, and leaves the trie in its original state, if the trie's
+    /// `Hold` fails to allocate any required new memory.
+    pub fn pop_first(&mut self) -> Result<Option<(K, V)>, HoldError> {
+        self.trie.pop_first()
+    }
+
+    /// Removes and returns the last leaf in iteration order, or `None` if
+    /// the map is empty. Returns a `HoldError`, and leaves the trie in its
+    /// original state, if the trie's `Hold` fails to allocate any required
+    /// new memory.
+    pub fn pop_last(&mut self) -> Result<Option<(K, V)>, HoldError> {
+        self.trie.pop_last()
+    }
+
+    /// Disassociates each of the given `keys`; returns the number of keys
+    /// that had associated values. If the trie's `Hold` fails to allocate
+    /// any required new memory, returns the number of keys removed before
+    /// the failure, along with a `HoldError`. Removals completed prior to
+    /// the failure remain in effect.
+    pub fn remove_all<I: IntoIterator<Item=K>>(&mut self, keys: I) -> Result<usize, (usize, HoldError)> {
+        let mut count = 0;
+        for key in keys {
+            match self.trie.remove(&key) {
+                Ok(Some(_)) => count += 1,
+                Ok(None) => (),
+                Err(error) => return Err((count, error)),
+            }
+        }
+        Ok(count)
+    }
+
+    /// Disassociates every key of this `HashTrieMap` that is also a key of
+    /// `other`, regardless of the values `other` associates with its keys;
+    /// returns the number of keys removed. Returns a `HoldError` if the
+    /// trie's `Hold` fails to allocate any required new memory, in which
+    /// case removals completed prior to the failure remain in effect.
+    pub fn remove_keys_in<'b, V2, H2>(&mut self, other: &HashTrieMap<'b, K, V2, H2>) -> Result<usize, HoldError> {
+        let mut count = 0;
+        for key in other.keys() {
+            if self.trie.remove(key)?.is_some() {
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    /// Feeds a hash of the contents of this `HashTrieMap` into `state`, which
+    /// is independent of the shape of the trie, and of insertion order. Each
+    /// key, value pair is hashed separately with the map's hasher, and the
+    /// per-pair hashes are combined with commutative wrapping addition. Maps
+    /// with equal contents, and equivalent hashers, feed equal hashes.
+    pub fn content_hash<S: Hasher>(&self, state: &mut S) where V: Hash {
+        let mut sum = 0u64;
+        for (key, value) in self.iter() {
+            let mut h = self.hasher().build_hasher();
+            key.hash(&mut h);
+            value.hash(&mut h);
+            sum = sum.wrapping_add(h.finish());
+        }
+        state.write_usize(self.len());
+        state.write_u64(sum);
+    }
+
+    /// Consumes this `HashTrieMap`, passing each owned key, value pair to `f`,
+    /// and inserting the `Some` results into a new map allocated in `hold`,
+    /// hashed with this map's hasher. Drops the pairs for which `f` returns
+    /// `None`. If `hold` fails to allocate any required new memory, drops the
+    /// partially built map, along with all remaining pairs, and returns the
+    /// `HoldError`.
+    pub fn filter_map<'b, W, F>(self, mut f: F, hold: &dyn Hold<'b>)
+        -> Result<HashTrieMap<'b, K, W, H>, HoldError>
+        where F: FnMut(K, V) -> Option<(K, W)>
+    {
+        let (hasher, drain) = self.trie.into_drain();
+        let mut map = HashTrieMap::hold_new_hasher(hold, hasher);
+        for (key, value) in drain {
+            if let Some((key, value)) = f(key, value) {
+                if let Err((_, _, error)) = map.insert(key, value) {
+                    return Err(error);
+                }
+            }
+        }
+        Ok(map)
+    }
+
+    /// Returns a new set, allocated in `hold` and hashed with a clone of this
+    /// map's hasher, containing clones of all keys of this `HashTrieMap`.
+    /// If `hold` fails to allocate any required new memory, drops the
+    /// partially built set and returns the `HoldError`.
+    pub fn key_set<'b>(&self, hold: &dyn Hold<'b>) -> Result<HashTrieSet<'b, K, H>, HoldError>
+        where K: Clone,
+              H: Clone,
+    {
+        let mut set = HashTrieSet::hold_new_hasher(hold, self.hasher().clone());
+        for key in self.keys() {
+            if let Err((_, error)) = set.insert(key.clone()) {
+                return Err(error);
+            }
+        }
+        Ok(set)
+    }
+
+    /// Consumes this `HashTrieMap`, moving each of its keys into a new set
+    /// allocated in `hold` and hashed with this map's hasher, and dropping
+    /// each of its values. If `hold` fails to allocate any required new
+    /// memory, drops the partially built set, along with all remaining
+    /// leafs, and returns the `HoldError`.
+    pub fn into_key_set<'b>(self, hold: &dyn Hold<'b>) -> Result<HashTrieSet<'b, K, H>, HoldError> {
+        let (hasher, drain) = self.trie.into_drain();
+        let mut set = HashTrieSet::hold_new_hasher(hold, hasher);
+        for (key, _) in drain {
+            if let Err((_, error)) = set.insert(key) {
+                return Err(error);
+            }
+        }
+        Ok(set)
+    }
+}
+
+impl<'m, 'a, K, V, H> HashTrieMapRawEntryBuilder<'m, 'a, K, V, H> {
+    /// Returns the leaf whose key satisfies `is_match`, searching along the
+    /// path of the given `hash` code. The `hash` must equal the hash code the
+    /// map's hasher computes for any key that satisfies `is_match`; otherwise
+    /// the lookup may fail to find a matching leaf.
+    pub fn from_hash<F>(self, hash: u64, is_match: F) -> Option<(&'m K, &'m V)>
+        where F: FnMut(&K) -> bool
+    {
+        let leaf = self.map.trie.find_raw(hash, is_match);
+        if !leaf.is_null() {
+            unsafe { Some((&(*leaf).0, &(*leaf).1)) }
+        } else {
+            None
+        }
+    }
+
+    /// Returns the leaf whose key equals `key`, searching along the path of
+    /// the given `hash` code, which must be the hash code of `key`.
+    #[inline]
+    pub fn from_key_hashed_nocheck<Q>(self, hash: u64, key: &Q) -> Option<(&'m K, &'m V)>
+        where K: Borrow<Q>,
+              Q: Eq + ?Sized,
+    {
+        self.from_hash(hash, |k| k.borrow() == key)
+    }
+}
+
+impl<'m, 'a, K, V, H> HashTrieMapRawEntryBuilderMut<'m, 'a, K, V, H> {
+    /// Returns an entry for the leaf whose key satisfies `is_match`, searching
+    /// along the path of the given `hash` code. The `hash` must equal the hash
+    /// code the map's hasher computes for any key that satisfies `is_match`.
+    pub fn from_hash<F>(self, hash: u64, is_match: F) -> HashTrieMapRawEntryMut<'m, 'a, K, V, H>
+        where F: FnMut(&K) -> bool
+    {
+        let leaf = self.map.trie.find_raw(hash, is_match);
+        if !leaf.is_null() {
+            HashTrieMapRawEntryMut::Occupied(HashTrieMapRawOccupiedEntryMut {
+                leaf: leaf,
+                marker: PhantomData,
+            })
+        } else {
+            HashTrieMapRawEntryMut::Vacant(HashTrieMapRawVacantEntryMut {
+                map: self.map,
+                hash: hash,
+            })
+        }
+    }
+
+    /// Returns an entry for the leaf whose key equals `key`, searching along
+    /// the path of the given `hash` code, which must be the hash code of `key`.
+    #[inline]
+    pub fn from_key_hashed_nocheck<Q>(self, hash: u64, key: &Q) -> HashTrieMapRawEntryMut<'m, 'a, K, V, H>
+        where K: Borrow<Q>,
+              Q: Eq + ?Sized,
+    {
+        self.from_hash(hash, |k| k.borrow() == key)
+    }
+}
+
+impl<'m, K, V> HashTrieMapRawOccupiedEntryMut<'m, K, V> {
+    /// Returns a reference to the key of this entry.
+    #[inline]
+    pub fn key(&self) -> &K {
+        unsafe { &(*self.leaf).0 }
+    }
+
+    /// Returns a reference to the value of this entry.
+    #[inline]
+    pub fn get(&self) -> &V {
+        unsafe { &(*self.leaf).1 }
+    }
+
+    /// Returns a mutable reference to the value of this entry.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut V {
+        unsafe { &mut (*self.leaf).1 }
+    }
+
+    /// Converts this entry into a mutable reference to its value, bound to
+    /// the lifetime of the map borrow.
+    #[inline]
+    pub fn into_mut(self) -> &'m mut V {
+        unsafe { &mut (*self.leaf).1 }
+    }
+
+    /// Replaces the value of this entry; returns the previous value.
+    #[inline]
+    pub fn insert(&mut self, value: V) -> V {
+        unsafe { mem::replace(&mut (*self.leaf).1, value) }
+    }
+}
+
+impl<'m, 'a, K: Eq + Hash, V, H: BuildHasher> HashTrieMapRawVacantEntryMut<'m, 'a, K, V, H> {
+    /// Associates a new `value` with the given `key`, using the hash code
+    /// with which this entry was looked up. The `key` must hash to that same
+    /// hash code using the map's hasher. If the trie's `Hold` fails to
+    /// allocate any required new memory, returns the `key` and `value`,
+    /// along with a `HoldError`, and leaves the trie in its original state.
+    pub fn insert(self, key: K, value: V) -> Result<(), (K, V, HoldError)> {
+        match self.map.trie.insert_hashed(self.hash, key, value) {
+            Ok(_) => Ok(()),
+            Err(error) => Err(error),
+        }
+    }
 }
 
 impl<'a, K, V, H> Holder<'a> for HashTrieMap<'a, K, V, H> {
@@ -266,6 +769,66 @@ impl<'a, K: 'a + fmt::Debug, V: 'a + fmt::Debug> fmt::Debug for HashTrieMapIter<
     }
 }
 
+impl<'m, 'a, K: 'a + Hash, V: 'a, H: BuildHasher> Iterator for HashTrieMapHashIter<'m, 'a, K, V, H> {
+    type Item = (u64, &'a K, &'a V);
+
+    fn next(&mut self) -> Option<(u64, &'a K, &'a V)> {
+        unsafe {
+            match self.iter.next() {
+                Some(leaf) => {
+                    let key = &(*leaf.as_ptr()).0;
+                    Some((hash_key(self.hasher, key), key, &(*leaf.as_ptr()).1))
+                },
+                None => None,
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.iter.len();
+        (len, Some(len))
+    }
+
+    fn count(self) -> usize {
+        self.iter.len()
+    }
+}
+
+impl<'m, 'a, K: 'a + Hash, V: 'a, H: BuildHasher> DoubleEndedIterator for HashTrieMapHashIter<'m, 'a, K, V, H> {
+    fn next_back(&mut self) -> Option<(u64, &'a K, &'a V)> {
+        unsafe {
+            match self.iter.next_back() {
+                Some(leaf) => {
+                    let key = &(*leaf.as_ptr()).0;
+                    Some((hash_key(self.hasher, key), key, &(*leaf.as_ptr()).1))
+                },
+                None => None,
+            }
+        }
+    }
+}
+
+impl<'m, 'a, K: 'a + Hash, V: 'a, H: BuildHasher> ExactSizeIterator for HashTrieMapHashIter<'m, 'a, K, V, H> {
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.iter.len() == 0
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        self.iter.len()
+    }
+}
+
+impl<'m, 'a, K: 'a + Hash, V: 'a, H: BuildHasher> FusedIterator for HashTrieMapHashIter<'m, 'a, K, V, H> {
+}
+
+impl<'m, 'a, K: 'a, V: 'a, H> Clone for HashTrieMapHashIter<'m, 'a, K, V, H> {
+    fn clone(&self) -> HashTrieMapHashIter<'m, 'a, K, V, H> {
+        HashTrieMapHashIter { iter: self.iter.clone(), hasher: self.hasher }
+    }
+}
+
 impl<'a, K: 'a, V: 'a> Iterator for HashTrieMapIterMut<'a, K, V> {
     type Item = (&'a K, &'a mut V);
 
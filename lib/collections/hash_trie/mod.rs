@@ -1,4 +1,5 @@
 use core::borrow::Borrow;
+use core::cmp;
 use core::hash::{BuildHasher, Hash, Hasher};
 use core::marker::PhantomData;
 use core::mem;
@@ -10,10 +11,18 @@ use tg_mem::alloc::{AllocTag, Hold, HoldError, Stow, TryClone, CloneIntoHold};
 
 mod map;
 mod set;
-
-pub use self::map::{HashTrieMap, HashTrieMapIter, HashTrieMapIterMut,
-                    HashTrieMapKeys, HashTrieMapVals, HashTrieMapValsMut};
-pub use self::set::{HashTrieSet, HashTrieSetIter};
+mod ordered;
+mod weak;
+
+pub use self::map::{HashTrieMap, HashTrieMapIter, HashTrieMapHashIter, HashTrieMapIterMut,
+                    HashTrieMapKeys, HashTrieMapVals, HashTrieMapValsMut,
+                    HashTrieMapRawEntryBuilder, HashTrieMapRawEntryBuilderMut,
+                    HashTrieMapRawEntryMut, HashTrieMapRawOccupiedEntryMut,
+                    HashTrieMapRawVacantEntryMut, HashTrieMapTryInsertError,
+                    HashTrieMapOccupiedError};
+pub use self::set::{HashTrieSet, HashTrieSetIter, FrozenSet, IntoHashTrieSet};
+pub use self::ordered::{OrderedTrieMap, OrderedTrieMapIter};
+pub use self::weak::WeakValueMap;
 
 /// Bit mask with a single 1 bit whose bit index equals a 5 bit value.
 /// For example, the 5 bit value `17` corresponds to the `BranchBit` mask
@@ -42,6 +51,9 @@ pub(crate) struct HashTrie<'a, K, V, H = Murmur3> {
     len: usize,
     /// Hasher used to hash keys.
     hasher: H,
+    /// Hash code shift at which colliding hash prefixes are stored in knots,
+    /// rather than in deeper nodes; `NO_MAX_SHIFT` when unbounded.
+    max_shift: u32,
 }
 
 /// Hash trie node mapping 5 bits of hash space to sub-trees and embedded leafs.
@@ -120,6 +132,9 @@ enum NodeInsert<'a, K, V> {
     /// The trie has been mutated in place, so the insert must not subsequently
     /// fail.
     Diff(V),
+    /// Found an existing leaf for the key in a descendant, and left it
+    /// unchanged, without taking ownership of the new key and value.
+    Same,
     /// Allocated a copy of the node with the new leaf inserted. The old node
     /// has been left intact in case the insert subsequently fails. Caller must
     /// drop the old node if the insert eventually succeeds.
@@ -158,6 +173,9 @@ enum KnotInsert<'a, K, V> {
     /// The knot has been mutated in place, so the insert must not subsequently
     /// fail.
     Diff(V),
+    /// Found an existing leaf for the key in the knot, and left it unchanged,
+    /// without taking ownership of the new key and value.
+    Same,
     /// Allocated a copy of the knot with the new leaf inserted. The old knot
     /// has been left intact in case the insert subsequently fails. Caller must
     /// drop the old knot if the insert eventually succeeds.
@@ -217,9 +235,23 @@ pub(crate) struct HashTrieIter<'a, K, V> {
     stack: [IterFrame<'a, K, V>; 14],
 }
 
+/// Consuming hash trie iterator that moves leafs out of a trie, and
+/// deallocates the trie structure when dropped.
+pub(crate) struct HashTrieDrain<'a, K, V> {
+    /// Root node of the drained trie.
+    root: NonNull<Node<'a, K, V>>,
+    /// Number of leafs in the drained trie.
+    len: usize,
+    /// Iterator over the leafs that remain to be moved out.
+    iter: HashTrieIter<'a, K, V>,
+}
+
+/// `HashTrie::max_shift` of a trie whose depth isn't bounded.
+const NO_MAX_SHIFT: u32 = u32::MAX;
+
 /// Computes the hash code of `key` using the supplied `hasher`.
 #[inline]
-fn hash_key<K, H>(hasher: &H, key: &K) -> u64
+pub(crate) fn hash_key<K, H>(hasher: &H, key: &K) -> u64
     where K: Hash + ?Sized,
           H: BuildHasher,
 {
@@ -228,6 +260,14 @@ fn hash_key<K, H>(hasher: &H, key: &K) -> u64
     h.finish()
 }
 
+/// Returns the shift of the first 5 bit string at which `hash0` and `hash1`
+/// differ, i.e. the shift of the node at which their paths through the trie
+/// diverge.
+#[inline]
+fn split_shift(hash0: u64, hash1: u64) -> u32 {
+    (hash0 ^ hash1).trailing_zeros() / 5 * 5
+}
+
 /// Returns a bit mask containing a single 1 bit, whose bit index equals the
 /// low 5 bits of the `hash` value after shifting it right by `shift` bits.
 #[inline]
@@ -271,6 +311,7 @@ impl<'a, K, V> HashTrie<'a, K, V> {
                 root: NonNull::new_unchecked(root),
                 len: 0,
                 hasher: Murmur3::new(),
+                max_shift: NO_MAX_SHIFT,
             }
         }
     }
@@ -291,6 +332,7 @@ impl<'a, K, V, H> HashTrie<'a, K, V, H> {
                 root: NonNull::new_unchecked(root),
                 len: 0,
                 hasher: hasher,
+                max_shift: NO_MAX_SHIFT,
             }
         }
     }
@@ -307,6 +349,52 @@ impl<'a, K, V, H> HashTrie<'a, K, V, H> {
         self.len
     }
 
+    /// Returns the total number of bytes allocated by the nodes and knots
+    /// of this `HashTrie`, excluding any memory owned by its leafs.
+    pub(crate) fn memory_usage(&self) -> usize {
+        if self.len != 0 {
+            unsafe { self.root.as_ptr().tree_size() }
+        } else {
+            // The empty root is a zero-sized block.
+            0
+        }
+    }
+
+    /// Returns the number of knots in this `HashTrie`, and the number of
+    /// leafs in its longest knot. Knots hold the leafs of keys whose hashes
+    /// fully collide, or whose hash prefixes collide at the bounded depth of
+    /// an `insert_bounded_depth`.
+    pub(crate) fn knot_stats(&self) -> (usize, usize) {
+        if self.len != 0 {
+            unsafe { self.root.as_ptr().knot_stats() }
+        } else {
+            (0, 0)
+        }
+    }
+
+    /// Folds the values of this `HashTrie` with the associative function `f`,
+    /// starting from `identity`. Each of the root node's 32 branches reduces
+    /// independently from `identity`, before combining with the accumulated
+    /// result of the preceding branches.
+    pub(crate) fn reduce_values<F>(&self, identity: V, f: &F) -> V
+        where V: Clone,
+              F: Fn(&V, &V) -> V,
+    {
+        if self.len != 0 {
+            unsafe { self.root.as_ptr().reduce_values(&identity, f) }
+        } else {
+            identity
+        }
+    }
+
+    /// Applies `f` in place to the key and value of every leaf in this
+    /// `HashTrie`, without restructuring the trie.
+    pub(crate) fn set_all_values<F: FnMut(&K, &mut V)>(&mut self, f: &mut F) {
+        if self.len != 0 {
+            unsafe { self.root.as_ptr().set_all_values(f) }
+        }
+    }
+
     /// Returns a reference to the `Hold` that allocates this `HashTrie`.
     #[inline]
     pub(crate) fn holder(&self) -> &'a dyn Hold<'a> {
@@ -325,6 +413,165 @@ impl<'a, K, V, H> HashTrie<'a, K, V, H> {
             }
         }
     }
+
+    /// Returns a pointer to the last leaf of this `HashTrie` in iteration
+    /// order, if any, by descending along the highest occupied branch of
+    /// each node. A fresh iterator can't step back from its start position,
+    /// so `next_back` can't be used to find the last leaf.
+    pub(crate) fn last_leaf(&self) -> Option<NonNull<(K, V)>> {
+        unsafe {
+            // Check if the root node exists.
+            if self.len == 0 {
+                return None;
+            }
+            // Start at the root node.
+            let mut node_ptr = self.root.as_ptr();
+            loop {
+                // Get a frame with pointers to the limbs and leafs of the node.
+                match IterFrame::from_node(node_ptr) {
+                    IterFrame::Node { limb_map, leaf_map, limb_ptr, leaf_ptr, .. } => {
+                        // Get the highest occupied branch of the node.
+                        let branch = 1 << (31 - (limb_map | leaf_map).leading_zeros());
+                        match BranchType::for_branch(limb_map, leaf_map, branch) {
+                            BranchType::Void => unreachable!(),
+                            BranchType::Leaf => {
+                                // Get the number of leafs in the node.
+                                let leaf_count = (leaf_map & !limb_map).count_ones() as usize;
+                                // Return the highest leaf of the node.
+                                return Some(NonNull::new_unchecked(leaf_ptr.wrapping_add(leaf_count - 1)));
+                            },
+                            BranchType::Node => {
+                                // Descend into the highest limb of the node.
+                                let limb_count = limb_map.count_ones() as usize;
+                                node_ptr = *limb_ptr.wrapping_add(limb_count - 1) as *mut Node<'a, K, V>;
+                            },
+                            BranchType::Knot => {
+                                // Get the highest limb of the node.
+                                let limb_count = limb_map.count_ones() as usize;
+                                let knot_ptr = *limb_ptr.wrapping_add(limb_count - 1) as *mut Knot<'a, K, V>;
+                                // Return the last leaf of the knot.
+                                match IterFrame::from_knot(knot_ptr) {
+                                    IterFrame::Knot { foot_ptr, .. } => return Some(NonNull::new_unchecked(foot_ptr.wrapping_sub(1))),
+                                    _ => unreachable!(),
+                                }
+                            },
+                        }
+                    },
+                    _ => unreachable!(),
+                }
+            }
+        }
+    }
+
+    /// Returns a reference to the hasher used to hash the keys of this `HashTrie`.
+    #[inline]
+    pub(crate) fn hasher(&self) -> &H {
+        &self.hasher
+    }
+
+    /// Returns a pointer to the leaf whose key satisfies `is_match`, searching
+    /// the trie along the path of the supplied `hash` code; returns a null
+    /// pointer if no matching leaf exists. The `hash` must equal the hash code
+    /// the trie's hasher computes for any key that satisfies `is_match`.
+    pub(crate) fn find_raw<F>(&self, hash: u64, mut is_match: F) -> *mut (K, V)
+        where F: FnMut(&K) -> bool
+    {
+        unsafe {
+            // Check if the root node exists.
+            if self.len != 0 {
+                // Search the trie for a matching leaf.
+                self.root.as_ptr().find_raw(hash, 0, &mut is_match)
+            } else {
+                // No leafs in an empty trie.
+                ptr::null_mut()
+            }
+        }
+    }
+
+    /// Consumes this `HashTrie`, returning its hasher, and a drain that moves
+    /// out each of its leafs.
+    pub(crate) fn into_drain(self) -> (H, HashTrieDrain<'a, K, V>) {
+        unsafe {
+            // Construct an iterator over all leafs in the trie.
+            let iter = self.iterator();
+            // Move the trie structure into the drain.
+            let drain = HashTrieDrain {
+                root: self.root,
+                len: self.len,
+                iter: iter,
+            };
+            // Move the hasher out of the trie.
+            let hasher = ptr::read(&self.hasher);
+            // Discard the trie, whose structure is now owned by the drain.
+            mem::forget(self);
+            (hasher, drain)
+        }
+    }
+
+    /// Makes `dst` a clone of this `HashTrie`, reusing the nodes and knots of
+    /// `dst` that have the same shape as their counterparts in this trie.
+    /// Any new structure is allocated in the `Hold` of `dst`. On failure,
+    /// leaves `dst` empty.
+    pub(crate) fn clone_into(&self, dst: &mut HashTrie<'a, K, V, H>) -> Result<(), HoldError>
+        where K: Clone, V: Clone, H: Clone
+    {
+        unsafe {
+            // Adopt the hasher and depth bound of this trie.
+            dst.hasher.clone_from(&self.hasher);
+            dst.max_shift = self.max_shift;
+            // Get a pointer to the destination root node.
+            let dst_root = dst.root.as_ptr();
+            // Get the hold in which to allocate any new structure.
+            let hold = dst.holder();
+            if self.len == 0 {
+                // Check if the destination trie is non-empty.
+                if dst.len != 0 {
+                    // Replace the destination tree with an empty root.
+                    dst.root = NonNull::new_unchecked(Node::empty(hold));
+                    dst.len = 0;
+                    dst_root.drop();
+                }
+            } else if dst.len == 0 {
+                // Clone the whole tree, bailing on failure.
+                let new_root = self.root.as_ptr().clone_tree(hold)?;
+                // Deallocate the zero-sized destination root block.
+                hold.dealloc(Block::from_raw_parts(dst_root as *mut u8, 0));
+                dst.root = NonNull::new_unchecked(new_root);
+                dst.len = self.len;
+            } else {
+                match self.root.as_ptr().clone_tree_into(dst_root, hold) {
+                    Ok(new_root) => {
+                        dst.root = NonNull::new_unchecked(new_root);
+                        dst.len = self.len;
+                    },
+                    Err(error) => {
+                        // Discard the partially updated destination tree.
+                        dst.root = NonNull::new_unchecked(Node::empty(hold));
+                        dst.len = 0;
+                        dst_root.drop();
+                        return Err(error);
+                    },
+                }
+            }
+            Ok(())
+        }
+    }
+
+    /// Returns the number of bytes of trie structure shared with `other`,
+    /// summing the sizes of the nodes and knots that are the same allocation
+    /// in both tries.
+    pub(crate) fn shared_bytes<G>(&self, other: &HashTrie<'a, K, V, G>) -> usize {
+        unsafe {
+            // Check if both root nodes exist.
+            if self.len != 0 && other.len != 0 {
+                // Walk both tries in lockstep.
+                self.root.as_ptr().shared_bytes(other.root.as_ptr())
+            } else {
+                // Empty tries share no structure.
+                0
+            }
+        }
+    }
 }
 
 impl<'a, K: Eq + Hash, V, H: BuildHasher> HashTrie<'a, K, V, H> {
@@ -366,31 +613,91 @@ impl<'a, K: Eq + Hash, V, H: BuildHasher> HashTrie<'a, K, V, H> {
     /// to allocate any required new memory, returns the `key` and `value`,
     /// along with a `HoldError`, and leaves the trie in its original state.
     pub(crate) fn insert(&mut self, key: K, value: V) -> Result<Option<V>, (K, V, HoldError)> {
+        // Hash the insert key.
+        let hash = hash_key(&self.hasher, &key);
+        // Insert the key along the path of its hash code.
+        self.insert_hashed(hash, key, value)
+    }
+
+    /// Associates a new `value` with the given `key`, bounding the depth of
+    /// the trie to `max_depth` levels of nodes. Keys whose hash codes share a
+    /// prefix spanning more than `max_depth` levels are stored in a knot,
+    /// rather than in a chain of single-branch nodes. The bound persists for
+    /// all subsequent inserts, and can only ever be lowered, since knots may
+    /// now hold keys whose hash codes differ beyond the bound.
+    pub(crate) fn insert_bounded_depth(&mut self, key: K, value: V, max_depth: u32)
+        -> Result<Option<V>, (K, V, HoldError)>
+    {
+        // Lower the depth bound of the trie.
+        self.max_shift = cmp::min(self.max_shift, max_depth.saturating_mul(5));
+        // Insert the key, bounded by the new depth.
+        self.insert(key, value)
+    }
+
+    /// Associates a new `value` with the given `key`, using the precomputed
+    /// `hash` code of the `key`; returns the previous value associated with
+    /// the `key`, if defined. The `hash` must equal the hash code the trie's
+    /// hasher computes for `key`. If the trie's `Hold` fails to allocate any
+    /// required new memory, returns the `key` and `value`, along with a
+    /// `HoldError`, and leaves the trie in its original state.
+    pub(crate) fn insert_hashed(&mut self, hash: u64, key: K, value: V) -> Result<Option<V>, (K, V, HoldError)> {
+        match self.insert_hashed_leaf(hash, key, value, true) {
+            Ok((old_val, _)) => Ok(old_val),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Associates a new `value` with the given `key`, using the precomputed
+    /// `hash` code of the `key`, in a single descent of the trie; returns the
+    /// previous value associated with the `key`, if `replace` is `true` and
+    /// the `key` was present, along with a pointer to the leaf holding the
+    /// `key`. If `replace` is `false`, and the `key` is already present,
+    /// leaves its leaf unchanged, and drops the new `key` and `value`. The
+    /// leaf stays put until the trie is next mutated. If the trie's `Hold`
+    /// fails to allocate any required new memory, returns the `key` and
+    /// `value`, along with a `HoldError`, and leaves the trie in its original
+    /// state.
+    pub(crate) fn insert_hashed_leaf(&mut self, hash: u64, key: K, value: V, replace: bool)
+        -> Result<(Option<V>, *mut (K, V)), (K, V, HoldError)>
+    {
         unsafe {
-            // Hash the insert key.
-            let hash = hash_key(&self.hasher, &key);
             // Get a pointer to the root node.
             let old_root = self.root.as_ptr();
             // Get the current length of the trie.
             let old_len = self.len;
+            // Declare the location of the inserted leaf.
+            let mut new_leaf = ptr::null_mut();
             // Check if the root node exists.
             if old_len != 0 {
                 // Trie is non-empty; try to insert the new key and value.
-                match old_root.insert(&self.hasher, &key, &value, hash, 0) {
+                match old_root.insert(&self.hasher, &key, &value, hash, 0, self.max_shift,
+                                      replace, &mut new_leaf) {
                     // Successfully inserted into descendant.
                     NodeInsert::None => {
+                        // Forget the new key and value, which moved into the trie.
+                        mem::forget(key);
+                        mem::forget(value);
                         // Increment the length of the trie; can't overflow.
                         self.len = old_len.wrapping_add(1);
                         // No previous value.
-                        Ok(None)
+                        Ok((None, new_leaf))
                     },
                     // Successfully updated descendant.
                     NodeInsert::Diff(old_val) => {
+                        // Forget the new key and value, which moved into the trie.
+                        mem::forget(key);
+                        mem::forget(value);
                         // Return the previous value.
-                        Ok(Some(old_val))
+                        Ok((Some(old_val), new_leaf))
                     },
+                    // Found the key, leaving its leaf unchanged; the new key
+                    // and value drop on return.
+                    NodeInsert::Same => Ok((None, new_leaf)),
                     // Successfully inserted into a copy of the root node.
                     NodeInsert::Copy(new_node) => {
+                        // Forget the new key and value, which moved into the trie.
+                        mem::forget(key);
+                        mem::forget(value);
                         // Deallocate the old root node.
                         old_root.dealloc();
                         // Increment the length of the trie; can't overflow.
@@ -398,7 +705,7 @@ impl<'a, K: Eq + Hash, V, H: BuildHasher> HashTrie<'a, K, V, H> {
                         // Update the root node pointer.
                         self.root = NonNull::new_unchecked(new_node);
                         // No previous value.
-                        Ok(None)
+                        Ok((None, new_leaf))
                     },
                     // Insert failed.
                     NodeInsert::Fail(error) => Err((key, value, error)),
@@ -406,18 +713,23 @@ impl<'a, K: Eq + Hash, V, H: BuildHasher> HashTrie<'a, K, V, H> {
             } else {
                 // Trie is empty; allocate a new root node, populated with the
                 // new key and value.
-                let root = match Node::unary(old_root.holder(), &key, &value, hash) {
+                let root = match Node::unary(old_root.holder(), &key, &value, hash, &mut new_leaf) {
                     Ok(root) => root,
                     Err(error) => return Err((key, value, error)),
                 };
-                // Allocation succeeded; deallocate the old root node.
-                old_root.dealloc();
+                // Forget the new key and value, which moved into the new root node.
+                mem::forget(key);
+                mem::forget(value);
+                // Allocation succeeded; reconstruct the zero-sized root block.
+                let block = Block::from_raw_parts(old_root as *mut u8, 0);
+                // Deallocate the zero-sized root block.
+                old_root.holder().dealloc(block);
                 // Update the root node pointer.
                 self.root = NonNull::new_unchecked(root);
                 // Set the length of the trie.
                 self.len = 1;
                 // No previous value associated with the insert key.
-                Ok(None)
+                Ok((None, new_leaf))
             }
         }
     }
@@ -427,6 +739,18 @@ impl<'a, K: Eq + Hash, V, H: BuildHasher> HashTrie<'a, K, V, H> {
     /// its original state, if the trie's `Hold` fails to allocate any required
     /// new memory.
     pub(crate) fn remove<J: Borrow<K> + ?Sized>(&mut self, key: &J) -> Result<Option<V>, HoldError> {
+        match self.remove_entry(key) {
+            Ok(Some((_, old_val))) => Ok(Some(old_val)),
+            Ok(None) => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Disassociates the given `key`; returns the removed key and its
+    /// previously associated value, if any. Returns a `HoldError`, and leaves
+    /// the trie in its original state, if the trie's `Hold` fails to allocate
+    /// any required new memory.
+    pub(crate) fn remove_entry<J: Borrow<K> + ?Sized>(&mut self, key: &J) -> Result<Option<(K, V)>, HoldError> {
         unsafe {
             // Get the current length of the trie.
             let old_len = self.len;
@@ -442,17 +766,13 @@ impl<'a, K: Eq + Hash, V, H: BuildHasher> HashTrie<'a, K, V, H> {
                     NodeRemove::None => Ok(None),
                     // Successfully removed the key from a descendant.
                     NodeRemove::Diff((old_key, old_val)) => {
-                        // Drop the old key.
-                        mem::drop(old_key);
                         // Decrement the length of the trie.
                         self.len = old_len.wrapping_sub(1);
-                        // Return the removed value.
-                        Ok(Some(old_val))
+                        // Return the removed leaf.
+                        Ok(Some((old_key, old_val)))
                     },
                     // Successfully removed the last key in the trie.
                     NodeRemove::Drop((old_key, old_val)) => {
-                        // Crop the old key.
-                        mem::drop(old_key);
                         // Construct a new empty root node in the hold.
                         let new_root = Node::<'a, K, V>::empty(old_root.holder());
                         // Deallocate the old root node.
@@ -461,8 +781,8 @@ impl<'a, K: Eq + Hash, V, H: BuildHasher> HashTrie<'a, K, V, H> {
                         self.root = NonNull::new_unchecked(new_root);
                         // Reset the trie length.
                         self.len = 0;
-                        // Return the removed value.
-                        Ok(Some(old_val))
+                        // Return the removed leaf.
+                        Ok(Some((old_key, old_val)))
                     },
                     // Successfully removed the next-to-last key in the trie.
                     NodeRemove::Lift((old_key, old_val), (new_key, new_val)) => {
@@ -470,14 +790,19 @@ impl<'a, K: Eq + Hash, V, H: BuildHasher> HashTrie<'a, K, V, H> {
                         let new_hash = hash_key(&self.hasher, &new_key);
                         // Allocate a new root node, populated with the remaining
                         // key and value.
-                        let new_root = match Node::unary(old_root.holder(), &new_key, &new_val, new_hash) {
+                        let new_root = match Node::unary(old_root.holder(), &new_key, &new_val, new_hash, &mut ptr::null_mut()) {
                             Ok(new_root) => new_root,
-                            Err(error) => return Err(error),
+                            Err(error) => {
+                                // Forget the leafs read out of the unchanged root node.
+                                mem::forget(old_key);
+                                mem::forget(old_val);
+                                mem::forget(new_key);
+                                mem::forget(new_val);
+                                return Err(error);
+                            },
                         };
                         // Deallocate the old root node.
                         old_root.dealloc();
-                        // Drop the old key.
-                        mem::drop(old_key);
                         // Forget the new key, which moved to the new root node.
                         mem::forget(new_key);
                         // Forget the new value, which moved to the new root node.
@@ -486,21 +811,19 @@ impl<'a, K: Eq + Hash, V, H: BuildHasher> HashTrie<'a, K, V, H> {
                         self.root = NonNull::new_unchecked(new_root);
                         // Set the length of the trie.
                         self.len = 1;
-                        // No previous value associated with the key.
-                        Ok(Some(old_val))
+                        // Return the removed leaf.
+                        Ok(Some((old_key, old_val)))
                     },
                     // Successfully removed from a copy of the root node.
                     NodeRemove::Copy((old_key, old_val), new_node) => {
                         // Deallocate the old root node.
                         old_root.dealloc();
-                        // Drop the old key.
-                        mem::drop(old_key);
                         // Update the root node pointer.
                         self.root = NonNull::new_unchecked(new_node);
                         // Decrement the length of the trie.
                         self.len = old_len.wrapping_sub(1);
-                        // Return the removed value.
-                        Ok(Some(old_val))
+                        // Return the removed leaf.
+                        Ok(Some((old_key, old_val)))
                     },
                     // Remove failed.
                     NodeRemove::Fail(error) => return Err(error),
@@ -511,6 +834,34 @@ impl<'a, K: Eq + Hash, V, H: BuildHasher> HashTrie<'a, K, V, H> {
             }
         }
     }
+
+    /// Removes and returns the first leaf in iteration order, if any. Returns
+    /// a `HoldError`, and leaves the trie in its original state, if the trie's
+    /// `Hold` fails to allocate any required new memory.
+    pub(crate) fn pop_first(&mut self) -> Result<Option<(K, V)>, HoldError> {
+        unsafe {
+            match self.iterator().next() {
+                // Remove the leaf by its key; the key is only compared against
+                // before the leaf is moved out of the trie.
+                Some(leaf) => self.remove_entry(&*(&(*leaf.as_ptr()).0 as *const K)),
+                None => Ok(None),
+            }
+        }
+    }
+
+    /// Removes and returns the last leaf in iteration order, if any. Returns
+    /// a `HoldError`, and leaves the trie in its original state, if the trie's
+    /// `Hold` fails to allocate any required new memory.
+    pub(crate) fn pop_last(&mut self) -> Result<Option<(K, V)>, HoldError> {
+        unsafe {
+            match self.last_leaf() {
+                // Remove the leaf by its key; the key is only compared against
+                // before the leaf is moved out of the trie.
+                Some(leaf) => self.remove_entry(&*(&(*leaf.as_ptr()).0 as *const K)),
+                None => Ok(None),
+            }
+        }
+    }
 }
 
 unsafe impl<'a, K: Send, V: Send, H: Send> Send for HashTrie<'a, K, V, H> {
@@ -554,10 +905,13 @@ impl<'a, K: Clone, V: Clone, H: Clone> TryClone for HashTrie<'a, K, V, H> {
                     root: NonNull::new_unchecked(new_root),
                     len: len,
                     hasher: self.hasher.clone(),
+                    max_shift: self.max_shift,
                 })
             } else {
                 // Return an empty trie in the new hold.
-                Ok(HashTrie::hold_new_hasher(self.holder(), self.hasher.clone()))
+                let mut trie = HashTrie::hold_new_hasher(self.holder(), self.hasher.clone());
+                trie.max_shift = self.max_shift;
+                Ok(trie)
             }
         }
     }
@@ -579,10 +933,13 @@ impl<'a, K: Clone, V: Clone, H: Clone> CloneIntoHold<'a, HashTrie<'a, K, V, H>>
                     root: NonNull::new_unchecked(new_root),
                     len: len,
                     hasher: self.hasher.clone(),
+                    max_shift: self.max_shift,
                 })
             } else {
                 // Return an empty trie in the new hold.
-                Ok(HashTrie::hold_new_hasher(hold, self.hasher.clone()))
+                let mut trie = HashTrie::hold_new_hasher(hold, self.hasher.clone());
+                trie.max_shift = self.max_shift;
+                Ok(trie)
             }
         }
     }
@@ -604,6 +961,8 @@ impl<'a, 'b, K, V, H> Stow<'b, HashTrie<'b, K, V, H>> for HashTrie<'a, K, V, H>
         if let err @ Err(_) = H::stow(&mut (*src).hasher, &mut (*dst).hasher, hold) {
             return err;
         }
+        // Write the depth bound of the destination trie.
+        ptr::write(&mut (*dst).max_shift, (*src).max_shift);
         // Check if the root node exists.
         if len != 0 {
             // Recursively reallocate the trie in the new hold.
@@ -747,6 +1106,145 @@ impl<'a, K, V> Node<'a, K, V> {
         self.holder().dealloc(block);
     }
 
+    /// Returns the number of knots in this sub-tree, and the number of leafs
+    /// in its longest knot.
+    unsafe fn knot_stats(self: *mut Node<'a, K, V>) -> (usize, usize) {
+        // Capture this node's limb map.
+        let mut limb_map = (*self).limb_map;
+        // Capture this node's leaf map.
+        let mut leaf_map = (*self).leaf_map;
+        // Get the offset of the limb array.
+        let limb_offset = Layout::for_type::<Node<'a, K, V>>()
+                                 .extended_by_array_unchecked::<*mut Limb<'a, K, V>>(0).1;
+        // Get a pointer to the first limb in the limb array.
+        let mut limb_ptr = (self as *mut u8).wrapping_add(limb_offset) as *mut *mut Limb<'a, K, V>;
+        // Start with no knots.
+        let mut knot_count = 0;
+        let mut max_knot_len = 0;
+        // Loop over the branches of the node.
+        while limb_map | leaf_map != 0 {
+            // Determine the type of the current branch.
+            let branch_type = BranchType::for_branch(limb_map, leaf_map, 1);
+            if branch_type == BranchType::Node {
+                // Accumulate the knots of the sub-tree.
+                let (sub_count, sub_max_len) = (*(limb_ptr as *mut *mut Node<'a, K, V>)).knot_stats();
+                knot_count += sub_count;
+                max_knot_len = cmp::max(max_knot_len, sub_max_len);
+                // Increment the limb pointer.
+                limb_ptr = limb_ptr.wrapping_add(1);
+            } else if branch_type == BranchType::Knot {
+                // Count the sub-knot.
+                knot_count += 1;
+                max_knot_len = cmp::max(max_knot_len, (**(limb_ptr as *mut *mut Knot<'a, K, V>)).len);
+                // Increment the limb pointer.
+                limb_ptr = limb_ptr.wrapping_add(1);
+            }
+            // Shift the limb map to the next branch.
+            limb_map >>= 1;
+            // Shift the leaf map to the next branch.
+            leaf_map >>= 1;
+        }
+        (knot_count, max_knot_len)
+    }
+
+    /// Folds the values of the sub-tree rooted at this `Node` with `f`,
+    /// reducing each branch independently from `identity`.
+    unsafe fn reduce_values<F>(self: *mut Node<'a, K, V>, identity: &V, f: &F) -> V
+        where V: Clone,
+              F: Fn(&V, &V) -> V,
+    {
+        // Capture this node's limb map.
+        let mut limb_map = (*self).limb_map;
+        // Capture this node's leaf map.
+        let mut leaf_map = (*self).leaf_map;
+        // Count the number of limbs in the node.
+        let limb_count = limb_map.count_ones() as usize;
+        // Compute the layout of the node header.
+        let layout = Layout::for_type::<Node<'a, K, V>>();
+        // Extend the layout to include the limbs.
+        let (layout, limb_offset) = layout.extended_by_array_unchecked::<*mut Limb<'a, K, V>>(limb_count);
+        // Get the offset of the leaf array.
+        let leaf_offset = layout.extended_by_array_unchecked::<(K, V)>(0).1;
+        // Get a pointer to the first limb in the limb array.
+        let mut limb_ptr = (self as *mut u8).wrapping_add(limb_offset) as *mut *mut Limb<'a, K, V>;
+        // Get a pointer to the first leaf in the leaf array.
+        let mut leaf_ptr = (self as *mut u8).wrapping_add(leaf_offset) as *mut (K, V);
+        // Start with the identity value.
+        let mut acc = identity.clone();
+        // Loop over the branches of the node.
+        while limb_map | leaf_map != 0 {
+            // Determine the type of the current branch.
+            let branch_type = BranchType::for_branch(limb_map, leaf_map, 1);
+            if branch_type == BranchType::Leaf {
+                // Combine the leaf value.
+                acc = f(&acc, &(*leaf_ptr).1);
+                // Increment the leaf pointer.
+                leaf_ptr = leaf_ptr.wrapping_add(1);
+            } else if branch_type != BranchType::Void {
+                // Reduce the branch independently of the preceding branches.
+                let branch = if branch_type == BranchType::Node {
+                    (*(limb_ptr as *mut *mut Node<'a, K, V>)).reduce_values(identity, f)
+                } else {
+                    (*(limb_ptr as *mut *mut Knot<'a, K, V>)).reduce_values(identity, f)
+                };
+                // Combine the branch result.
+                acc = f(&acc, &branch);
+                // Increment the limb pointer.
+                limb_ptr = limb_ptr.wrapping_add(1);
+            }
+            // Shift the limb map to the next branch.
+            limb_map >>= 1;
+            // Shift the leaf map to the next branch.
+            leaf_map >>= 1;
+        }
+        acc
+    }
+
+    /// Applies `f` in place to the key and value of every leaf in this
+    /// `Node` and its descendants.
+    unsafe fn set_all_values<F: FnMut(&K, &mut V)>(self: *mut Node<'a, K, V>, f: &mut F) {
+        // Capture this node's limb map.
+        let mut limb_map = (*self).limb_map;
+        // Capture this node's leaf map.
+        let mut leaf_map = (*self).leaf_map;
+        // Count the number of limbs in the node.
+        let limb_count = limb_map.count_ones() as usize;
+        // Compute the layout of the node header.
+        let layout = Layout::for_type::<Node<'a, K, V>>();
+        // Extend the layout to include the limbs.
+        let (layout, limb_offset) = layout.extended_by_array_unchecked::<*mut Limb<'a, K, V>>(limb_count);
+        // Get the offset of the leaf array.
+        let leaf_offset = layout.extended_by_array_unchecked::<(K, V)>(0).1;
+        // Get a pointer to the first limb in the limb array.
+        let mut limb_ptr = (self as *mut u8).wrapping_add(limb_offset) as *mut *mut Limb<'a, K, V>;
+        // Get a pointer to the first leaf in the leaf array.
+        let mut leaf_ptr = (self as *mut u8).wrapping_add(leaf_offset) as *mut (K, V);
+        // Loop over the branches of the node.
+        while limb_map | leaf_map != 0 {
+            // Determine the type of the current branch.
+            let branch_type = BranchType::for_branch(limb_map, leaf_map, 1);
+            if branch_type == BranchType::Leaf {
+                // Update the leaf value.
+                f(&(*leaf_ptr).0, &mut (*leaf_ptr).1);
+                // Increment the leaf pointer.
+                leaf_ptr = leaf_ptr.wrapping_add(1);
+            } else if branch_type != BranchType::Void {
+                // Update the values of the branch.
+                if branch_type == BranchType::Node {
+                    (*(limb_ptr as *mut *mut Node<'a, K, V>)).set_all_values(f);
+                } else {
+                    (*(limb_ptr as *mut *mut Knot<'a, K, V>)).set_all_values(f);
+                }
+                // Increment the limb pointer.
+                limb_ptr = limb_ptr.wrapping_add(1);
+            }
+            // Shift the limb map to the next branch.
+            limb_map >>= 1;
+            // Shift the leaf map to the next branch.
+            leaf_map >>= 1;
+        }
+    }
+
     /// Releases the memory owned bu this `Node`, after dropping its
     /// descendants and leafs.
     unsafe fn drop(self: *mut Node<'a, K, V>) {
@@ -806,9 +1304,11 @@ impl<'a, K, V> Node<'a, K, V> {
 
     /// Allocates a new `Node` in `hold` containing a single leaf at the branch
     /// for the low 5 bit value of `hash`. Copies `key` and `val` to the new
-    /// node on success, logically transferring ownership. Returns a `HoldError`
-    /// if allocation fails, leaving ownership of `key` and `val` with the caller.
-    unsafe fn unary(hold: &dyn Hold<'a>, key: *const K, val: *const V, hash: u64)
+    /// node on success, logically transferring ownership, and points
+    /// `new_leaf` at the new leaf. Returns a `HoldError` if allocation fails,
+    /// leaving ownership of `key` and `val` with the caller.
+    unsafe fn unary(hold: &dyn Hold<'a>, key: *const K, val: *const V, hash: u64,
+                    new_leaf: &mut *mut (K, V))
         -> Result<*mut Node<'a, K, V>, HoldError>
     {
         // Use an empty limb map.
@@ -828,6 +1328,8 @@ impl<'a, K, V> Node<'a, K, V> {
         ptr::copy_nonoverlapping(key, &mut (*leaf_ptr).0, 1);
         // Copy the value to the new leaf, transferring ownership.
         ptr::copy_nonoverlapping(val, &mut (*leaf_ptr).1, 1);
+        // Point to the new leaf.
+        *new_leaf = leaf_ptr;
         // Return a pointer to the new node.
         Ok(node)
     }
@@ -1195,9 +1697,88 @@ impl<'a, K, V> Node<'a, K, V> {
         Ok(new_node)
     }
 
-    /// Returns a new node, allocated in `hold` containing two leafs.
+    /// Makes the `dst` sub-tree a clone of this sub-tree, allocating any new
+    /// structure in `hold`. Nodes and knots with the same shape in both
+    /// sub-trees are reused, and their leafs cloned in place; mismatched
+    /// branches are cloned anew, and the replaced `dst` branches dropped.
+    /// Returns a pointer to the root of the updated `dst` sub-tree, which
+    /// is `dst` itself if its shape matches. On failure, `dst` remains a
+    /// valid sub-tree, but with an unspecified mix of old and cloned leafs.
+    unsafe fn clone_tree_into(self: *mut Node<'a, K, V>, dst: *mut Node<'a, K, V>, hold: &dyn Hold<'a>)
+        -> Result<*mut Node<'a, K, V>, HoldError>
+        where K: Clone, V: Clone
+    {
+        // Capture this node's limb map;
+        let mut limb_map = (*self).limb_map;
+        // Capture this node's leaf map;
+        let mut leaf_map = (*self).leaf_map;
+        // Check if the destination node has a different shape.
+        if (*dst).limb_map != limb_map || (*dst).leaf_map != leaf_map {
+            // Clone this sub-tree anew, bailing on failure.
+            let new_node = self.clone_tree(hold)?;
+            // Drop the replaced destination sub-tree.
+            dst.drop();
+            // Return a pointer to the new sub-tree.
+            return Ok(new_node);
+        }
+
+        // Count the number of limbs in the node.
+        let limb_count = limb_map.count_ones() as usize;
+        // Compute the offset of the limb array.
+        let (layout, limb_offset) = Layout::for_type::<Node<'a, K, V>>()
+                                           .extended_by_array_unchecked::<*mut Limb<'a, K, V>>(limb_count);
+        // Compute the offset of the leaf array.
+        let leaf_offset = layout.extended_by_array_unchecked::<(K, V)>(0).1;
+
+        // Get a pointer to this node's limb array.
+        let mut src_limb_ptr = (self as *mut u8).wrapping_add(limb_offset) as *mut *mut Limb<'a, K, V>;
+        // Get a pointer to this node's leaf array.
+        let mut src_leaf_ptr = (self as *mut u8).wrapping_add(leaf_offset) as *mut (K, V);
+        // Get a pointer to the destination node's limb array.
+        let mut dst_limb_ptr = (dst as *mut u8).wrapping_add(limb_offset) as *mut *mut Limb<'a, K, V>;
+        // Get a pointer to the destination node's leaf array.
+        let mut dst_leaf_ptr = (dst as *mut u8).wrapping_add(leaf_offset) as *mut (K, V);
+
+        // Loop over the branches of both nodes.
+        while limb_map | leaf_map != 0 {
+            // Determine the type of the current branch.
+            let branch_type = BranchType::for_branch(limb_map, leaf_map, 1);
+            if branch_type == BranchType::Leaf {
+                // Clone the leaf over the destination leaf.
+                (*dst_leaf_ptr).clone_from(&*src_leaf_ptr);
+                src_leaf_ptr = src_leaf_ptr.wrapping_add(1);
+                dst_leaf_ptr = dst_leaf_ptr.wrapping_add(1);
+            } else if branch_type == BranchType::Node || branch_type == BranchType::Knot {
+                if branch_type == BranchType::Node {
+                    // Clone the sub-tree over the destination sub-tree, bailing on failure.
+                    let new_sub_node = (*(src_limb_ptr as *mut *mut Node<'a, K, V>))
+                        .clone_tree_into(*(dst_limb_ptr as *mut *mut Node<'a, K, V>), hold)?;
+                    // Update the destination limb pointer.
+                    *(dst_limb_ptr as *mut *mut Node<'a, K, V>) = new_sub_node;
+                } else {
+                    // Clone the sub-knot over the destination sub-knot, bailing on failure.
+                    let new_sub_knot = (*(src_limb_ptr as *mut *mut Knot<'a, K, V>))
+                        .clone_tree_into(*(dst_limb_ptr as *mut *mut Knot<'a, K, V>), hold)?;
+                    // Update the destination limb pointer.
+                    *(dst_limb_ptr as *mut *mut Knot<'a, K, V>) = new_sub_knot;
+                }
+                src_limb_ptr = src_limb_ptr.wrapping_add(1);
+                dst_limb_ptr = dst_limb_ptr.wrapping_add(1);
+            }
+            // Shift the limb map to the next branch.
+            limb_map >>= 1;
+            // Shift the leaf map to the next branch.
+            leaf_map >>= 1;
+        }
+        // Return a pointer to the reused destination node.
+        Ok(dst)
+    }
+
+    /// Returns a new node, allocated in `hold` containing two leafs; points
+    /// `new_leaf` at the second leaf.
     unsafe fn merged_leaf(hold: &dyn Hold<'a>, key0: *const K, val0: *const V, hash0: u64,
-                          key1: *const K, val1: *const V, hash1: u64, shift: u32)
+                          key1: *const K, val1: *const V, hash1: u64, shift: u32,
+                          new_leaf: &mut *mut (K, V))
         -> Result<*mut Node<'a, K, V>, HoldError>
     {
         // Verify there's no hash collision.
@@ -1227,7 +1808,7 @@ impl<'a, K, V> Node<'a, K, V> {
 
             // Recursively allocate a sub-node containing the two leafs.
             let sub_node = match Node::merged_leaf(hold, key0, val0, hash0,
-                                                   key1, val1, hash1, shift.wrapping_add(5)) {
+                                                   key1, val1, hash1, shift.wrapping_add(5), new_leaf) {
                 Ok(sub_node) => sub_node,
                 err @ Err(..) => {
                     // Deallocate the new node.
@@ -1269,12 +1850,16 @@ impl<'a, K, V> Node<'a, K, V> {
                 ptr::copy_nonoverlapping(key1, &mut (*leaf_ptr).0, 1);
                 // Write the second value.
                 ptr::copy_nonoverlapping(val1, &mut (*leaf_ptr).1, 1);
+                // Point to the second leaf.
+                *new_leaf = leaf_ptr;
             } else {
                 // The second leaf precedes the first leaf, in hash order.
                 // Write the second key.
                 ptr::copy_nonoverlapping(key1, &mut (*leaf_ptr).0, 1);
                 // Write the second value.
                 ptr::copy_nonoverlapping(val1, &mut (*leaf_ptr).1, 1);
+                // Point to the second leaf.
+                *new_leaf = leaf_ptr;
                 // Increment the leaf pointer.
                 let leaf_ptr = leaf_ptr.wrapping_add(1);
                 // Write the first key.
@@ -1287,9 +1872,11 @@ impl<'a, K, V> Node<'a, K, V> {
         Ok(node)
     }
 
-    /// Returns a new node, allocated in `hold`, containing a knot and a leaf.
+    /// Returns a new node, allocated in `hold`, containing a knot and a leaf;
+    /// points `new_leaf` at the leaf.
     unsafe fn merged_knot(hold: &dyn Hold<'a>, knot0: *mut Knot<'a, K, V>, hash0: u64,
-                          key1: *const K, val1: *const V, hash1: u64, shift: u32)
+                          key1: *const K, val1: *const V, hash1: u64, shift: u32,
+                          new_leaf: &mut *mut (K, V))
         -> Result<*mut Node<'a, K, V>, HoldError>
     {
         // Verify there's no hash collision.
@@ -1319,7 +1906,7 @@ impl<'a, K, V> Node<'a, K, V> {
 
             // Recursively allocate a sub-node containing the knot and the leaf.
             let sub_node = match Node::merged_knot(hold, knot0, hash0,
-                                                   key1, val1, hash1, shift.wrapping_add(5)) {
+                                                   key1, val1, hash1, shift.wrapping_add(5), new_leaf) {
                 Ok(sub_node) => sub_node,
                 err @ Err(..) => {
                     // Deallocate the new node.
@@ -1357,10 +1944,193 @@ impl<'a, K, V> Node<'a, K, V> {
             ptr::copy_nonoverlapping(key1, &mut (*leaf_ptr).0, 1);
             // Write the value to the leaf.
             ptr::copy_nonoverlapping(val1, &mut (*leaf_ptr).1, 1);
+            // Point to the leaf.
+            *new_leaf = leaf_ptr;
         }
         // Return a pointer to the new node.
         Ok(node)
     }
+
+    /// Returns a pointer to the leaf whose key satisfies `is_match`, branching
+    /// off the externally supplied `hash` code shifted right by `shift` bits;
+    /// returns a null pointer if no matching leaf exists.
+    unsafe fn find_raw<F>(mut self: *mut Node<'a, K, V>, hash: u64, mut shift: u32, is_match: &mut F)
+        -> *mut (K, V)
+        where F: FnMut(&K) -> bool
+    {
+        // Recursively descend the trie.
+        loop {
+            // Capture this node's limb map.
+            let limb_map = (*self).limb_map;
+            // Capture this node's leaf map.
+            let leaf_map = (*self).leaf_map;
+            // Get the branch bit for the next 5 bit string of the hash code.
+            let branch = branch32(hash, shift);
+            // Determine the type of branch for the bit string.
+            let branch_type = BranchType::for_branch(limb_map, leaf_map, branch);
+            // Check if the trie terminates at this branch.
+            if branch_type == BranchType::Void {
+                // No matching leaf.
+                return ptr::null_mut();
+            } else {
+                // Branch exists; compute the layout of the node header.
+                let layout = Layout::for_type::<Node<'a, K, V>>();
+                // Check if the node has a leaf at this branch.
+                if branch_type == BranchType::Leaf {
+                    // Count the number of limbs in the node.
+                    let limb_count = limb_map.count_ones() as usize;
+                    // Get the index of the leaf in the leaf array.
+                    let leaf_idx = (!limb_map & leaf_map & branch.wrapping_sub(1)).count_ones() as usize;
+                    // Get the offset of the leaf in the leaf array.
+                    let leaf_offset = layout.extended_by_array_unchecked::<*mut Limb<'a, K, V>>(limb_count).0
+                                            .extended_by_array_unchecked::<(K, V)>(leaf_idx).0
+                                            .size();
+                    // Get a pointer to the leaf.
+                    let leaf_ptr = (self as *mut u8).wrapping_add(leaf_offset) as *mut (K, V);
+                    // Check if the leaf key satisfies the match predicate.
+                    if is_match(&(*leaf_ptr).0) {
+                        // Return a pointer to the matched leaf.
+                        return leaf_ptr;
+                    } else {
+                        // Leaf doesn't match.
+                        return ptr::null_mut();
+                    }
+                } else {
+                    // Trie has a limb at this branch.
+                    // Get the index of the limb in the limb array.
+                    let limb_idx = (limb_map & branch.wrapping_sub(1)).count_ones() as usize;
+                    // Get the offset of the limb in the limb array.
+                    let limb_offset = layout.extended_by_array_unchecked::<*mut Limb<'a, K, V>>(limb_idx).0
+                                            .size();
+                    // Get a pointer to the limb.
+                    let limb_ptr = (self as *mut u8).wrapping_add(limb_offset) as *mut *mut Limb<'a, K, V>;
+                    // Check the type of limb at this branch.
+                    if branch_type == BranchType::Node {
+                        // Descend into the sub-tree at this branch.
+                        self = *(limb_ptr as *mut *mut Node<'a, K, V>);
+                        // Having matched 5 bits of the hash code.
+                        shift += 5;
+                        // Recurse.
+                        continue;
+                    } else if branch_type == BranchType::Knot {
+                        // Search the knot at this branch for a matching leaf.
+                        return (*(limb_ptr as *mut *mut Knot<'a, K, V>)).find_raw(is_match);
+                    }
+                }
+            }
+            unreachable!();
+        }
+    }
+
+    /// Returns the size in bytes of this `Node`, excluding its descendants.
+    unsafe fn size(self: *mut Node<'a, K, V>) -> usize {
+        // Capture this node's limb map.
+        let limb_map = (*self).limb_map;
+        // Capture this node's leaf map.
+        let leaf_map = (*self).leaf_map;
+        // Count the number of limbs in the node.
+        let limb_count = limb_map.count_ones() as usize;
+        // Count the number of leafs in the node.
+        let leaf_count = (!limb_map & leaf_map).count_ones() as usize;
+        // Compute the size of the node.
+        Layout::for_type::<Node<'a, K, V>>()
+               .extended_by_array_unchecked::<*mut Limb<'a, K, V>>(limb_count).0
+               .extended_by_array_unchecked::<(K, V)>(leaf_count).0
+               .size()
+    }
+
+    /// Returns the size in bytes of this sub-tree, including all descendant
+    /// nodes and knots.
+    unsafe fn tree_size(self: *mut Node<'a, K, V>) -> usize {
+        // Capture this node's limb map.
+        let mut limb_map = (*self).limb_map;
+        // Capture this node's leaf map.
+        let mut leaf_map = (*self).leaf_map;
+        // Get the offset of the limb array.
+        let limb_offset = Layout::for_type::<Node<'a, K, V>>()
+                                 .extended_by_array_unchecked::<*mut Limb<'a, K, V>>(0).1;
+        // Get a pointer to the first limb in the limb array.
+        let mut limb_ptr = (self as *mut u8).wrapping_add(limb_offset) as *mut *mut Limb<'a, K, V>;
+        // Start with the size of this node.
+        let mut size = self.size();
+        // Loop over the branches of the node.
+        while limb_map | leaf_map != 0 {
+            // Determine the type of the current branch.
+            let branch_type = BranchType::for_branch(limb_map, leaf_map, 1);
+            if branch_type == BranchType::Node || branch_type == BranchType::Knot {
+                if branch_type == BranchType::Node {
+                    // Add the size of the sub-tree.
+                    size = size.wrapping_add((*(limb_ptr as *mut *mut Node<'a, K, V>)).tree_size());
+                } else {
+                    // Add the size of the sub-knot.
+                    size = size.wrapping_add((*(limb_ptr as *mut *mut Knot<'a, K, V>)).size());
+                }
+                // Increment the limb pointer.
+                limb_ptr = limb_ptr.wrapping_add(1);
+            }
+            // Shift the limb map to the next branch.
+            limb_map >>= 1;
+            // Shift the leaf map to the next branch.
+            leaf_map >>= 1;
+        }
+        size
+    }
+
+    /// Returns the number of bytes of sub-tree structure shared by this
+    /// `Node` and `that` node, walking both sub-trees in lockstep.
+    unsafe fn shared_bytes(self: *mut Node<'a, K, V>, that: *mut Node<'a, K, V>) -> usize {
+        // Check if both sub-trees are the same allocation.
+        if self == that {
+            // The entire sub-tree is shared.
+            return self.tree_size();
+        }
+        // Capture the limb maps of both nodes.
+        let mut limb_map0 = (*self).limb_map;
+        let mut limb_map1 = (*that).limb_map;
+        // Capture the leaf maps of both nodes.
+        let mut leaf_map0 = (*self).leaf_map;
+        let mut leaf_map1 = (*that).leaf_map;
+        // Get the offset of the limb arrays.
+        let limb_offset = Layout::for_type::<Node<'a, K, V>>()
+                                 .extended_by_array_unchecked::<*mut Limb<'a, K, V>>(0).1;
+        // Get pointers to the first limbs in both limb arrays.
+        let mut limb_ptr0 = (self as *mut u8).wrapping_add(limb_offset) as *mut *mut Limb<'a, K, V>;
+        let mut limb_ptr1 = (that as *mut u8).wrapping_add(limb_offset) as *mut *mut Limb<'a, K, V>;
+        let mut size = 0usize;
+        // Loop over the branches of both nodes.
+        while limb_map0 | leaf_map0 != 0 && limb_map1 | leaf_map1 != 0 {
+            // Determine the types of the current branches.
+            let branch_type0 = BranchType::for_branch(limb_map0, leaf_map0, 1);
+            let branch_type1 = BranchType::for_branch(limb_map1, leaf_map1, 1);
+            if branch_type0 == BranchType::Node && branch_type1 == BranchType::Node {
+                // Both nodes have sub-trees at this branch; compare them.
+                let node0 = *(limb_ptr0 as *mut *mut Node<'a, K, V>);
+                let node1 = *(limb_ptr1 as *mut *mut Node<'a, K, V>);
+                size = size.wrapping_add(node0.shared_bytes(node1));
+            } else if branch_type0 == BranchType::Knot && branch_type1 == BranchType::Knot {
+                // Both nodes have knots at this branch; check if they're the same allocation.
+                let knot0 = *(limb_ptr0 as *mut *mut Knot<'a, K, V>);
+                let knot1 = *(limb_ptr1 as *mut *mut Knot<'a, K, V>);
+                if knot0 == knot1 {
+                    size = size.wrapping_add(knot0.size());
+                }
+            }
+            // Increment the limb pointers past any limbs at this branch.
+            if branch_type0 == BranchType::Node || branch_type0 == BranchType::Knot {
+                limb_ptr0 = limb_ptr0.wrapping_add(1);
+            }
+            if branch_type1 == BranchType::Node || branch_type1 == BranchType::Knot {
+                limb_ptr1 = limb_ptr1.wrapping_add(1);
+            }
+            // Shift the limb maps to the next branch.
+            limb_map0 >>= 1;
+            limb_map1 >>= 1;
+            // Shift the leaf maps to the next branch.
+            leaf_map0 >>= 1;
+            leaf_map1 >>= 1;
+        }
+        size
+    }
 }
 
 impl<'a, K: Eq + Hash, V> Node<'a, K, V> {
@@ -1495,10 +2265,15 @@ impl<'a, K: Eq + Hash, V> Node<'a, K, V> {
     }
 
     /// Associates a new value with the given key, branching off the key's hash
-    /// code shifted right by `shift` bits.
+    /// code shifted right by `shift` bits. Keys whose hash codes share a prefix
+    /// extending to `max_shift` bits are stored together in a knot, rather
+    /// than in deeper nodes. Leaves an existing leaf for the key unchanged,
+    /// unless `replace` is `true`. Points `new_leaf` at the leaf holding the
+    /// key on success.
     unsafe fn insert<H: BuildHasher>(self: *mut Node<'a, K, V>, hasher: &H,
                                      new_key: *const K, new_val: *const V, new_hash: u64,
-                                     shift: u32)
+                                     shift: u32, max_shift: u32, replace: bool,
+                                     new_leaf: &mut *mut (K, V))
         -> NodeInsert<'a, K, V>
     {
         // Capture this node's limb map.
@@ -1536,6 +2311,8 @@ impl<'a, K: Eq + Hash, V> Node<'a, K, V> {
             ptr::copy_nonoverlapping(new_key, &mut (*new_leaf_ptr).0, 1);
             // Copy the inserted value into the new leaf.
             ptr::copy_nonoverlapping(new_val, &mut (*new_leaf_ptr).1, 1);
+            // Point to the new leaf.
+            *new_leaf = new_leaf_ptr;
             // Return a pointer to the new node.
             return NodeInsert::Copy(new_node);
         } else if branch_type == BranchType::Leaf {
@@ -1553,6 +2330,13 @@ impl<'a, K: Eq + Hash, V> Node<'a, K, V> {
             // Check if the old key matches the new key.
             if &(*old_leaf_ptr).0 == &*new_key {
                 // Keys match.
+                // Point to the matching leaf.
+                *new_leaf = old_leaf_ptr;
+                // Check if the existing leaf should be kept.
+                if !replace {
+                    // Leave the leaf unchanged.
+                    return NodeInsert::Same;
+                }
                 // Drop the old key.
                 ptr::drop_in_place(&mut (*old_leaf_ptr).0);
                 // Read out the old value.
@@ -1574,8 +2358,9 @@ impl<'a, K: Eq + Hash, V> Node<'a, K, V> {
                                             .size();
                 // Hash the old key.
                 let old_hash = hash_key(hasher, &(*old_leaf_ptr).0);
-                // Check if the old hash code differs from the new hash code.
-                if old_hash != new_hash {
+                // Check if the old hash code diverges from the new hash code
+                // before reaching the depth bound of the trie.
+                if old_hash != new_hash && split_shift(old_hash, new_hash) < max_shift {
                     // Hashes differ; merge the leafs into sub-tree.
                     // Unset the leaf flag for the branch.
                     let new_leaf_map = old_leaf_map ^ branch;
@@ -1588,7 +2373,7 @@ impl<'a, K: Eq + Hash, V> Node<'a, K, V> {
                     let sub_node = match Node::merged_leaf(self.holder(),
                                                            &(*old_leaf_ptr).0, &(*old_leaf_ptr).1, old_hash,
                                                            new_key, new_val, new_hash,
-                                                           shift.wrapping_add(5)) {
+                                                           shift.wrapping_add(5), new_leaf) {
                         Ok(sub_node) => sub_node,
                         Err(error) => {
                             // Deallocate the remapped node.
@@ -1604,7 +2389,8 @@ impl<'a, K: Eq + Hash, V> Node<'a, K, V> {
                     // Return a pointer to the new node.
                     return NodeInsert::Copy(new_node);
                 } else {
-                    // Hashes match; merge the leafs into a sub-knot.
+                    // Hashes match, or share a prefix extending beyond the
+                    // depth bound; merge the leafs into a sub-knot.
                     // Keep the leaf flag set for the branch.
                     let new_leaf_map = old_leaf_map;
                     // Reallocate the node with a limb for the branch, bailing on failure.
@@ -1615,7 +2401,7 @@ impl<'a, K: Eq + Hash, V> Node<'a, K, V> {
                     // Allocate a collision bucket containing the two leafs.
                     let sub_knot = match Knot::binary(self.holder(), new_hash,
                                                       &(*old_leaf_ptr).0, &(*old_leaf_ptr).1,
-                                                      new_key, new_val) {
+                                                      new_key, new_val, new_leaf) {
                         Ok(sub_knot) => sub_knot,
                         Err(error) => {
                             // Deallocate the remaped node.
@@ -1647,11 +2433,14 @@ impl<'a, K: Eq + Hash, V> Node<'a, K, V> {
                 // Get a pointer to the old sub-node.
                 let old_sub_node = *(sub_limb_ptr as *mut *mut Node<'a, K, V>);
                 // Insert the new key and value into the sub-node.
-                match old_sub_node.insert(hasher, new_key, new_val, new_hash, shift.wrapping_add(5)) {
+                match old_sub_node.insert(hasher, new_key, new_val, new_hash, shift.wrapping_add(5),
+                                          max_shift, replace, new_leaf) {
                     // Inserted into a descendant of the sub-node.
                     none @ NodeInsert::None => return none,
                     // Mutated the sub-node in place.
                     diff @ NodeInsert::Diff(..) => return diff,
+                    // Found the key in the sub-node.
+                    same @ NodeInsert::Same => return same,
                     // Inserted into a copy of the sub-node.
                     NodeInsert::Copy(new_sub_node) => {
                         // Deallocate the old sub-node.
@@ -1671,11 +2460,14 @@ impl<'a, K: Eq + Hash, V> Node<'a, K, V> {
                 // Get the hash code of the sub-knot.
                 let old_hash = (*old_sub_knot).hash;
                 // Compare the old hash code to the new hash code.
-                if old_hash == new_hash {
-                    // Hashes match; insert the new key and value into the knot.
-                    match old_sub_knot.insert(new_key, new_val) {
+                if old_hash == new_hash || split_shift(old_hash, new_hash) >= max_shift {
+                    // Hashes match, or share a prefix extending beyond the
+                    // depth bound; insert the new key and value into the knot.
+                    match old_sub_knot.insert(new_key, new_val, replace, new_leaf) {
                         // Mutated the sub-knot in place.
                         KnotInsert::Diff(old_val) => return NodeInsert::Diff(old_val),
+                        // Found the key in the sub-knot.
+                        KnotInsert::Same => return NodeInsert::Same,
                         // Inserted into a copy of the sub-knot.
                         KnotInsert::Copy(new_sub_knot) => {
                             // Deallocate the old sub knot.
@@ -1692,7 +2484,7 @@ impl<'a, K: Eq + Hash, V> Node<'a, K, V> {
                     // Hashes differ; merge the knot and leaf into a new sub-node.
                     let sub_node = match Node::merged_knot(self.holder(), old_sub_knot, old_hash,
                                                            new_key, new_val, new_hash,
-                                                           shift.wrapping_add(5)) {
+                                                           shift.wrapping_add(5), new_leaf) {
                         Ok(sub_node) => sub_node,
                         Err(error) => return NodeInsert::Fail(error),
                     };
@@ -1777,7 +2569,11 @@ impl<'a, K: Eq + Hash, V> Node<'a, K, V> {
                 // Reallocate the node with the leaf removed.
                 let new_node = match self.remap(self.holder(), new_limb_map, new_leaf_map) {
                     Ok(new_node) => new_node,
-                    Err(error) => return NodeRemove::Fail(error),
+                    Err(error) => {
+                        // Forget the leaf read out of the unchanged trie.
+                        mem::forget(old_leaf);
+                        return NodeRemove::Fail(error);
+                    },
                 };
                 // Return the removed leaf, and a pointer to the new node.
                 return NodeRemove::Copy(old_leaf, new_node);
@@ -1843,7 +2639,11 @@ impl<'a, K: Eq + Hash, V> Node<'a, K, V> {
                         // New node still has limbs; reallocate the node with the sub-node removed.
                         let new_node = match self.remap(self.holder(), new_limb_map, new_leaf_map) {
                             Ok(new_node) => new_node,
-                            Err(error) => return NodeRemove::Fail(error),
+                            Err(error) => {
+                                // Forget the leaf read out of the unchanged trie.
+                                mem::forget(old_leaf);
+                                return NodeRemove::Fail(error);
+                            },
                         };
                         // All allocations succeeded; deallocate the old sub-node.
                         old_sub_node.dealloc();
@@ -1859,7 +2659,12 @@ impl<'a, K: Eq + Hash, V> Node<'a, K, V> {
                         // Reallocate the node with a leaf, instead of a limb, for the branch.
                         let new_node = match self.remap(self.holder(), new_limb_map, new_leaf_map) {
                             Ok(new_node) => new_node,
-                            Err(error) => return NodeRemove::Fail(error),
+                            Err(error) => {
+                                // Forget the leafs read out of the unchanged trie.
+                                mem::forget(old_leaf);
+                                mem::forget(new_leaf);
+                                return NodeRemove::Fail(error);
+                            },
                         };
                         // All allocations succeeded; deallocate the old sub-node.
                         old_sub_node.dealloc();
@@ -1936,7 +2741,11 @@ impl<'a, K: Eq + Hash, V> Node<'a, K, V> {
                         // New node still has limbs; reallocate the node with the sub-knot removed.
                         let new_node = match self.remap(self.holder(), new_limb_map, new_leaf_map) {
                             Ok(new_node) => new_node,
-                            Err(error) => return NodeRemove::Fail(error),
+                            Err(error) => {
+                                // Forget the leaf read out of the unchanged trie.
+                                mem::forget(old_leaf);
+                                return NodeRemove::Fail(error);
+                            },
                         };
                         // All allocations succeeded; deallocate the old sub-knot.
                         old_sub_knot.dealloc();
@@ -1952,7 +2761,12 @@ impl<'a, K: Eq + Hash, V> Node<'a, K, V> {
                         // Reallocate the node with a leaf, instead of a limb, for the branch.
                         let new_node = match self.remap(self.holder(), new_limb_map, new_leaf_map) {
                             Ok(new_node) => new_node,
-                            Err(error) => return NodeRemove::Fail(error),
+                            Err(error) => {
+                                // Forget the leafs read out of the unchanged trie.
+                                mem::forget(old_leaf);
+                                mem::forget(new_leaf);
+                                return NodeRemove::Fail(error);
+                            },
                         };
                         // All allocations succeeded; deallocate the old sub-knot.
                         old_sub_knot.dealloc();
@@ -2034,9 +2848,12 @@ impl<'a, K, V> Knot<'a, K, V> {
         self.holder().dealloc(block);
     }
 
-    /// Allocates a new `Knot` in `hold` containing two leafs.
+    /// Allocates a new `Knot` in `hold` containing two leafs; points
+    /// `new_leaf` at the second leaf.
     unsafe fn binary(hold: &dyn Hold<'a>, hash: u64, key0: *const K, value0: *const V,
-                     key1: *const K, value1: *const V) -> Result<*mut Knot<'a, K, V>, HoldError> {
+                     key1: *const K, value1: *const V, new_leaf: &mut *mut (K, V))
+        -> Result<*mut Knot<'a, K, V>, HoldError>
+    {
         // Allocate a knot with uninitialized capacity for two leafs, bailing on failure.
         let knot = Knot::alloc(hold, hash, 2)?;
         // Get a pointer to the first leaf.
@@ -2047,6 +2864,8 @@ impl<'a, K, V> Knot<'a, K, V> {
         let leaf_ptr = leaf_ptr.wrapping_add(1);
         ptr::copy_nonoverlapping(key1, &mut (*leaf_ptr).0, 1);
         ptr::copy_nonoverlapping(value1, &mut (*leaf_ptr).1, 1);
+        // Point to the second leaf.
+        *new_leaf = leaf_ptr;
         // Return a pointer to the new knot.
         Ok(knot)
     }
@@ -2057,6 +2876,29 @@ impl<'a, K, V> Knot<'a, K, V> {
         AllocTag::from_ptr(self as *mut u8).holder()
     }
 
+    /// Folds the values of the leafs in this `Knot` with `f`, starting
+    /// from `identity`.
+    unsafe fn reduce_values<F>(self: *mut Knot<'a, K, V>, identity: &V, f: &F) -> V
+        where V: Clone,
+              F: Fn(&V, &V) -> V,
+    {
+        let leafs = slice::from_raw_parts(self.leaf_array(), (*self).len);
+        let mut acc = identity.clone();
+        for leaf in leafs {
+            acc = f(&acc, &leaf.1);
+        }
+        acc
+    }
+
+    /// Applies `f` in place to the key and value of every leaf in this
+    /// `Knot`.
+    unsafe fn set_all_values<F: FnMut(&K, &mut V)>(self: *mut Knot<'a, K, V>, f: &mut F) {
+        let leafs = slice::from_raw_parts_mut(self.leaf_array(), (*self).len);
+        for leaf in leafs {
+            f(&leaf.0, &mut leaf.1);
+        }
+    }
+
     #[inline]
     unsafe fn leaf_array(self: *mut Knot<'a, K, V>) -> *mut (K, V) {
         let offset = Layout::for_type::<Knot<'a, K, V>>().padded_to_type::<(K, V)>().size();
@@ -2115,6 +2957,47 @@ impl<'a, K, V> Knot<'a, K, V> {
         new_slice.clone_from_slice(old_slice);
         Ok(new_knot)
     }
+
+    /// Makes the `dst` knot a clone of this knot, cloning leafs in place if
+    /// both knots have the same length, or replacing `dst` with a new knot,
+    /// allocated in `hold`, otherwise. Returns a pointer to the updated knot.
+    unsafe fn clone_tree_into(self: *mut Knot<'a, K, V>, dst: *mut Knot<'a, K, V>, hold: &dyn Hold<'a>)
+        -> Result<*mut Knot<'a, K, V>, HoldError>
+        where K: Clone, V: Clone
+    {
+        let len = (*self).len;
+        if (*dst).len != len {
+            let new_knot = self.clone_tree(hold)?;
+            dst.drop();
+            return Ok(new_knot);
+        }
+        (*dst).hash = (*self).hash;
+        let src_slice = slice::from_raw_parts(self.leaf_array(), len);
+        let dst_slice = slice::from_raw_parts_mut(dst.leaf_array(), len);
+        dst_slice.clone_from_slice(src_slice);
+        Ok(dst)
+    }
+
+    /// Returns the size in bytes of this `Knot`.
+    unsafe fn size(self: *mut Knot<'a, K, V>) -> usize {
+        Layout::for_type::<Knot<'a, K, V>>().extended_by_array_unchecked::<(K, V)>((*self).len).0.size()
+    }
+
+    /// Returns a pointer to the first leaf whose key satisfies `is_match`,
+    /// or a null pointer if no leaf matches.
+    unsafe fn find_raw<F>(self: *mut Knot<'a, K, V>, is_match: &mut F) -> *mut (K, V)
+        where F: FnMut(&K) -> bool
+    {
+        let mut head = self.leaf_array();
+        let foot = head.wrapping_add((*self).len);
+        while head < foot {
+            if is_match(&(*head).0) {
+                return head;
+            }
+            head = head.wrapping_add(1);
+        }
+        ptr::null_mut()
+    }
 }
 
 impl<'a, K: Eq, V> Knot<'a, K, V> {
@@ -2145,9 +3028,11 @@ impl<'a, K: Eq, V> Knot<'a, K, V> {
         None
     }
 
-    /// Associates a new value with a key; leaves the knot in its original
-    /// state on allocation failure.
-    unsafe fn insert(self: *mut Knot<'a, K, V>, new_key: *const K, new_val: *const V)
+    /// Associates a new value with a key, unless the key is present and
+    /// `replace` is `false`, pointing `new_leaf` at the leaf holding the key;
+    /// leaves the knot in its original state on allocation failure.
+    unsafe fn insert(self: *mut Knot<'a, K, V>, new_key: *const K, new_val: *const V,
+                     replace: bool, new_leaf: &mut *mut (K, V))
         -> KnotInsert<'a, K, V>
     {
         // Get the number of leafs in the old knot.
@@ -2162,6 +3047,13 @@ impl<'a, K: Eq, V> Knot<'a, K, V> {
         while old_head < old_foot {
             // Check if the head leaf key matches the search key.
             if &(*old_head).0 == &*new_key {
+                // Point to the matching leaf.
+                *new_leaf = old_head;
+                // Check if the existing leaf should be kept.
+                if !replace {
+                    // Leave the leaf unchanged.
+                    return KnotInsert::Same;
+                }
                 // Drop the old key.
                 ptr::drop_in_place(&mut (*old_head).0);
                 // Copy the old value into local memory.
@@ -2196,6 +3088,8 @@ impl<'a, K: Eq, V> Knot<'a, K, V> {
         ptr::copy_nonoverlapping(new_key, &mut (*new_head).0, 1);
         // Copy the inserted value into the new leaf.
         ptr::copy_nonoverlapping(new_val, &mut (*new_head).1, 1);
+        // Point to the new leaf.
+        *new_leaf = new_head;
         // Return a pointer to the new knot.
         // Caller takes responsibility for deallocating the old knot.
         KnotInsert::Copy(new_knot)
@@ -2242,7 +3136,11 @@ impl<'a, K: Eq, V> Knot<'a, K, V> {
                     // Allocation succeeded.
                     Ok(new_knot) => new_knot,
                     // Allocation failed; return the error, leaving this knot in its original state.
-                    Err(error) => return KnotRemove::Fail(error),
+                    Err(error) => {
+                        // Forget the leaf read out of the unchanged knot.
+                        mem::forget(old_leaf);
+                        return KnotRemove::Fail(error);
+                    },
                 };
                 // Get a pointer to the base address of the new leaf array.
                 let new_base = new_knot.leaf_array();
@@ -2471,3 +3369,46 @@ impl<'a, K: 'a, V: 'a> Clone for HashTrieIter<'a, K, V> {
         }
     }
 }
+
+impl<'a, K, V> Iterator for HashTrieDrain<'a, K, V> {
+    type Item = (K, V);
+
+    #[inline]
+    fn next(&mut self) -> Option<(K, V)> {
+        unsafe {
+            match self.iter.next() {
+                // Move the leaf out of the trie; the trie won't drop it.
+                Some(leaf) => Some(ptr::read(leaf.as_ptr())),
+                None => None,
+            }
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.iter.len();
+        (len, Some(len))
+    }
+}
+
+impl<'a, K, V> Drop for HashTrieDrain<'a, K, V> {
+    fn drop(&mut self) {
+        // Drop all leafs that haven't been moved out.
+        while let Some(_) = self.next() {
+        }
+        unsafe {
+            // Get a pointer to the root node.
+            let root = self.root.as_ptr();
+            // Check if the root node exists.
+            if self.len != 0 {
+                // Trie is non-empty; deallocate the trie without dropping any leafs.
+                root.dealloc_tree();
+            } else {
+                // Trie is empty; reconstruct the zero-sized root block.
+                let block = Block::from_raw_parts(root as *mut u8, 0);
+                // Deallocate the zero-sized root block.
+                root.holder().dealloc(block);
+            }
+        }
+    }
+}
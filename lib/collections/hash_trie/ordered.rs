@@ -0,0 +1,255 @@
+use core::borrow::Borrow;
+use core::fmt;
+use core::hash::{BuildHasher, Hash};
+use core::iter::{ExactSizeIterator, FusedIterator};
+use core::mem;
+use core::slice;
+use tg_core::murmur3::Murmur3;
+use tg_mem::alloc::{Hold, Holder, HoldError};
+use tg_mem::lease::RawBuf;
+use crate::hash_trie::{HashTrieMap, HashTrieMapRawEntryMut, hash_key};
+
+/// Hash array mapped trie map that iterates in insertion order. Pairs a
+/// `HashTrieMap` from each key to the index of its entry, with a buffer of
+/// entries in insertion order. Removed entries leave tombstones in the
+/// buffer, which get compacted away once they outnumber the live entries.
+pub struct OrderedTrieMap<'a, K, V, H = Murmur3> {
+    /// Index of the entry for each key.
+    index: HashTrieMap<'a, K, usize, H>,
+    /// Entries in insertion order; `None` for removed entries.
+    entries: RawBuf<'a, Option<(K, V)>>,
+}
+
+/// Iterator over the entries of an `OrderedTrieMap`, in insertion order.
+pub struct OrderedTrieMapIter<'m, K: 'm, V: 'm> {
+    iter: slice::Iter<'m, Option<(K, V)>>,
+    len: usize,
+}
+
+impl<K, V> OrderedTrieMap<'static, K, V> {
+    /// Constructs a new `OrderedTrieMap` that will allocate its data in the
+    /// global `Hold`.
+    #[inline]
+    pub fn new() -> OrderedTrieMap<'static, K, V> {
+        OrderedTrieMap::hold_new(Hold::global())
+    }
+}
+
+impl<'a, K, V> OrderedTrieMap<'a, K, V> {
+    /// Constructs a new `OrderedTrieMap` that will allocate its data in `Hold`.
+    #[inline]
+    pub fn hold_new(hold: &dyn Hold<'a>) -> OrderedTrieMap<'a, K, V> {
+        OrderedTrieMap {
+            index: HashTrieMap::hold_new(hold),
+            entries: RawBuf::hold_empty(hold),
+        }
+    }
+}
+
+impl<'a, K, V, H> OrderedTrieMap<'a, K, V, H> {
+    /// Constructs a new `OrderedTrieMap` that will allocate its data in
+    /// `Hold`, and hash its keys using the supplied `hasher`.
+    #[inline]
+    pub fn hold_new_hasher(hold: &dyn Hold<'a>, hasher: H) -> OrderedTrieMap<'a, K, V, H> {
+        OrderedTrieMap {
+            index: HashTrieMap::hold_new_hasher(hold, hasher),
+            entries: RawBuf::hold_empty(hold),
+        }
+    }
+
+    /// Returns `true` if this `OrderedTrieMap` contains no entries.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// Returns the number of entries in this `OrderedTrieMap`.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    /// Returns an iterator over the entries of this `OrderedTrieMap`,
+    /// in insertion order.
+    pub fn iter(&self) -> OrderedTrieMapIter<'_, K, V> {
+        OrderedTrieMapIter {
+            iter: self.entries.iter(),
+            len: self.index.len(),
+        }
+    }
+
+    /// Returns the first inserted entry, or `None` if the map is empty.
+    pub fn first(&self) -> Option<(&K, &V)> {
+        self.iter().next()
+    }
+}
+
+impl<'a, K: Eq + Hash + Clone, V, H: BuildHasher> OrderedTrieMap<'a, K, V, H> {
+    /// Returns `true` if this `OrderedTrieMap` contains the given `key`.
+    pub fn contains_key<J: Borrow<K> + ?Sized>(&self, key: &J) -> bool {
+        self.index.contains_key(key)
+    }
+
+    /// Returns the value associated with the given `key`, or `None` if no
+    /// association exists.
+    pub fn get<J: Borrow<K> + ?Sized>(&self, key: &J) -> Option<&V> {
+        match self.index.get(key) {
+            Some(&i) => match self.entries[i] {
+                Some((_, ref value)) => Some(value),
+                None => None,
+            },
+            None => None,
+        }
+    }
+
+    /// Returns a mutable reference to the value associated with the given
+    /// `key`, or `None` if no association exists.
+    pub fn get_mut<J: Borrow<K> + ?Sized>(&mut self, key: &J) -> Option<&mut V> {
+        match self.index.get(key) {
+            Some(&i) => match self.entries[i] {
+                Some((_, ref mut value)) => Some(value),
+                None => None,
+            },
+            None => None,
+        }
+    }
+
+    /// Associates a new `value` with the given `key`; returns the previous
+    /// value associated with the `key`, if defined. Replacing the value of an
+    /// existing key retains the key's original insertion position; new keys
+    /// are ordered last. If the map's `Hold` fails to allocate any required
+    /// new memory, returns the `key` and `value`, along with a `HoldError`,
+    /// and leaves the map in its original state.
+    pub fn insert(&mut self, key: K, value: V) -> Result<Option<V>, (K, V, HoldError)> {
+        if let Some(&i) = self.index.get(&key) {
+            if let Some((_, ref mut old_value)) = self.entries[i] {
+                return Ok(Some(mem::replace(old_value, value)));
+            }
+        }
+        // Reserve the entry slot first, so that the index can't get ahead of the entries.
+        if let Err(error) = self.entries.try_reserve(1) {
+            return Err((key, value, error));
+        }
+        if let Err((_, _, error)) = self.index.insert(key.clone(), self.entries.len()) {
+            return Err((key, value, error));
+        }
+        // Can't reallocate, because the slot was reserved above.
+        self.entries.push(Some((key, value)));
+        Ok(None)
+    }
+
+    /// Disassociates the given `key`; returns the previous value associated
+    /// with the `key`, if any, retaining the relative order of all remaining
+    /// entries. Returns a `HoldError`, and leaves the map in its original
+    /// state, if the map's `Hold` fails to allocate any required new memory.
+    pub fn remove<J: Borrow<K> + ?Sized>(&mut self, key: &J) -> Result<Option<V>, HoldError> {
+        let i = match self.index.get(key) {
+            Some(&i) => i,
+            None => return Ok(None),
+        };
+        self.index.remove(key)?;
+        let value = match self.entries[i].take() {
+            Some((_, value)) => Some(value),
+            None => None,
+        };
+        // Compact the entries once tombstones outnumber live entries.
+        if self.entries.len() - self.index.len() > self.index.len() {
+            self.compact();
+        }
+        Ok(value)
+    }
+
+    /// Moves all live entries to the front of the entries buffer, in order,
+    /// re-indexing every moved key. Doesn't allocate.
+    fn compact(&mut self) {
+        let len = self.entries.len();
+        let mut j = 0;
+        for i in 0..len {
+            if let Some(entry) = self.entries[i].take() {
+                if i != j {
+                    // Re-index the moved key in place.
+                    let hash = hash_key(self.index.hasher(), &entry.0);
+                    if let HashTrieMapRawEntryMut::Occupied(mut slot) = self.index.raw_entry_mut()
+                        .from_key_hashed_nocheck(hash, &entry.0) {
+                        *slot.get_mut() = j;
+                    }
+                }
+                self.entries[j] = Some(entry);
+                j += 1;
+            }
+        }
+        self.entries.truncate(j);
+    }
+}
+
+impl<'a, K, V, H> Holder<'a> for OrderedTrieMap<'a, K, V, H> {
+    #[inline]
+    fn holder(&self) -> &'a dyn Hold<'a> {
+        self.index.holder()
+    }
+}
+
+impl<'m, 'a, K, V, H> IntoIterator for &'m OrderedTrieMap<'a, K, V, H> {
+    type Item = (&'m K, &'m V);
+    type IntoIter = OrderedTrieMapIter<'m, K, V>;
+
+    #[inline]
+    fn into_iter(self) -> OrderedTrieMapIter<'m, K, V> {
+        self.iter()
+    }
+}
+
+impl<'a, K: fmt::Debug, V: fmt::Debug, H> fmt::Debug for OrderedTrieMap<'a, K, V, H> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<'m, K: 'm, V: 'm> Iterator for OrderedTrieMapIter<'m, K, V> {
+    type Item = (&'m K, &'m V);
+
+    fn next(&mut self) -> Option<(&'m K, &'m V)> {
+        while let Some(entry) = self.iter.next() {
+            if let Some((ref key, ref value)) = *entry {
+                self.len -= 1;
+                return Some((key, value));
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'m, K: 'm, V: 'm> DoubleEndedIterator for OrderedTrieMapIter<'m, K, V> {
+    fn next_back(&mut self) -> Option<(&'m K, &'m V)> {
+        while let Some(entry) = self.iter.next_back() {
+            if let Some((ref key, ref value)) = *entry {
+                self.len -= 1;
+                return Some((key, value));
+            }
+        }
+        None
+    }
+}
+
+impl<'m, K: 'm, V: 'm> ExactSizeIterator for OrderedTrieMapIter<'m, K, V> {
+    #[inline]
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
+impl<'m, K: 'm, V: 'm> FusedIterator for OrderedTrieMapIter<'m, K, V> {
+}
+
+impl<'m, K: 'm, V: 'm> Clone for OrderedTrieMapIter<'m, K, V> {
+    fn clone(&self) -> OrderedTrieMapIter<'m, K, V> {
+        OrderedTrieMapIter {
+            iter: self.iter.clone(),
+            len: self.len,
+        }
+    }
+}
@@ -1,9 +1,10 @@
 use core::borrow::Borrow;
 use core::fmt;
 use core::hash::{BuildHasher, Hash};
-use core::iter::{ExactSizeIterator, FusedIterator, TrustedLen};
+use core::iter::{ExactSizeIterator, FromIterator, FusedIterator, TrustedLen};
 use tg_core::murmur3::Murmur3;
 use tg_mem::alloc::{Hold, Holder, HoldError, Stow, TryClone, CloneIntoHold};
+use tg_mem::lease::RawBuf;
 use crate::hash_trie::{HashTrie, HashTrieIter};
 
 /// Hash array mapped trie set.
@@ -16,6 +17,43 @@ pub struct HashTrieSetIter<'a, T: 'a> {
     iter: HashTrieIter<'a, T, ()>
 }
 
+/// Read-only view of a `HashTrieSet`. Exposes membership, iteration, and
+/// set predicates, but no means of mutating the viewed set, so it can be
+/// handed to code that must not insert or remove elements.
+///
+/// ```
+/// # extern crate tg_c_rt;
+/// # use tg_mem::alloc::Hold;
+/// # use tg_collections::hash_trie::HashTrieSet;
+/// let mut set = HashTrieSet::<u32>::hold_new(Hold::global());
+/// set.insert(1).unwrap();
+/// let frozen = set.as_frozen();
+/// assert!(frozen.contains(&1));
+/// ```
+///
+/// Elements can't be inserted through a `FrozenSet`:
+///
+/// ```compile_fail
+/// # extern crate tg_c_rt;
+/// # use tg_mem::alloc::Hold;
+/// # use tg_collections::hash_trie::HashTrieSet;
+/// let mut set = HashTrieSet::<u32>::hold_new(Hold::global());
+/// let frozen = set.as_frozen();
+/// frozen.insert(2);
+/// ```
+pub struct FrozenSet<'s, 'a: 's, T: 's, H: 's = Murmur3> {
+    set: &'s HashTrieSet<'a, T, H>,
+}
+
+/// Conversion of an owned buffer into a `HashTrieSet` of its distinct elements.
+pub trait IntoHashTrieSet<'a, T> {
+    /// Consumes this buffer, moving each of its elements into a new
+    /// `HashTrieSet` allocated in `hold`. Keeps the first occurrence of each
+    /// distinct element, and drops every later duplicate. If the `hold` fails
+    /// to allocate, drops the partially built set and all remaining elements.
+    fn into_hash_set(self, hold: &dyn Hold<'a>) -> Result<HashTrieSet<'a, T>, HoldError>;
+}
+
 impl<T> HashTrieSet<'static, T> {
     /// Constructs a new `HashTrieSet` that will allocate its data in the
     /// global `Hold`.
@@ -44,6 +82,29 @@ impl<'a, T> HashTrieSet<'a, T> {
     }
 }
 
+impl<'a, T: Eq + Hash> HashTrieSet<'a, T> {
+    /// Constructs a new `HashTrieSet`, allocated in `hold`, containing the
+    /// distinct elements of `iter`; duplicate elements are dropped. Returns
+    /// a `HoldError` if the `hold` fails to allocate any required memory.
+    pub fn try_hold_from_iter<I: IntoIterator<Item=T>>(hold: &dyn Hold<'a>, iter: I)
+        -> Result<HashTrieSet<'a, T>, HoldError>
+    {
+        let mut set = HashTrieSet::hold_new(hold);
+        set.try_extend(iter)?;
+        Ok(set)
+    }
+
+    /// Constructs a new `HashTrieSet`, allocated in `hold`, containing the
+    /// distinct elements of `iter`; duplicate elements are dropped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `hold` fails to allocate any required memory.
+    pub fn hold_from_iter<I: IntoIterator<Item=T>>(hold: &dyn Hold<'a>, iter: I) -> HashTrieSet<'a, T> {
+        HashTrieSet::try_hold_from_iter(hold, iter).unwrap()
+    }
+}
+
 impl<'a, T, H> HashTrieSet<'a, T, H> {
     /// Constructs a new `HashTrieSet` that will allocate its data in `Hold`,
     /// and hash its keys using the supplied `hasher`. Allocates a zero-sized
@@ -66,12 +127,41 @@ impl<'a, T, H> HashTrieSet<'a, T, H> {
         self.trie.len()
     }
 
+    /// Returns the number of bytes this `HashTrieSet` has allocated for its trie
+    /// nodes and collision buckets, including the inline storage of its
+    /// leafs, but excluding any memory owned by the leafs themselves.
+    pub fn memory_usage(&self) -> usize {
+        self.trie.memory_usage()
+    }
+
+    /// Returns the number of collision buckets in this `HashTrieSet`. Keys whose
+    /// 64-bit hashes fully collide share a bucket, which is searched linearly;
+    /// a non-zero count for keys expected to hash uniquely indicates a poor
+    /// quality hasher.
+    pub fn collision_count(&self) -> usize {
+        self.trie.knot_stats().0
+    }
+
+    /// Returns the number of keys in the largest collision bucket of this
+    /// `HashTrieSet`, or `0` if no keys collide.
+    pub fn max_collision_len(&self) -> usize {
+        self.trie.knot_stats().1
+    }
+
     /// Returns an iterator over the leafs of this `HashTrieSet`.
     pub fn iter(&self) -> HashTrieSetIter<'a, T> {
         HashTrieSetIter { iter: self.trie.iterator() }
     }
 }
 
+impl<'a, T, H> HashTrieSet<'a, T, H> {
+    /// Returns a read-only view of this `HashTrieSet`.
+    #[inline]
+    pub fn as_frozen(&self) -> FrozenSet<'_, 'a, T, H> {
+        FrozenSet { set: self }
+    }
+}
+
 impl<'a, T: Eq + Hash, H: BuildHasher> HashTrieSet<'a, T, H> {
     /// Returns `true` if this `HashTrieSet` contains the given `elem`.
     pub fn contains<U: Borrow<T> + ?Sized>(&self, elem: &U) -> bool {
@@ -101,6 +191,75 @@ impl<'a, T: Eq + Hash, H: BuildHasher> HashTrieSet<'a, T, H> {
             Err(error) => Err(error),
         }
     }
+
+    /// Includes every element of `iter` in this `HashTrieSet`; elements the
+    /// set already contains are dropped. If the trie's `Hold` fails to
+    /// allocate any required new memory, returns a `HoldError`, and drops
+    /// the failed element, along with all remaining elements; elements
+    /// included prior to the failure remain in the set.
+    pub fn try_extend<I: IntoIterator<Item=T>>(&mut self, iter: I) -> Result<(), HoldError> {
+        for elem in iter {
+            // Check for duplicates first, because insert replaces equal elements.
+            if !self.contains(&elem) {
+                if let Err((_, error)) = self.insert(elem) {
+                    return Err(error);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns `true` if every element of this `HashTrieSet` is also
+    /// contained in the `other` set.
+    pub fn is_subset<'b, H2: BuildHasher>(&self, other: &HashTrieSet<'b, T, H2>) -> bool {
+        if self.len() > other.len() {
+            return false;
+        }
+        for elem in self.iter() {
+            if !other.contains(elem) {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Returns `true` if every element of the `other` set is also contained
+    /// in this `HashTrieSet`.
+    #[inline]
+    pub fn is_superset<'b, H2: BuildHasher>(&self, other: &HashTrieSet<'b, T, H2>) -> bool {
+        other.is_subset(self)
+    }
+
+    /// Returns `true` if this `HashTrieSet` has no elements in common with
+    /// the `other` set.
+    pub fn is_disjoint<'b, H2: BuildHasher>(&self, other: &HashTrieSet<'b, T, H2>) -> bool {
+        if self.len() <= other.len() {
+            for elem in self.iter() {
+                if other.contains(elem) {
+                    return false;
+                }
+            }
+        } else {
+            for elem in other.iter() {
+                if self.contains(elem) {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+}
+
+impl<'a, T: Eq + Hash, H: BuildHasher> Extend<T> for HashTrieSet<'a, T, H> {
+    fn extend<I: IntoIterator<Item=T>>(&mut self, iter: I) {
+        self.try_extend(iter).unwrap();
+    }
+}
+
+impl<T: Eq + Hash> FromIterator<T> for HashTrieSet<'static, T> {
+    fn from_iter<I: IntoIterator<Item=T>>(iter: I) -> HashTrieSet<'static, T> {
+        HashTrieSet::hold_from_iter(Hold::global(), iter)
+    }
 }
 
 impl<'a, T, H> Holder<'a> for HashTrieSet<'a, T, H> {
@@ -154,6 +313,94 @@ impl<'a, T: 'a + fmt::Debug> fmt::Debug for HashTrieSet<'a, T> {
     }
 }
 
+impl<'s, 'a, T, H> FrozenSet<'s, 'a, T, H> {
+    /// Returns `true` if the viewed set contains no leafs.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.set.is_empty()
+    }
+
+    /// Returns the number of leafs in the viewed set.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.set.len()
+    }
+
+    /// Returns an iterator over the leafs of the viewed set.
+    #[inline]
+    pub fn iter(&self) -> HashTrieSetIter<'a, T> {
+        self.set.iter()
+    }
+}
+
+impl<'s, 'a, T: Eq + Hash, H: BuildHasher> FrozenSet<'s, 'a, T, H> {
+    /// Returns `true` if the viewed set contains the given `elem`.
+    #[inline]
+    pub fn contains<U: Borrow<T> + ?Sized>(&self, elem: &U) -> bool {
+        self.set.contains(elem)
+    }
+
+    /// Returns `true` if every element of the viewed set is also contained
+    /// in the `other` set.
+    #[inline]
+    pub fn is_subset<'b, H2: BuildHasher>(&self, other: &HashTrieSet<'b, T, H2>) -> bool {
+        self.set.is_subset(other)
+    }
+
+    /// Returns `true` if every element of the `other` set is also contained
+    /// in the viewed set.
+    #[inline]
+    pub fn is_superset<'b, H2: BuildHasher>(&self, other: &HashTrieSet<'b, T, H2>) -> bool {
+        self.set.is_superset(other)
+    }
+
+    /// Returns `true` if the viewed set has no elements in common with the
+    /// `other` set.
+    #[inline]
+    pub fn is_disjoint<'b, H2: BuildHasher>(&self, other: &HashTrieSet<'b, T, H2>) -> bool {
+        self.set.is_disjoint(other)
+    }
+}
+
+impl<'s, 'a, T, H> Clone for FrozenSet<'s, 'a, T, H> {
+    #[inline]
+    fn clone(&self) -> FrozenSet<'s, 'a, T, H> {
+        *self
+    }
+}
+
+impl<'s, 'a, T, H> Copy for FrozenSet<'s, 'a, T, H> {
+}
+
+impl<'s, 'a, T, H> From<&'s HashTrieSet<'a, T, H>> for FrozenSet<'s, 'a, T, H> {
+    #[inline]
+    fn from(set: &'s HashTrieSet<'a, T, H>) -> FrozenSet<'s, 'a, T, H> {
+        set.as_frozen()
+    }
+}
+
+impl<'s, 'a, T: 'a, H> IntoIterator for FrozenSet<'s, 'a, T, H> {
+    type Item = &'a T;
+    type IntoIter = HashTrieSetIter<'a, T>;
+
+    #[inline]
+    fn into_iter(self) -> HashTrieSetIter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<'s, 'a, T: 'a + fmt::Debug, H> fmt::Debug for FrozenSet<'s, 'a, T, H> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<'a, 'b, T: Eq + Hash, M> IntoHashTrieSet<'a, T> for RawBuf<'b, T, M> {
+    fn into_hash_set(self, hold: &dyn Hold<'a>) -> Result<HashTrieSet<'a, T>, HoldError> {
+        HashTrieSet::try_hold_from_iter(hold, self)
+    }
+}
+
 impl<'a, T: 'a> Iterator for HashTrieSetIter<'a, T> {
     type Item = &'a T;
 
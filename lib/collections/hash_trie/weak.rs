@@ -0,0 +1,123 @@
+use core::borrow::Borrow;
+use core::hash::{BuildHasher, Hash};
+use tg_core::murmur3::Murmur3;
+use tg_mem::alloc::{Hold, Holder, HoldError};
+use tg_mem::lease::{HardBox, SoftBox, RawBuf};
+use crate::hash_trie::HashTrieMap;
+
+/// Hash array mapped trie map from keys to soft references to boxed values.
+/// The map doesn't keep its values alive; a value lives only as long as some
+/// hard lease outside the map retains it, making the map suitable for caches
+/// that must not extend the lifetime of their entries. Entries whose values
+/// have been dropped are dead; `get` removes the dead entries it encounters,
+/// and `sweep` removes all of them.
+pub struct WeakValueMap<'a, K, V, H = Murmur3> {
+    map: HashTrieMap<'a, K, SoftBox<'a, V>, H>,
+}
+
+impl<K, V> WeakValueMap<'static, K, V> {
+    /// Constructs a new `WeakValueMap` that will allocate its data in the
+    /// global `Hold`.
+    #[inline]
+    pub fn new() -> WeakValueMap<'static, K, V> {
+        WeakValueMap::hold_new(Hold::global())
+    }
+}
+
+impl<'a, K, V> WeakValueMap<'a, K, V> {
+    /// Constructs a new `WeakValueMap` that will allocate its data in `Hold`.
+    #[inline]
+    pub fn hold_new(hold: &dyn Hold<'a>) -> WeakValueMap<'a, K, V> {
+        WeakValueMap { map: HashTrieMap::hold_new(hold) }
+    }
+}
+
+impl<'a, K, V, H> WeakValueMap<'a, K, V, H> {
+    /// Constructs a new `WeakValueMap` that will allocate its data in `Hold`,
+    /// and hash its keys using the supplied `hasher`.
+    #[inline]
+    pub fn hold_new_hasher(hold: &dyn Hold<'a>, hasher: H) -> WeakValueMap<'a, K, V, H> {
+        WeakValueMap { map: HashTrieMap::hold_new_hasher(hold, hasher) }
+    }
+
+    /// Returns `true` if this `WeakValueMap` contains no entries, live or dead.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Returns the number of entries in this `WeakValueMap`, including dead
+    /// entries not yet removed.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns the number of entries in this `WeakValueMap` whose values
+    /// have been dropped.
+    pub fn dead_count(&self) -> usize {
+        self.map.values().filter(|value| value.hard_count() == 0).count()
+    }
+}
+
+impl<'a, K: Eq + Hash, V, H: BuildHasher> WeakValueMap<'a, K, V, H> {
+    /// Associates the given `key` with a soft reference to the value of the
+    /// hard lease `value`, replacing any previous entry for the `key`. If
+    /// the soft reference count of `value` would overflow, or the trie's
+    /// `Hold` fails to allocate any required new memory, returns the `key`
+    /// along with a `HoldError`, and leaves the map in its original state.
+    pub fn insert(&mut self, key: K, value: &HardBox<'a, V>) -> Result<(), (K, HoldError)> {
+        let soft = match value.try_to_soft() {
+            Ok(soft) => soft,
+            Err(error) => return Err((key, error.into())),
+        };
+        match self.map.insert(key, soft) {
+            Ok(_) => Ok(()),
+            Err((key, _, error)) => Err((key, error)),
+        }
+    }
+
+    /// Returns a hard lease to the value associated with the given `key`, if
+    /// the value is still alive. Removes the entry for the `key` if its value
+    /// has been dropped; a dead entry whose removal fails to allocate stays
+    /// in the map until the next `get` or `sweep`.
+    pub fn get<J: Borrow<K> + ?Sized>(&mut self, key: &J) -> Option<HardBox<'a, V>> {
+        match self.map.get(key) {
+            Some(soft) => match soft.try_to_hard() {
+                Ok(hard) => return Some(hard),
+                Err(_) => (),
+            },
+            None => return None,
+        }
+        let _ = self.map.remove(key);
+        None
+    }
+
+    /// Disassociates the given `key`; returns `true` if the map had an
+    /// entry, live or dead, for the `key`. Returns a `HoldError`, and leaves
+    /// the map in its original state, if the trie's `Hold` fails to allocate
+    /// any required new memory.
+    pub fn remove<J: Borrow<K> + ?Sized>(&mut self, key: &J) -> Result<bool, HoldError> {
+        self.map.remove(key).map(|soft| soft.is_some())
+    }
+
+    /// Removes every dead entry from this `WeakValueMap`; returns the number
+    /// of entries removed. Collects clones of the dead keys in a buffer
+    /// allocated in the map's `Hold` before removing any entries. Returns a
+    /// `HoldError` if the `Hold` fails to allocate any required new memory,
+    /// in which case some dead entries may remain.
+    pub fn sweep(&mut self) -> Result<usize, HoldError> where K: Clone {
+        let dead_count = self.dead_count();
+        if dead_count == 0 {
+            return Ok(0);
+        }
+        let mut dead_keys = RawBuf::try_hold_cap(self.map.holder(), dead_count)?;
+        for (key, value) in self.map.iter() {
+            if value.hard_count() == 0 {
+                // Can't reallocate, because the buffer has capacity for every dead key.
+                dead_keys.push(key.clone());
+            }
+        }
+        self.map.remove_all(dead_keys).map_err(|(_, error)| error)
+    }
+}
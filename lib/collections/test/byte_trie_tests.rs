@@ -0,0 +1,132 @@
+extern crate tg_mem;
+extern crate tg_collections;
+
+use tg_mem::block::Block;
+use tg_mem::alloc::Pack;
+use tg_collections::byte_trie::ByteTrieMap;
+
+const KEYS: [&'static [u8]; 8] = [b"roman", b"romane", b"romanus", b"romulus",
+                                  b"rubens", b"ruber", b"rubicon", b"rubicundus"];
+
+fn prefix_keys(xs: &ByteTrieMap<usize>, prefix: &[u8]) -> Vec<Vec<u8>> {
+    xs.try_iter_prefix(prefix).unwrap()
+        .map(|entry| entry.unwrap().0.to_vec())
+        .collect()
+}
+
+#[test]
+fn test_byte_trie_map_exact_lookup() {
+    static mut TEST_AREA: [u8; 65536] = [0; 65536];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let mut xs = ByteTrieMap::<usize>::hold_new(pack);
+        for (i, key) in KEYS.iter().enumerate() {
+            assert_eq!(xs.insert(key, i).unwrap(), None);
+        }
+        assert_eq!(xs.len(), KEYS.len());
+        for (i, key) in KEYS.iter().enumerate() {
+            assert_eq!(xs.get(key), Some(&i));
+        }
+        // Proper prefixes and extensions of keys aren't keys.
+        assert_eq!(xs.get(b""), None);
+        assert_eq!(xs.get(b"rom"), None);
+        assert_eq!(xs.get(b"rubi"), None);
+        assert_eq!(xs.get(b"romanes"), None);
+        assert_eq!(xs.get(b"x"), None);
+
+        assert_eq!(xs.insert(b"ruber", 10).unwrap(), Some(5));
+        *xs.get_mut(b"roman").unwrap() = 11;
+        assert_eq!(xs.get(b"ruber"), Some(&10));
+        assert_eq!(xs.get(b"roman"), Some(&11));
+        assert_eq!(xs.len(), KEYS.len());
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+#[test]
+fn test_byte_trie_map_iter_prefix() {
+    static mut TEST_AREA: [u8; 65536] = [0; 65536];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let mut xs = ByteTrieMap::<usize>::hold_new(pack);
+        // Insert out of order.
+        for (i, key) in KEYS.iter().enumerate().rev() {
+            xs.insert(key, i).unwrap();
+        }
+        let all = prefix_keys(&xs, b"");
+        assert_eq!(all, KEYS.iter().map(|key| key.to_vec()).collect::<Vec<Vec<u8>>>());
+        let values = xs.try_iter().unwrap().map(|entry| *entry.unwrap().1).collect::<Vec<usize>>();
+        assert_eq!(values, [0, 1, 2, 3, 4, 5, 6, 7]);
+
+        // Prefix ending on an edge boundary.
+        assert_eq!(prefix_keys(&xs, b"roman"), [b"roman".to_vec(), b"romane".to_vec(), b"romanus".to_vec()]);
+        // Prefix ending within an edge label.
+        assert_eq!(prefix_keys(&xs, b"rom"), [b"roman".to_vec(), b"romane".to_vec(),
+                                              b"romanus".to_vec(), b"romulus".to_vec()]);
+        assert_eq!(prefix_keys(&xs, b"rubic"), [b"rubicon".to_vec(), b"rubicundus".to_vec()]);
+        // Prefix equal to a single key.
+        assert_eq!(prefix_keys(&xs, b"rubens"), [b"rubens".to_vec()]);
+        // Prefixes diverging from every key.
+        assert!(prefix_keys(&xs, b"rubes").is_empty());
+        assert!(prefix_keys(&xs, b"romanes").is_empty());
+        assert!(prefix_keys(&xs, b"s").is_empty());
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+#[test]
+fn test_byte_trie_map_iter_prefix_infallible() {
+    static mut TEST_AREA: [u8; 65536] = [0; 65536];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let mut xs = ByteTrieMap::<usize>::hold_new(pack);
+        for (i, key) in KEYS.iter().enumerate() {
+            xs.insert(key, i).unwrap();
+        }
+        // The panicking iterators yield the same entries as the fallible ones.
+        for prefix in [&b""[..], b"r", b"rom", b"roman", b"rubic", b"rubes", b"s"].iter() {
+            let keys = xs.iter_prefix(prefix).map(|(key, _)| key.to_vec()).collect::<Vec<Vec<u8>>>();
+            assert_eq!(keys, prefix_keys(&xs, prefix));
+        }
+        let values = xs.iter_prefix(b"rub").map(|(_, &value)| value).collect::<Vec<usize>>();
+        assert_eq!(values, [4, 5, 6, 7]);
+        let values = xs.iter().map(|(_, &value)| value).collect::<Vec<usize>>();
+        assert_eq!(values, [0, 1, 2, 3, 4, 5, 6, 7]);
+        assert!(ByteTrieMap::<usize>::hold_new(pack).iter().next().is_none());
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+#[test]
+fn test_byte_trie_map_remove_collapses_edges() {
+    static mut TEST_AREA: [u8; 65536] = [0; 65536];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let mut xs = ByteTrieMap::<usize>::hold_new(pack);
+        let empty = pack.live();
+        xs.insert(b"romane", 1).unwrap();
+        let live = pack.live();
+        // Splits the "romane" edge into "roman", "e", and "us".
+        xs.insert(b"romanus", 2).unwrap();
+        assert!(pack.live() > live);
+        assert_eq!(xs.remove(b"romanus"), Some(2));
+        assert_eq!(xs.remove(b"romanus"), None);
+        // The valueless "roman" node merges back with its only child.
+        assert_eq!(pack.live(), live);
+        assert_eq!(xs.len(), 1);
+        assert_eq!(xs.get(b"romane"), Some(&1));
+        assert_eq!(prefix_keys(&xs, b"rom"), [b"romane".to_vec()]);
+
+        for (i, key) in KEYS.iter().enumerate() {
+            xs.insert(key, i).unwrap();
+        }
+        for key in KEYS.iter() {
+            assert!(xs.remove(key).is_some());
+        }
+        // Every edge gets pruned once all keys are removed.
+        assert!(xs.is_empty());
+        assert_eq!(pack.live(), empty);
+        assert!(prefix_keys(&xs, b"").is_empty());
+    }
+    assert_eq!(pack.live(), 0);
+}
@@ -0,0 +1,256 @@
+#![cfg(feature = "failpoints")]
+
+extern crate tg_mem;
+extern crate tg_collections;
+
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::Ordering::Relaxed;
+use tg_mem::block::Block;
+use tg_mem::alloc::{Hold, HoldError, Pack, TryClone};
+use tg_collections::byte_trie::ByteTrieMap;
+use tg_collections::hash_trie::{HashTrieMap, HashTrieMapTryInsertError, OrderedTrieMap};
+
+#[test]
+fn test_hash_trie_map_clone_failure_rollback() {
+    static mut TEST_AREA: [u8; 65536] = [0; 65536];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let mut xs = HashTrieMap::<i32, i32>::hold_new(pack);
+        for k in 0..256 {
+            xs.insert(k, -k).unwrap();
+        }
+        let live = pack.live();
+        let used = pack.used();
+        assert!(live > 3);
+        // Fail the third allocation, part way through cloning the trie's nodes.
+        pack.fail_after(2).unwrap();
+        match xs.try_clone() {
+            Err(HoldError::OutOfMemory) => (),
+            _ => panic!(),
+        }
+        // The partial clone is freed, and the source map is intact.
+        assert_eq!(pack.live(), live);
+        assert_eq!(pack.used(), used);
+        assert_eq!(xs.len(), 256);
+        for k in 0..256 {
+            assert_eq!(xs.get(&k), Some(&-k));
+        }
+
+        let ys = xs.try_clone().unwrap();
+        assert_eq!(ys.len(), 256);
+        assert_eq!(ys.get(&255), Some(&-255));
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+struct Counted<'c>(i32, &'c AtomicUsize);
+
+impl<'c> Drop for Counted<'c> {
+    fn drop(&mut self) {
+        self.1.fetch_add(1, Relaxed);
+    }
+}
+
+#[test]
+fn test_hash_trie_map_drops_leafs_once() {
+    static mut TEST_AREA: [u8; 65536] = [0; 65536];
+    let drops = AtomicUsize::new(0);
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let mut xs = HashTrieMap::<i32, Counted>::hold_new(pack);
+        for k in 0..64 {
+            xs.insert(k, Counted(k, &drops)).ok().unwrap();
+        }
+        // Inserted values move into the trie.
+        assert_eq!(drops.load(Relaxed), 0);
+
+        // Replacing a value hands back the old one, and drops neither.
+        let old = xs.insert(7, Counted(-7, &drops)).ok().unwrap().unwrap();
+        assert_eq!(old.0, 7);
+        assert_eq!(drops.load(Relaxed), 0);
+        drop(old);
+        assert_eq!(drops.load(Relaxed), 1);
+
+        // A failed insert hands back the new value.
+        pack.fail_after(0).unwrap();
+        match xs.insert(100, Counted(100, &drops)) {
+            Err((100, value, HoldError::OutOfMemory)) => {
+                assert_eq!(drops.load(Relaxed), 1);
+                drop(value);
+            },
+            _ => panic!(),
+        }
+        assert_eq!(drops.load(Relaxed), 2);
+
+        // A failed removal leaves the leaf in the trie, undropped.
+        pack.fail_after(0).unwrap();
+        match xs.remove(&9) {
+            Err(HoldError::OutOfMemory) => (),
+            _ => panic!(),
+        }
+        assert_eq!(drops.load(Relaxed), 2);
+        assert_eq!(xs.get(&9).unwrap().0, 9);
+
+        // A successful removal hands back the value.
+        let removed = xs.remove(&9).unwrap().unwrap();
+        assert_eq!(drops.load(Relaxed), 2);
+        drop(removed);
+        assert_eq!(drops.load(Relaxed), 3);
+        assert_eq!(xs.len(), 63);
+    }
+    assert_eq!(drops.load(Relaxed), 66);
+    assert_eq!(pack.live(), 0);
+}
+
+#[test]
+fn test_ordered_trie_map_insert_failure_rollback() {
+    static mut TEST_AREA: [u8; 65536] = [0; 65536];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let mut xs = OrderedTrieMap::<i32, i32>::hold_new(pack);
+        // Fail reserving the entry slot.
+        pack.fail_next_alloc().unwrap();
+        match xs.insert(1, 10) {
+            Err((1, 10, HoldError::OutOfMemory)) => (),
+            _ => panic!(),
+        }
+        assert!(xs.is_empty());
+        assert_eq!(xs.iter().next(), None);
+        // Reserve the entry slot, then fail indexing its key.
+        pack.fail_after(1).unwrap();
+        match xs.insert(1, 10) {
+            Err((1, 10, HoldError::OutOfMemory)) => (),
+            _ => panic!(),
+        }
+        assert!(xs.is_empty());
+        assert!(!xs.contains_key(&1));
+        assert_eq!(xs.iter().next(), None);
+
+        xs.insert(1, 10).unwrap();
+        xs.insert(2, 20).unwrap();
+        let entries = xs.iter().map(|(&k, &v)| (k, v)).collect::<Vec<(i32, i32)>>();
+        assert_eq!(entries, [(1, 10), (2, 20)]);
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+#[test]
+fn test_hash_trie_map_entry_or_default_failure() {
+    static mut TEST_AREA: [u8; 65536] = [0; 65536];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let mut xs = HashTrieMap::<i32, usize>::hold_new(pack);
+        for k in 0..64 {
+            *xs.entry_or_default(k).unwrap() += 1;
+        }
+        let live = pack.live();
+        // Fail allocating the node for an absent key.
+        pack.fail_next_alloc().unwrap();
+        match xs.entry_or_default(64) {
+            Err((64, HoldError::OutOfMemory)) => (),
+            _ => panic!(),
+        }
+        // The map is left as it was.
+        assert_eq!(pack.live(), live);
+        assert_eq!(xs.len(), 64);
+        assert_eq!(xs.get(&64), None);
+        // Present keys don't allocate, so they don't trip the failpoint.
+        pack.fail_next_alloc().unwrap();
+        *xs.entry_or_default(63).unwrap() += 1;
+        assert_eq!(xs.get(&63), Some(&2));
+        // The failpoint stays armed for the next absent key.
+        match xs.entry_or_default(64) {
+            Err((64, HoldError::OutOfMemory)) => (),
+            _ => panic!(),
+        }
+        assert_eq!(pack.live(), live);
+
+        *xs.entry_or_default(64).unwrap() += 1;
+        assert_eq!(xs.len(), 65);
+        assert_eq!(xs.get(&64), Some(&1));
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+#[test]
+fn test_byte_trie_map_iter_failure() {
+    static mut TEST_AREA: [u8; 65536] = [0; 65536];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let mut xs = ByteTrieMap::<usize>::hold_new(pack);
+        xs.insert(b"ab", 1).unwrap();
+        xs.insert(b"ac", 2).unwrap();
+        // Fail allocating the traversal path.
+        pack.fail_next_alloc().unwrap();
+        match xs.try_iter_prefix(b"a") {
+            Err(HoldError::OutOfMemory) => (),
+            _ => panic!(),
+        }
+        // Fail copying the first key; the iteration ends after the error.
+        let mut iter = xs.try_iter_prefix(b"a").unwrap();
+        pack.fail_next_alloc().unwrap();
+        loop {
+            match iter.next() {
+                Some(Ok(_)) => panic!(),
+                Some(Err(HoldError::OutOfMemory)) => break,
+                _ => panic!(),
+            }
+        }
+        assert!(iter.next().is_none());
+        drop(iter);
+        let keys = xs.try_iter().unwrap().map(|entry| entry.unwrap().0.to_vec()).collect::<Vec<Vec<u8>>>();
+        assert_eq!(keys, [b"ab".to_vec(), b"ac".to_vec()]);
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+#[test]
+fn test_hash_trie_map_try_insert_failure() {
+    static mut TEST_AREA: [u8; 65536] = [0; 65536];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let mut xs = HashTrieMap::<i32, i32>::hold_new(pack);
+        for k in 0..64 {
+            xs.insert(k, -k).unwrap();
+        }
+        let live = pack.live();
+        let used = pack.used();
+        pack.fail_next_alloc().unwrap();
+        // A duplicate key is rejected without allocating.
+        match xs.try_insert(1, 0) {
+            Err(HashTrieMapTryInsertError::Occupied(error)) => assert_eq!(*error.entry.get(), -1),
+            _ => panic!(),
+        }
+        // A fresh key fails copying the node that receives the new leaf.
+        match xs.try_insert(64, -64) {
+            Err(HashTrieMapTryInsertError::Hold(64, -64, HoldError::OutOfMemory)) => (),
+            _ => panic!(),
+        }
+        // The map is unchanged.
+        assert_eq!(pack.live(), live);
+        assert_eq!(pack.used(), used);
+        assert_eq!(xs.len(), 64);
+        assert_eq!(xs.get(&64), None);
+        for k in 0..64 {
+            assert_eq!(xs.get(&k), Some(&-k));
+        }
+
+        assert_eq!(*xs.try_insert(64, -64).ok().unwrap(), -64);
+        assert_eq!(xs.len(), 65);
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+#[test]
+#[should_panic]
+fn test_byte_trie_map_iter_prefix_failure_panics() {
+    static mut TEST_AREA: [u8; 65536] = [0; 65536];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    let mut xs = ByteTrieMap::<usize>::hold_new(pack);
+    xs.insert(b"ab", 1).unwrap();
+    xs.insert(b"ac", 2).unwrap();
+    let mut iter = xs.iter_prefix(b"a");
+    // Fail copying the first key.
+    pack.fail_next_alloc().unwrap();
+    iter.next();
+}
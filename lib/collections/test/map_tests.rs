@@ -0,0 +1,948 @@
+extern crate tg_mem;
+extern crate tg_collections;
+
+use core::hash::{BuildHasher, Hash, Hasher};
+use core::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+use tg_mem::block::Block;
+use tg_mem::alloc::{Hold, Pack, TryClone};
+#[cfg(feature = "trace")]
+use tg_mem::alloc::HoldTrace;
+use tg_collections::hash_trie::{HashTrieMap, HashTrieSet, HashTrieMapRawEntryMut, HashTrieMapTryInsertError};
+
+/// Hasher whose hash codes share their low 40 bits, so that every key
+/// descends the same 8 levels of the trie before branching.
+#[derive(Clone, Default)]
+struct PrefixHasher(u64);
+
+impl Hasher for PrefixHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 = self.0 << 8 | b as u64;
+        }
+    }
+
+    fn write_u32(&mut self, n: u32) {
+        self.0 = n as u64;
+    }
+
+    fn finish(&self) -> u64 {
+        self.0 << 40 | 0x5A5A5A5A5A
+    }
+}
+
+impl BuildHasher for PrefixHasher {
+    type Hasher = PrefixHasher;
+
+    fn build_hasher(&self) -> PrefixHasher {
+        PrefixHasher(0)
+    }
+}
+
+/// Hasher that maps each `u32` to its residue modulo 8, so that keys
+/// congruent modulo 8 fully collide into a shared knot.
+#[derive(Clone, Default)]
+struct ModHasher(u64);
+
+impl Hasher for ModHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 = self.0 << 8 | b as u64;
+        }
+    }
+
+    fn write_u32(&mut self, n: u32) {
+        self.0 = (n % 8) as u64;
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+impl BuildHasher for ModHasher {
+    type Hasher = ModHasher;
+
+    fn build_hasher(&self) -> ModHasher {
+        ModHasher(0)
+    }
+}
+
+/// Hasher that hashes every key to the same constant, so that all keys
+/// fully collide into a single knot.
+#[derive(Clone, Default)]
+struct ConstHasher;
+
+impl Hasher for ConstHasher {
+    fn write(&mut self, _bytes: &[u8]) {
+    }
+
+    fn finish(&self) -> u64 {
+        0x0123456789ABCDEF
+    }
+}
+
+impl BuildHasher for ConstHasher {
+    type Hasher = ConstHasher;
+
+    fn build_hasher(&self) -> ConstHasher {
+        ConstHasher
+    }
+}
+
+#[test]
+fn test_hash_trie_map_insert_bounded_depth() {
+    static mut TEST_AREA: [u8; 262144] = [0; 262144];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let mut deep = HashTrieMap::<u32, u32, PrefixHasher>::hold_new_hasher(pack, PrefixHasher(0));
+        let mut flat = HashTrieMap::<u32, u32, PrefixHasher>::hold_new_hasher(pack, PrefixHasher(0));
+        for k in 0..64 {
+            assert_eq!(deep.insert(k, k + 1).unwrap(), None);
+            assert_eq!(flat.insert_bounded_depth(k, k + 1, 2).unwrap(), None);
+        }
+        // Unbounded, the keys branch apart beneath a chain of single-branch nodes.
+        assert_eq!(deep.collision_count(), 0);
+        // Bounded, the keys past the second level share a single knot.
+        assert_eq!(flat.collision_count(), 1);
+        assert_eq!(flat.max_collision_len(), 64);
+        assert!(flat.memory_usage() < deep.memory_usage());
+        for k in 0..64 {
+            assert_eq!(flat.get(&k), Some(&(k + 1)));
+        }
+        assert_eq!(flat.get(&64), None);
+
+        // The bound applies to subsequent plain inserts, and removals.
+        assert_eq!(flat.insert(64, 65).unwrap(), None);
+        assert_eq!(flat.collision_count(), 1);
+        assert_eq!(flat.max_collision_len(), 65);
+        for k in 0..60 {
+            assert_eq!(flat.remove(&k).unwrap(), Some(k + 1));
+        }
+        assert_eq!(flat.len(), 5);
+        for k in 60..65 {
+            assert_eq!(flat.get(&k), Some(&(k + 1)));
+        }
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+fn assert_map_eq(xs: &HashTrieMap<i32, i32>, ys: &HashTrieMap<i32, i32>) {
+    assert_eq!(xs.len(), ys.len());
+    for (k, v) in xs.iter() {
+        assert_eq!(ys.get(k), Some(v));
+    }
+}
+
+#[test]
+fn test_hash_trie_map_clone_into() {
+    static mut TEST_AREA: [u8; 262144] = [0; 262144];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let mut src = HashTrieMap::<i32, i32>::hold_new(pack);
+        let mut dst = HashTrieMap::<i32, i32>::hold_new(pack);
+        for k in 0..256 {
+            src.insert(k, -k).unwrap();
+        }
+        // Clone into an empty map.
+        src.clone_into(&mut dst).unwrap();
+        assert_map_eq(&src, &dst);
+        assert_eq!(src.shared_bytes(&dst), 0);
+
+        // Clone into a map with different keys and values.
+        for k in 0..128 {
+            src.remove(&k).unwrap();
+        }
+        for k in 256..320 {
+            src.insert(k, k).unwrap();
+        }
+        src.insert(200, 2).unwrap();
+        src.clone_into(&mut dst).unwrap();
+        assert_map_eq(&src, &dst);
+        assert_eq!(dst.get(&0), None);
+        assert_eq!(dst.get(&200), Some(&2));
+
+        // Changing the clone leaves the source untouched.
+        dst.insert(0, 0).unwrap();
+        assert_eq!(src.get(&0), None);
+
+        // Clone an empty map.
+        let empty = HashTrieMap::<i32, i32>::hold_new(pack);
+        empty.clone_into(&mut dst).unwrap();
+        assert!(dst.is_empty());
+        assert_eq!(dst.get(&200), None);
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+#[cfg(feature = "trace")]
+#[test]
+fn test_hash_trie_map_clone_into_reuses_structure() {
+    static mut TEST_AREA: [u8; 262144] = [0; 262144];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let mut src = HashTrieMap::<i32, i32>::hold_new(pack);
+        let mut dst = HashTrieMap::<i32, i32>::hold_new(pack);
+        for k in 0..256 {
+            src.insert(k, k).unwrap();
+        }
+        src.clone_into(&mut dst).unwrap();
+        let live = pack.live();
+        // Refresh the destination from a source with the same keys, and new
+        // values, as a simulation loop would.
+        for step in 1..4 {
+            for v in src.values_mut() {
+                *v += 1;
+            }
+            let start = HoldTrace::get();
+            src.clone_into(&mut dst).unwrap();
+            // Every node and knot of the destination gets reused.
+            let trace = HoldTrace::since(start);
+            assert_eq!(trace.allocs, 0);
+            assert_eq!(trace.deallocs, 0);
+            assert_eq!(pack.live(), live);
+            for k in 0..256 {
+                assert_eq!(dst.get(&k), Some(&(k + step)));
+            }
+        }
+
+        // A source differing in a single key reallocates only along its path.
+        src.insert(1000, 1000).unwrap();
+        let start = HoldTrace::get();
+        src.clone_into(&mut dst).unwrap();
+        let trace = HoldTrace::since(start);
+        assert!(trace.allocs > 0);
+        assert!(trace.allocs < src.len() / 8);
+        assert_map_eq(&src, &dst);
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+#[test]
+fn test_hash_trie_map_shared_bytes() {
+    static mut TEST_AREA: [u8; 262144] = [0; 262144];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let mut xs = HashTrieMap::<i32, i32>::hold_new(pack);
+        let empty = HashTrieMap::<i32, i32>::hold_new(pack);
+        assert_eq!(xs.shared_bytes(&empty), 0);
+        for k in 0..256 {
+            xs.insert(k, k).unwrap();
+        }
+        // A map shares all of its structure with itself.
+        assert_eq!(xs.shared_bytes(&xs), xs.memory_usage());
+        // Empty maps share no structure.
+        assert_eq!(xs.shared_bytes(&empty), 0);
+        assert_eq!(empty.shared_bytes(&xs), 0);
+        // Deep clones share no structure, even with identical shapes.
+        let ys = xs.try_clone().unwrap();
+        assert_eq!(xs.memory_usage(), ys.memory_usage());
+        assert_eq!(xs.shared_bytes(&ys), 0);
+        assert_eq!(ys.shared_bytes(&xs), 0);
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+/// Value that counts its drops in a per-test counter.
+struct Counted<'c>(i32, &'c AtomicUsize);
+
+impl<'c> Drop for Counted<'c> {
+    fn drop(&mut self) {
+        self.1.fetch_add(1, Relaxed);
+    }
+}
+
+#[test]
+fn test_hash_trie_map_filter_map() {
+    static mut TEST_AREA: [u8; 262144] = [0; 262144];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let drops = AtomicUsize::new(0);
+        let mut xs = HashTrieMap::<i32, Counted>::hold_new(pack);
+        for k in 0..64 {
+            xs.insert(k, Counted(-k, &drops)).ok().unwrap();
+        }
+        // Drop the even keyed entries, and rekey the odd keyed entries.
+        let ys = xs.filter_map(|k, v| if k % 2 == 0 { None } else { Some((k * 100, v)) }, pack).unwrap();
+        // Each filtered out value dropped exactly once; no survivor dropped.
+        assert_eq!(drops.load(Relaxed), 32);
+        assert_eq!(ys.len(), 32);
+        for k in 0..64 {
+            match ys.get(&(k * 100)) {
+                Some(v) => {
+                    assert_eq!(k % 2, 1);
+                    assert_eq!(v.0, -k);
+                },
+                None => assert_eq!(k % 2, 0),
+            }
+        }
+        assert!(ys.get(&1).is_none());
+        drop(ys);
+        // Dropping the new map drops each survivor exactly once.
+        assert_eq!(drops.load(Relaxed), 64);
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+fn hash_str<H: BuildHasher>(hasher: &H, key: &str) -> u64 {
+    let mut h = hasher.build_hasher();
+    key.hash(&mut h);
+    h.finish()
+}
+
+#[test]
+fn test_hash_trie_map_raw_entry() {
+    static mut TEST_AREA: [u8; 65536] = [0; 65536];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let mut xs = HashTrieMap::<String, usize>::hold_new(pack);
+        for (i, key) in ["alpha", "beta", "gamma"].iter().enumerate() {
+            xs.insert(key.to_string(), i).ok().unwrap();
+        }
+        // Look up owned keys by borrowed str, without allocating.
+        let hash = hash_str(xs.hasher(), "beta");
+        let (key, value) = xs.raw_entry().from_key_hashed_nocheck(hash, "beta").unwrap();
+        assert_eq!((key.as_str(), *value), ("beta", 1));
+        let hash = hash_str(xs.hasher(), "gamma");
+        assert_eq!(xs.raw_entry().from_hash(hash, |k| k == "gamma").map(|(_, &v)| v), Some(2));
+        // A matching hash alone doesn't match a leaf.
+        assert!(xs.raw_entry().from_hash(hash, |k| k == "beta").is_none());
+        let hash = hash_str(xs.hasher(), "delta");
+        assert!(xs.raw_entry().from_key_hashed_nocheck(hash, "delta").is_none());
+
+        // Insert with the precomputed hash of a vacant entry.
+        match xs.raw_entry_mut().from_key_hashed_nocheck(hash, "delta") {
+            HashTrieMapRawEntryMut::Vacant(entry) => entry.insert("delta".to_string(), 3).ok().unwrap(),
+            HashTrieMapRawEntryMut::Occupied(..) => panic!("occupied"),
+        }
+        assert_eq!(xs.len(), 4);
+        assert_eq!(xs.get(&"delta".to_string()), Some(&3));
+        assert_eq!(xs.raw_entry().from_key_hashed_nocheck(hash, "delta").map(|(_, &v)| v), Some(3));
+
+        // Update an occupied entry in place.
+        let hash = hash_str(xs.hasher(), "alpha");
+        match xs.raw_entry_mut().from_hash(hash, |k| k == "alpha") {
+            HashTrieMapRawEntryMut::Occupied(mut entry) => {
+                assert_eq!(entry.key(), "alpha");
+                assert_eq!(entry.insert(10), 0);
+                *entry.get_mut() += 1;
+            },
+            HashTrieMapRawEntryMut::Vacant(..) => panic!("vacant"),
+        }
+        assert_eq!(xs.get(&"alpha".to_string()), Some(&11));
+        assert_eq!(xs.len(), 4);
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+#[test]
+fn test_hash_trie_map_entry_or_default_counts() {
+    static mut TEST_AREA: [u8; 262144] = [0; 262144];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let mut counts = HashTrieMap::<i32, usize>::hold_new(pack);
+        // Count the residues of 0..1000 modulo 100; each key sees 10 hits.
+        for n in 0..1000 {
+            let count = counts.entry_or_default(n % 100).unwrap();
+            *count += 1;
+        }
+        assert_eq!(counts.len(), 100);
+        for k in 0..100 {
+            assert_eq!(counts.get(&k), Some(&10));
+        }
+        // Absent keys start at the default.
+        assert_eq!(*counts.entry_or_default(100).unwrap(), 0);
+        assert_eq!(counts.len(), 101);
+        // Present keys keep their value.
+        assert_eq!(*counts.entry_or_default(7).unwrap(), 10);
+        assert_eq!(counts.len(), 101);
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+#[test]
+fn test_hash_trie_map_remove_all_deep_prefix() {
+    static mut TEST_AREA: [u8; 262144] = [0; 262144];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        // Every key descends the same chain of single-branch nodes.
+        let mut xs = HashTrieMap::<u32, u32, PrefixHasher>::hold_new_hasher(pack, PrefixHasher(0));
+        for k in 0..128 {
+            xs.insert(k, k + 1).unwrap();
+        }
+        // Remove every third key, plus keys that were never present.
+        let removed = xs.remove_all((0..128).filter(|k| k % 3 == 0).chain(200..210)).unwrap();
+        assert_eq!(removed, 43);
+        assert_eq!(xs.len(), 85);
+        for k in 0..128 {
+            if k % 3 == 0 {
+                assert_eq!(xs.get(&k), None);
+            } else {
+                assert_eq!(xs.get(&k), Some(&(k + 1)));
+            }
+        }
+        assert_eq!(xs.iter().count(), 85);
+
+        // Repeated keys are only counted once.
+        assert_eq!(xs.remove_all(vec![1, 1, 2, 2, 3]).unwrap(), 2);
+        assert_eq!(xs.len(), 83);
+
+        // Removing the remaining keys empties the map, and frees its nodes.
+        let rest: Vec<u32> = xs.keys().cloned().collect();
+        assert_eq!(xs.remove_all(rest).unwrap(), 83);
+        assert!(xs.is_empty());
+        assert_eq!(xs.iter().count(), 0);
+        assert_eq!(xs.memory_usage(), 0);
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+#[test]
+fn test_hash_trie_map_into_hold() {
+    static mut TEST_AREA: [u8; 262144] = [0; 262144];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    let mut xs = HashTrieMap::<u32, u32>::hold_new(pack);
+    for k in 0..256 {
+        xs.insert(k, k * k).unwrap();
+    }
+    assert!(pack.live() > 0);
+    // Move the map out of the arena, and into the global hold.
+    let ys = xs.into_hold(Hold::global()).ok().unwrap();
+    // Every allocation made in the arena got released.
+    assert_eq!(pack.live(), 0);
+    assert_eq!(pack.used(), 0);
+    // Reset the arena, and fill it with unrelated data.
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let mut zs = HashTrieMap::<u32, u32>::hold_new(pack);
+        for k in 0..256 {
+            zs.insert(k, 0).unwrap();
+        }
+    }
+    assert_eq!(pack.live(), 0);
+    // The relocated map is unaffected by the reuse of its former arena.
+    assert_eq!(ys.len(), 256);
+    for k in 0..256 {
+        assert_eq!(ys.get(&k), Some(&(k * k)));
+    }
+    assert_eq!(ys.iter().count(), 256);
+}
+
+#[test]
+fn test_hash_trie_map_first_last_pop() {
+    static mut TEST_AREA: [u8; 262144] = [0; 262144];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let mut xs = HashTrieMap::<i32, i32>::hold_new(pack);
+        assert_eq!(xs.first(), None);
+        assert_eq!(xs.last(), None);
+        assert_eq!(xs.pop_first().unwrap(), None);
+        assert_eq!(xs.pop_last().unwrap(), None);
+
+        for k in 0..100 {
+            xs.insert(k, -k).unwrap();
+        }
+        // The extremes agree with the first and last yields of the iterator.
+        assert_eq!(xs.first(), xs.iter().next());
+        assert_eq!(xs.last(), xs.iter().last());
+        assert_ne!(xs.first(), xs.last());
+
+        // Popping the last entry removes the iterator's last yield.
+        let order: Vec<(i32, i32)> = xs.iter().map(|(&k, &v)| (k, v)).collect();
+        assert_eq!(xs.pop_last().unwrap(), Some(order[99]));
+        assert_eq!(xs.len(), 99);
+
+        // Repeatedly popping the first entry drains the map in hash order.
+        for (i, &entry) in order[..99].iter().enumerate() {
+            assert_eq!(xs.first(), Some((&entry.0, &entry.1)));
+            assert_eq!(xs.pop_first().unwrap(), Some(entry));
+            assert_eq!(xs.len(), 98 - i);
+            assert_eq!(xs.get(&entry.0), None);
+        }
+        assert!(xs.is_empty());
+        assert_eq!(xs.first(), None);
+        assert_eq!(xs.pop_first().unwrap(), None);
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+#[test]
+fn test_hash_trie_map_try_insert() {
+    static mut TEST_AREA: [u8; 65536] = [0; 65536];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let mut xs = HashTrieMap::<String, i32>::hold_new(pack);
+        // Inserting a fresh key returns a reference to the new value.
+        {
+            let value = xs.try_insert("one".to_string(), 1).ok().unwrap();
+            assert_eq!(*value, 1);
+            *value += 10;
+        }
+        assert_eq!(xs.get(&"one".to_string()), Some(&11));
+        for k in 0..64 {
+            assert_eq!(*xs.try_insert(k.to_string(), k).ok().unwrap(), k);
+        }
+        assert_eq!(xs.len(), 65);
+
+        // Inserting a duplicate key returns the rejected key and value,
+        // along with the existing entry.
+        match xs.try_insert("one".to_string(), 2) {
+            Err(HashTrieMapTryInsertError::Occupied(mut error)) => {
+                assert_eq!(error.key, "one");
+                assert_eq!(error.value, 2);
+                assert_eq!(error.entry.key(), "one");
+                assert_eq!(*error.entry.get(), 11);
+                *error.entry.get_mut() += 1;
+            },
+            _ => panic!("vacant"),
+        }
+        match xs.try_insert("7".to_string(), 0) {
+            Err(HashTrieMapTryInsertError::Occupied(error)) => assert_eq!(*error.entry.get(), 7),
+            _ => panic!("vacant"),
+        }
+        // The existing values remain in place.
+        assert_eq!(xs.len(), 65);
+        assert_eq!(xs.get(&"one".to_string()), Some(&12));
+        for k in 0..64 {
+            assert_eq!(xs.get(&k.to_string()), Some(&k));
+        }
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+#[test]
+fn test_hash_trie_map_to_pairs_buf() {
+    static mut TEST_AREA: [u8; 262144] = [0; 262144];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let mut xs = HashTrieMap::<i32, String>::hold_new(pack);
+        assert_eq!(xs.to_pairs_buf(pack).unwrap().len(), 0);
+        for k in 0..200 {
+            xs.insert(k, k.to_string()).unwrap();
+        }
+        let pairs = xs.to_pairs_buf(pack).unwrap();
+        assert_eq!(pairs.len(), xs.len());
+        // Every pair of the map appears exactly once, in any order.
+        let mut seen = [false; 200];
+        for &(k, ref v) in pairs.iter() {
+            assert_eq!(xs.get(&k), Some(v));
+            assert!(!seen[k as usize]);
+            seen[k as usize] = true;
+        }
+        assert!(seen.iter().all(|&s| s));
+        // The buffer holds clones, independent of the map.
+        drop(xs);
+        assert_eq!(pairs.iter().filter(|&&(k, ref v)| *v == k.to_string()).count(), 200);
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+fn content_hash_of(xs: &HashTrieMap<i32, i32>) -> u64 {
+    let mut state = std::collections::hash_map::DefaultHasher::new();
+    xs.content_hash(&mut state);
+    state.finish()
+}
+
+#[test]
+fn test_hash_trie_map_content_hash() {
+    static mut TEST_AREA: [u8; 262144] = [0; 262144];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let mut xs = HashTrieMap::<i32, i32>::hold_new(pack);
+        let mut ys = HashTrieMap::<i32, i32>::hold_new(pack);
+        assert_eq!(content_hash_of(&xs), content_hash_of(&ys));
+        // Insert the same pairs in opposite orders, with detours through
+        // keys that get removed again.
+        for k in 0..256 {
+            xs.insert(k, k * 3).unwrap();
+        }
+        for k in (0..256).rev() {
+            ys.insert(k, k * 3).unwrap();
+            ys.insert(k + 1000, 0).unwrap();
+        }
+        for k in 1000..1256 {
+            ys.remove(&k).unwrap();
+        }
+        assert_eq!(content_hash_of(&xs), content_hash_of(&ys));
+
+        // Changing a single value changes the hash.
+        ys.insert(17, 0).unwrap();
+        assert_ne!(content_hash_of(&xs), content_hash_of(&ys));
+        ys.insert(17, 51).unwrap();
+        assert_eq!(content_hash_of(&xs), content_hash_of(&ys));
+
+        // Moving a value to a different key changes the hash.
+        ys.insert(17, 54).unwrap();
+        ys.insert(18, 51).unwrap();
+        assert_ne!(content_hash_of(&xs), content_hash_of(&ys));
+
+        // Removing a pair changes the hash.
+        let mut zs = xs.try_clone().unwrap();
+        zs.remove(&0).unwrap();
+        assert_ne!(content_hash_of(&xs), content_hash_of(&zs));
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+#[test]
+fn test_hash_trie_map_get_many() {
+    static mut TEST_AREA: [u8; 262144] = [0; 262144];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let mut xs = HashTrieMap::<i32, i32>::hold_new(pack);
+        for k in 0..128 {
+            xs.insert(2 * k, -k).unwrap();
+        }
+        // Interleave present even keys with absent odd and out of range keys,
+        // including repeated keys.
+        let keys = [0, 1, 2, 255, 254, 1000, -2, 2, 100, 101];
+        let refs: Vec<&i32> = keys.iter().collect();
+        let values = xs.get_many(&refs, pack).unwrap();
+        assert_eq!(values.len(), keys.len());
+        for (key, value) in keys.iter().zip(values.iter()) {
+            assert_eq!(*value, xs.get(key));
+        }
+        assert_eq!(values[0], Some(&0));
+        assert_eq!(values[1], None);
+        assert_eq!(values[4], Some(&-127));
+        assert_eq!(values[8], Some(&-50));
+
+        // No keys yield an empty buffer.
+        assert_eq!(xs.get_many::<i32>(&[], pack).unwrap().len(), 0);
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+#[test]
+fn test_hash_trie_map_reduce_values() {
+    static mut TEST_AREA: [u8; 262144] = [0; 262144];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        // Every key collides into one of eight knots.
+        let mut xs = HashTrieMap::<u32, u64, ModHasher>::hold_new_hasher(pack, ModHasher(0));
+        assert_eq!(xs.reduce_values(0, |a, b| a + b), 0);
+        for k in 0..100 {
+            xs.insert(k, (k as u64 * 37) % 101).unwrap();
+        }
+        assert_eq!(xs.collision_count(), 8);
+        let sum: u64 = xs.values().sum();
+        assert_eq!(xs.reduce_values(0, |a, b| a + b), sum);
+        assert_eq!(xs.reduce_values(0, |a, b| *a.max(b)), 100);
+
+        // A map of distinctly hashed keys reduces likewise, before and after growing.
+        let mut ys = HashTrieMap::<u32, u64>::hold_new(pack);
+        for k in 0..1000 {
+            ys.insert(k, k as u64).unwrap();
+        }
+        assert_eq!(ys.reduce_values(0, |a, b| a + b), 499500);
+        assert_eq!(ys.reduce_values(0, |a, b| *a.max(b)), 999);
+        for (k, v) in xs.iter() {
+            ys.insert(*k + 1000, *v + 1000).unwrap();
+        }
+        assert_eq!(ys.reduce_values(0, |a, b| a + b), 499500 + sum + 100000);
+        assert_eq!(ys.reduce_values(0, |a, b| *a.max(b)), 1100);
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+#[test]
+fn test_hash_trie_map_key_set() {
+    static mut TEST_AREA: [u8; 262144] = [0; 262144];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let drops = AtomicUsize::new(0);
+        let mut xs = HashTrieMap::<u32, Counted, ModHasher>::hold_new_hasher(pack, ModHasher(0));
+        for k in 0..100 {
+            xs.insert(k * 5, Counted(k as i32, &drops)).ok().unwrap();
+        }
+        // The key set contains exactly the keys of the map, knots included.
+        let keys: HashTrieSet<u32, ModHasher> = xs.key_set(pack).unwrap();
+        assert_eq!(keys.len(), xs.len());
+        assert_eq!(keys.collision_count(), xs.collision_count());
+        for k in 0..500 {
+            assert_eq!(keys.contains(&k), xs.contains_key(&k));
+        }
+        for k in xs.keys() {
+            assert!(keys.contains(k));
+        }
+        // Building the key set leaves the map's values alone.
+        assert_eq!(drops.load(Relaxed), 0);
+        assert_eq!(xs.len(), 100);
+
+        // Consuming the map moves its keys, and drops each value exactly once.
+        let moved = xs.into_key_set(pack).unwrap();
+        assert_eq!(drops.load(Relaxed), 100);
+        assert_eq!(moved.len(), 100);
+        assert!(moved.is_subset(&keys));
+        assert!(keys.is_subset(&moved));
+        drop(moved);
+        assert_eq!(drops.load(Relaxed), 100);
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+#[test]
+fn test_hash_trie_map_into_key_set_owned_keys() {
+    static mut TEST_AREA: [u8; 262144] = [0; 262144];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let drops = AtomicUsize::new(0);
+        let mut xs = HashTrieMap::<String, Counted>::hold_new(pack);
+        for k in 0..64 {
+            xs.insert(format!("key{}", k), Counted(k, &drops)).ok().unwrap();
+        }
+        let keys = xs.into_key_set(pack).unwrap();
+        assert_eq!(drops.load(Relaxed), 64);
+        assert_eq!(keys.len(), 64);
+        for k in 0..64 {
+            assert!(keys.contains(&format!("key{}", k)));
+        }
+        assert!(!keys.contains(&"key64".to_string()));
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+fn hash_of<H: BuildHasher, T: Hash>(hasher: &H, key: &T) -> u64 {
+    let mut h = hasher.build_hasher();
+    key.hash(&mut h);
+    h.finish()
+}
+
+#[test]
+fn test_hash_trie_map_iter_with_hash() {
+    static mut TEST_AREA: [u8; 262144] = [0; 262144];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let mut xs = HashTrieMap::<u32, u32>::hold_new(pack);
+        for k in 0..300 {
+            xs.insert(k, k + 1).unwrap();
+        }
+        // Each yielded hash is the hash of its key under the map's hasher,
+        // and leafs are yielded in iteration order.
+        assert_eq!(xs.iter_with_hash().len(), 300);
+        for ((hash, key, value), (k, v)) in xs.iter_with_hash().zip(xs.iter()) {
+            assert_eq!(hash, hash_of(xs.hasher(), key));
+            assert_eq!((key, value), (k, v));
+        }
+
+        // Fully colliding keys report identical hashes.
+        let mut ys = HashTrieMap::<u32, u32, ModHasher>::hold_new_hasher(pack, ModHasher(0));
+        for k in 0..40 {
+            ys.insert(k, k).unwrap();
+        }
+        let mut count = 0;
+        for (hash, key, value) in ys.iter_with_hash() {
+            assert_eq!(hash, (key % 8) as u64);
+            assert_eq!(hash, hash_of(ys.hasher(), key));
+            assert_eq!(key, value);
+            count += 1;
+        }
+        assert_eq!(count, 40);
+        let hash = hash_of(ys.hasher(), &3);
+        assert_eq!(ys.iter_with_hash().filter(|&(h, _, _)| h == hash).count(), 5);
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+#[test]
+fn test_hash_trie_map_set_all_values() {
+    static mut TEST_AREA: [u8; 262144] = [0; 262144];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let mut xs = HashTrieMap::<u32, u64, ModHasher>::hold_new_hasher(pack, ModHasher(0));
+        let mut ys = HashTrieMap::<u32, u64>::hold_new(pack);
+        for k in 0..200 {
+            xs.insert(k, k as u64 + 1).unwrap();
+            ys.insert(k, k as u64 + 1).unwrap();
+        }
+        let live = pack.live();
+        let used = pack.used();
+        let usage = (xs.memory_usage(), ys.memory_usage());
+
+        // Zero every value, visiting each entry exactly once.
+        let mut seen = [0u8; 200];
+        xs.set_all_values(|k, v| {
+            seen[*k as usize] += 1;
+            *v = 0;
+        });
+        assert!(seen.iter().all(|&n| n == 1));
+        assert!(xs.values().all(|&v| v == 0));
+
+        // Transform values based on their keys.
+        let mut visits = 0;
+        ys.set_all_values(|k, v| {
+            visits += 1;
+            *v = *v * 1000 + (*k as u64 % 7);
+        });
+        assert_eq!(visits, 200);
+        for k in 0..200 {
+            assert_eq!(ys.get(&k), Some(&((k as u64 + 1) * 1000 + k as u64 % 7)));
+        }
+
+        // Neither map allocated, freed, or restructured anything.
+        assert_eq!(pack.live(), live);
+        assert_eq!(pack.used(), used);
+        assert_eq!((xs.memory_usage(), ys.memory_usage()), usage);
+        assert_eq!(xs.len(), 200);
+        assert_eq!(xs.collision_count(), 8);
+        assert_eq!(ys.len(), 200);
+
+        // An empty map visits nothing.
+        let mut empty = HashTrieMap::<u32, u64>::hold_new(pack);
+        empty.set_all_values(|_, _| panic!("visited"));
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+#[test]
+fn test_hash_trie_map_remove_keys_in() {
+    static mut TEST_AREA: [u8; 262144] = [0; 262144];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        // Every key of the map collides into one of eight knots.
+        let mut xs = HashTrieMap::<u32, u32, ModHasher>::hold_new_hasher(pack, ModHasher(0));
+        for k in 0..80 {
+            xs.insert(k, k * 2).unwrap();
+        }
+        // Remove the multiples of 3, and keys that collide with the map's
+        // keys without being present, looked up through a map with a
+        // different value type and hasher.
+        let mut other = HashTrieMap::<u32, String>::hold_new(pack);
+        for k in (0..80).filter(|k| k % 3 == 0).chain(80..96) {
+            other.insert(k, k.to_string()).unwrap();
+        }
+        assert_eq!(xs.remove_keys_in(&other).unwrap(), 27);
+        assert_eq!(xs.len(), 53);
+        for k in 0..96 {
+            if k < 80 && k % 3 != 0 {
+                assert_eq!(xs.get(&k), Some(&(k * 2)));
+            } else {
+                assert_eq!(xs.get(&k), None);
+            }
+        }
+        assert_eq!(xs.iter().count(), 53);
+        assert_eq!(xs.collision_count(), 8);
+        // The other map is untouched.
+        assert_eq!(other.len(), 43);
+
+        // Removing the same keys again removes nothing.
+        assert_eq!(xs.remove_keys_in(&other).unwrap(), 0);
+        assert_eq!(xs.len(), 53);
+
+        // Removing every key empties the map, knots included.
+        let all = xs.try_clone().unwrap();
+        assert_eq!(xs.remove_keys_in(&all).unwrap(), 53);
+        assert!(xs.is_empty());
+        assert_eq!(xs.collision_count(), 0);
+        assert_eq!(xs.memory_usage(), 0);
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+#[test]
+fn test_hash_trie_map_constant_hasher() {
+    static mut TEST_AREA: [u8; 262144] = [0; 262144];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let mut xs = HashTrieMap::<u32, u32, ConstHasher>::hold_new_hasher(pack, ConstHasher);
+        assert_eq!(xs.collision_count(), 0);
+        assert_eq!(xs.max_collision_len(), 0);
+        // The first key occupies a plain leaf.
+        assert_eq!(xs.insert(0, 0).unwrap(), None);
+        assert_eq!(xs.collision_count(), 0);
+        // Every subsequent key joins a single knot.
+        for k in 1..100 {
+            assert_eq!(xs.insert(k, k * 10).unwrap(), None);
+            assert_eq!(xs.collision_count(), 1);
+            assert_eq!(xs.max_collision_len(), k as usize + 1);
+        }
+        assert_eq!(xs.len(), 100);
+        // Replacing a value in the knot keeps its size.
+        assert_eq!(xs.insert(50, 5).unwrap(), Some(500));
+        assert_eq!(xs.max_collision_len(), 100);
+        assert_eq!(xs.insert(50, 500).unwrap(), Some(5));
+
+        // Lookups compare keys within the knot.
+        for k in 0..100 {
+            assert_eq!(xs.get(&k), Some(&(k * 10)));
+        }
+        assert_eq!(xs.get(&100), None);
+        assert!(!xs.contains_key(&1000));
+        assert_eq!(xs.iter().count(), 100);
+
+        // Removals shrink the knot, down to a plain leaf.
+        assert_eq!(xs.remove(&100).unwrap(), None);
+        for k in (0..99).rev() {
+            assert_eq!(xs.remove(&k).unwrap(), Some(k * 10));
+            assert_eq!(xs.get(&k), None);
+            assert_eq!(xs.len(), k as usize + 1);
+        }
+        assert_eq!(xs.collision_count(), 0);
+        assert_eq!(xs.get(&99), Some(&990));
+        assert_eq!(xs.remove(&99).unwrap(), Some(990));
+        assert!(xs.is_empty());
+        assert_eq!(xs.memory_usage(), 0);
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+#[cfg(feature = "trace")]
+#[test]
+fn test_hash_trie_map_memory_usage_traced() {
+    static mut TEST_AREA: [u8; 262144] = [0; 262144];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let start = HoldTrace::get();
+        let mut xs = HashTrieMap::<u32, u64>::hold_new(pack);
+        let mut ys = HashTrieMap::<u32, u64, ModHasher>::hold_new_hasher(pack, ModHasher(0));
+        for k in 0..500 {
+            xs.insert(k, k as u64).unwrap();
+        }
+        for k in 0..100 {
+            ys.insert(k, k as u64).unwrap();
+        }
+        for k in 0..250 {
+            xs.remove(&(2 * k)).unwrap();
+        }
+        for k in 0..20 {
+            ys.remove(&(5 * k)).unwrap();
+        }
+        // The maps use exactly the bytes their construction left allocated.
+        let trace = HoldTrace::since(start);
+        assert!(trace.allocs > 0);
+        assert_eq!(xs.memory_usage() + ys.memory_usage(), trace.alloc_bytes - trace.dealloc_bytes);
+        assert_eq!(xs.memory_usage() + ys.memory_usage(), pack.used());
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+#[test]
+fn test_hash_trie_map_memory_usage_colliding() {
+    static mut TEST_AREA: [u8; 262144] = [0; 262144];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let leaf_size = core::mem::size_of::<(u32, u32)>();
+        let mut xs = HashTrieMap::<u32, u32, ConstHasher>::hold_new_hasher(pack, ConstHasher);
+        assert_eq!(xs.memory_usage(), 0);
+        xs.insert(0, 0).unwrap();
+        xs.insert(1, 1).unwrap();
+        assert_eq!(xs.memory_usage(), pack.used());
+        // Each colliding key grows the knot by a single leaf.
+        for k in 2..64 {
+            let usage = xs.memory_usage();
+            xs.insert(k, k).unwrap();
+            assert_eq!(xs.memory_usage(), usage + leaf_size);
+            assert_eq!(xs.memory_usage(), pack.used());
+        }
+        // Each removed key shrinks the knot by a single leaf.
+        for k in 0..62 {
+            let usage = xs.memory_usage();
+            xs.remove(&k).unwrap();
+            assert_eq!(xs.memory_usage(), usage - leaf_size);
+            assert_eq!(xs.memory_usage(), pack.used());
+        }
+        xs.remove(&62).unwrap();
+        xs.remove(&63).unwrap();
+        assert_eq!(xs.memory_usage(), 0);
+    }
+    assert_eq!(pack.live(), 0);
+}
@@ -0,0 +1,103 @@
+extern crate tg_mem;
+extern crate tg_collections;
+
+use tg_mem::block::Block;
+use tg_mem::alloc::Pack;
+use tg_collections::hash_trie::OrderedTrieMap;
+
+#[test]
+fn test_ordered_trie_map_iterates_in_insertion_order() {
+    static mut TEST_AREA: [u8; 65536] = [0; 65536];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let mut xs = OrderedTrieMap::<i32, i32>::hold_new(pack);
+        // Keys inserted out of hash order.
+        for &k in [7, 3, 100, -5, 42, 0].iter() {
+            assert_eq!(xs.insert(k, k * 10).unwrap(), None);
+        }
+        assert_eq!(xs.len(), 6);
+        let keys = xs.iter().map(|(&k, _)| k).collect::<Vec<i32>>();
+        assert_eq!(keys, [7, 3, 100, -5, 42, 0]);
+        assert_eq!(xs.first(), Some((&7, &70)));
+        let keys = xs.iter().rev().map(|(&k, _)| k).collect::<Vec<i32>>();
+        assert_eq!(keys, [0, 42, -5, 100, 3, 7]);
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+#[test]
+fn test_ordered_trie_map_replace_retains_position() {
+    static mut TEST_AREA: [u8; 65536] = [0; 65536];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let mut xs = OrderedTrieMap::<i32, i32>::hold_new(pack);
+        xs.insert(1, 10).unwrap();
+        xs.insert(2, 20).unwrap();
+        xs.insert(3, 30).unwrap();
+        assert_eq!(xs.insert(1, 11).unwrap(), Some(10));
+        assert_eq!(xs.len(), 3);
+        let entries = xs.iter().map(|(&k, &v)| (k, v)).collect::<Vec<(i32, i32)>>();
+        assert_eq!(entries, [(1, 11), (2, 20), (3, 30)]);
+        *xs.get_mut(&2).unwrap() = 22;
+        assert_eq!(xs.get(&2), Some(&22));
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+#[test]
+fn test_ordered_trie_map_interleaved_insert_remove() {
+    static mut TEST_AREA: [u8; 262144] = [0; 262144];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let mut xs = OrderedTrieMap::<i32, i32>::hold_new(pack);
+        let mut expected = Vec::new();
+        for k in 0..256 {
+            xs.insert(k, -k).unwrap();
+            expected.push(k);
+            // Remove every third key soon after inserting it.
+            if k % 3 == 2 {
+                let r = k - 1;
+                assert_eq!(xs.remove(&r).unwrap(), Some(-r));
+                expected.retain(|&x| x != r);
+            }
+        }
+        assert_eq!(xs.remove(&1).unwrap(), None);
+        // Reinserting a removed key orders it last.
+        xs.insert(1, -1).unwrap();
+        expected.push(1);
+        assert_eq!(xs.len(), expected.len());
+        let keys = xs.iter().map(|(&k, _)| k).collect::<Vec<i32>>();
+        assert_eq!(keys, expected);
+        for &k in expected.iter() {
+            assert_eq!(xs.get(&k), Some(&-k));
+        }
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+#[test]
+fn test_ordered_trie_map_compacts_tombstones() {
+    static mut TEST_AREA: [u8; 262144] = [0; 262144];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let mut xs = OrderedTrieMap::<i32, i32>::hold_new(pack);
+        for k in 0..128 {
+            xs.insert(k, k).unwrap();
+        }
+        // Removing most leading entries compacts the survivors to the front,
+        // re-indexing each moved key.
+        for k in 0..120 {
+            assert_eq!(xs.remove(&k).unwrap(), Some(k));
+        }
+        assert_eq!(xs.len(), 8);
+        let keys = xs.iter().map(|(&k, _)| k).collect::<Vec<i32>>();
+        assert_eq!(keys, [120, 121, 122, 123, 124, 125, 126, 127]);
+        for k in 120..128 {
+            assert_eq!(xs.get(&k), Some(&k));
+            assert_eq!(xs.remove(&k).unwrap(), Some(k));
+        }
+        assert!(xs.is_empty());
+        assert_eq!(xs.iter().next(), None);
+    }
+    assert_eq!(pack.live(), 0);
+}
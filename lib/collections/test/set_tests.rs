@@ -0,0 +1,315 @@
+extern crate tg_mem;
+extern crate tg_collections;
+
+use core::hash::{BuildHasher, Hash, Hasher};
+use core::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+use tg_mem::block::Block;
+use tg_mem::alloc::{HoldError, Pack};
+use tg_collections::hash_trie::{FrozenSet, HashTrieSet};
+
+/// Hasher that maps each `u32` to its residue modulo 4, so that elements
+/// congruent modulo 4 fully collide into a shared knot.
+#[derive(Clone, Default)]
+struct ModHasher(u64);
+
+impl Hasher for ModHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 = self.0 << 8 | b as u64;
+        }
+    }
+
+    fn write_u32(&mut self, n: u32) {
+        self.0 = (n % 4) as u64;
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+impl BuildHasher for ModHasher {
+    type Hasher = ModHasher;
+
+    fn build_hasher(&self) -> ModHasher {
+        ModHasher(0)
+    }
+}
+
+#[test]
+fn test_hash_trie_set_subset_superset_nested() {
+    static mut TEST_AREA: [u8; 262144] = [0; 262144];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let empty = HashTrieSet::<u32>::hold_new(pack);
+        let small = HashTrieSet::<u32>::hold_from_iter(pack, 0..16);
+        let medium = HashTrieSet::<u32>::hold_from_iter(pack, 0..64);
+        let large = HashTrieSet::<u32>::hold_from_iter(pack, 0..256);
+
+        // Each set of the chain contains the sets nested within it.
+        assert!(small.is_subset(&medium));
+        assert!(medium.is_subset(&large));
+        assert!(small.is_subset(&large));
+        assert!(large.is_superset(&medium));
+        assert!(large.is_superset(&small));
+        assert!(!large.is_subset(&small));
+        assert!(!small.is_superset(&large));
+
+        // Every set is both a subset and a superset of itself.
+        assert!(medium.is_subset(&medium));
+        assert!(medium.is_superset(&medium));
+
+        // The empty set is a subset of every set.
+        assert!(empty.is_subset(&small));
+        assert!(empty.is_subset(&empty));
+        assert!(small.is_superset(&empty));
+        assert!(!small.is_subset(&empty));
+
+        // A set no larger than another, but with an element outside of it,
+        // is not a subset.
+        let mut shifted = HashTrieSet::<u32>::hold_from_iter(pack, 1..16);
+        shifted.insert(1000).unwrap();
+        assert_eq!(shifted.len(), small.len());
+        assert!(!shifted.is_subset(&medium));
+        assert!(!medium.is_superset(&shifted));
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+#[test]
+fn test_hash_trie_set_disjoint() {
+    static mut TEST_AREA: [u8; 262144] = [0; 262144];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let empty = HashTrieSet::<u32>::hold_new(pack);
+        let evens = HashTrieSet::<u32>::hold_from_iter(pack, (0..128).map(|n| 2 * n));
+        let odds = HashTrieSet::<u32>::hold_from_iter(pack, (0..64).map(|n| 2 * n + 1));
+        assert!(evens.is_disjoint(&odds));
+        assert!(odds.is_disjoint(&evens));
+        assert!(!evens.is_subset(&odds));
+        assert!(!evens.is_superset(&odds));
+
+        // The empty set is disjoint from every set, including itself.
+        assert!(empty.is_disjoint(&evens));
+        assert!(evens.is_disjoint(&empty));
+        assert!(empty.is_disjoint(&empty));
+
+        // A single shared element breaks disjointness, whichever set is larger.
+        let mut mixed = HashTrieSet::<u32>::hold_from_iter(pack, (0..64).map(|n| 2 * n + 1));
+        mixed.insert(254).unwrap();
+        assert!(!evens.is_disjoint(&mixed));
+        assert!(!mixed.is_disjoint(&evens));
+        assert!(!mixed.is_disjoint(&mixed));
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+#[test]
+fn test_hash_trie_set_predicates_with_knots() {
+    static mut TEST_AREA: [u8; 262144] = [0; 262144];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        // Every element of these sets collides into one of four knots.
+        let mut xs = HashTrieSet::<u32, ModHasher>::hold_new_hasher(pack, ModHasher(0));
+        let mut ys = HashTrieSet::<u32, ModHasher>::hold_new_hasher(pack, ModHasher(0));
+        for n in 0..32 {
+            xs.insert(n).unwrap();
+        }
+        for n in 0..64 {
+            ys.insert(n).unwrap();
+        }
+        assert_eq!(xs.collision_count(), 4);
+        assert_eq!(xs.max_collision_len(), 8);
+        assert!(xs.is_subset(&ys));
+        assert!(ys.is_superset(&xs));
+        assert!(!ys.is_subset(&xs));
+        assert!(!xs.is_disjoint(&ys));
+
+        // Elements colliding with the other set's elements, without being
+        // equal to any of them, are neither contained nor shared.
+        let mut zs = HashTrieSet::<u32, ModHasher>::hold_new_hasher(pack, ModHasher(0));
+        for n in 64..96 {
+            zs.insert(n).unwrap();
+        }
+        assert!(zs.is_disjoint(&xs));
+        assert!(xs.is_disjoint(&zs));
+        assert!(!zs.is_subset(&ys));
+
+        // Sets with differing hashers probe each other with their own hashers.
+        let plain = HashTrieSet::<u32>::hold_from_iter(pack, 0..32);
+        assert_eq!(plain.collision_count(), 0);
+        assert!(plain.is_subset(&ys));
+        assert!(plain.is_subset(&xs));
+        assert!(xs.is_subset(&plain));
+        assert!(ys.is_superset(&plain));
+        assert!(plain.is_disjoint(&zs));
+        assert!(zs.is_disjoint(&plain));
+
+        // Removing a knotted element breaks the subset relation.
+        ys.remove(&4).unwrap();
+        assert!(!xs.is_subset(&ys));
+        assert!(!plain.is_subset(&ys));
+        assert!(ys.is_superset(&HashTrieSet::<u32>::hold_from_iter(pack, 5..32)));
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+fn count_members(set: FrozenSet<u32>, elems: &[u32]) -> usize {
+    elems.iter().filter(|elem| set.contains(*elem)).count()
+}
+
+#[test]
+fn test_hash_trie_set_frozen() {
+    static mut TEST_AREA: [u8; 262144] = [0; 262144];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let mut xs = HashTrieSet::<u32>::hold_new(pack);
+        assert!(xs.as_frozen().is_empty());
+        for n in 0..100 {
+            xs.insert(3 * n).unwrap();
+        }
+        let frozen = xs.as_frozen();
+        assert!(!frozen.is_empty());
+        assert_eq!(frozen.len(), 100);
+        // Membership agrees with the viewed set.
+        for n in 0..300 {
+            assert_eq!(frozen.contains(&n), n % 3 == 0);
+        }
+        // Iteration yields the viewed set's elements, in the same order.
+        assert!(frozen.iter().eq(xs.iter()));
+        assert_eq!(frozen.into_iter().count(), 100);
+        assert_eq!(frozen.into_iter().map(|&n| n as u64).sum::<u64>(), 3 * 4950);
+
+        // Views are copied freely, and passed to read-only code.
+        let copy = frozen;
+        assert_eq!(count_members(copy, &[0, 1, 2, 3, 297, 300]), 3);
+        assert_eq!(count_members(FrozenSet::from(&xs), &[6, 9]), 2);
+
+        // Set predicates see the viewed set.
+        let small = HashTrieSet::<u32>::hold_from_iter(pack, vec![0, 3, 6]);
+        let other = HashTrieSet::<u32>::hold_from_iter(pack, vec![1, 2, 4]);
+        assert!(frozen.is_superset(&small));
+        assert!(!frozen.is_subset(&small));
+        assert!(small.as_frozen().is_subset(&xs));
+        assert!(frozen.is_disjoint(&other));
+        assert_eq!(format!("{:?}", small.as_frozen()), format!("{:?}", small));
+
+        // A view taken after mutating the set sees the change.
+        xs.remove(&0).unwrap();
+        assert!(!xs.as_frozen().contains(&0));
+        assert_eq!(xs.as_frozen().len(), 99);
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+/// Element that counts its drops in a per-test counter; compares and hashes
+/// by its number alone.
+struct Counted<'c>(u32, &'c AtomicUsize);
+
+impl<'c> PartialEq for Counted<'c> {
+    fn eq(&self, that: &Counted<'c>) -> bool {
+        self.0 == that.0
+    }
+}
+
+impl<'c> Eq for Counted<'c> {
+}
+
+impl<'c> Hash for Counted<'c> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state);
+    }
+}
+
+impl<'c> Drop for Counted<'c> {
+    fn drop(&mut self) {
+        self.1.fetch_add(1, Relaxed);
+    }
+}
+
+#[test]
+fn test_hash_trie_set_from_iter_duplicates() {
+    static mut TEST_AREA: [u8; 262144] = [0; 262144];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let drops = AtomicUsize::new(0);
+        // Every element appears three times; the later copies are dropped.
+        let xs = HashTrieSet::try_hold_from_iter(pack, (0..300).map(|n| Counted(n % 100, &drops))).unwrap();
+        assert_eq!(xs.len(), 100);
+        assert_eq!(drops.load(Relaxed), 200);
+        let probe = AtomicUsize::new(0);
+        for n in 0..100 {
+            assert!(xs.contains(&Counted(n, &probe)));
+        }
+        assert!(!xs.contains(&Counted(100, &probe)));
+        drop(xs);
+        assert_eq!(drops.load(Relaxed), 300);
+
+        let ys = HashTrieSet::<u32>::hold_from_iter(pack, vec![3, 1, 3, 2, 1]);
+        assert_eq!(ys.len(), 3);
+        assert!(ys.is_superset(&HashTrieSet::<u32>::hold_from_iter(pack, 1..4)));
+        assert!(HashTrieSet::<u32>::hold_from_iter(pack, 0..0).is_empty());
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+#[test]
+fn test_hash_trie_set_extend_duplicates() {
+    static mut TEST_AREA: [u8; 262144] = [0; 262144];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let drops = AtomicUsize::new(0);
+        let mut xs = HashTrieSet::hold_new(pack);
+        xs.try_extend((0..50).map(|n| Counted(n, &drops))).unwrap();
+        assert_eq!(xs.len(), 50);
+        // Elements the set already contains are dropped, and the originals kept.
+        xs.try_extend((25..75).map(|n| Counted(n, &drops))).unwrap();
+        assert_eq!(xs.len(), 75);
+        assert_eq!(drops.load(Relaxed), 25);
+        xs.extend((70..80).map(|n| Counted(n, &drops)));
+        assert_eq!(xs.len(), 80);
+        assert_eq!(drops.load(Relaxed), 30);
+        drop(xs);
+        assert_eq!(drops.load(Relaxed), 110);
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+#[test]
+fn test_hash_trie_set_from_iter_bounded_hold() {
+    static mut TEST_AREA: [u8; 8192] = [0; 8192];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let drops = AtomicUsize::new(0);
+        // The hold runs out of memory part way through building the set,
+        // which drops the partial set along with every remaining element.
+        let elems: Vec<Counted> = (0..1000).map(|n| Counted(n, &drops)).collect();
+        match HashTrieSet::try_hold_from_iter(pack, elems) {
+            Err(HoldError::OutOfMemory) => (),
+            _ => panic!(),
+        }
+        assert_eq!(drops.load(Relaxed), 1000);
+        assert_eq!(pack.live(), 0);
+
+        // Extending keeps the elements included before the failure, and drops
+        // the failed element along with the remaining elements.
+        let drops = AtomicUsize::new(0);
+        let mut xs = HashTrieSet::hold_new(pack);
+        let elems: Vec<Counted> = (0..1000).map(|n| Counted(n, &drops)).collect();
+        match xs.try_extend(elems) {
+            Err(HoldError::OutOfMemory) => (),
+            _ => panic!(),
+        }
+        let len = xs.len();
+        assert!(len > 0 && len < 1000);
+        assert_eq!(drops.load(Relaxed), 1000 - len);
+        let probe = AtomicUsize::new(0);
+        for n in 0..len as u32 {
+            assert!(xs.contains(&Counted(n, &probe)));
+        }
+        assert!(!xs.contains(&Counted(len as u32, &probe)));
+        drop(xs);
+        assert_eq!(drops.load(Relaxed), 1000);
+    }
+    assert_eq!(pack.live(), 0);
+}
@@ -0,0 +1,87 @@
+extern crate tg_mem;
+extern crate tg_collections;
+
+use tg_mem::block::Block;
+use tg_mem::alloc::Pack;
+use tg_mem::lease::HardBox;
+use tg_collections::hash_trie::WeakValueMap;
+
+#[test]
+fn test_weak_value_map_get_evicts_dropped_values() {
+    static mut TEST_AREA: [u8; 65536] = [0; 65536];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let mut xs = WeakValueMap::<i32, usize>::hold_new(pack);
+        let live = HardBox::hold_new(pack, 5usize);
+        let dead = HardBox::hold_new(pack, 7usize);
+        xs.insert(1, &live).unwrap();
+        xs.insert(2, &dead).unwrap();
+        assert_eq!(xs.len(), 2);
+        assert_eq!(xs.dead_count(), 0);
+
+        // The map doesn't keep its values alive.
+        drop(dead);
+        assert_eq!(xs.len(), 2);
+        assert_eq!(xs.dead_count(), 1);
+        assert!(xs.get(&2).is_none());
+        // Getting the dead entry removes it.
+        assert_eq!(xs.len(), 1);
+        assert_eq!(xs.dead_count(), 0);
+        assert!(xs.get(&2).is_none());
+
+        // A live value is returned, and stays reachable.
+        let value = xs.get(&1).unwrap();
+        assert_eq!(*value.to_ref(), 5);
+        assert_eq!(value.hard_count(), 2);
+        drop(value);
+        assert_eq!(*xs.get(&1).unwrap().to_ref(), 5);
+        assert_eq!(xs.len(), 1);
+
+        drop(live);
+        assert!(xs.get(&1).is_none());
+        assert!(xs.is_empty());
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+#[test]
+fn test_weak_value_map_sweep() {
+    static mut TEST_AREA: [u8; 65536] = [0; 65536];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let mut xs = WeakValueMap::<i32, usize>::hold_new(pack);
+        let mut values = Vec::new();
+        for k in 0..64 {
+            let value = HardBox::hold_new(pack, k as usize);
+            xs.insert(k, &value).unwrap();
+            values.push(value);
+        }
+        assert_eq!(xs.sweep().unwrap(), 0);
+        // Drop the odd keyed values.
+        let mut k = 0;
+        values.retain(|_| { k += 1; k % 2 == 1 });
+        assert_eq!(xs.dead_count(), 32);
+        assert_eq!(xs.sweep().unwrap(), 32);
+        assert_eq!(xs.len(), 32);
+        assert_eq!(xs.dead_count(), 0);
+        for k in 0..64 {
+            match xs.get(&k) {
+                Some(value) => {
+                    assert_eq!(k % 2, 0);
+                    assert_eq!(*value.to_ref(), k as usize);
+                },
+                None => assert_eq!(k % 2, 1),
+            }
+        }
+        // Replacing a dead entry's value revives the key.
+        let value = HardBox::hold_new(pack, 99usize);
+        xs.insert(1, &value).unwrap();
+        assert_eq!(*xs.get(&1).unwrap().to_ref(), 99);
+        assert_eq!(xs.remove(&1).unwrap(), true);
+        assert!(xs.get(&1).is_none());
+        drop(values);
+        assert_eq!(xs.sweep().unwrap(), 32);
+        assert!(xs.is_empty());
+    }
+    assert_eq!(pack.live(), 0);
+}
@@ -254,6 +254,16 @@ impl<'a> Heap<'a> for AddrSpace<'a> {
         (*self.ptr).grow(self.size);
         (*self.ptr).dealloc(block)
     }
+
+    #[inline]
+    fn min_alignment(&self) -> usize {
+        EXTENT_ALIGN
+    }
+
+    #[inline]
+    fn allocation_granularity(&self) -> usize {
+        EXTENT_ALIGN
+    }
 }
 
 impl<'a> Clone for AddrSpace<'a> {
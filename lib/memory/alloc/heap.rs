@@ -15,6 +15,36 @@ pub trait Heap<'a> {
     /// Deallocates a memory block previously allocated by `alloc`.
     /// Returns the number of freed bytes.
     unsafe fn dealloc(&self, block: Block<'a>) -> usize;
+
+    /// Returns the alignment that every block allocated by this `Heap` is
+    /// guaranteed to satisfy, independent of the requested layout.
+    fn min_alignment(&self) -> usize {
+        1
+    }
+
+    /// Returns the unit to which this `Heap` rounds up the sizes of the
+    /// blocks it allocates, such as a slab's block size, or a page size.
+    fn allocation_granularity(&self) -> usize {
+        1
+    }
+
+    /// Makes the allocation that follows the next `n` allocations made by
+    /// this `Heap` on the current thread fail with `HeapError::OutOfMemory`;
+    /// subsequent allocations proceed normally. Shares the current thread's
+    /// failpoint with `Hold::fail_after`. Returns `HeapError::Unsupported`
+    /// if this `Heap` can't inject allocation failures. Available only with
+    /// the `failpoints` feature.
+    #[cfg(feature = "failpoints")]
+    fn fail_after(&self, _n: usize) -> Result<(), HeapError> {
+        Err(HeapError::Unsupported("fail_after"))
+    }
+
+    /// Makes the next allocation made by this `Heap` on the current thread
+    /// fail with `HeapError::OutOfMemory`; equivalent to `fail_after(0)`.
+    #[cfg(feature = "failpoints")]
+    fn fail_next_alloc(&self) -> Result<(), HeapError> {
+        self.fail_after(0)
+    }
 }
 
 impl<'a> Heap<'a> {
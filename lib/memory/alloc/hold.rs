@@ -1,9 +1,11 @@
-use core::cell::UnsafeCell;
+use core::cell::{Cell, UnsafeCell};
+use core::cmp;
 use core::mem;
 use core::ptr;
+use core::sync::atomic::Ordering::Relaxed;
 use tg_core::reify::{Reified, Reify};
 use crate::block::{Block, Layout, LayoutError};
-use crate::alloc::{AllocTag, HeapError};
+use crate::alloc::{AllocTag, HeapError, LocalPack, Restow};
 
 #[allow(improper_ctypes)]
 extern "Rust" {
@@ -30,6 +32,24 @@ pub unsafe trait Hold<'a> {
     /// immediately preceding the block.
     unsafe fn alloc(&self, layout: Layout) -> Result<Block<'a>, HoldError>;
 
+    /// Returns an unmanaged pointer to an uninitialized memory block sized
+    /// and aligned to `layout`, preferably located close in address to the
+    /// `hint` pointer; returns an `Err` if the allocation fails. The hint is
+    /// best-effort: if no space exists near `hint`, the block is allocated
+    /// as if by `alloc`.
+    unsafe fn alloc_near(&self, layout: Layout, _hint: *const u8) -> Result<Block<'a>, HoldError> {
+        self.alloc(layout)
+    }
+
+    /// Returns an unmanaged pointer to an uninitialized memory block sized
+    /// and aligned to `layout`, in which the address `offset` bytes into the
+    /// block, where a lease places its resident after a header, satisfies
+    /// the `min_alignment` of this `Hold`; returns an `Err` if the allocation
+    /// fails. The `offset` must be a multiple of the layout's alignment.
+    unsafe fn alloc_offset(&self, layout: Layout, _offset: usize) -> Result<Block<'a>, HoldError> {
+        self.alloc(layout)
+    }
+
     /// Releases a memory `block` allocated by this `Hold`.
     /// Returns the number of freed bytes.
     unsafe fn dealloc(&self, block: Block<'a>) -> usize;
@@ -40,13 +60,17 @@ pub unsafe trait Hold<'a> {
     unsafe fn resize(&self, block: Block<'a>, layout: Layout) -> Result<Block<'a>, HoldError>;
 
     /// Attempts to resize a memory `block` allocated by this `Hold` to fit
-    /// a new `layout`. Returns `Ok` with the resized memory block on success;
-    /// returns a `HoldError` on failure.
+    /// a new `layout`, moving the contents of the block to a newly allocated
+    /// block if it can't be resized in place. Returns `Ok` with the resized
+    /// memory block on success; returns a `HoldError`, and leaves the
+    /// original block intact, on failure.
     unsafe fn realloc(&self, block: Block<'a>, layout: Layout) -> Result<Block<'a>, HoldError> {
         match self.resize(block, layout) {
             ok @ Ok(_) => ok,
             Err(_) => match self.alloc(layout) {
                 Ok(new_block) => {
+                    // Copy the contents of the moved block.
+                    ptr::copy_nonoverlapping(block.as_ptr(), new_block.as_ptr(), cmp::min(block.size(), new_block.size()));
                     self.dealloc(block);
                     Ok(new_block)
                 },
@@ -54,6 +78,49 @@ pub unsafe trait Hold<'a> {
             },
         }
     }
+
+    /// Returns the alignment that every block allocated by this `Hold` is
+    /// guaranteed to satisfy, independent of the requested layout. Requesting
+    /// a greater alignment may fail with `HoldError::Misaligned`.
+    fn min_alignment(&self) -> usize {
+        mem::align_of::<AllocTag>()
+    }
+
+    /// Returns the unit to which this `Hold` rounds up the sizes of the
+    /// blocks it allocates.
+    fn allocation_granularity(&self) -> usize {
+        1
+    }
+
+    /// Relocates the `live` residents of this `Hold` into compact, adjacent
+    /// memory, and frees the gaps left behind. The caller supplies the full
+    /// set of live roots, since a `Hold` can't discover them on its own.
+    /// Each resident relocates via `Stow`; a resident that fails to relocate
+    /// is left in place, and the error is returned. Returns
+    /// `HoldError::Unsupported` if this `Hold` can't compact its memory.
+    fn defragment(&self, _live: &mut [&mut dyn Restow<'a>]) -> Result<(), HoldError> {
+        Err(HoldError::Unsupported("defragment"))
+    }
+
+    /// Makes the allocation that follows the next `n` allocations made by
+    /// this `Hold` on the current thread fail with `HoldError::OutOfMemory`;
+    /// subsequent allocations proceed normally. Each call to `alloc`,
+    /// `resize`, or `realloc` counts as one allocation. Arming a failpoint
+    /// replaces any failpoint previously armed on the current thread.
+    /// Returns `HoldError::Unsupported` if this `Hold` can't inject
+    /// allocation failures. Available only with the `failpoints` feature,
+    /// for testing that fallible paths recover from allocation failures.
+    #[cfg(feature = "failpoints")]
+    fn fail_after(&self, _n: usize) -> Result<(), HoldError> {
+        Err(HoldError::Unsupported("fail_after"))
+    }
+
+    /// Makes the next allocation made by this `Hold` on the current thread
+    /// fail with `HoldError::OutOfMemory`; equivalent to `fail_after(0)`.
+    #[cfg(feature = "failpoints")]
+    fn fail_next_alloc(&self) -> Result<(), HoldError> {
+        self.fail_after(0)
+    }
 }
 
 impl<'a> Hold<'a> {
@@ -121,6 +188,14 @@ unsafe impl<'a> Hold<'a> for HoldScope<'a> {
         self.hold.alloc(layout)
     }
 
+    unsafe fn alloc_near(&self, layout: Layout, hint: *const u8) -> Result<Block<'a>, HoldError> {
+        self.hold.alloc_near(layout, hint)
+    }
+
+    unsafe fn alloc_offset(&self, layout: Layout, offset: usize) -> Result<Block<'a>, HoldError> {
+        self.hold.alloc_offset(layout, offset)
+    }
+
     unsafe fn dealloc(&self, _block: Block<'a>) -> usize {
         // Never directly deallocates anything; deallocations always dispatch
         // to the underlying hold.
@@ -132,6 +207,455 @@ unsafe impl<'a> Hold<'a> for HoldScope<'a> {
         // underlying hold.
         unimplemented!();
     }
+
+    fn min_alignment(&self) -> usize {
+        self.hold.min_alignment()
+    }
+
+    fn allocation_granularity(&self) -> usize {
+        self.hold.allocation_granularity()
+    }
+
+    fn defragment(&self, live: &mut [&mut dyn Restow<'a>]) -> Result<(), HoldError> {
+        self.hold.defragment(live)
+    }
+
+    #[cfg(feature = "failpoints")]
+    fn fail_after(&self, n: usize) -> Result<(), HoldError> {
+        self.hold.fail_after(n)
+    }
+}
+
+/// `Hold` adapter that allocates every block near a fixed `hint` address in
+/// an underlying `Hold`. Blocks are tagged by, and dealloc through, the
+/// underlying hold.
+pub(crate) struct HoldNear<'a> {
+    hold: &'a dyn Hold<'a>,
+    hint: *const u8,
+}
+
+impl<'a> HoldNear<'a> {
+    pub(crate) fn new(hold: &'a dyn Hold<'a>, hint: *const u8) -> HoldNear<'a> {
+        HoldNear {
+            hold: hold,
+            hint: hint,
+        }
+    }
+}
+
+unsafe impl<'a> Hold<'a> for HoldNear<'a> {
+    unsafe fn alloc(&self, layout: Layout) -> Result<Block<'a>, HoldError> {
+        self.hold.alloc_near(layout, self.hint)
+    }
+
+    unsafe fn alloc_near(&self, layout: Layout, hint: *const u8) -> Result<Block<'a>, HoldError> {
+        self.hold.alloc_near(layout, hint)
+    }
+
+    unsafe fn dealloc(&self, block: Block<'a>) -> usize {
+        self.hold.dealloc(block)
+    }
+
+    unsafe fn resize(&self, block: Block<'a>, layout: Layout) -> Result<Block<'a>, HoldError> {
+        self.hold.resize(block, layout)
+    }
+
+    fn min_alignment(&self) -> usize {
+        self.hold.min_alignment()
+    }
+
+    fn allocation_granularity(&self) -> usize {
+        self.hold.allocation_granularity()
+    }
+
+    #[cfg(feature = "failpoints")]
+    fn fail_after(&self, n: usize) -> Result<(), HoldError> {
+        self.hold.fail_after(n)
+    }
+}
+
+/// `Hold` adapter that aligns every allocation to at least `align` bytes,
+/// allocating from an underlying `Hold`. Each block is preceded by an
+/// `AlignedBlock` header that tags the block, so that blocks dealloc, resize,
+/// and realloc back through the adapter, and keep their alignment when they
+/// move. Leases that place their resident after a header allocate through
+/// `alloc_offset`, which aligns the resident, rather than the block.
+pub struct AlignedHold<'a> {
+    hold: &'a dyn Hold<'a>,
+    align: usize,
+}
+
+impl<'a> AlignedHold<'a> {
+    /// Returns a `Hold` that allocates from `hold` with at least `align`
+    /// byte alignment.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `align` is not a power of two.
+    pub fn new(hold: &'a dyn Hold<'a>, align: usize) -> AlignedHold<'a> {
+        if !align.is_power_of_two() {
+            panic!("alignment not a power of two");
+        }
+        AlignedHold {
+            hold: hold,
+            align: align,
+        }
+    }
+
+    /// Returns the minimum alignment of every block allocated by this `Hold`.
+    #[inline]
+    pub fn align(&self) -> usize {
+        self.align
+    }
+}
+
+unsafe impl<'a> Hold<'a> for AlignedHold<'a> {
+    unsafe fn alloc(&self, layout: Layout) -> Result<Block<'a>, HoldError> {
+        AlignedBlock::alloc_in(self.hold, self.align, layout, 0, ptr::null())
+    }
+
+    unsafe fn alloc_near(&self, layout: Layout, hint: *const u8) -> Result<Block<'a>, HoldError> {
+        AlignedBlock::alloc_in(self.hold, self.align, layout, 0, hint)
+    }
+
+    unsafe fn alloc_offset(&self, layout: Layout, offset: usize) -> Result<Block<'a>, HoldError> {
+        AlignedBlock::alloc_in(self.hold, self.align, layout, offset, ptr::null())
+    }
+
+    unsafe fn dealloc(&self, block: Block<'a>) -> usize {
+        (*AlignedBlock::from_ptr(block.as_ptr())).dealloc(block)
+    }
+
+    unsafe fn resize(&self, block: Block<'a>, layout: Layout) -> Result<Block<'a>, HoldError> {
+        (*AlignedBlock::from_ptr(block.as_ptr())).resize(block, layout)
+    }
+
+    fn min_alignment(&self) -> usize {
+        cmp::max(self.hold.min_alignment(), self.align)
+    }
+
+    fn allocation_granularity(&self) -> usize {
+        self.hold.allocation_granularity()
+    }
+
+    #[cfg(feature = "failpoints")]
+    fn fail_after(&self, n: usize) -> Result<(), HoldError> {
+        self.hold.fail_after(n)
+    }
+}
+
+/// Header at the start of each underlying block allocated by an `AlignedHold`,
+/// followed by the `AllocTag` of the aligned block it contains. Tags the
+/// aligned block, which deallocs and resizes through the header, and
+/// reallocs with the same alignment and resident offset.
+#[repr(C)]
+struct AlignedBlock<'a> {
+    /// Reified `Hold` vtable of the header.
+    base: Reified<Hold<'a>>,
+    /// Hold that allocated the underlying block.
+    hold: &'a dyn Hold<'a>,
+    /// Alignment of the resident in the aligned block.
+    align: usize,
+    /// Offset of the aligned resident from the start of the aligned block.
+    offset: usize,
+    /// Number of bytes preceding the aligned block in the underlying block.
+    prefix: usize,
+    /// Size of the underlying block.
+    size: Cell<usize>,
+}
+
+impl<'a> AlignedBlock<'a> {
+    /// Allocates from `hold`, near `hint` if non-null, an underlying block
+    /// containing an `AlignedBlock` header, followed by a block sized to
+    /// `layout`, whose address `offset` bytes in is aligned to at least
+    /// `align` bytes.
+    unsafe fn alloc_in(hold: &'a dyn Hold<'a>, align: usize, layout: Layout,
+                       offset: usize, hint: *const u8) -> Result<Block<'a>, HoldError> {
+        // Check that the offset preserves the alignment of the block.
+        if offset & layout.align().wrapping_sub(1) != 0 {
+            return Err(HoldError::Misaligned);
+        }
+        // Get the alignment of both the resident, and the underlying block.
+        let align = cmp::max(cmp::max(align, layout.align()), mem::align_of::<AlignedBlock<'a>>());
+        // Get the size of the header and the tag of the aligned block.
+        let header_size = mem::size_of::<AlignedBlock<'a>>().wrapping_add(mem::size_of::<AllocTag<'a>>());
+        // Get the offset of the resident in the underlying block by rounding up
+        // the header size plus the resident offset to the alignment.
+        let resident_offset = match header_size.checked_add(offset).and_then(|size| size.checked_add(align - 1)) {
+            Some(size) => size & !(align - 1),
+            None => return Err(HoldError::Oversized),
+        };
+        // Get the offset of the aligned block in the underlying block.
+        let prefix = resident_offset.wrapping_sub(offset);
+        // Compute the layout of the underlying block.
+        let size = match prefix.checked_add(layout.size()) {
+            Some(size) => size,
+            None => return Err(HoldError::Oversized),
+        };
+        let outer_layout = Layout::from_size_align(size, align)?;
+        // Allocate the underlying block, bailing on failure.
+        let outer = if hint.is_null() {
+            hold.alloc(outer_layout)?
+        } else {
+            hold.alloc_near(outer_layout, hint)?
+        };
+        // Get a pointer to the header at the start of the underlying block.
+        let header = outer.as_ptr() as *mut AlignedBlock<'a>;
+        // Initialize the header.
+        ptr::write(&mut (*header).hold, hold);
+        ptr::write(&mut (*header).align, align);
+        ptr::write(&mut (*header).offset, offset);
+        ptr::write(&mut (*header).prefix, prefix);
+        ptr::write(&mut (*header).size, Cell::new(outer.size()));
+        // Initialize the hold base with the concrete type of the header.
+        AlignedBlock::deify(&mut *header);
+        // Get a pointer to the aligned block.
+        let data = outer.as_ptr().wrapping_add(prefix);
+        // Tag the aligned block with the header.
+        ptr::write(AllocTag::from_ptr(data), AllocTag::new(&(*header).base));
+        // Return the aligned block.
+        Ok(Block::from_raw_parts(data, outer.size().wrapping_sub(prefix)))
+    }
+
+    /// Returns a pointer to the header of an aligned block allocated by an
+    /// `AlignedHold` at address `data`.
+    #[inline]
+    unsafe fn from_ptr(data: *mut u8) -> *const AlignedBlock<'a> {
+        (*AllocTag::from_ptr(data)).base.load(Relaxed) as *const AlignedBlock<'a>
+    }
+
+    /// Returns the underlying block containing this header.
+    #[inline]
+    unsafe fn outer(&self) -> Block<'a> {
+        Block::from_raw_parts(self as *const AlignedBlock<'a> as *mut u8, self.size.get())
+    }
+}
+
+unsafe impl<'a> Hold<'a> for AlignedBlock<'a> {
+    unsafe fn alloc(&self, layout: Layout) -> Result<Block<'a>, HoldError> {
+        AlignedBlock::alloc_in(self.hold, self.align, layout, 0, ptr::null())
+    }
+
+    unsafe fn alloc_near(&self, layout: Layout, hint: *const u8) -> Result<Block<'a>, HoldError> {
+        AlignedBlock::alloc_in(self.hold, self.align, layout, 0, hint)
+    }
+
+    unsafe fn alloc_offset(&self, layout: Layout, offset: usize) -> Result<Block<'a>, HoldError> {
+        AlignedBlock::alloc_in(self.hold, self.align, layout, offset, ptr::null())
+    }
+
+    unsafe fn dealloc(&self, _block: Block<'a>) -> usize {
+        // Copy the underlying hold out of the header before freeing it.
+        let hold = self.hold;
+        // Deallocate the underlying block, header and all.
+        hold.dealloc(self.outer())
+    }
+
+    unsafe fn resize(&self, block: Block<'a>, layout: Layout) -> Result<Block<'a>, HoldError> {
+        // Check that the block satisfies the new alignment.
+        if layout.align() > self.align || (block.as_ptr() as usize) & layout.align().wrapping_sub(1) != 0 {
+            return Err(HoldError::Misaligned);
+        }
+        // Compute the layout of the resized underlying block.
+        let size = match self.prefix.checked_add(layout.size()) {
+            Some(size) => size,
+            None => return Err(HoldError::Oversized),
+        };
+        let outer_layout = Layout::from_size_align(size, self.align)?;
+        // Resize the underlying block in place, bailing on failure.
+        let outer = self.hold.resize(self.outer(), outer_layout)?;
+        // Record the new size of the underlying block.
+        self.size.set(outer.size());
+        // Return the resized aligned block.
+        Ok(Block::from_raw_parts(block.as_ptr(), outer.size().wrapping_sub(self.prefix)))
+    }
+
+    unsafe fn realloc(&self, block: Block<'a>, layout: Layout) -> Result<Block<'a>, HoldError> {
+        match self.resize(block, layout) {
+            ok @ Ok(_) => ok,
+            // Move the block, keeping its resident aligned at the same offset.
+            Err(_) => match self.alloc_offset(layout, self.offset) {
+                Ok(new_block) => {
+                    // Copy the contents of the moved block.
+                    ptr::copy_nonoverlapping(block.as_ptr(), new_block.as_ptr(), cmp::min(block.size(), new_block.size()));
+                    self.dealloc(block);
+                    Ok(new_block)
+                },
+                err @ Err(_) => err,
+            },
+        }
+    }
+
+    fn min_alignment(&self) -> usize {
+        cmp::max(self.hold.min_alignment(), self.align)
+    }
+
+    fn allocation_granularity(&self) -> usize {
+        self.hold.allocation_granularity()
+    }
+
+    #[cfg(feature = "failpoints")]
+    fn fail_after(&self, n: usize) -> Result<(), HoldError> {
+        self.hold.fail_after(n)
+    }
+}
+
+impl<'a> Reify<'a, Hold<'a> + 'a> for AlignedBlock<'a> {
+    #[inline]
+    unsafe fn deify(object: &mut (Hold<'a> + 'a)) {
+        Reified::<Hold<'a>>::deify(mem::transmute(object));
+    }
+
+    #[inline]
+    unsafe fn reify(base: &'a Reified<Hold<'a> + 'a>) -> &'a (Hold<'a> + 'a) {
+        mem::transmute(base.reify())
+    }
+}
+
+/// Allocation failpoint armed on the current thread: the address of the
+/// armed `Hold`, or zero if disarmed, and the number of allocations by that
+/// `Hold` still to succeed before one fails.
+#[cfg(feature = "failpoints")]
+#[thread_local]
+static FAILPOINT: core::cell::Cell<(usize, usize)> = core::cell::Cell::new((0, 0));
+
+/// Arms the current thread's failpoint to fail the allocation that follows
+/// the next `n` allocations by the `Hold` at address `hold`.
+#[cfg(feature = "failpoints")]
+pub(crate) fn arm_failpoint(hold: *const u8, n: usize) {
+    FAILPOINT.set((hold as usize, n));
+}
+
+/// Disarms the current thread's failpoint; returns its prior state, for
+/// restoring with `restore_failpoint`.
+#[cfg(feature = "failpoints")]
+pub(crate) fn disarm_failpoint() -> (usize, usize) {
+    FAILPOINT.replace((0, 0))
+}
+
+#[cfg(feature = "failpoints")]
+pub(crate) fn restore_failpoint(state: (usize, usize)) {
+    FAILPOINT.set(state);
+}
+
+/// Counts an allocation by the allocator at address `alloc` against the
+/// current thread's failpoint. Returns `true`, and disarms the failpoint,
+/// if the allocation should fail.
+#[cfg(feature = "failpoints")]
+pub(crate) fn trip_failpoint(alloc: *const u8) -> bool {
+    let (armed, n) = FAILPOINT.get();
+    if armed == 0 || armed != alloc as usize {
+        false
+    } else if n != 0 {
+        FAILPOINT.set((armed, n.wrapping_sub(1)));
+        false
+    } else {
+        FAILPOINT.set((0, 0));
+        true
+    }
+}
+
+/// Counts an allocation by the `Hold` at address `hold` against the current
+/// thread's failpoint. Returns `HoldError::OutOfMemory` if the allocation
+/// should fail.
+#[cfg(feature = "failpoints")]
+pub(crate) fn check_failpoint(hold: *const u8) -> Result<(), HoldError> {
+    if trip_failpoint(hold) {
+        Err(HoldError::OutOfMemory)
+    } else {
+        Ok(())
+    }
+}
+
+/// Tally of the allocator operations performed by the current thread, for
+/// testing how code allocates. Available only with the `trace` feature.
+#[cfg(feature = "trace")]
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct HoldTrace {
+    /// Number of block allocations.
+    pub allocs: usize,
+    /// Number of block deallocations.
+    pub deallocs: usize,
+    /// Number of in place block resizes.
+    pub resizes: usize,
+    /// Number of the above operations that atomically updated allocator
+    /// state shared with other threads.
+    pub atomic_ops: usize,
+    /// Number of bytes requested by successful block allocations, and by
+    /// the new layouts of successful resizes.
+    pub alloc_bytes: usize,
+    /// Number of bytes in deallocated blocks, and in the old blocks of
+    /// successful resizes, as sized by the caller.
+    pub dealloc_bytes: usize,
+}
+
+#[cfg(feature = "trace")]
+impl HoldTrace {
+    /// Returns the tally of the allocator operations performed by the
+    /// current thread so far.
+    pub fn get() -> HoldTrace {
+        HOLD_TRACE.get()
+    }
+
+    /// Returns the tally of the allocator operations performed by the
+    /// current thread since `start` was returned by `HoldTrace::get`.
+    pub fn since(start: HoldTrace) -> HoldTrace {
+        let trace = HOLD_TRACE.get();
+        HoldTrace {
+            allocs: trace.allocs.wrapping_sub(start.allocs),
+            deallocs: trace.deallocs.wrapping_sub(start.deallocs),
+            resizes: trace.resizes.wrapping_sub(start.resizes),
+            atomic_ops: trace.atomic_ops.wrapping_sub(start.atomic_ops),
+            alloc_bytes: trace.alloc_bytes.wrapping_sub(start.alloc_bytes),
+            dealloc_bytes: trace.dealloc_bytes.wrapping_sub(start.dealloc_bytes),
+        }
+    }
+}
+
+/// Allocator operation tallied by `trace`.
+#[cfg(feature = "trace")]
+#[derive(Clone, Copy)]
+pub(crate) enum TraceOp {
+    Alloc,
+    Dealloc,
+    Resize,
+}
+
+#[cfg(feature = "trace")]
+#[thread_local]
+static HOLD_TRACE: core::cell::Cell<HoldTrace> = core::cell::Cell::new(HoldTrace {
+    allocs: 0,
+    deallocs: 0,
+    resizes: 0,
+    atomic_ops: 0,
+    alloc_bytes: 0,
+    dealloc_bytes: 0,
+});
+
+/// Tallies an allocator operation performed by the current thread; `atomic`
+/// indicates whether the operation updated shared allocator state.
+#[cfg(feature = "trace")]
+pub(crate) fn trace(op: TraceOp, atomic: bool) {
+    let mut trace = HOLD_TRACE.get();
+    match op {
+        TraceOp::Alloc => trace.allocs = trace.allocs.wrapping_add(1),
+        TraceOp::Dealloc => trace.deallocs = trace.deallocs.wrapping_add(1),
+        TraceOp::Resize => trace.resizes = trace.resizes.wrapping_add(1),
+    }
+    if atomic {
+        trace.atomic_ops = trace.atomic_ops.wrapping_add(1);
+    }
+    HOLD_TRACE.set(trace);
+}
+
+/// Tallies the bytes allocated and deallocated by a successful allocator
+/// operation performed by the current thread.
+#[cfg(feature = "trace")]
+pub(crate) fn trace_bytes(alloc_bytes: usize, dealloc_bytes: usize) {
+    let mut trace = HOLD_TRACE.get();
+    trace.alloc_bytes = trace.alloc_bytes.wrapping_add(alloc_bytes);
+    trace.dealloc_bytes = trace.dealloc_bytes.wrapping_add(dealloc_bytes);
+    HOLD_TRACE.set(trace);
 }
 
 #[thread_local]
@@ -177,6 +701,32 @@ impl<'a> LocalHold<'a> {
             LocalHold { scope: scope }
         }
     }
+
+    /// Leases a thread-pinned `LocalPack` of `size` bytes from `hold`, and
+    /// calls `f` with the pack installed as the current thread local `Hold`.
+    /// Allocations in the scope bump-allocate from the pack without atomic
+    /// operations. The pack, and every lease allocated in it, is bound to
+    /// `f`; the pack's memory block returns to `hold` when `f` returns.
+    /// See `LocalPack::try_scope`.
+    pub fn try_pinned<F, R>(hold: &'a dyn Hold<'a>, size: usize, f: F) -> Result<R, HoldError>
+        where F: for<'p> FnOnce(&'p LocalPack<'p>) -> R
+    {
+        LocalPack::try_scope(hold, size, |pack| {
+            let mut scope = HoldScope::new(pack);
+            // The local hold frame pops before the pack's scope ends.
+            let _local = unsafe { LocalHold::enter(&mut *(&mut scope as *mut HoldScope)) };
+            f(pack)
+        })
+    }
+
+    /// Leases a thread-pinned `LocalPack` of `size` bytes from `hold`, and
+    /// calls `f` with the pack installed as the current thread local `Hold`;
+    /// panics if the pack can't be leased.
+    pub fn pinned<F, R>(hold: &'a dyn Hold<'a>, size: usize, f: F) -> R
+        where F: for<'p> FnOnce(&'p LocalPack<'p>) -> R
+    {
+        LocalHold::try_pinned(hold, size, f).unwrap()
+    }
 }
 
 unsafe impl<'a> Hold<'a> for LocalHold<'a> {
@@ -184,6 +734,14 @@ unsafe impl<'a> Hold<'a> for LocalHold<'a> {
         self.scope.alloc(layout)
     }
 
+    unsafe fn alloc_near(&self, layout: Layout, hint: *const u8) -> Result<Block<'a>, HoldError> {
+        self.scope.alloc_near(layout, hint)
+    }
+
+    unsafe fn alloc_offset(&self, layout: Layout, offset: usize) -> Result<Block<'a>, HoldError> {
+        self.scope.alloc_offset(layout, offset)
+    }
+
     unsafe fn dealloc(&self, _block: Block<'a>) -> usize {
         // Never directly deallocates anything; deallocations always dispatch
         // to the underlying hold.
@@ -195,6 +753,18 @@ unsafe impl<'a> Hold<'a> for LocalHold<'a> {
         // underlying hold.
         unimplemented!();
     }
+
+    fn min_alignment(&self) -> usize {
+        self.scope.min_alignment()
+    }
+
+    fn allocation_granularity(&self) -> usize {
+        self.scope.allocation_granularity()
+    }
+
+    fn defragment(&self, live: &mut [&mut dyn Restow<'a>]) -> Result<(), HoldError> {
+        self.scope.defragment(live)
+    }
 }
 
 impl<'a> Drop for LocalHold<'a> {
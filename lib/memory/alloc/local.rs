@@ -0,0 +1,380 @@
+use core::cell::Cell;
+use core::cmp;
+use core::marker::PhantomPinned;
+use core::mem;
+use core::ptr;
+use core::u32;
+use tg_core::reify::{Reified, Reify};
+use crate::block::{Block, Layout};
+use crate::alloc::{AllocTag, Hold, HoldError};
+#[cfg(feature = "trace")]
+use crate::alloc::hold::{trace, trace_bytes, TraceOp};
+
+/// Thread-pinned linear allocator for a memory block leased from a parent
+/// `Hold`.
+///
+/// A local pack allocates space by advancing a pointer into its memory
+/// block, like a `Pack`, but maintains its mark and accounting counters
+/// without atomic operations. A `LocalPack` only exists for the duration of
+/// a `LocalPack::scope` closure, which bounds the lifetimes of all leases
+/// allocated in the pack. A `LocalPack` must only ever be accessed by the
+/// thread that created it; leases that move to another thread within the
+/// scope panic if they deallocate or resize there, rather than race on the
+/// pack's counters. Allocations made through a `LocalPack` don't touch the
+/// parent hold; the parent only accounts for the single block leased by
+/// the pack, until the pack's scope returns the block.
+pub struct LocalPack<'a> {
+    /// Polymorphic hold type.
+    base: Reified<Hold<'a>>,
+    /// Address of the `THREAD_MARKER` of the thread that owns this pack.
+    owner: usize,
+    /// Total number of bytes in the memory block, including pack header.
+    size: Cell<u32>,
+    /// Offset from the base pack address of the next free byte in the memory block.
+    mark: Cell<u32>,
+    /// Number of live allocations in this pack.
+    live: Cell<u32>,
+    /// Number of currently allocated bytes in this pack.
+    used: Cell<u32>,
+    /// Tag shared by all zero-sized allocations in this pack.
+    zero: AllocTag<'a>,
+    /// Pin to the base address of the memory block.
+    #[allow(dead_code)]
+    pinned: PhantomPinned,
+}
+
+impl<'a> !Send for LocalPack<'a> {
+}
+
+impl<'a> !Sync for LocalPack<'a> {
+}
+
+/// Thread local byte whose address identifies the current thread.
+#[thread_local]
+static THREAD_MARKER: u8 = 0;
+
+/// Returns an address that uniquely identifies the current thread.
+#[inline]
+fn current_thread() -> usize {
+    &THREAD_MARKER as *const u8 as usize
+}
+
+/// Returns a `LocalPack`'s memory block to its parent hold when the pack's
+/// scope unwinds.
+struct LocalPackLease<'a> {
+    pack: &'a LocalPack<'a>,
+}
+
+impl<'a> Drop for LocalPackLease<'a> {
+    fn drop(&mut self) {
+        // Leak the pack if any allocations remain live, which would otherwise
+        // dangle once the parent reuses the pack's memory block.
+        if self.pack.live.get() == 0 {
+            unsafe { self.pack.release(); }
+        }
+    }
+}
+
+impl<'a> LocalPack<'a> {
+    /// Leases a memory block of `size` bytes, including the pack header,
+    /// from the `parent` hold, constructs a `LocalPack` in it, and calls `f`
+    /// with the pack. Leases allocated in the pack can't outlive `f`. When
+    /// `f` returns, the pack's memory block returns to `parent`, leaving the
+    /// parent's accounting exactly as it was before the pack was leased.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any allocations remain live in the pack when `f` returns,
+    /// which can only happen if leases allocated in the pack were leaked;
+    /// the pack's memory block then remains leased from `parent`.
+    pub fn try_scope<F, R>(parent: &'a dyn Hold<'a>, size: usize, f: F) -> Result<R, HoldError>
+        where F: for<'p> FnOnce(&'p LocalPack<'p>) -> R
+    {
+        let pack = LocalPack::try_hold_new(parent, size)?;
+        // Release the pack if `f` unwinds.
+        let lease = LocalPackLease { pack: pack };
+        let result = f(pack);
+        if pack.live.get() != 0 {
+            // Leak the pack's memory block, rather than free live allocations.
+            mem::forget(lease);
+            panic!("leaky local pack");
+        }
+        mem::drop(lease);
+        Ok(result)
+    }
+
+    /// Leases a memory block of `size` bytes from the `parent` hold, and
+    /// calls `f` with a `LocalPack` in it; panics if the block can't be
+    /// leased. See `LocalPack::try_scope`.
+    pub fn scope<F, R>(parent: &'a dyn Hold<'a>, size: usize, f: F) -> R
+        where F: for<'p> FnOnce(&'p LocalPack<'p>) -> R
+    {
+        LocalPack::try_scope(parent, size, f).unwrap()
+    }
+
+    fn try_hold_new(parent: &'a dyn Hold<'a>, size: usize) -> Result<&'a LocalPack<'a>, HoldError> {
+        // Get the alignment of the allocation tag.
+        let tag_align = mem::align_of::<AllocTag>();
+        // Round the header size up to the alignment of the first allocation tag.
+        let header_size = mem::size_of::<LocalPack<'a>>().wrapping_add(tag_align).wrapping_sub(1) & !tag_align.wrapping_sub(1);
+        if size < header_size {
+            return Err(HoldError::Unsupported("local pack too small"));
+        }
+        if size > u32::MAX as usize {
+            return Err(HoldError::Oversized);
+        }
+        unsafe {
+            // Lease a memory block from the parent hold, bailing on failure.
+            let layout = Layout::from_size_align(size, mem::align_of::<LocalPack<'a>>())?;
+            let block = parent.alloc(layout)?;
+            // Get the address of the local pack header.
+            let pack = block.as_ptr() as *mut LocalPack<'a>;
+            // Initialize the local pack header to the beginning of the memory block.
+            ptr::write(pack, LocalPack {
+                base: Reified::uninitialized(),
+                owner: current_thread(),
+                size: Cell::new(block.size() as u32),
+                mark: Cell::new(header_size as u32),
+                live: Cell::new(0),
+                used: Cell::new(0),
+                zero: AllocTag::null(),
+                pinned: PhantomPinned,
+            });
+            // Initialize the zero-sized allocation tag.
+            (*pack).zero.init(&(*pack).base);
+            // Initialize the hold base with the concrete type of the local pack.
+            LocalPack::deify(&mut *pack);
+            // Return a reference to the local pack.
+            Ok(&*pack)
+        }
+    }
+
+    /// Panics if the current thread doesn't own this `LocalPack`.
+    #[inline]
+    fn check_owner(&self) {
+        if self.owner != current_thread() {
+            panic!("local pack accessed from a foreign thread");
+        }
+    }
+
+    /// Returns the zero-sized block for this `LocalPack`.
+    #[inline]
+    fn empty(&self) -> Block<'a> {
+        // Get the address of the zero-sized allocation tag.
+        let tag_addr = &self.zero as *const AllocTag<'a> as usize;
+        // Get the address of the zero-sized block immediately following the tag.
+        let zero_addr = tag_addr.wrapping_add(mem::size_of::<AllocTag<'a>>());
+        // Return the zero-sized block for this local pack.
+        unsafe { Block::from_raw_parts(zero_addr as *mut u8, 0) }
+    }
+
+    /// Returns the total number of bytes in the memory block, including the pack header.
+    #[inline]
+    pub fn size(&self) -> usize {
+        self.size.get() as usize
+    }
+
+    /// Returns the number of free bytes available for allocation in this `LocalPack`.
+    #[inline]
+    pub fn free(&self) -> usize {
+        self.size.get().wrapping_sub(self.mark.get()) as usize
+    }
+
+    /// Return the number of live allocations in this `LocalPack`.
+    #[inline]
+    pub fn live(&self) -> usize {
+        self.live.get() as usize
+    }
+
+    /// Returns the number of bytes currently allocated in this `LocalPack`.
+    #[inline]
+    pub fn used(&self) -> usize {
+        self.used.get() as usize
+    }
+
+    /// Returns this pack's memory block to its parent hold.
+    ///
+    /// # Safety
+    ///
+    /// No allocations may be live in the pack, and the pack must not be used
+    /// after it has been released.
+    unsafe fn release(&self) {
+        // Get the memory block leased from the parent.
+        let block = Block::from_raw_parts(self as *const LocalPack<'a> as *mut u8, self.size());
+        // Return the whole block to the parent.
+        AllocTag::from_ptr(block.as_ptr()).dealloc(block);
+    }
+}
+
+unsafe impl<'a> Hold<'a> for LocalPack<'a> {
+    unsafe fn alloc(&self, layout: Layout) -> Result<Block<'a>, HoldError> {
+        // Reallocating leases can move to another thread within the pack's scope.
+        self.check_owner();
+        #[cfg(feature = "trace")]
+        trace(TraceOp::Alloc, false);
+        // Check if the layout represents a zero-sized type.
+        if layout.size() == 0 {
+            // Increment the live allocation count.
+            self.live.set(self.live.get().wrapping_add(1));
+            // Return the empty block.
+            return Ok(self.empty());
+        }
+        // Get the alignment of the allocation tag.
+        let tag_align = mem::align_of::<AllocTag>();
+        // Get the size of the allocation tag.
+        let tag_size = mem::size_of::<AllocTag>();
+        // Align the block for the preceding allocation tag.
+        let align = cmp::max(layout.align(), tag_align);
+        // Round the block size up to the alignment of the next allocation tag.
+        let size = layout.size().wrapping_add(tag_align).wrapping_sub(1) & !tag_align.wrapping_sub(1);
+        // Get the base address of the memory block.
+        let base_addr = self as *const LocalPack<'a> as usize;
+        // Compute the block start address by adding the mark offset to the base address.
+        let start_addr = base_addr.wrapping_add(self.mark.get() as usize);
+        // Make room for the allocation tag that will directly precede the allocated block.
+        let block_addr = start_addr.wrapping_add(tag_size);
+        // Round up to the alignment required by the block.
+        let block_addr = block_addr.wrapping_add(align).wrapping_sub(1) & !align.wrapping_sub(1);
+        // Compute the end address of the block; bail on overflow.
+        let end_addr = match block_addr.checked_add(size) {
+            Some(addr) => addr,
+            None => return Err(HoldError::OutOfMemory),
+        };
+        // Compute the new mark offset by subtracting the base pointer.
+        let new_mark = end_addr.wrapping_sub(base_addr);
+        // Bail if the block would overflow the memory block.
+        if new_mark > self.size.get() as usize {
+            return Err(HoldError::OutOfMemory);
+        }
+        // Advance the mark offset; no other thread can observe this pack.
+        self.mark.set(new_mark as u32);
+        // Subtract the tag size from the block address.
+        let tag_addr = block_addr.wrapping_sub(tag_size) as *mut AllocTag<'a>;
+        // Initialize the allocation tag.
+        ptr::write(tag_addr, AllocTag::new(&self.base));
+        // Increment the live allocation count.
+        self.live.set(self.live.get().wrapping_add(1));
+        // Increase the allocated byte count.
+        self.used.set(self.used.get().wrapping_add(size as u32));
+        #[cfg(feature = "trace")]
+        trace_bytes(layout.size(), 0);
+        // Return the allocated block.
+        Ok(Block::from_raw_parts(block_addr as *mut u8, size))
+    }
+
+    unsafe fn dealloc(&self, block: Block<'a>) -> usize {
+        // Leases can move to another thread within the pack's scope.
+        self.check_owner();
+        #[cfg(feature = "trace")]
+        trace(TraceOp::Dealloc, false);
+        #[cfg(feature = "trace")]
+        trace_bytes(0, block.size());
+        // Get the alignment of the allocation tag.
+        let tag_align = mem::align_of::<AllocTag>();
+        // Get the size of the allocation tag.
+        let tag_size = mem::size_of::<AllocTag>();
+        // Compute the size of the allocated block.
+        let size = block.size().wrapping_add(tag_align).wrapping_sub(1) & !tag_align.wrapping_sub(1);
+        // Check if the block has non-zero size.
+        if size != 0 {
+            // Get the base address of the memory block.
+            let base_addr = self as *const LocalPack<'a> as usize;
+            // Compute the offset of the end of the block.
+            let end_mark = (block.as_ptr() as usize).wrapping_add(size).wrapping_sub(base_addr) as u32;
+            // Rewind the mark offset if it still points to the end of the block,
+            // i.e. pop the stack, if we can.
+            if self.mark.get() == end_mark {
+                let tag_mark = (block.as_ptr() as usize).wrapping_sub(tag_size).wrapping_sub(base_addr) as u32;
+                self.mark.set(tag_mark);
+            }
+        }
+        // Decrease the allocated byte count.
+        self.used.set(self.used.get().wrapping_sub(size as u32));
+        // Decrement the live allocation count.
+        self.live.set(self.live.get().wrapping_sub(1));
+        // Return the number of freed bytes.
+        size
+    }
+
+    unsafe fn resize(&self, block: Block<'a>, layout: Layout) -> Result<Block<'a>, HoldError> {
+        // Leases can move to another thread within the pack's scope.
+        self.check_owner();
+        #[cfg(feature = "trace")]
+        trace(TraceOp::Resize, false);
+        let tag_align = mem::align_of::<AllocTag>();
+        // Get the address of the current block.
+        let block_addr = block.as_ptr() as usize;
+        // Check if the alignment of the current block is suitable for the proposed new block.
+        if block_addr % layout.align() != 0 {
+            // Misaligned.
+            return Err(HoldError::Misaligned);
+        }
+        // Round the old block size up to the alignment of the next allocation tag.
+        let old_size = block.size().wrapping_add(tag_align).wrapping_sub(1) & !tag_align.wrapping_sub(1);
+        // Check if the old block has zero size.
+        if old_size == 0 {
+            // Can't resize zero size blocks.
+            return Err(HoldError::Unsupported("resize from zero"));
+        }
+        // Round the new block size up to the alignment of the next allocation tag.
+        let new_size = layout.size().wrapping_add(tag_align).wrapping_sub(1) & !tag_align.wrapping_sub(1);
+        // Check if the new block has zero size.
+        if new_size == 0 {
+            // Can't resize to zero.
+            return Err(HoldError::Unsupported("resize to zero"));
+        } else if new_size == old_size {
+            #[cfg(feature = "trace")]
+            trace_bytes(layout.size(), block.size());
+            // Unchanged size. Return the original block.
+            return Ok(block);
+        }
+        // Get the base address of the memory block.
+        let base_addr = self as *const LocalPack<'a> as usize;
+        // Compute the end offset of the old block.
+        let old_end_mark = block_addr.wrapping_add(old_size).wrapping_sub(base_addr);
+        // Can only resize the most recently allocated block.
+        if self.mark.get() as usize != old_end_mark {
+            return Err(HoldError::Oversized);
+        }
+        // Compute the end offset of the proposed new block; bail on overflow.
+        let new_end_mark = match block_addr.checked_add(new_size) {
+            Some(addr) => addr.wrapping_sub(base_addr),
+            None => return Err(HoldError::Oversized),
+        };
+        // Bail if the resized block would overflow the memory block.
+        if new_end_mark > self.size.get() as usize {
+            return Err(HoldError::OutOfMemory);
+        }
+        // Move the mark offset to the end of the resized block.
+        self.mark.set(new_end_mark as u32);
+        // Adjust the allocated byte count by the size difference.
+        self.used.set(self.used.get().wrapping_sub(old_size as u32).wrapping_add(new_size as u32));
+        #[cfg(feature = "trace")]
+        trace_bytes(layout.size(), block.size());
+        // Return the resized block.
+        Ok(Block::from_raw_parts(block.as_ptr(), new_size))
+    }
+
+    #[inline]
+    fn min_alignment(&self) -> usize {
+        mem::align_of::<AllocTag>()
+    }
+
+    #[inline]
+    fn allocation_granularity(&self) -> usize {
+        // Block sizes round up to the alignment of the next allocation tag.
+        mem::align_of::<AllocTag>()
+    }
+}
+
+impl<'a> Reify<'a, Hold<'a> + 'a> for LocalPack<'a> {
+    #[inline]
+    unsafe fn deify(object: &mut (Hold<'a> + 'a)) {
+        Reified::<Hold<'a>>::deify(mem::transmute(object));
+    }
+
+    #[inline]
+    unsafe fn reify(base: &'a Reified<Hold<'a> + 'a>) -> &'a (Hold<'a> + 'a) {
+        mem::transmute(base.reify())
+    }
+}
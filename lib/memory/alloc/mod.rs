@@ -11,15 +11,20 @@ mod addr;
 mod slab;
 mod pack;
 mod pool;
+mod local;
 
 pub use self::heap::{Heap, HeapError};
-pub use self::hold::{Hold, HoldScope, LocalHold, Holder, HoldError};
+pub use self::hold::{Hold, HoldScope, LocalHold, Holder, HoldError, AlignedHold};
+#[cfg(feature = "trace")]
+pub use self::hold::HoldTrace;
+pub(crate) use self::hold::HoldNear;
 pub use self::tag::AllocTag;
 
-pub use self::stow::{Stow, StowFrom, StowInto};
+pub use self::stow::{Stow, StowFrom, StowInto, Restow};
 pub use self::clone::{TryClone, CloneIntoHold};
 
 pub use self::addr::{AddrSpace, ExtentList};
 pub use self::slab::Slab;
 pub use self::pack::Pack;
 pub use self::pool::Pool;
+pub use self::local::LocalPack;
@@ -8,6 +8,12 @@ use core::u32;
 use tg_core::reify::{Reified, Reify};
 use crate::block::{Block, Layout};
 use crate::alloc::{AllocTag, Hold, HoldError};
+#[cfg(debug_assertions)]
+use crate::alloc::tag;
+#[cfg(feature = "failpoints")]
+use crate::alloc::hold::{arm_failpoint, check_failpoint, disarm_failpoint, restore_failpoint};
+#[cfg(feature = "trace")]
+use crate::alloc::hold::{trace, trace_bytes, TraceOp};
 
 /// Base linear allocator for a fixed-size memory block.
 ///
@@ -72,6 +78,14 @@ impl<'a> PackBase<'a> {
         self.size.wrapping_sub(self.mark.load(Relaxed)) as usize
     }
 
+    /// Returns `true` if the address `addr` lies within the memory block
+    /// managed by this `PackBase`.
+    #[inline]
+    pub(crate) fn contains(&self, addr: usize) -> bool {
+        let base_addr = self as *const PackBase<'a> as usize;
+        addr >= base_addr && addr.wrapping_sub(base_addr) < self.size as usize
+    }
+
     /// Returns the memory block managed by this `PackBase`.
     #[inline]
     pub(crate) unsafe fn as_block(&mut self) -> Block<'a> {
@@ -131,6 +145,9 @@ impl<'a> PackBase<'a> {
             let tag_addr = block_addr.wrapping_sub(mem::size_of::<AllocTag>()) as *mut AllocTag<'a>;
             // Initialize the allocation tag.
             ptr::write(tag_addr, AllocTag::new(&self.base));
+            // Record the size of the block for debug dealloc checks.
+            #[cfg(debug_assertions)]
+            tag::record_size(block_addr, size);
 
             // Return the allocated block.
             return Ok(Block::from_raw_parts(block_addr as *mut u8, size))
@@ -151,6 +168,9 @@ impl<'a> PackBase<'a> {
             // Get the base address of the memory block.
             let base_addr = self as *const PackBase<'a> as usize;
 
+            // Check that the block has the size it was allocated with.
+            #[cfg(debug_assertions)]
+            tag::check_size(block.as_ptr() as usize, size);
             // Compute the end address of the block.
             let end_addr = (block.as_ptr() as usize).wrapping_add(size);
             // Compute the address of the allocation tag.
@@ -216,7 +236,13 @@ impl<'a> PackBase<'a> {
             return Ok(block);
         };
         // Compute the end offset of the proposed new block by subtracting the base address.
-        let new_end_mark = new_end_addr.wrapping_sub(base_addr) as u32;
+        let new_end_offset = new_end_addr.wrapping_sub(base_addr);
+        // Check if the proposed new block overflows the memory block.
+        if new_end_offset > self.size as usize {
+            // Insufficient space to grow in place.
+            return Err(HoldError::OutOfMemory);
+        }
+        let new_end_mark = new_end_offset as u32;
 
         // Move the mark offset if it still points to the end of the current block.
         // Synchronize to prevent alloc and dealloc from relative to this.
@@ -228,6 +254,9 @@ impl<'a> PackBase<'a> {
             return Err(HoldError::Oversized);
         }
 
+        // Record the new size of the block for debug dealloc checks.
+        #[cfg(debug_assertions)]
+        tag::record_size(block_addr, new_size);
         // Return the resized block.
         return Ok(Block::from_raw_parts(block.as_ptr(), new_size))
     }
@@ -333,6 +362,11 @@ impl<'a> Pack<'a> {
 
 unsafe impl<'a> Hold<'a> for Pack<'a> {
     unsafe fn alloc(&self, layout: Layout) -> Result<Block<'a>, HoldError> {
+        // Fail the allocation if the current thread's failpoint trips.
+        #[cfg(feature = "failpoints")]
+        check_failpoint(self as *const Pack<'a> as *const u8)?;
+        #[cfg(feature = "trace")]
+        trace(TraceOp::Alloc, true);
         // Check if the layout represents a zero-sized type.
         if layout.size() == 0 {
             // Increment the live allocation count.
@@ -346,11 +380,17 @@ unsafe impl<'a> Hold<'a> for Pack<'a> {
         self.live.fetch_add(1, Relaxed);
         // Increase the allocated byte count.
         self.used.fetch_add(block.size() as u32, Relaxed);
+        #[cfg(feature = "trace")]
+        trace_bytes(layout.size(), 0);
         // Return the allocated block.
         Ok(block)
     }
 
     unsafe fn dealloc(&self, block: Block<'a>) -> usize {
+        #[cfg(feature = "trace")]
+        trace(TraceOp::Dealloc, true);
+        #[cfg(feature = "trace")]
+        trace_bytes(0, block.size());
         // Delegate deallocation to the base pack.
         let size = self.base.dealloc(block);
         // Decrease the allocated byte count.
@@ -362,6 +402,11 @@ unsafe impl<'a> Hold<'a> for Pack<'a> {
     }
 
     unsafe fn resize(&self, block: Block<'a>, layout: Layout) -> Result<Block<'a>, HoldError> {
+        // Fail the resize if the current thread's failpoint trips.
+        #[cfg(feature = "failpoints")]
+        check_failpoint(self as *const Pack<'a> as *const u8)?;
+        #[cfg(feature = "trace")]
+        trace(TraceOp::Resize, true);
         // Get the size of the current block.
         let old_size = block.size();
         // Delegate resizing to the base pack.
@@ -377,12 +422,55 @@ unsafe impl<'a> Hold<'a> for Pack<'a> {
                 } else if size_diff < 0 {
                     self.used.fetch_sub(-size_diff as u32, Relaxed);
                 }
+                #[cfg(feature = "trace")]
+                trace_bytes(layout.size(), old_size);
                 // Return the resized block.
                 Ok(block)
             },
             err @ Err(_) => err,
         }
     }
+
+    #[cfg(feature = "failpoints")]
+    unsafe fn realloc(&self, block: Block<'a>, layout: Layout) -> Result<Block<'a>, HoldError> {
+        // Fail the reallocation if the current thread's failpoint trips.
+        check_failpoint(self as *const Pack<'a> as *const u8)?;
+        // Disarm the failpoint, so that the reallocation counts as a single
+        // allocation, whether it resizes in place, or moves.
+        let failpoint = disarm_failpoint();
+        let result = match self.resize(block, layout) {
+            ok @ Ok(_) => ok,
+            Err(_) => match self.alloc(layout) {
+                Ok(new_block) => {
+                    // Copy the contents of the moved block.
+                    ptr::copy_nonoverlapping(block.as_ptr(), new_block.as_ptr(), cmp::min(block.size(), new_block.size()));
+                    self.dealloc(block);
+                    Ok(new_block)
+                },
+                err @ Err(_) => err,
+            },
+        };
+        // Rearm the failpoint.
+        restore_failpoint(failpoint);
+        result
+    }
+
+    #[inline]
+    fn min_alignment(&self) -> usize {
+        mem::align_of::<AllocTag>()
+    }
+
+    #[inline]
+    fn allocation_granularity(&self) -> usize {
+        // Block sizes round up to the alignment of the next allocation tag.
+        mem::align_of::<AllocTag>()
+    }
+
+    #[cfg(feature = "failpoints")]
+    fn fail_after(&self, n: usize) -> Result<(), HoldError> {
+        arm_failpoint(self as *const Pack<'a> as *const u8, n);
+        Ok(())
+    }
 }
 
 impl<'a> Reify<'a, Hold<'a> + 'a> for Pack<'a> {
@@ -1,18 +1,25 @@
 use core::mem;
 use core::ptr;
-use core::sync::atomic::{AtomicPtr, AtomicUsize};
-use core::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+#[cfg(feature = "failpoints")]
+use core::cmp;
+use core::sync::atomic::{self, AtomicBool, AtomicPtr, AtomicUsize};
+use core::sync::atomic::Ordering::{Acquire, Relaxed, Release, SeqCst};
 use tg_core::reify::{Reified, Reify};
 use crate::block::{Block, Layout};
-use crate::alloc::{Heap, Hold, HoldError};
+use crate::alloc::{AllocTag, Heap, Hold, HoldError, Restow};
 use crate::alloc::pack::PackBase;
+#[cfg(feature = "failpoints")]
+use crate::alloc::hold::{arm_failpoint, check_failpoint, disarm_failpoint, restore_failpoint};
+#[cfg(feature = "trace")]
+use crate::alloc::hold::{trace, trace_bytes, TraceOp};
 
 /// Linear allocator for a dynamically growable set of memory blocks.
 ///
 /// A `Pool` allocates space in a sequence of linear memory `Pack`s allocated
 /// by a `Heap`. Pools only reclaim space when the most recent allocation drops,
 /// and when the whole pool drops. To free a pool, use `IntoHold` to move live
-/// values to another hold, then drop the pool.
+/// values to another hold, then drop the pool. To reclaim the gaps left by
+/// dropped values, `defragment` the pool's live values into fresh packs.
 pub struct Pool<'a> {
     /// Heap used to allocate memory for the pack list.
     heap: &'a Heap<'a>,
@@ -24,18 +31,37 @@ pub struct Pool<'a> {
     live: AtomicUsize,
     /// Number of currently allocated bytes in the pool.
     used: AtomicUsize,
+    /// Parity of the current pack list walk epoch, which `defragment`
+    /// advances when it detaches the pack list.
+    epoch: AtomicUsize,
+    /// Number of threads walking the pack list in each walk epoch parity.
+    walkers: [AtomicUsize; 2],
+    /// Whether a `defragment` is in progress.
+    defragmenting: AtomicBool,
 }
 
 impl<'a> Pool<'a> {
     /// Returns a new `Pool` that allocates memory from the given `heap`.
     #[inline]
     pub fn new(heap: &'a Heap<'a>) -> Pool<'a> {
+        Pool::with_heap(heap)
+    }
+
+    /// Returns a new `Pool` backed by `heap`. Every pack the pool grows by is
+    /// allocated from `heap`, so pools can be layered explicitly over an
+    /// `AddrSpace`, a chained `Slab`, or any other `Heap`; errors from the
+    /// backing heap propagate as `HoldError`s.
+    #[inline]
+    pub fn with_heap(heap: &'a dyn Heap<'a>) -> Pool<'a> {
         Pool {
             heap: heap,
             head: AtomicPtr::new(ptr::null_mut()),
             size: AtomicUsize::new(0),
             live: AtomicUsize::new(0),
             used: AtomicUsize::new(0),
+            epoch: AtomicUsize::new(0),
+            walkers: [AtomicUsize::new(0), AtomicUsize::new(0)],
+            defragmenting: AtomicBool::new(false),
         }
     }
 
@@ -45,6 +71,12 @@ impl<'a> Pool<'a> {
         self.heap
     }
 
+    /// Returns the `Heap` that backs this `Pool`.
+    #[inline]
+    pub fn backing_heap(&self) -> &dyn Heap<'a> {
+        self.heap
+    }
+
     /// Returns the number of reserved bytes in this `Pool`.
     #[inline]
     pub fn size(&self) -> usize {
@@ -123,10 +155,57 @@ impl<'a> Pool<'a> {
             self.used.fetch_sub(-size_diff as usize, Relaxed);
         }
     }
+
+    /// Registers the current thread as walking the pack list, so that a
+    /// concurrent `defragment` doesn't release any pack the walk can reach.
+    /// Returns the walk epoch to pass to `leave_walk`.
+    #[inline]
+    fn enter_walk(&self) -> usize {
+        loop {
+            // Load the current walk epoch.
+            let epoch = self.epoch.load(SeqCst);
+            // Count the walk in the epoch.
+            self.walkers[epoch & 1].fetch_add(1, SeqCst);
+            // Check that the epoch didn't advance before the walk was counted;
+            // a defragment may already have stopped waiting on the epoch.
+            if self.epoch.load(SeqCst) == epoch {
+                return epoch;
+            }
+            // Uncount the walk and try again in the new epoch.
+            self.walkers[epoch & 1].fetch_sub(1, Release);
+        }
+    }
+
+    /// Unregisters a pack list walk entered in the walk `epoch`.
+    #[inline]
+    fn leave_walk(&self, epoch: usize) {
+        self.walkers[epoch & 1].fetch_sub(1, Release);
+    }
+
+    /// Prepends the pack list running from `first` through `last` to the
+    /// used pack list.
+    unsafe fn push_packs(&self, first: *mut PackList<'a>, last: *mut PackList<'a>) {
+        // Load the current head of the pack list.
+        let mut next = self.head.load(Relaxed);
+        loop {
+            // Set the tail of the pushed pack list to the current pack list.
+            (*last).next.store(next, Relaxed);
+            // Compare and swap the current pack list for the pushed pack list.
+            match self.head.compare_exchange_weak(next, first, Release, Relaxed) {
+                // CAS succeeded.
+                Ok(_) => return,
+                // CAS failed; set the next pointer to the new head of the pack list and try again.
+                Err(pack) => next = pack,
+            }
+        }
+    }
 }
 
-unsafe impl<'a> Hold<'a> for Pool<'a> {
-    unsafe fn alloc(&self, layout: Layout) -> Result<Block<'a>, HoldError> {
+impl<'a> Pool<'a> {
+    /// Allocates a block in the head pack of the pack list, pushing a new
+    /// head pack if the current head is full. The caller must be walking
+    /// the pack list.
+    unsafe fn alloc_head(&self, layout: Layout) -> Result<Block<'a>, HoldError> {
         // Allocated block in the proposed new head of the pack list.
         let mut block = None;
         // Proposed new head of the pack list.
@@ -137,7 +216,7 @@ unsafe impl<'a> Hold<'a> for Pool<'a> {
             // Try to allocate a block in the next pack in the list.
             if !next.is_null() {
                 // Next pack exists; try the allocation.
-                if let Ok(block) = (*next).base.alloc(layout) {
+                if let Ok(block) = (*next).alloc_block(layout) {
                     // Successfully allocated new block.
                     // Check if we previously poposed a new head pack.
                     if !head.is_null() {
@@ -158,7 +237,7 @@ unsafe impl<'a> Hold<'a> for Pool<'a> {
                 if let Ok(pack) = self.alloc_pack(layout) {
                     // Pack allocation succeeded.
                     // Try to pre-allocate a block in the new pack.
-                    if let Ok(new_block) = (*pack).base.alloc(layout) {
+                    if let Ok(new_block) = (*pack).alloc_block(layout) {
                         // Block allocation succeeded.
                         // Save the new pack reference in case the head CAS fails.
                         head = pack;
@@ -199,6 +278,71 @@ unsafe impl<'a> Hold<'a> for Pool<'a> {
         }
     }
 
+    /// Allocates a block in the pack containing the `hint` address, if it has
+    /// room; otherwise allocates a block in the head pack. The caller must
+    /// be walking the pack list.
+    unsafe fn alloc_near_hint(&self, layout: Layout, hint: *const u8) -> Result<Block<'a>, HoldError> {
+        // Get the address of the hint.
+        let hint_addr = hint as usize;
+        // Load the current head of the pack list.
+        let mut next = self.head.load(Relaxed);
+        // Search the pack list for the pack containing the hint address.
+        while !next.is_null() {
+            // Check if the hint address lies within the next pack.
+            if (*next).base.contains(hint_addr) {
+                // Try to allocate the block in the hinted pack.
+                if let Ok(block) = (*next).alloc_block(layout) {
+                    // Account for the allocation.
+                    self.did_alloc(block.size());
+                    // Return the nearby block.
+                    return Ok(block);
+                }
+                // No room left in the hinted pack.
+                break;
+            }
+            // Load the next pack in the list.
+            next = (*next).next.load(Relaxed);
+        }
+        // No nearby space; fall back to a normal allocation.
+        self.alloc_head(layout)
+    }
+}
+
+unsafe impl<'a> Hold<'a> for Pool<'a> {
+    unsafe fn alloc(&self, layout: Layout) -> Result<Block<'a>, HoldError> {
+        // Fail the allocation if the current thread's failpoint trips.
+        #[cfg(feature = "failpoints")]
+        check_failpoint(self as *const Pool<'a> as *const u8)?;
+        #[cfg(feature = "trace")]
+        trace(TraceOp::Alloc, true);
+        // Walk the pack list to allocate the block.
+        let epoch = self.enter_walk();
+        let result = self.alloc_head(layout);
+        self.leave_walk(epoch);
+        #[cfg(feature = "trace")]
+        if result.is_ok() {
+            trace_bytes(layout.size(), 0);
+        }
+        result
+    }
+
+    unsafe fn alloc_near(&self, layout: Layout, hint: *const u8) -> Result<Block<'a>, HoldError> {
+        // Fail the allocation if the current thread's failpoint trips.
+        #[cfg(feature = "failpoints")]
+        check_failpoint(self as *const Pool<'a> as *const u8)?;
+        #[cfg(feature = "trace")]
+        trace(TraceOp::Alloc, true);
+        // Walk the pack list to find the hinted pack.
+        let epoch = self.enter_walk();
+        let result = self.alloc_near_hint(layout, hint);
+        self.leave_walk(epoch);
+        #[cfg(feature = "trace")]
+        if result.is_ok() {
+            trace_bytes(layout.size(), 0);
+        }
+        result
+    }
+
     unsafe fn dealloc(&self, _block: Block<'a>) -> usize {
         // Never directly deallocates anything; deallocations always dispatch
         // to the allocating pack list item.
@@ -210,6 +354,88 @@ unsafe impl<'a> Hold<'a> for Pool<'a> {
         // allocating pack list item.
         unimplemented!();
     }
+
+    #[inline]
+    fn min_alignment(&self) -> usize {
+        mem::align_of::<AllocTag>()
+    }
+
+    #[inline]
+    fn allocation_granularity(&self) -> usize {
+        // Block sizes round up to the alignment of the next allocation tag.
+        mem::align_of::<AllocTag>()
+    }
+
+    /// Arms the current thread's failpoint on this `Pool`. Allocations,
+    /// resizes, and reallocations of blocks in any of the pool's packs
+    /// count against the failpoint.
+    #[cfg(feature = "failpoints")]
+    fn fail_after(&self, n: usize) -> Result<(), HoldError> {
+        arm_failpoint(self as *const Pool<'a> as *const u8, n);
+        Ok(())
+    }
+
+    /// Relocates the `live` values into fresh, densely packed packs, and
+    /// releases every pack left without live allocations. Packs that still
+    /// contain live allocations, such as those of values omitted from the
+    /// `live` set, or of a value that failed to relocate, are retained.
+    /// Concurrent allocations proceed during defragmentation; vacated packs
+    /// are only released once no concurrent allocation can still reach them.
+    /// Returns `HoldError::Unsupported` if another defragmentation of this
+    /// pool is in progress.
+    fn defragment(&self, live: &mut [&mut dyn Restow<'a>]) -> Result<(), HoldError> {
+        // Exclude concurrent defragmentations.
+        if self.defragmenting.swap(true, Acquire) {
+            return Err(HoldError::Unsupported("concurrent defragment"));
+        }
+        unsafe {
+            // Detach the current pack list so that relocations allocate in fresh packs.
+            // Synchronize with concurrent head allocations.
+            let mut pack = self.head.swap(ptr::null_mut(), SeqCst);
+            // Advance the walk epoch; walks entered from now on can only reach
+            // the fresh pack list.
+            let epoch = self.epoch.fetch_add(1, SeqCst);
+            // Relocate each live value, bailing on the first failure.
+            let mut result = Ok(());
+            for value in live.iter_mut() {
+                if let Err(error) = value.try_restow(self) {
+                    result = Err(error);
+                    break;
+                }
+            }
+            // Wait for walks that may have loaded a detached pack to finish.
+            while self.walkers[epoch & 1].load(SeqCst) != 0 {
+                atomic::spin_loop_hint();
+            }
+            // List of detached packs that still contain live allocations.
+            let mut first: *mut PackList<'a> = ptr::null_mut();
+            let mut last: *mut PackList<'a> = ptr::null_mut();
+            // Sweep the detached pack list.
+            while !pack.is_null() {
+                // Load the next detached pack.
+                let next = (*pack).next.load(Relaxed);
+                // Check if the pack has any remaining live allocations.
+                if (*pack).live.load(Acquire) == 0 {
+                    // Release the vacated pack.
+                    self.dealloc_pack(pack);
+                } else {
+                    // Retain the pack.
+                    (*pack).next.store(first, Relaxed);
+                    if last.is_null() {
+                        last = pack;
+                    }
+                    first = pack;
+                }
+                pack = next;
+            }
+            // Reattach the retained packs to the pack list.
+            if !first.is_null() {
+                self.push_packs(first, last);
+            }
+            self.defragmenting.store(false, Release);
+            result
+        }
+    }
 }
 
 impl<'a> Drop for Pool<'a> {
@@ -255,6 +481,8 @@ struct PackList<'a> {
     next: AtomicPtr<PackList<'a>>,
     /// Non-zero pointer to the pool that owns this pack.
     pool: *mut Pool<'a>,
+    /// Number of live allocations in this pack.
+    live: AtomicUsize,
 }
 
 impl<'a> PackList<'a> {
@@ -266,6 +494,8 @@ impl<'a> PackList<'a> {
         ptr::write(&mut (*pack).next, AtomicPtr::new(ptr::null_mut()));
         // Initialize the pool pointer.
         ptr::write(&mut (*pack).pool, pool);
+        // Initialize the live allocation count.
+        ptr::write(&mut (*pack).live, AtomicUsize::new(0));
         // Initialize the hold base with the concrete type of the pack list.
         PackList::deify(&mut *pack);
         // Return a pointer to the pack list header.
@@ -277,6 +507,16 @@ impl<'a> PackList<'a> {
     unsafe fn as_block(&mut self) -> Block<'a> {
         self.base.as_block()
     }
+
+    /// Allocates a block in this pack's memory, without accounting for it
+    /// in the owning pool.
+    #[inline]
+    unsafe fn alloc_block(&self, layout: Layout) -> Result<Block<'a>, HoldError> {
+        let block = self.base.alloc(layout)?;
+        // Increment the live allocation count.
+        self.live.fetch_add(1, Relaxed);
+        Ok(block)
+    }
 }
 
 unsafe impl<'a> Hold<'a> for PackList<'a> {
@@ -286,18 +526,36 @@ unsafe impl<'a> Hold<'a> for PackList<'a> {
         (*self.pool).alloc(layout)
     }
 
+    #[inline]
+    unsafe fn alloc_near(&self, layout: Layout, hint: *const u8) -> Result<Block<'a>, HoldError> {
+        // Delegate nearby allocation back to the pool.
+        (*self.pool).alloc_near(layout, hint)
+    }
+
     #[inline]
     unsafe fn dealloc(&self, block: Block<'a>) -> usize {
+        #[cfg(feature = "trace")]
+        trace(TraceOp::Dealloc, true);
+        #[cfg(feature = "trace")]
+        trace_bytes(0, block.size());
         // Delegate deallocation to the base pack.
         let size = self.base.dealloc(block);
         // Inform the pool of the deallocation.
         (*self.pool).did_dealloc(size);
+        // Decrement the live allocation count last; a defragment may release
+        // the pack as soon as it has no live allocations.
+        self.live.fetch_sub(1, Release);
         // Return the number of freed bytes.
         size
     }
 
     #[inline]
     unsafe fn resize(&self, block: Block<'a>, layout: Layout) -> Result<Block<'a>, HoldError> {
+        // Fail the resize if the current thread's failpoint trips for the pool.
+        #[cfg(feature = "failpoints")]
+        check_failpoint(self.pool as *const u8)?;
+        #[cfg(feature = "trace")]
+        trace(TraceOp::Resize, true);
         // Get the size of the current block.
         let old_size = block.size();
         // Delegate resizing to the base pack.
@@ -307,12 +565,48 @@ unsafe impl<'a> Hold<'a> for PackList<'a> {
                 let new_size = block.size();
                 // Inform the pool of the resize.
                 (*self.pool).did_resize(old_size, new_size);
+                #[cfg(feature = "trace")]
+                trace_bytes(layout.size(), old_size);
                 // Return the resized block.
                 Ok(block)
             },
             err @ Err(_) => err,
         }
     }
+
+    #[cfg(feature = "failpoints")]
+    unsafe fn realloc(&self, block: Block<'a>, layout: Layout) -> Result<Block<'a>, HoldError> {
+        // Fail the reallocation if the current thread's failpoint trips for the pool.
+        check_failpoint(self.pool as *const u8)?;
+        // Disarm the failpoint, so that the reallocation counts as a single
+        // allocation, whether it resizes in place, or moves.
+        let failpoint = disarm_failpoint();
+        let result = match self.resize(block, layout) {
+            ok @ Ok(_) => ok,
+            Err(_) => match self.alloc(layout) {
+                Ok(new_block) => {
+                    // Copy the contents of the moved block.
+                    ptr::copy_nonoverlapping(block.as_ptr(), new_block.as_ptr(), cmp::min(block.size(), new_block.size()));
+                    self.dealloc(block);
+                    Ok(new_block)
+                },
+                err @ Err(_) => err,
+            },
+        };
+        // Rearm the failpoint.
+        restore_failpoint(failpoint);
+        result
+    }
+
+    #[inline]
+    fn min_alignment(&self) -> usize {
+        unsafe { (*self.pool).min_alignment() }
+    }
+
+    #[inline]
+    fn allocation_granularity(&self) -> usize {
+        unsafe { (*self.pool).allocation_granularity() }
+    }
 }
 
 impl<'a> Reify<'a, Hold<'a> + 'a> for PackList<'a> {
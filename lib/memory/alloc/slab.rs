@@ -1,11 +1,15 @@
 use core::marker::PhantomData;
 use core::mem;
 use core::ptr;
-use core::sync::atomic::{AtomicPtr, AtomicU32};
+use core::sync::atomic::{AtomicPtr, AtomicU32, AtomicUsize};
 use core::sync::atomic::Ordering::{Acquire, Relaxed, Release};
 use core::u32;
 use crate::block::{Block, Layout};
 use crate::alloc::{Heap, HeapError};
+#[cfg(debug_assertions)]
+use crate::alloc::tag;
+#[cfg(feature = "failpoints")]
+use crate::alloc::hold::{arm_failpoint, trip_failpoint};
 
 /// Allocator for a hunk of memory partitioned into fixed size memory blocks.
 pub struct Slab<'a> {
@@ -17,10 +21,31 @@ pub struct Slab<'a> {
     live: AtomicU32,
     /// Pointer to the first block in the free block list.
     head: AtomicPtr<FreeList>,
+    /// Total number of bytes in all regions chained by `add_region`.
+    region_size: AtomicUsize,
+    /// Total number of blocks in all regions chained by `add_region`.
+    region_blocks: AtomicUsize,
+    /// Bitwise or of the base addresses of the hunk, all chained regions,
+    /// and the unit size; used to compute the minimum block alignment.
+    addr_bits: AtomicUsize,
+    /// Occupancy bitmap of the first `SLOT_BITMAP_LEN` blocks of the hunk;
+    /// used to detect double deallocations in debug builds.
+    #[cfg(debug_assertions)]
+    slots: [AtomicUsize; SLOT_BITMAP_LEN / WORD_BITS],
     /// Variant over 'a.
     hunk_marker: PhantomData<&'a ()>,
 }
 
+/// Number of hunk blocks tracked by the debug occupancy bitmap of a `Slab`.
+/// Blocks past the tracked prefix of the hunk, and in chained regions, go
+/// unchecked.
+#[cfg(debug_assertions)]
+const SLOT_BITMAP_LEN: usize = 4096;
+
+/// Number of bits in each word of a slot occupancy bitmap.
+#[cfg(debug_assertions)]
+const WORD_BITS: usize = mem::size_of::<usize>() * 8;
+
 impl<'a> Slab<'a> {
     /// Returns a new `Slab` that allocates a hunk of memory in `unit`-sized blocks.
     #[inline]
@@ -31,45 +56,97 @@ impl<'a> Slab<'a> {
         if unit > u32::MAX as usize {
             panic!("unit too large");
         }
-        // Initialize the head of the free list to nil.
-        let mut head = ptr::null_mut();
-        // Compute the number of blocks that can fit in the hunk.
-        let block_count = hunk.size() / unit;
-        // Check if at least one block fits in the hunk.
-        if block_count != 0 {
-            // Get the base address of the hunk.
-            let base = hunk.as_ptr() as usize;
-            // Start with the last block in the hunk.
-            let mut next = base.wrapping_add(unit.wrapping_mul(block_count.wrapping_sub(1)));
-            // Loop over all blocks in the hunk, back to front.
-            loop {
-                // Interpret the next block as the current tail of the free list.
-                let tail = next as *mut FreeList;
-                // Set the next pointer of the current tail to the current head of the free list.
-                unsafe { ptr::write(&mut (*tail).next, AtomicPtr::new(head)); }
-                // Make the current tail the new head of the free list.
-                head = tail;
-                // Break if the next block is the first block in the hunk.
-                if next == base {
-                    break;
-                }
-                // Set the next block to the previous block in the hunk.
-                next = next.wrapping_sub(unit);
-            }
-        }
+        // Link all blocks in the hunk into a new free list.
+        let (head, _) = Slab::link_blocks(hunk, unit);
+        // All blocks start out vacant.
+        #[cfg(debug_assertions)]
+        const VACANT: AtomicUsize = AtomicUsize::new(0);
         Slab {
             hunk: hunk,
             unit: unit as u32,
             live: AtomicU32::new(0),
             head: AtomicPtr::new(head),
+            region_size: AtomicUsize::new(0),
+            region_blocks: AtomicUsize::new(0),
+            addr_bits: AtomicUsize::new(hunk.as_ptr() as usize | unit),
+            #[cfg(debug_assertions)]
+            slots: [VACANT; SLOT_BITMAP_LEN / WORD_BITS],
             hunk_marker: PhantomData,
         }
     }
 
-    /// Returns the total number of bytes in this `Slab`.
+    /// Links all `unit`-sized blocks in `region` into a new free list, in
+    /// address order. Returns the head and tail of the new list, which are
+    /// both nil if no blocks fit in the region.
+    fn link_blocks(region: Block<'a>, unit: usize) -> (*mut FreeList, *mut FreeList) {
+        // Initialize the head of the free list to nil.
+        let mut head = ptr::null_mut();
+        // Compute the number of blocks that can fit in the region.
+        let block_count = region.size() / unit;
+        // Check if at least one block fits in the region.
+        if block_count == 0 {
+            return (head, head);
+        }
+        // Get the base address of the region.
+        let base = region.as_ptr() as usize;
+        // Start with the last block in the region.
+        let mut next = base.wrapping_add(unit.wrapping_mul(block_count.wrapping_sub(1)));
+        // Remember the last block as the tail of the free list.
+        let last = next as *mut FreeList;
+        // Loop over all blocks in the region, back to front.
+        loop {
+            // Interpret the next block as the current tail of the free list.
+            let tail = next as *mut FreeList;
+            // Set the next pointer of the current tail to the current head of the free list.
+            unsafe { ptr::write(&mut (*tail).next, AtomicPtr::new(head)); }
+            // Make the current tail the new head of the free list.
+            head = tail;
+            // Break if the next block is the first block in the region.
+            if next == base {
+                break;
+            }
+            // Set the next block to the previous block in the region.
+            next = next.wrapping_sub(unit);
+        }
+        (head, last)
+    }
+
+    /// Chains the blocks of an additional backing `region` into the free list
+    /// of this `Slab`, growing its supply of blocks. Safe to call concurrently
+    /// with allocations and deallocations. Chained regions are not returned
+    /// by `into_block`; they remain borrowed for the lifetime of the slab.
+    pub fn add_region(&self, region: Block<'a>) {
+        let unit = self.unit as usize;
+        // Link all blocks in the region into a new free list.
+        let (head, tail) = Slab::link_blocks(region, unit);
+        // Check if any blocks fit in the region.
+        if head.is_null() {
+            return;
+        }
+        // Account for the new region before its blocks become allocatable.
+        self.region_size.fetch_add(region.size(), Relaxed);
+        self.region_blocks.fetch_add(region.size() / unit, Relaxed);
+        self.addr_bits.fetch_or(region.as_ptr() as usize, Relaxed);
+        // Load the current head of the free block list.
+        let mut old_head = self.head.load(Relaxed);
+        loop {
+            // Set the next pointer of the new tail block to the current free block list.
+            unsafe { (*tail).next.store(old_head, Relaxed); }
+            // Compare and swap the current free list for the new free list.
+            match self.head.compare_exchange_weak(old_head, head, Release, Relaxed) {
+                // CAS succeeded; the region's blocks are now allocatable.
+                Ok(_) => return,
+                // CAS failed; set the old head pointer to the new head of the free list and try again.
+                Err(block) => old_head = block,
+            }
+        }
+    }
+
+    /// Returns the total number of bytes in this `Slab`, including all
+    /// chained regions.
     #[inline]
     pub fn size(&self) -> usize {
-        self.hunk.size()
+        self.hunk.size() + self.region_size.load(Relaxed)
     }
 
     /// Returns the number of bytes in each memory block of this `Slab`.
@@ -78,10 +155,11 @@ impl<'a> Slab<'a> {
         self.unit as usize
     }
 
-    /// Returns the total number of blocks in this `Slab`.
+    /// Returns the total number of blocks in this `Slab`, including all
+    /// chained regions.
     #[inline]
     pub fn block_count(&self) -> usize {
-        self.hunk.size() / self.unit as usize
+        self.hunk.size() / self.unit as usize + self.region_blocks.load(Relaxed)
     }
 
     /// Returns the number of currently allocated memory blocks.
@@ -96,6 +174,39 @@ impl<'a> Slab<'a> {
         self.block_count() - self.live()
     }
 
+    /// Returns the word index and bit mask of the occupancy bit of the block
+    /// at address `addr`, or `None` if the block isn't tracked.
+    #[cfg(debug_assertions)]
+    fn slot_bit(&self, addr: *mut u8) -> Option<(usize, usize)> {
+        // Get the offset of the block in the hunk.
+        let offset = (addr as usize).wrapping_sub(self.hunk.as_ptr() as usize);
+        // Get the index of the block in the hunk.
+        let index = offset / self.unit as usize;
+        // Check that the block lies in the tracked prefix of the hunk.
+        if offset >= self.hunk.size() || index >= SLOT_BITMAP_LEN {
+            return None;
+        }
+        Some((index / WORD_BITS, 1 << (index % WORD_BITS)))
+    }
+
+    /// Marks the block at address `addr` as occupied.
+    #[cfg(debug_assertions)]
+    fn occupy_slot(&self, addr: *mut u8) {
+        if let Some((word, bit)) = self.slot_bit(addr) {
+            self.slots[word].fetch_or(bit, Relaxed);
+        }
+    }
+
+    /// Marks the block at address `addr` as vacant; returns `false` if the
+    /// block was already vacant.
+    #[cfg(debug_assertions)]
+    fn vacate_slot(&self, addr: *mut u8) -> bool {
+        match self.slot_bit(addr) {
+            Some((word, bit)) => self.slots[word].fetch_and(!bit, Relaxed) & bit != 0,
+            None => true,
+        }
+    }
+
     /// Consumes this `Slab` and returns its hunk of memory.
     #[inline]
     pub fn into_block(self) -> Block<'a> {
@@ -108,6 +219,13 @@ impl<'a> Slab<'a> {
 
 impl<'a> Heap<'a> for Slab<'a> {
     unsafe fn alloc(&self, layout: Layout) -> Result<Block<'a>, HeapError> {
+        // Fail the allocation if the current thread's failpoint trips.
+        #[cfg(feature = "failpoints")]
+        {
+            if trip_failpoint(self as *const Slab<'a> as *const u8) {
+                return Err(HeapError::OutOfMemory);
+            }
+        }
         // Check if the layout will fit in a block.
         if layout.size() > self.unit as usize {
             return Err(HeapError::Oversized);
@@ -132,6 +250,12 @@ impl<'a> Heap<'a> for Slab<'a> {
                 Ok(block) => { // CAS succeeded.
                     // Increment the live block count.
                     self.live.fetch_add(1, Relaxed);
+                    // Record the size of the block, and mark it occupied, for debug dealloc checks.
+                    #[cfg(debug_assertions)]
+                    {
+                        tag::record_size(block as usize, self.unit as usize);
+                        self.occupy_slot(block as *mut u8);
+                    }
                     // Return the free block.
                     return Ok(Block::from_raw_parts(block as *mut u8, self.unit as usize));
                 },
@@ -145,6 +269,15 @@ impl<'a> Heap<'a> for Slab<'a> {
     }
 
     unsafe fn dealloc(&self, block: Block<'a>) -> usize {
+        // Check that the block is not already free.
+        #[cfg(debug_assertions)]
+        {
+            let occupied = self.vacate_slot(block.as_ptr());
+            debug_assert!(occupied, "double dealloc");
+        }
+        // Check that the block has the size it was allocated with.
+        #[cfg(debug_assertions)]
+        tag::check_size(block.as_ptr() as usize, block.size());
         let size = block.size();
         // Interpret the memory block as the new head of free block list.
         let head = block.as_ptr() as *mut FreeList;
@@ -169,6 +302,25 @@ impl<'a> Heap<'a> for Slab<'a> {
             };
         }
     }
+
+    #[inline]
+    fn min_alignment(&self) -> usize {
+        // Every block address is the hunk or region address plus a multiple
+        // of the unit size, so blocks share the lowest set bit of them all.
+        let bits = self.addr_bits.load(Relaxed);
+        bits & bits.wrapping_neg()
+    }
+
+    #[inline]
+    fn allocation_granularity(&self) -> usize {
+        self.unit as usize
+    }
+
+    #[cfg(feature = "failpoints")]
+    fn fail_after(&self, n: usize) -> Result<(), HeapError> {
+        arm_failpoint(self as *const Slab<'a> as *const u8, n);
+        Ok(())
+    }
 }
 
 #[repr(C)]
@@ -72,6 +72,31 @@ impl<'b, S, T: StowFrom<'b, S>> StowInto<'b, T> for S {
     }
 }
 
+/// An object-safe handle to a value that can be relocated into a `Hold`.
+/// Enables heterogeneous sets of live values to be passed to
+/// `Hold::defragment`.
+pub trait Restow<'b> {
+    /// Moves this value into `hold`, in place, releasing the memory it
+    /// previously occupied. Leaves this value unchanged on failure.
+    fn try_restow(&mut self, hold: &dyn Hold<'b>) -> Result<(), HoldError>;
+}
+
+impl<'b, T: Stow<'b>> Restow<'b> for T {
+    fn try_restow(&mut self, hold: &dyn Hold<'b>) -> Result<(), HoldError> {
+        unsafe {
+            // Uninitialized destination, which is never dropped, so that an
+            // unwinding stow leaves this value as the sole owner of its children.
+            let mut dst = mem::MaybeUninit::<T>::uninit();
+            // Stow the value in place into the hold; a failed stow reverts itself.
+            T::stow(self, dst.as_mut_ptr(), hold)?;
+            // Move the relocated value into place, and drop the stowed source,
+            // releasing the memory it occupied.
+            mem::drop(mem::replace(self, dst.assume_init()));
+            Ok(())
+        }
+    }
+}
+
 macro_rules! stow_from_value {
     ($type:ty) => (
         impl<'b> StowFrom<'b, $type> for $type {
@@ -2,7 +2,11 @@ use core::marker::PhantomPinned;
 use core::mem;
 use core::ptr;
 use core::sync::atomic::AtomicPtr;
+#[cfg(debug_assertions)]
+use core::sync::atomic::AtomicUsize;
 use core::sync::atomic::Ordering::Relaxed;
+#[cfg(debug_assertions)]
+use core::sync::atomic::Ordering::{Acquire, Release};
 use swim_core::reify::Reified;
 use crate::block::{Block, Layout};
 use crate::alloc::{Hold, HoldError};
@@ -115,3 +119,76 @@ impl<'a> AllocTag<'a> {
         hold.resize(block, layout)
     }
 }
+
+/// Number of entries in the debug table of allocated block sizes.
+#[cfg(debug_assertions)]
+const SIZE_TABLE_LEN: usize = 4096;
+
+/// Number of consecutive entries probed for a block address.
+#[cfg(debug_assertions)]
+const SIZE_TABLE_PROBES: usize = 8;
+
+/// Address and size of an allocated block, recorded for debug assertions.
+#[cfg(debug_assertions)]
+struct SizeEntry {
+    /// Address of the allocated block, or zero if the entry is vacant.
+    addr: AtomicUsize,
+    /// Size of the allocated block.
+    size: AtomicUsize,
+}
+
+#[cfg(debug_assertions)]
+impl SizeEntry {
+    const VACANT: SizeEntry = SizeEntry {
+        addr: AtomicUsize::new(0),
+        size: AtomicUsize::new(0),
+    };
+}
+
+/// Sizes of allocated blocks, keyed by block address. Blocks allocated while
+/// all probed entries are occupied go unrecorded, and unchecked.
+#[cfg(debug_assertions)]
+static SIZE_TABLE: [SizeEntry; SIZE_TABLE_LEN] = [SizeEntry::VACANT; SIZE_TABLE_LEN];
+
+/// Returns the index of the first entry probed for the block address `addr`.
+#[cfg(debug_assertions)]
+#[inline]
+fn size_table_index(addr: usize) -> usize {
+    (addr >> 3) & SIZE_TABLE_LEN.wrapping_sub(1)
+}
+
+/// Records the `size` of the block allocated at address `addr`, or updates
+/// the recorded size of a resized block.
+#[cfg(debug_assertions)]
+pub(crate) fn record_size(addr: usize, size: usize) {
+    let index = size_table_index(addr);
+    for i in 0..SIZE_TABLE_PROBES {
+        let entry = &SIZE_TABLE[index.wrapping_add(i) & SIZE_TABLE_LEN.wrapping_sub(1)];
+        // Claim a vacant entry, or reuse a stale entry for the same address,
+        // left behind by a block that was never deallocated.
+        match entry.addr.compare_exchange(0, addr, Acquire, Relaxed) {
+            Ok(_) => (),
+            Err(key) if key == addr => (),
+            Err(_) => continue,
+        }
+        entry.size.store(size, Release);
+        return;
+    }
+}
+
+/// Unrecords the block at address `addr`, and checks that it was allocated
+/// with the same `size` that it's being deallocated with.
+#[cfg(debug_assertions)]
+pub(crate) fn check_size(addr: usize, size: usize) {
+    let index = size_table_index(addr);
+    for i in 0..SIZE_TABLE_PROBES {
+        let entry = &SIZE_TABLE[index.wrapping_add(i) & SIZE_TABLE_LEN.wrapping_sub(1)];
+        if entry.addr.load(Acquire) == addr {
+            // Load the recorded size before vacating the entry.
+            let recorded = entry.size.load(Acquire);
+            entry.addr.store(0, Release);
+            debug_assert!(recorded == size, "dealloc size mismatch");
+            return;
+        }
+    }
+}
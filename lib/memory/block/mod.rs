@@ -4,9 +4,11 @@ use core::ptr;
 
 mod block;
 mod layout;
+mod zeroable;
 
 pub use self::block::Block;
 pub use self::layout::{Layout, LayoutError};
+pub use self::zeroable::Zeroable;
 /// zero sentinel pointer
 /// Non-zero sentinel pointer to a zero-sized value.
 pub const ZSP: *mut u8 = 1 as *mut u8;
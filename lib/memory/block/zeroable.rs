@@ -0,0 +1,17 @@
+/// Marker for types whose all-zero bit pattern is a valid value.
+///
+/// # Safety
+///
+/// Implementors must guarantee that a value of the type consisting entirely
+/// of zero bytes is valid to read, use, and drop.
+pub unsafe trait Zeroable {
+}
+
+macro_rules! impl_zeroable {
+    ($($t:ty),*) => ($(
+        unsafe impl Zeroable for $t {
+        }
+    )*);
+}
+
+impl_zeroable!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64);
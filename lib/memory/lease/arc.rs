@@ -10,58 +10,94 @@ use crate::resident::{Resident, ResidentFromValue, ResidentFromClone,
                       ResidentWithCapacity};
 use crate::lease::{Lease, Mut, Ref, Hard, Soft};
 
+/// Bit widths of the reference count bit fields packed into the status field
+/// of an arc. The hard count occupies the low order bits of the status field,
+/// followed by the soft count, followed by the ref count, which takes all
+/// remaining bits below the mut and relocated flags. The masks, shifts, and
+/// maximum counts are all derived from `HARD_COUNT_BITS` and `SOFT_COUNT_BITS`.
+///
+/// `Mut`, `Ref`, and `Soft` leases always use the `DefaultCountLayout`;
+/// a `Hard` lease with any other layout can't be converted into them.
+pub trait CountLayout {
+    /// Number of bits in the hard reference count bit field.
+    const HARD_COUNT_BITS: usize;
+
+    /// Number of bits in the soft reference count bit field.
+    const SOFT_COUNT_BITS: usize;
+
+    /// Hard reference count bit field mask.
+    const HARD_COUNT_MASK: usize = (1 << Self::HARD_COUNT_BITS) - 1;
+
+    /// Maximum number of hard references per lease.
+    const HARD_COUNT_MAX: usize = Self::HARD_COUNT_MASK;
+
+    /// Number of trailing bits after the soft reference count bit field.
+    const SOFT_COUNT_SHIFT: usize = Self::HARD_COUNT_BITS;
+
+    /// Maximum number of soft references per lease.
+    const SOFT_COUNT_MAX: usize = (1 << Self::SOFT_COUNT_BITS) - 1;
+
+    /// Soft reference count bit field mask.
+    const SOFT_COUNT_MASK: usize = Self::SOFT_COUNT_MAX << Self::SOFT_COUNT_SHIFT;
+
+    /// Number of trailing bits after the immutable reference count bit field.
+    const REF_COUNT_SHIFT: usize = Self::HARD_COUNT_BITS + Self::SOFT_COUNT_BITS;
+
+    /// Immutable reference count bit field mask.
+    const REF_COUNT_MASK: usize = !(Self::HARD_COUNT_MASK | Self::SOFT_COUNT_MASK |
+                                    MUT_FLAG | RELOCATED_FLAG);
+
+    /// Maximum number of immutable references per lease.
+    const REF_COUNT_MAX: usize = Self::REF_COUNT_MASK >> Self::REF_COUNT_SHIFT;
+
+    /// Bit mask indicating the existence of mutable or immutable leases.
+    const ALIASED_MASK: usize = Self::REF_COUNT_MASK | MUT_FLAG;
+}
+
+/// Count layout used by all arc leases, unless otherwise specified. Splits
+/// the low order bits of the status field evenly between hard and soft counts.
+pub struct DefaultCountLayout;
+
+impl CountLayout for DefaultCountLayout {
+    #[cfg(target_pointer_width = "64")]
+    const HARD_COUNT_BITS: usize = 24;
+    #[cfg(target_pointer_width = "32")]
+    const HARD_COUNT_BITS: usize = 12;
+
+    #[cfg(target_pointer_width = "64")]
+    const SOFT_COUNT_BITS: usize = 24;
+    #[cfg(target_pointer_width = "32")]
+    const SOFT_COUNT_BITS: usize = 12;
+}
+
 /// Hard reference count bit field mask.
-#[cfg(target_pointer_width = "64")]
-pub(crate) const HARD_COUNT_MASK: usize = 0x0000000000FFFFFF;
-#[cfg(target_pointer_width = "32")]
-pub(crate) const HARD_COUNT_MASK: usize = 0x00000FFF;
+pub(crate) const HARD_COUNT_MASK: usize = DefaultCountLayout::HARD_COUNT_MASK;
 
 /// Maximum number of hard references per lease. A `Mut`, `Ref`, or `Hard`
 /// lease each holds its own hard reference to its pointed-to arc.
-#[cfg(target_pointer_width = "64")]
-pub const HARD_COUNT_MAX: usize = 0xFFFFFF;
-#[cfg(target_pointer_width = "32")]
-pub const HARD_COUNT_MAX: usize = 0xFFF;
+pub const HARD_COUNT_MAX: usize = DefaultCountLayout::HARD_COUNT_MAX;
 
 /// Soft reference count bit field mask.
-#[cfg(target_pointer_width = "64")]
-pub(crate) const SOFT_COUNT_MASK: usize = 0x0000FFFFFF000000;
-#[cfg(target_pointer_width = "32")]
-pub(crate) const SOFT_COUNT_MASK: usize = 0x00FFF000;
+pub(crate) const SOFT_COUNT_MASK: usize = DefaultCountLayout::SOFT_COUNT_MASK;
 
 /// Number of trailing bits after the soft reference count bit field.
-#[cfg(target_pointer_width = "64")]
-pub(crate) const SOFT_COUNT_SHIFT: usize = 24;
-#[cfg(target_pointer_width = "32")]
-pub(crate) const SOFT_COUNT_SHIFT: usize = 12;
+pub(crate) const SOFT_COUNT_SHIFT: usize = DefaultCountLayout::SOFT_COUNT_SHIFT;
 
 /// Maximum number of soft references per lease. A `Soft` lease holds a soft
 /// softreference to its pointed-to arc. A `Mut`, `Ref`, or `Hard` lease
 /// temporarily acquire a soft reference when it drops, if it holds the last
 /// hard reference to its arc, and outstanding soft references remain.
-#[cfg(target_pointer_width = "64")]
-pub const SOFT_COUNT_MAX: usize = 0xFFFFFF;
-#[cfg(target_pointer_width = "32")]
-pub const SOFT_COUNT_MAX: usize = 0xFFF;
+pub const SOFT_COUNT_MAX: usize = DefaultCountLayout::SOFT_COUNT_MAX;
 
 /// Immutable reference count bit field mask.
-#[cfg(target_pointer_width = "64")]
-pub(crate) const REF_COUNT_MASK: usize = 0x3FFF000000000000;
-#[cfg(target_pointer_width = "32")]
-pub(crate) const REF_COUNT_MASK: usize = 0x3F000000;
+pub(crate) const REF_COUNT_MASK: usize = DefaultCountLayout::REF_COUNT_MASK;
 
 /// Number of trailing bits after the soft reference count bit field.
-#[cfg(target_pointer_width = "64")]
-pub(crate) const REF_COUNT_SHIFT: usize = 48;
-#[cfg(target_pointer_width = "32")]
-pub(crate) const REF_COUNT_SHIFT: usize = 24;
+pub(crate) const REF_COUNT_SHIFT: usize = DefaultCountLayout::REF_COUNT_SHIFT;
 
 /// Maximum number of immutable references per lease. A `Ref` leases hold an
 /// immutable reference to its pointed-to arc.
-#[cfg(target_pointer_width = "64")]
-pub const REF_COUNT_MAX: usize = 0x3FFF;
-#[cfg(target_pointer_width = "32")]
-pub const REF_COUNT_MAX: usize = 0x3F;
+pub const REF_COUNT_MAX: usize = DefaultCountLayout::REF_COUNT_MAX;
 
 /// Bit flag indicating the existence of a mutable reference. A `Mut` lease
 /// holds the sole mutable reference to its pointed-to arc.
@@ -77,10 +113,7 @@ pub(crate) const RELOCATED_FLAG: usize = 0x8000000000000000;
 pub(crate) const RELOCATED_FLAG: usize = 0x80000000;
 
 /// Bit mask indicating the existence of mutable or immutable leases.
-#[cfg(target_pointer_width = "64")]
-pub(crate) const ALIASED_MASK: usize = 0x7FFF000000000000;
-#[cfg(target_pointer_width = "32")]
-pub(crate) const ALIASED_MASK: usize = 0x7F000000;
+pub(crate) const ALIASED_MASK: usize = DefaultCountLayout::ALIASED_MASK;
 
 /// Bit mask indicating the existence of references of any kind.
 #[cfg(target_pointer_width = "64")]
@@ -137,6 +170,10 @@ pub struct ArcHeader<M = ()> {
     pub(crate) relocation: AtomicUsize,
     /// Reference counts, and relocation flag.
     pub(crate) status: AtomicUsize,
+    /// Optional callback invoked with the metadata immediately before the
+    /// arc structure is deallocated.
+    #[cfg(feature = "finalizers")]
+    pub(crate) finalizer: Option<fn(*mut M)>,
     /// User-provided metadata.
     pub(crate) meta: M,
 }
@@ -262,7 +299,8 @@ impl<M> ArcHeader<M> {
     }
 
     /// Drops the arc header. Releases the relocation lease, if relocated;
-    /// drops the associated metadata, if not relocated.
+    /// runs the finalizer, if any, and drops the associated metadata, if not
+    /// relocated.
     #[inline]
     pub(crate) fn drop<R: Resident>(&mut self, data: *mut R::Data) {
         unsafe {
@@ -273,6 +311,12 @@ impl<M> ArcHeader<M> {
                 // Reify and drop the arc's relocation lease.
                 mem::drop(mem::transmute::<*mut R::Data, Hard<R>>(relocation));
             } else {
+                // Check if the arc has a finalizer.
+                #[cfg(feature = "finalizers")]
+                if let Some(finalizer) = self.finalizer.take() {
+                    // Run the finalizer before dropping the metadata.
+                    finalizer(&mut self.meta);
+                }
                 // Drop the unrelocated metadata.
                 ptr::drop_in_place(&mut self.meta);
             }
@@ -292,19 +336,48 @@ pub(crate) unsafe fn alloc_new<'a, R, L, T, M>(hold: &Hold<'a>, data: &T, meta:
     let (layout, offset) = Layout::for_type::<ArcHeader<R::Meta>>()
         .extended(R::new_resident_layout(data, meta))?;
     // Allocate a block of memory to hold the arc structure, bailing on failure.
-    let block = hold.alloc(layout)?;
+    let block = hold.alloc_offset(layout, offset)?;
     // Get a pointer to the header field of the new arc.
     let header = block.as_ptr() as *mut ArcHeader<R::Meta>;
     // Initialize the relocation address to zero.
     ptr::write(&mut (*header).relocation, AtomicUsize::new(0));
     // Initialize the lease status field.
     ptr::write(&mut (*header).status, AtomicUsize::new(status));
+    // Initialize the finalizer to none.
+    #[cfg(feature = "finalizers")]
+    ptr::write(&mut (*header).finalizer, None);
     // Get a raw pointer to the resident field of the new arc.
     let resident = (header as *mut u8).wrapping_add(offset);
     // Return a fat pointer to the resident field.
     Ok(R::new_resident_ptr(resident, data, meta))
 }
 
+/// Allocate a new arc structure in `hold` for an uninitialized resident of
+/// sized type `T`, initialized with the given lease `status`. The resident
+/// and its metadata are left uninitialized.
+#[inline]
+pub(crate) unsafe fn alloc_uninit<'a, R, T>(hold: &Hold<'a>, status: usize)
+    -> Result<*mut T, HoldError>
+    where R: Resident<Data=T>,
+{
+    // Compute the layout of the arc structure, capturing the offset of its resident field.
+    let (layout, offset) = Layout::for_type::<ArcHeader<R::Meta>>()
+        .extended(Layout::for_type::<T>())?;
+    // Allocate a block of memory to hold the arc structure, bailing on failure.
+    let block = hold.alloc_offset(layout, offset)?;
+    // Get a pointer to the header field of the new arc.
+    let header = block.as_ptr() as *mut ArcHeader<R::Meta>;
+    // Initialize the relocation address to zero.
+    ptr::write(&mut (*header).relocation, AtomicUsize::new(0));
+    // Initialize the lease status field.
+    ptr::write(&mut (*header).status, AtomicUsize::new(status));
+    // Initialize the finalizer to none.
+    #[cfg(feature = "finalizers")]
+    ptr::write(&mut (*header).finalizer, None);
+    // Return a pointer to the resident field of the new arc.
+    Ok((header as *mut u8).wrapping_add(offset) as *mut T)
+}
+
 /// Allocate a new arc structure in `hold` for a resident with a clone of the
 /// given `data` and `meta` data, initialized with the given lease `status`.
 #[inline]
@@ -318,13 +391,16 @@ pub(crate) unsafe fn alloc_clone<'a, R, L, T, M>(hold: &Hold<'a>, data: &T, meta
     let (layout, offset) = Layout::for_type::<ArcHeader<R::Meta>>()
         .extended(R::new_resident_layout(data, meta))?;
     // Allocate a block of memory to hold the arc structure, bailing on failure.
-    let block = hold.alloc(layout)?;
+    let block = hold.alloc_offset(layout, offset)?;
     // Get a pointer to the header field of the new arc.
     let header = block.as_ptr() as *mut ArcHeader<R::Meta>;
     // Initialize the relocation address to zero.
     ptr::write(&mut (*header).relocation, AtomicUsize::new(0));
     // Initialize the lease status field.
     ptr::write(&mut (*header).status, AtomicUsize::new(status));
+    // Initialize the finalizer to none.
+    #[cfg(feature = "finalizers")]
+    ptr::write(&mut (*header).finalizer, None);
     // Get a raw pointer to the resident field of the new arc.
     let resident = (header as *mut u8).wrapping_add(offset);
     // Return a fat pointer to the resident field.
@@ -345,13 +421,16 @@ pub(crate) unsafe fn alloc_clone_unchecked<'a, R, L, T, M>(hold: &Hold<'a>, data
     let (layout, offset) = Layout::for_type::<ArcHeader<R::Meta>>()
         .extended(R::new_resident_layout(data, meta))?;
     // Allocate a block of memory to hold the arc structure, bailing on failure.
-    let block = hold.alloc(layout)?;
+    let block = hold.alloc_offset(layout, offset)?;
     // Get a pointer to the header field of the new arc.
     let header = block.as_ptr() as *mut ArcHeader<R::Meta>;
     // Initialize the relocation address to zero.
     ptr::write(&mut (*header).relocation, AtomicUsize::new(0));
     // Initialize the lease status field.
     ptr::write(&mut (*header).status, AtomicUsize::new(status));
+    // Initialize the finalizer to none.
+    #[cfg(feature = "finalizers")]
+    ptr::write(&mut (*header).finalizer, None);
     // Get a raw pointer to the resident field of the new arc.
     let resident = (header as *mut u8).wrapping_add(offset);
     // Return a fat pointer to the resident field.
@@ -371,13 +450,16 @@ pub(crate) unsafe fn alloc_copy<'a, R, L, T, M>(hold: &Hold<'a>, data: &T, meta:
     let (layout, offset) = Layout::for_type::<ArcHeader<R::Meta>>()
         .extended(R::new_resident_layout(data, meta))?;
     // Allocate a block of memory to hold the arc structure, bailing on failure.
-    let block = hold.alloc(layout)?;
+    let block = hold.alloc_offset(layout, offset)?;
     // Get a pointer to the header field of the new arc.
     let header = block.as_ptr() as *mut ArcHeader<R::Meta>;
     // Initialize the relocation address to zero.
     ptr::write(&mut (*header).relocation, AtomicUsize::new(0));
     // Initialize the lease status field.
     ptr::write(&mut (*header).status, AtomicUsize::new(status));
+    // Initialize the finalizer to none.
+    #[cfg(feature = "finalizers")]
+    ptr::write(&mut (*header).finalizer, None);
     // Get a raw pointer to the resident field of the new arc.
     let resident = (header as *mut u8).wrapping_add(offset);
     // Return a fat pointer to the resident field.
@@ -398,13 +480,16 @@ pub(crate) unsafe fn alloc_copy_unchecked<'a, R, L, T, M>(hold: &Hold<'a>, data:
     let (layout, offset) = Layout::for_type::<ArcHeader<R::Meta>>()
         .extended(R::new_resident_layout(data, meta))?;
     // Allocate a block of memory to hold the arc structure, bailing on failure.
-    let block = hold.alloc(layout)?;
+    let block = hold.alloc_offset(layout, offset)?;
     // Get a pointer to the header field of the new arc.
     let header = block.as_ptr() as *mut ArcHeader<R::Meta>;
     // Initialize the relocation address to zero.
     ptr::write(&mut (*header).relocation, AtomicUsize::new(0));
     // Initialize the lease status field.
     ptr::write(&mut (*header).status, AtomicUsize::new(status));
+    // Initialize the finalizer to none.
+    #[cfg(feature = "finalizers")]
+    ptr::write(&mut (*header).finalizer, None);
     // Get a raw pointer to the resident field of the new arc.
     let resident = (header as *mut u8).wrapping_add(offset);
     // Return a fat pointer to the resident field.
@@ -423,13 +508,16 @@ pub(crate) unsafe fn alloc_empty<'a, R, L, M>(hold: &Hold<'a>, meta: &M, status:
     let (layout, offset) = Layout::for_type::<ArcHeader<R::Meta>>()
         .extended(R::new_resident_layout(meta))?;
     // Allocate a block of memory to hold the arc structure, bailing on failure.
-    let block = hold.alloc(layout)?;
+    let block = hold.alloc_offset(layout, offset)?;
     // Get a pointer to the header field of the new arc.
     let header = block.as_ptr() as *mut ArcHeader<R::Meta>;
     // Initialize the relocation address to zero.
     ptr::write(&mut (*header).relocation, AtomicUsize::new(0));
     // Initialize the lease status field.
     ptr::write(&mut (*header).status, AtomicUsize::new(status));
+    // Initialize the finalizer to none.
+    #[cfg(feature = "finalizers")]
+    ptr::write(&mut (*header).finalizer, None);
     // Get a raw pointer to the resident field of the new arc.
     let resident = (header as *mut u8).wrapping_add(offset);
     // Return a fat pointer to the resident field.
@@ -448,13 +536,16 @@ pub(crate) unsafe fn alloc_cap<'a, R, L, M>(hold: &Hold<'a>, cap: usize, meta: &
     let (layout, offset) = Layout::for_type::<ArcHeader<R::Meta>>()
         .extended(R::new_resident_layout(cap, meta)?)?;
     // Allocate a block of memory to hold the arc structure, bailing on failure.
-    let block = hold.alloc(layout)?;
+    let block = hold.alloc_offset(layout, offset)?;
     // Get a pointer to the header field of the new arc.
     let header = block.as_ptr() as *mut ArcHeader<R::Meta>;
     // Initialize the relocation address to zero.
     ptr::write(&mut (*header).relocation, AtomicUsize::new(0));
     // Initialize the lease status field.
     ptr::write(&mut (*header).status, AtomicUsize::new(status));
+    // Initialize the finalizer to none.
+    #[cfg(feature = "finalizers")]
+    ptr::write(&mut (*header).finalizer, None);
     // Get a raw pointer to the resident field of the new arc.
     let resident = (header as *mut u8).wrapping_add(offset);
     // Return a fat pointer to the resident field.
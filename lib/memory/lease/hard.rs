@@ -1,3 +1,4 @@
+use core::cmp::Ordering;
 use core::fmt::{self, Display, Debug, Pointer, Formatter};
 use core::hash::{Hash, Hasher};
 use core::marker::PhantomData;
@@ -7,16 +8,20 @@ use core::sync::atomic::{self, AtomicUsize};
 use core::sync::atomic::Ordering::{Acquire, Relaxed, Release, SeqCst};
 use crate::block::{self, Block, Layout};
 use crate::alloc::{AllocTag, Hold, Holder, HoldError, Stow, TryClone};
-use crate::lease::{arc, ArcHeader, ArcError, Lease, Mut, Ref, Soft};
+use crate::lease::{arc, ArcHeader, ArcError, CountLayout, DefaultCountLayout, Lease, Mut, Ref, Soft};
 use crate::resident::{Resident, ResidentFromValue, ResidentFromClone,
                       ResidentFromCloneUnchecked, ResidentFromCopy,
                       ResidentFromCopyUnchecked, ResidentFromEmpty,
-                      ResidentWithCapacity, ResidentUnwrap, ResidentHash,
+                      ResidentWithCapacity, ResidentUnwrap, ResidentPartialEq,
+                      ResidentEq, ResidentPartialOrd, ResidentOrd, ResidentHash,
                       ResidentDisplay, ResidentDebug, ResidentStow};
 
 /// A thread-safe, atomically counted, undereferenceable hard reference to a
-/// `Resident` occuping a shared, `Hold`-allocated memory block.
-pub struct Hard<'a, R: Resident> {
+/// `Resident` occuping a shared, `Hold`-allocated memory block. The
+/// `CountLayout` parameter `L` determines the widths of the arc's reference
+/// count bit fields; only `Hard` leases with the `DefaultCountLayout` can be
+/// converted into `Mut`, `Ref`, or `Soft` leases.
+pub struct Hard<'a, R: Resident, L: CountLayout = DefaultCountLayout> {
     /// Pointer to the resident memory block.
     data: NonNull<R::Data>,
     /// Variant over R::Data, with drop check.
@@ -25,23 +30,38 @@ pub struct Hard<'a, R: Resident> {
     meta_lifetime: PhantomData<ArcHeader<R::Meta>>,
     /// Variant over 'a.
     hold_lifetime: PhantomData<&'a ()>,
+    /// Variant over the count layout.
+    layout: PhantomData<L>,
 }
 
-unsafe impl<'a, R: Resident> Send for Hard<'a, R> where R::Data: Send, R::Meta: Send {
+/// An in-progress relocation of the resident shared by a `Hard` lease,
+/// begun by `Hard::try_begin_relocation`. While the token lives, the old
+/// arc has its relocated flag set, so no new mutable or immutable leases to
+/// the resident can be acquired, and relocation traversals report the
+/// resident as relocating. Publish the new location of the resident with
+/// `Hard::complete_relocation`; dropping the token aborts the relocation,
+/// leaving the resident in place.
+#[must_use]
+pub struct RelocationToken<'h, 'a: 'h, R: Resident> {
+    /// Hard lease to the resident being relocated.
+    lease: &'h Hard<'a, R>,
 }
 
-unsafe impl<'a, R: Resident> Sync for Hard<'a, R> where R::Data: Sync, R::Meta: Sync {
+unsafe impl<'a, R: Resident, L: CountLayout> Send for Hard<'a, R, L> where R::Data: Send, R::Meta: Send {
 }
 
-impl<'a, R: Resident> Hard<'a, R> {
+unsafe impl<'a, R: Resident, L: CountLayout> Sync for Hard<'a, R, L> where R::Data: Sync, R::Meta: Sync {
+}
+
+impl<'a, R: Resident, L: CountLayout> Hard<'a, R, L> {
     #[inline]
     pub fn try_hold_new_meta<T, M>(hold: &dyn Hold<'a>, data: T, meta: M)
-        -> Result<Hard<'a, R>, HoldError>
-        where R: ResidentFromValue<Hard<'a, R>, T, M>
+        -> Result<Hard<'a, R, L>, HoldError>
+        where R: ResidentFromValue<Hard<'a, R, L>, T, M>
     {
         unsafe {
             // Allocate a new arc structure.
-            let resident = arc::alloc_new::<R, Hard<'a, R>, T, M>(hold, &data, &meta, arc::HARD_STATUS_INIT)?;
+            let resident = arc::alloc_new::<R, Hard<'a, R, L>, T, M>(hold, &data, &meta, arc::HARD_STATUS_INIT)?;
             // Construct a new Hard lease.
             let mut lease = Hard::from_raw(resident);
             // Initialize the new resident.
@@ -53,12 +73,12 @@ impl<'a, R: Resident> Hard<'a, R> {
 
     #[inline]
     pub fn try_hold_clone_meta<T: ?Sized, M>(hold: &dyn Hold<'a>, data: &T, meta: M)
-        -> Result<Hard<'a, R>, HoldError>
-        where R: ResidentFromClone<Hard<'a, R>, T, M>
+        -> Result<Hard<'a, R, L>, HoldError>
+        where R: ResidentFromClone<Hard<'a, R, L>, T, M>
     {
         unsafe {
             // Allocate a new arc structure.
-            let resident = arc::alloc_clone::<R, Hard<'a, R>, T, M>(hold, &data, &meta, arc::HARD_STATUS_INIT)?;
+            let resident = arc::alloc_clone::<R, Hard<'a, R, L>, T, M>(hold, &data, &meta, arc::HARD_STATUS_INIT)?;
             // Construct a new Hard lease.
             let mut lease = Hard::from_raw(resident);
             // Initialize the new resident.
@@ -70,11 +90,11 @@ impl<'a, R: Resident> Hard<'a, R> {
 
     #[inline]
     pub unsafe fn try_hold_clone_unchecked_meta<T: ?Sized, M>(hold: &dyn Hold<'a>, data: &T, meta: M)
-        -> Result<Hard<'a, R>, HoldError>
-        where R: ResidentFromCloneUnchecked<Hard<'a, R>, T, M>
+        -> Result<Hard<'a, R, L>, HoldError>
+        where R: ResidentFromCloneUnchecked<Hard<'a, R, L>, T, M>
     {
         // Allocate a new arc structure.
-        let resident = arc::alloc_clone_unchecked::<R, Hard<'a, R>, T, M>(hold, &data, &meta, arc::HARD_STATUS_INIT)?;
+        let resident = arc::alloc_clone_unchecked::<R, Hard<'a, R, L>, T, M>(hold, &data, &meta, arc::HARD_STATUS_INIT)?;
         // Construct a new Hard lease.
         let mut lease = Hard::from_raw(resident);
         // Initialize the new resident.
@@ -85,12 +105,12 @@ impl<'a, R: Resident> Hard<'a, R> {
 
     #[inline]
     pub fn try_hold_copy_meta<T: ?Sized, M>(hold: &dyn Hold<'a>, data: &T, meta: M)
-        -> Result<Hard<'a, R>, HoldError>
-        where R: ResidentFromCopy<Hard<'a, R>, T, M>
+        -> Result<Hard<'a, R, L>, HoldError>
+        where R: ResidentFromCopy<Hard<'a, R, L>, T, M>
     {
         unsafe {
             // Allocate a new arc structure.
-            let resident = arc::alloc_copy::<R, Hard<'a, R>, T, M>(hold, &data, &meta, arc::HARD_STATUS_INIT)?;
+            let resident = arc::alloc_copy::<R, Hard<'a, R, L>, T, M>(hold, &data, &meta, arc::HARD_STATUS_INIT)?;
             // Construct a new Hard lease.
             let mut lease = Hard::from_raw(resident);
             // Initialize the new resident.
@@ -102,11 +122,11 @@ impl<'a, R: Resident> Hard<'a, R> {
 
     #[inline]
     pub unsafe fn try_hold_copy_unchecked_meta<T: ?Sized, M>(hold: &dyn Hold<'a>, data: &T, meta: M)
-        -> Result<Hard<'a, R>, HoldError>
-        where R: ResidentFromCopyUnchecked<Hard<'a, R>, T, M>
+        -> Result<Hard<'a, R, L>, HoldError>
+        where R: ResidentFromCopyUnchecked<Hard<'a, R, L>, T, M>
     {
         // Allocate a new arc structure.
-        let resident = arc::alloc_copy_unchecked::<R, Hard<'a, R>, T, M>(hold, &data, &meta, arc::HARD_STATUS_INIT)?;
+        let resident = arc::alloc_copy_unchecked::<R, Hard<'a, R, L>, T, M>(hold, &data, &meta, arc::HARD_STATUS_INIT)?;
         // Construct a new Hard lease.
         let mut lease = Hard::from_raw(resident);
         // Initialize the new resident.
@@ -117,12 +137,12 @@ impl<'a, R: Resident> Hard<'a, R> {
 
     #[inline]
     pub fn try_hold_empty_meta<M>(hold: &dyn Hold<'a>, meta: M)
-        -> Result<Hard<'a, R>, HoldError>
-        where R: ResidentFromEmpty<Hard<'a, R>, M>
+        -> Result<Hard<'a, R, L>, HoldError>
+        where R: ResidentFromEmpty<Hard<'a, R, L>, M>
     {
         unsafe {
             // Allocate a new arc structure.
-            let resident = arc::alloc_empty::<R, Hard<'a, R>, M>(hold, &meta, arc::HARD_STATUS_INIT)?;
+            let resident = arc::alloc_empty::<R, Hard<'a, R, L>, M>(hold, &meta, arc::HARD_STATUS_INIT)?;
             // Construct a new Hard lease.
             let mut lease = Hard::from_raw(resident);
             // Initialize the new resident.
@@ -134,12 +154,12 @@ impl<'a, R: Resident> Hard<'a, R> {
 
     #[inline]
     pub fn try_hold_cap_meta<M>(hold: &dyn Hold<'a>, cap: usize, meta: M)
-        -> Result<Hard<'a, R>, HoldError>
-        where R: ResidentWithCapacity<Hard<'a, R>, M>
+        -> Result<Hard<'a, R, L>, HoldError>
+        where R: ResidentWithCapacity<Hard<'a, R, L>, M>
     {
         unsafe {
             // Allocate a new arc structure.
-            let resident = arc::alloc_cap::<R, Hard<'a, R>, M>(hold, cap, &meta, arc::HARD_STATUS_INIT)?;
+            let resident = arc::alloc_cap::<R, Hard<'a, R, L>, M>(hold, cap, &meta, arc::HARD_STATUS_INIT)?;
             // Construct a new Hard lease.
             let mut lease = Hard::from_raw(resident);
             // Initialize the new resident.
@@ -150,160 +170,191 @@ impl<'a, R: Resident> Hard<'a, R> {
     }
 
     #[inline]
-    pub fn try_hold_new<T>(hold: &dyn Hold<'a>, data: T) -> Result<Hard<'a, R>, HoldError>
-        where R: ResidentFromValue<Hard<'a, R>, T>
+    pub fn try_hold_new<T>(hold: &dyn Hold<'a>, data: T) -> Result<Hard<'a, R, L>, HoldError>
+        where R: ResidentFromValue<Hard<'a, R, L>, T>
     {
         Hard::try_hold_new_meta(hold, data, ())
     }
 
     #[inline]
-    pub fn try_hold_clone<T: ?Sized>(hold: &dyn Hold<'a>, data: &T) -> Result<Hard<'a, R>, HoldError>
-        where R: ResidentFromClone<Hard<'a, R>, T>
+    pub fn try_hold_clone<T: ?Sized>(hold: &dyn Hold<'a>, data: &T) -> Result<Hard<'a, R, L>, HoldError>
+        where R: ResidentFromClone<Hard<'a, R, L>, T>
     {
         Hard::try_hold_clone_meta(hold, data, ())
     }
 
     #[inline]
-    pub unsafe fn try_hold_clone_unchecked<T: ?Sized>(hold: &dyn Hold<'a>, data: &T) -> Result<Hard<'a, R>, HoldError>
-        where R: ResidentFromCloneUnchecked<Hard<'a, R>, T>
+    pub unsafe fn try_hold_clone_unchecked<T: ?Sized>(hold: &dyn Hold<'a>, data: &T) -> Result<Hard<'a, R, L>, HoldError>
+        where R: ResidentFromCloneUnchecked<Hard<'a, R, L>, T>
     {
         Hard::try_hold_clone_unchecked_meta(hold, data, ())
     }
 
     #[inline]
-    pub fn try_hold_copy<T: ?Sized>(hold: &dyn Hold<'a>, data: &T) -> Result<Hard<'a, R>, HoldError>
-        where R: ResidentFromCopy<Hard<'a, R>, T>
+    pub fn try_hold_copy<T: ?Sized>(hold: &dyn Hold<'a>, data: &T) -> Result<Hard<'a, R, L>, HoldError>
+        where R: ResidentFromCopy<Hard<'a, R, L>, T>
     {
         Hard::try_hold_copy_meta(hold, data, ())
     }
 
     #[inline]
-    pub unsafe fn try_hold_copy_unchecked<T: ?Sized>(hold: &dyn Hold<'a>, data: &T) -> Result<Hard<'a, R>, HoldError>
-        where R: ResidentFromCopyUnchecked<Hard<'a, R>, T>
+    pub unsafe fn try_hold_copy_unchecked<T: ?Sized>(hold: &dyn Hold<'a>, data: &T) -> Result<Hard<'a, R, L>, HoldError>
+        where R: ResidentFromCopyUnchecked<Hard<'a, R, L>, T>
     {
         Hard::try_hold_copy_unchecked_meta(hold, data, ())
     }
 
     #[inline]
-    pub fn try_hold_empty(hold: &dyn Hold<'a>) -> Result<Hard<'a, R>, HoldError>
-        where R: ResidentFromEmpty<Hard<'a, R>>
+    pub fn try_hold_empty(hold: &dyn Hold<'a>) -> Result<Hard<'a, R, L>, HoldError>
+        where R: ResidentFromEmpty<Hard<'a, R, L>>
     {
         Hard::try_hold_empty_meta(hold, ())
     }
 
     #[inline]
-    pub fn try_hold_cap(hold: &dyn Hold<'a>, cap: usize) -> Result<Hard<'a, R>, HoldError>
-        where R: ResidentWithCapacity<Hard<'a, R>>
+    pub fn try_hold_cap(hold: &dyn Hold<'a>, cap: usize) -> Result<Hard<'a, R, L>, HoldError>
+        where R: ResidentWithCapacity<Hard<'a, R, L>>
     {
         Hard::try_hold_cap_meta(hold, cap, ())
     }
 
+    /// Allocates a new resident with the given `data`, registering a
+    /// `finalizer` to run exactly once with the arc's metadata, immediately
+    /// before the arc structure is deallocated. The finalizer runs when the
+    /// last hard or soft reference to the arc drops, whichever comes last.
+    /// Available only with the `finalizers` feature, which adds a word to
+    /// every arc header.
+    #[cfg(feature = "finalizers")]
     #[inline]
-    pub fn hold_new<T>(hold: &dyn Hold<'a>, data: T) -> Hard<'a, R>
-        where R: ResidentFromValue<Hard<'a, R>, T>
+    pub fn try_hold_new_with_finalizer<T>(hold: &dyn Hold<'a>, data: T, finalizer: fn(*mut R::Meta))
+        -> Result<Hard<'a, R, L>, HoldError>
+        where R: ResidentFromValue<Hard<'a, R, L>, T>
+    {
+        // Allocate a new resident, bailing on failure.
+        let lease = Hard::try_hold_new(hold, data)?;
+        unsafe {
+            // Register the finalizer; can't race because the new lease is unique.
+            (*lease.header()).finalizer = Some(finalizer);
+        }
+        // Return the new lease.
+        Ok(lease)
+    }
+
+    #[inline]
+    pub fn hold_new<T>(hold: &dyn Hold<'a>, data: T) -> Hard<'a, R, L>
+        where R: ResidentFromValue<Hard<'a, R, L>, T>
     {
         Hard::try_hold_new(hold, data).unwrap()
     }
 
+    #[cfg(feature = "finalizers")]
     #[inline]
-    pub fn hold_clone<T: ?Sized>(hold: &dyn Hold<'a>, data: &T) -> Hard<'a, R>
-        where R: ResidentFromClone<Hard<'a, R>, T>
+    pub fn hold_new_with_finalizer<T>(hold: &dyn Hold<'a>, data: T, finalizer: fn(*mut R::Meta)) -> Hard<'a, R, L>
+        where R: ResidentFromValue<Hard<'a, R, L>, T>
+    {
+        Hard::try_hold_new_with_finalizer(hold, data, finalizer).unwrap()
+    }
+
+    #[inline]
+    pub fn hold_clone<T: ?Sized>(hold: &dyn Hold<'a>, data: &T) -> Hard<'a, R, L>
+        where R: ResidentFromClone<Hard<'a, R, L>, T>
     {
         Hard::try_hold_clone(hold, data).unwrap()
     }
 
     #[inline]
-    pub unsafe fn hold_clone_unchecked<T: ?Sized>(hold: &dyn Hold<'a>, data: &T) -> Hard<'a, R>
-        where R: ResidentFromCloneUnchecked<Hard<'a, R>, T>
+    pub unsafe fn hold_clone_unchecked<T: ?Sized>(hold: &dyn Hold<'a>, data: &T) -> Hard<'a, R, L>
+        where R: ResidentFromCloneUnchecked<Hard<'a, R, L>, T>
     {
         Hard::try_hold_clone_unchecked(hold, data).unwrap()
     }
 
     #[inline]
-    pub fn hold_copy<T: ?Sized>(hold: &dyn Hold<'a>, data: &T) -> Hard<'a, R>
-        where R: ResidentFromCopy<Hard<'a, R>, T>
+    pub fn hold_copy<T: ?Sized>(hold: &dyn Hold<'a>, data: &T) -> Hard<'a, R, L>
+        where R: ResidentFromCopy<Hard<'a, R, L>, T>
     {
         Hard::try_hold_copy(hold, data).unwrap()
     }
 
     #[inline]
-    pub unsafe fn hold_copy_unchecked<T: ?Sized>(hold: &dyn Hold<'a>, data: &T) -> Hard<'a, R>
-        where R: ResidentFromCopyUnchecked<Hard<'a, R>, T>
+    pub unsafe fn hold_copy_unchecked<T: ?Sized>(hold: &dyn Hold<'a>, data: &T) -> Hard<'a, R, L>
+        where R: ResidentFromCopyUnchecked<Hard<'a, R, L>, T>
     {
         Hard::try_hold_copy_unchecked(hold, data).unwrap()
     }
 
     #[inline]
-    pub fn hold_empty(hold: &dyn Hold<'a>) -> Hard<'a, R>
-        where R: ResidentFromEmpty<Hard<'a, R>>
+    pub fn hold_empty(hold: &dyn Hold<'a>) -> Hard<'a, R, L>
+        where R: ResidentFromEmpty<Hard<'a, R, L>>
     {
         Hard::try_hold_empty(hold).unwrap()
     }
 
     #[inline]
-    pub fn hold_cap(hold: &dyn Hold<'a>, cap: usize) -> Hard<'a, R>
-        where R: ResidentWithCapacity<Hard<'a, R>>
+    pub fn hold_cap(hold: &dyn Hold<'a>, cap: usize) -> Hard<'a, R, L>
+        where R: ResidentWithCapacity<Hard<'a, R, L>>
     {
         Hard::try_hold_cap(hold, cap).unwrap()
     }
 
     #[inline]
-    pub fn new<T>(data: T) -> Hard<'a, R>
-        where R: ResidentFromValue<Hard<'a, R>, T>
+    pub fn new<T>(data: T) -> Hard<'a, R, L>
+        where R: ResidentFromValue<Hard<'a, R, L>, T>
     {
         Hard::hold_new(Hold::global(), data)
     }
 
     #[inline]
-    pub fn from_clone<T: ?Sized>(data: &T) -> Hard<'a, R>
-        where R: ResidentFromClone<Hard<'a, R>, T>
+    pub fn from_clone<T: ?Sized>(data: &T) -> Hard<'a, R, L>
+        where R: ResidentFromClone<Hard<'a, R, L>, T>
     {
         Hard::hold_clone(Hold::global(), data)
     }
 
     #[inline]
-    pub unsafe fn from_clone_unchecked<T: ?Sized>(data: &T) -> Hard<'a, R>
-        where R: ResidentFromCloneUnchecked<Hard<'a, R>, T>
+    pub unsafe fn from_clone_unchecked<T: ?Sized>(data: &T) -> Hard<'a, R, L>
+        where R: ResidentFromCloneUnchecked<Hard<'a, R, L>, T>
     {
         Hard::hold_clone_unchecked(Hold::global(), data)
     }
 
     #[inline]
-    pub fn from_copy<T: ?Sized>(data: &T) -> Hard<'a, R>
-        where R: ResidentFromCopy<Hard<'a, R>, T>
+    pub fn from_copy<T: ?Sized>(data: &T) -> Hard<'a, R, L>
+        where R: ResidentFromCopy<Hard<'a, R, L>, T>
     {
         Hard::hold_copy(Hold::global(), data)
     }
 
     #[inline]
-    pub unsafe fn from_copy_unchecked<T: ?Sized>(data: &T) -> Hard<'a, R>
-        where R: ResidentFromCopyUnchecked<Hard<'a, R>, T>
+    pub unsafe fn from_copy_unchecked<T: ?Sized>(data: &T) -> Hard<'a, R, L>
+        where R: ResidentFromCopyUnchecked<Hard<'a, R, L>, T>
     {
         Hard::hold_copy_unchecked(Hold::global(), data)
     }
 
     #[inline]
-    pub fn empty() -> Hard<'a, R>
-        where R: ResidentFromEmpty<Hard<'a, R>>
+    pub fn empty() -> Hard<'a, R, L>
+        where R: ResidentFromEmpty<Hard<'a, R, L>>
     {
         Hard::hold_empty(Hold::global())
     }
 
     #[inline]
-    pub fn with_cap(cap: usize) -> Hard<'a, R>
-        where R: ResidentWithCapacity<Hard<'a, R>>
+    pub fn with_cap(cap: usize) -> Hard<'a, R, L>
+        where R: ResidentWithCapacity<Hard<'a, R, L>>
     {
         Hard::hold_cap(Hold::global(), cap)
     }
 
     /// Constructs a `Hard` lease from a raw pointer returned by `Hard::into_raw`.
     #[inline]
-    pub unsafe fn from_raw(data: *mut R::Data) -> Hard<'a, R> {
+    pub unsafe fn from_raw(data: *mut R::Data) -> Hard<'a, R, L> {
         Hard {
             data: NonNull::new_unchecked(data),
             data_lifetime: PhantomData,
             meta_lifetime: PhantomData,
             hold_lifetime: PhantomData,
+            layout: PhantomData,
         }
     }
 
@@ -317,21 +368,21 @@ impl<'a, R: Resident> Hard<'a, R> {
     /// Does not traverse relocations.
     #[inline]
     pub fn hard_count(&self) -> usize {
-        unsafe { (*self.header()).hard_count() }
+        unsafe { (*self.header()).status.load(SeqCst) & L::HARD_COUNT_MASK }
     }
 
     /// Returns the number of soft references to the shared resident.
     /// Does not traverse relocations.
     #[inline]
     pub fn soft_count(&self) -> usize {
-        unsafe { (*self.header()).soft_count() }
+        unsafe { ((*self.header()).status.load(SeqCst) & L::SOFT_COUNT_MASK) >> L::SOFT_COUNT_SHIFT }
     }
 
     /// Returns the number of immutable references to the shared resident.
     /// Does not traverse relocations.
     #[inline]
     pub fn ref_count(&self) -> usize {
-        unsafe { (*self.header()).ref_count() }
+        unsafe { ((*self.header()).status.load(SeqCst) & L::REF_COUNT_MASK) >> L::REF_COUNT_SHIFT }
     }
 
     /// Returns `true` if the shared resident is mutably referenced.
@@ -351,7 +402,158 @@ impl<'a, R: Resident> Hard<'a, R> {
     /// Does not traverse relocations.
     #[inline]
     pub fn is_aliased(&self) -> bool {
-        unsafe { (*self.header()).is_aliased() }
+        unsafe { (*self.header()).status.load(SeqCst) & L::ALIASED_MASK != 0 }
+    }
+
+    /// Returns `true` if this is the only hard reference to the shared
+    /// resident, and the resident is neither immutably nor mutably
+    /// referenced; soft references are not counted. A cache holding one
+    /// hard lease per entry can poll this to find entries that nothing
+    /// else retains, and which are therefore safe to evict. The result is
+    /// a snapshot, decoded from a single load of the arc status.
+    /// Does not traverse relocations.
+    #[inline]
+    pub fn is_exclusive(&self) -> bool {
+        let status = unsafe { (*self.header()).status.load(SeqCst) };
+        status & L::HARD_COUNT_MASK == 1 && status & L::ALIASED_MASK == 0
+    }
+
+    /// Converts this hard lease into a raw pointer to the shared resident.
+    /// Use `Hard::from_raw` to reconstitute the returned pointer back into
+    /// a hard lease.
+    ///
+    /// # Safety
+    ///
+    /// The shared resident is not pinned to the returned memory address, and
+    /// may be concurrently relocated at any time. A memory leak will occur
+    /// unless the returned pointer is eventually converted back into a hard
+    /// lease and dropped.
+    #[inline]
+    pub unsafe fn into_raw(self) -> *mut R::Data {
+        let data = self.data.as_ptr();
+        mem::forget(self);
+        data
+    }
+
+    /// Returns a raw pointer to the shared resident.
+    ///
+    /// # Safety
+    ///
+    /// The shared resident may be uninitialized, or mutably aliased,
+    /// or may have been have relocated.
+    #[inline]
+    pub unsafe fn as_ptr_unchecked(&self) -> *mut R::Data {
+        self.data.as_ptr()
+    }
+}
+
+impl<'a, R: Resident> Hard<'a, R> {
+    /// Allocates a new resident whose data is returned by `init`, which gets
+    /// passed a soft lease to the not yet initialized resident, so that the
+    /// new data can hold soft references to itself. The soft lease can't be
+    /// upgraded to a hard or immutable lease before `init` returns. If `init`
+    /// panics, the arc structure gets deallocated once the last soft lease
+    /// drops, without dropping the uninitialized resident.
+    pub fn try_hold_new_cyclic<T, F>(hold: &dyn Hold<'a>, init: F) -> Result<Hard<'a, R>, HoldError>
+        where R: Resident<Data=T, Meta=()> + ResidentFromValue<Hard<'a, R>, T>,
+              F: FnOnce(&Soft<'a, R>) -> T,
+    {
+        unsafe {
+            // Allocate a new arc structure with a single soft reference, and no hard references.
+            let resident = arc::alloc_uninit::<R, T>(hold, 1 << arc::SOFT_COUNT_SHIFT)?;
+            // Construct the initial soft lease; can't be upgraded while the hard count is zero.
+            let soft = Soft::from_raw(resident);
+            // Produce the resident data, which may retain clones of the soft lease.
+            let data = init(&soft);
+            // Construct the new hard lease, whose reference is acquired below.
+            let mut lease = Hard::from_raw(resident);
+            // Initialize the new resident.
+            R::new_resident(&mut lease, data, ());
+            // Acquire the first hard reference, publishing the initialized resident to soft leases.
+            (*lease.header()).status.fetch_add(1, Release);
+            // Release the initial soft reference.
+            mem::drop(soft);
+            // Return the new lease.
+            Ok(lease)
+        }
+    }
+
+    #[inline]
+    pub fn hold_new_cyclic<T, F>(hold: &dyn Hold<'a>, init: F) -> Hard<'a, R>
+        where R: Resident<Data=T, Meta=()> + ResidentFromValue<Hard<'a, R>, T>,
+              F: FnOnce(&Soft<'a, R>) -> T,
+    {
+        Hard::try_hold_new_cyclic(hold, init).unwrap()
+    }
+
+    #[inline]
+    pub fn new_cyclic<T, F>(init: F) -> Hard<'a, R>
+        where R: Resident<Data=T, Meta=()> + ResidentFromValue<Hard<'a, R>, T>,
+              F: FnOnce(&Soft<'a, R>) -> T,
+    {
+        Hard::hold_new_cyclic(Hold::global(), init)
+    }
+
+    /// Begins relocating the shared resident, for use by custom movers, such
+    /// as defragmenters, that follow the same protocol as `stow`. Atomically
+    /// sets the relocated flag of the arc, returning a token that represents
+    /// the in-progress relocation; returns `None` if the resident is mutably
+    /// or immutably referenced, or has already begun relocating.
+    /// Does not traverse relocations.
+    pub fn try_begin_relocation(&self) -> Option<RelocationToken<'_, 'a, R>> {
+        unsafe {
+            // Get a pointer to the arc header preceding the resident.
+            let header = self.header();
+            // Load the status field; synchronized by subsequent CAS.
+            let mut old_status = (*header).status.load(Relaxed);
+            // Spin until the relocated flag is set.
+            loop {
+                // Check if the resident can't be mutably referenced, or is already relocating.
+                if old_status & arc::READ_LOCKED_MASK != 0 {
+                    return None;
+                }
+                // Set the relocated flag in the status field.
+                let new_status = old_status | arc::RELOCATED_FLAG;
+                // Atomically update the status field, synchronizing with relocation initiation and traversal.
+                match (*header).status.compare_exchange_weak(old_status, new_status, SeqCst, Relaxed) {
+                    // CAS succeeded; relocation initiated.
+                    Ok(_) => return Some(RelocationToken { lease: self }),
+                    // CAS failed; update the status field and try again.
+                    Err(status) => old_status = status,
+                }
+            }
+        }
+    }
+
+    /// Completes the relocation represented by `token`, publishing `new_data`
+    /// as the new location of the resident. Outstanding leases to the old
+    /// arc traverse to `new_data` from then on. The old arc retains the hard
+    /// reference of `new_data` until the last lease to the old arc drops.
+    /// Transfers the old arc's finalizer, if any, to the new arc.
+    ///
+    /// # Safety
+    ///
+    /// `new_data` must hold the resident, and its metadata, moved out of the
+    /// old arc while `token` was live. The old arc never drops its resident
+    /// or metadata once relocated, so they must not be dropped through any
+    /// other means either. Every token must be either completed, or dropped
+    /// to abort the relocation; forgetting a token leaves the resident
+    /// permanently relocating.
+    pub unsafe fn complete_relocation(token: RelocationToken<'_, 'a, R>, new_data: Hard<'a, R>) {
+        // Get a pointer to the old arc header.
+        let src_header = token.lease.header();
+        // Transfer the finalizer to the new arc, which now owns the metadata.
+        #[cfg(feature = "finalizers")]
+        {
+            (*new_data.header()).finalizer = (*src_header).finalizer.take();
+        }
+        // Relinquish the token without aborting the relocation.
+        mem::forget(token);
+        // Convert the new lease into the old arc's relocation lease.
+        let dst_data = new_data.into_raw();
+        // Write the relocation address of the new resident into the old arc header,
+        // synchronizing with relocation traversals, completing the relocation.
+        (*src_header).relocation.store(dst_data as *mut u8 as usize, Release);
     }
 
     /// Returns a mutable lease to the resident, traversing any completed
@@ -932,6 +1134,37 @@ impl<'a, R: Resident> Hard<'a, R> {
         self.try_to_ref().unwrap()
     }
 
+    /// Returns a hard lease to a clone of the shared resident, allocated in a
+    /// new arc in `hold`, independent of this lease's reference counts.
+    /// Acquires an immutable lease to the resident for the duration of the
+    /// clone, traversing any completed relocations, and waiting for any
+    /// outstanding mutable lease to drop. Returns an error on allocation
+    /// failure, or if the source reference counts overflow.
+    pub fn try_clone_into_hold<'b>(&self, hold: &dyn Hold<'b>) -> Result<Hard<'b, R>, HoldError>
+        where R::Data: TryClone,
+              R::Meta: TryClone,
+    {
+        // Immutably lease the source resident.
+        let src = self.try_to_ref()?;
+        // Clone the resident into the target hold.
+        let dst = Ref::try_clone_into_hold(&src, hold)?;
+        // Downgrade the clone to a hard lease.
+        Ok(Ref::into_hard(dst))
+    }
+
+    /// Returns a hard lease to a clone of the shared resident, allocated in a
+    /// new arc in `hold`.
+    ///
+    /// # Panics
+    ///
+    /// Panics on allocation failure.
+    pub fn clone_into_hold<'b>(&self, hold: &dyn Hold<'b>) -> Hard<'b, R>
+        where R::Data: TryClone,
+              R::Meta: TryClone,
+    {
+        self.try_clone_into_hold(hold).unwrap()
+    }
+
     /// Converts this hard lease into an immutable lease to the shared
     /// resident, traversing any completed relocations, waiting for any
     /// concurrent relocation to complete and for any outstanding mutable
@@ -1095,8 +1328,9 @@ impl<'a, R: Resident> Hard<'a, R> {
                 match (*header).status.compare_exchange_weak(old_status, new_status, SeqCst, Relaxed) {
                     // CAS succeeded.
                     Ok(_) => {
-                        // Check if the hard count dropped to zero.
-                        if new_hard_count == 0 {
+                        // Check if the hard count dropped to zero, and the resident hasn't
+                        // relocated; a relocated resident has already been moved out.
+                        if new_hard_count == 0 && new_status & arc::RELOCATED_FLAG == 0 {
                             // Drop the shared resident.
                             R::resident_drop(data, &mut (*header).meta);
                         }
@@ -1122,23 +1356,6 @@ impl<'a, R: Resident> Hard<'a, R> {
         self.try_into_soft().unwrap()
     }
 
-    /// Converts this hard lease into a raw pointer to the shared resident.
-    /// Use `Hard::from_raw` to reconstitute the returned pointer back into
-    /// a hard lease.
-    ///
-    /// # Safety
-    ///
-    /// The shared resident is not pinned to the returned memory address, and
-    /// may be concurrently relocated at any time. A memory leak will occur
-    /// unless the returned pointer is eventually converted back into a hard
-    /// lease and dropped.
-    #[inline]
-    pub unsafe fn into_raw(self) -> *mut R::Data {
-        let data = self.data.as_ptr();
-        mem::forget(self);
-        data
-    }
-
     /// Returns an immutable lease to the shared resident, traversing any
     /// completed moves, without waiting.
     ///
@@ -1161,18 +1378,6 @@ impl<'a, R: Resident> Hard<'a, R> {
         }
     }
 
-    /// Returns a raw pointer to the shared resident.
-    ///
-    /// # Safety
-    ///
-    /// The shared resident may be uninitialized, or mutably aliased,
-    /// or may have been have relocated.
-    #[inline]
-    pub unsafe fn as_ptr_unchecked(&self) -> *mut R::Data {
-        self.data.as_ptr()
-    }
-
-    /// Consumes this hard lease, traversing any completed relocations,
     /// and returns the shared resident; returns an error if there are
     /// any outstanding hard, mutable, or immutable leases.
     pub fn try_unwrap(mut self) -> Result<R::Target, Hard<'a, R>> where R: ResidentUnwrap<Hard<'a, R>> {
@@ -1209,6 +1414,8 @@ impl<'a, R: Resident> Hard<'a, R> {
                     let old_soft_count = (old_status & arc::SOFT_COUNT_MASK) >> arc::SOFT_COUNT_SHIFT;
                     // Check if all soft references have dropped.
                     if old_soft_count == 0 {
+                        // Synchronize with the releases of all other references.
+                        atomic::fence(Acquire);
                         // Store the new status field; can't fail because we're the last reference of any kind.
                         (*header).status.store(new_status, Relaxed);
                         // Read the resident out of the arc structure.
@@ -1261,6 +1468,8 @@ impl<'a, R: Resident> Hard<'a, R> {
                                         Ok(_) => {
                                             // Check if all soft references have been released.
                                             if new_soft_count == 0 {
+                                                // Synchronize with the releases of all soft references.
+                                                atomic::fence(Acquire);
                                                 // Drop the arc header.
                                                 (*header).drop::<R>(data);
                                                 // Get the block of memory containing the arc structure.
@@ -1322,14 +1531,14 @@ impl<'a, R: Resident> Hard<'a, R> {
     }
 }
 
-impl<'a, R: Resident> Holder<'a> for Hard<'a, R> {
+impl<'a, R: Resident, L: CountLayout> Holder<'a> for Hard<'a, R, L> {
     #[inline]
     fn holder(&self) -> &'a dyn Hold<'a> {
         AllocTag::from_ptr(self.header() as *mut u8).holder()
     }
 }
 
-impl<'a, R: Resident> Lease for Hard<'a, R> {
+impl<'a, R: Resident, L: CountLayout> Lease for Hard<'a, R, L> {
     type Data = R::Data;
 
     type Meta = R::Meta;
@@ -1345,6 +1554,55 @@ impl<'a, R: Resident> Lease for Hard<'a, R> {
     }
 }
 
+impl<'a, R: ResidentPartialEq<Ref<'a, R>>> PartialEq for Hard<'a, R> {
+    #[inline]
+    fn eq(&self, other: &Hard<'a, R>) -> bool {
+        R::resident_eq(&self.borrow(), &other.borrow())
+    }
+
+    #[inline]
+    fn ne(&self, other: &Hard<'a, R>) -> bool {
+        R::resident_ne(&self.borrow(), &other.borrow())
+    }
+}
+
+impl<'a, R: ResidentEq<Ref<'a, R>>> Eq for Hard<'a, R> {
+}
+
+impl<'a, R: ResidentPartialOrd<Ref<'a, R>>> PartialOrd for Hard<'a, R> {
+    #[inline]
+    fn partial_cmp(&self, other: &Hard<'a, R>) -> Option<Ordering> {
+        R::resident_partial_cmp(&self.borrow(), &other.borrow())
+    }
+
+    #[inline]
+    fn lt(&self, other: &Hard<'a, R>) -> bool {
+        R::resident_lt(&self.borrow(), &other.borrow())
+    }
+
+    #[inline]
+    fn le(&self, other: &Hard<'a, R>) -> bool {
+        R::resident_le(&self.borrow(), &other.borrow())
+    }
+
+    #[inline]
+    fn ge(&self, other: &Hard<'a, R>) -> bool {
+        R::resident_ge(&self.borrow(), &other.borrow())
+    }
+
+    #[inline]
+    fn gt(&self, other: &Hard<'a, R>) -> bool {
+        R::resident_gt(&self.borrow(), &other.borrow())
+    }
+}
+
+impl<'a, R: ResidentOrd<Ref<'a, R>>> Ord for Hard<'a, R> {
+    #[inline]
+    fn cmp(&self, other: &Hard<'a, R>) -> Ordering {
+        R::resident_cmp(&self.borrow(), &other.borrow())
+    }
+}
+
 impl<'a, R: ResidentHash<Ref<'a, R>>> Hash for Hard<'a, R> {
     #[inline]
     fn hash<H: Hasher>(&self, state: &mut H) {
@@ -1366,15 +1624,15 @@ impl<'a, R: ResidentDebug<Ref<'a, R>>> Debug for Hard<'a, R> {
     }
 }
 
-impl<'a, R: Resident> Pointer for Hard<'a, R> {
+impl<'a, R: Resident, L: CountLayout> Pointer for Hard<'a, R, L> {
     #[inline]
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         Pointer::fmt(&self.data.as_ptr(), f)
     }
 }
 
-impl<'a, R: Resident> TryClone for Hard<'a, R> {
-    fn try_clone(&self) -> Result<Hard<'a, R>, HoldError> {
+impl<'a, R: Resident, L: CountLayout> TryClone for Hard<'a, R, L> {
+    fn try_clone(&self) -> Result<Hard<'a, R, L>, HoldError> {
         unsafe {
             // Get a pointer to the shared resident.
             let data = self.data.as_ptr();
@@ -1385,15 +1643,15 @@ impl<'a, R: Resident> TryClone for Hard<'a, R> {
             // Spin until a hard reference is acquired.
             loop {
                 // Extract the hard reference count from the status field.
-                let old_hard_count = old_status & arc::HARD_COUNT_MASK;
+                let old_hard_count = old_status & L::HARD_COUNT_MASK;
                 // Increment the hard reference count.
                 let new_hard_count = old_hard_count.wrapping_add(1);
                 // Check if the incremented hard reference count overflows its bit field.
-                if new_hard_count > arc::HARD_COUNT_MAX {
+                if new_hard_count > L::HARD_COUNT_MAX {
                     return Err(HoldError::Unsupported("hard count overflow"));
                 }
                 // Clear the hard reference count bit field.
-                let new_status = old_status & !arc::HARD_COUNT_MASK;
+                let new_status = old_status & !L::HARD_COUNT_MASK;
                 // Splice the incremented hard reference count into the status field.
                 let new_status = new_status | new_hard_count;
                 // Atomically update the status field, synchronizing with reference releases.
@@ -1408,8 +1666,8 @@ impl<'a, R: Resident> TryClone for Hard<'a, R> {
     }
 }
 
-impl<'a, R: Resident> Clone for Hard<'a, R> {
-    fn clone(&self) -> Hard<'a, R> {
+impl<'a, R: Resident, L: CountLayout> Clone for Hard<'a, R, L> {
+    fn clone(&self) -> Hard<'a, R, L> {
         self.try_clone().unwrap()
     }
 }
@@ -1474,7 +1732,7 @@ impl<'a, 'b, R: ResidentStow<'b, Hard<'a, R>, Hard<'b, R>>> Stow<'b, Hard<'b, R>
         let (dst_layout, dst_offset) = Layout::for_type::<ArcHeader<R::Meta>>()
             .extended(R::new_resident_layout(&*src))?;
         // Allocate a block of memory to hold the new arc structure, bailing on failure.
-        let dst_block = match hold.alloc(dst_layout) {
+        let dst_block = match hold.alloc_offset(dst_layout, dst_offset) {
             // Allocation succeeded.
             Ok(block) => block,
             // Allocation failed.
@@ -1501,6 +1759,9 @@ impl<'a, 'b, R: ResidentStow<'b, Hard<'a, R>, Hard<'b, R>>> Stow<'b, Hard<'b, R>
         // owned by the relocation reference of the source lease, and the other
         // owned by the destination lease.
         ptr::write(&mut (*dst_header).status, AtomicUsize::new(2));
+        // Initialize the new arc's finalizer to none, until the stow succeeds.
+        #[cfg(feature = "finalizers")]
+        ptr::write(&mut (*dst_header).finalizer, None);
         // Get a fat pointer to the destination resident.
         let dst_data = block::set_address(src_data, (dst_header as usize).wrapping_add(dst_offset));
         // Initialize the destination lease.
@@ -1522,6 +1783,11 @@ impl<'a, 'b, R: ResidentStow<'b, Hard<'a, R>, Hard<'b, R>>> Stow<'b, Hard<'b, R>
                 }
             }
         }
+        // Transfer the finalizer to the new arc, which now owns the metadata.
+        #[cfg(feature = "finalizers")]
+        {
+            (*dst_header).finalizer = (*src_header).finalizer.take();
+        }
         // Write the relocation address of the new resident into the old arc header,
         // synchronizing with relocation traversals, completing the relocation.
         (*src_header).relocation.store(dst_data as *mut u8 as usize, Release);
@@ -1570,6 +1836,11 @@ impl<'a, 'b, R: ResidentStow<'b, Hard<'a, R>, Hard<'b, R>>> Stow<'b, Hard<'b, R>
                 }
                 // Unstow the resident.
                 R::resident_unstow(&mut *src, &mut *dst);
+                // Transfer the finalizer back to the source arc.
+                #[cfg(feature = "finalizers")]
+                {
+                    (*src_header).finalizer = (*dst_header).finalizer.take();
+                }
                 // Compute the total size of the arc structure.
                 let size = offset.wrapping_add(R::resident_size(dst_data, &mut (*dst_header).meta));
                 // Get the memory block containing the destination arc.
@@ -1604,7 +1875,30 @@ impl<'a, 'b, R: ResidentStow<'b, Hard<'a, R>, Hard<'b, R>>> Stow<'b, Hard<'b, R>
     }
 }
 
-unsafe impl<'a, #[may_dangle] R: Resident> Drop for Hard<'a, R> {
+impl<'h, 'a, R: Resident> Drop for RelocationToken<'h, 'a, R> {
+    fn drop(&mut self) {
+        unsafe {
+            // Get a pointer to the arc header preceding the resident.
+            let header = self.lease.header();
+            // Load the status field; synchronized by subsequent CAS.
+            let mut old_status = (*header).status.load(Relaxed);
+            // Spin until the relocated flag is unset.
+            loop {
+                // Unset the relocated flag in the status field.
+                let new_status = old_status & !arc::RELOCATED_FLAG;
+                // Atomically update the status field, synchronizing with relocation initiation and traversal.
+                match (*header).status.compare_exchange_weak(old_status, new_status, SeqCst, Relaxed) {
+                    // CAS succeeded; relocation aborted.
+                    Ok(_) => return,
+                    // CAS failed; update the status field and try again.
+                    Err(status) => old_status = status,
+                }
+            }
+        }
+    }
+}
+
+unsafe impl<'a, #[may_dangle] R: Resident, L: CountLayout> Drop for Hard<'a, R, L> {
     fn drop(&mut self) {
         unsafe {
             // Get a pointer to the shared resident.
@@ -1625,14 +1919,14 @@ unsafe impl<'a, #[may_dangle] R: Resident> Drop for Hard<'a, R> {
             // Spin until the hard reference has been released.
             loop {
                 // Extract the hard reference count from the status field.
-                let old_hard_count = old_status & arc::HARD_COUNT_MASK;
+                let old_hard_count = old_status & L::HARD_COUNT_MASK;
                 // Decrement the hard reference count, checking for underflow.
                 let new_hard_count = match old_hard_count.checked_sub(1) {
                     Some(hard_count) => hard_count,
                     None => panic!("hard count underflow"),
                 };
                 // Clear the hard reference count field.
-                let new_status = old_status & !arc::HARD_COUNT_MASK;
+                let new_status = old_status & !L::HARD_COUNT_MASK;
                 // Splice the decremented hard reference count into the status field.
                 let new_status = new_status | new_hard_count;
                 // Check if any hard references will remain.
@@ -1646,9 +1940,12 @@ unsafe impl<'a, #[may_dangle] R: Resident> Drop for Hard<'a, R> {
                     }
                 } else {
                     // Extract the soft reference count from the status field.
-                    let old_soft_count = (old_status & arc::SOFT_COUNT_MASK) >> arc::SOFT_COUNT_SHIFT;
+                    let old_soft_count = (old_status & L::SOFT_COUNT_MASK) >> L::SOFT_COUNT_SHIFT;
                     // Check if all soft references have dropped.
                     if old_soft_count == 0 {
+                        // Synchronize with the releases of all other references, and with
+                        // any completed relocation, before reading the relocated flag.
+                        atomic::fence(Acquire);
                         // Store the new status field; can't fail because we're the last reference of any kind.
                         (*header).status.store(new_status, Relaxed);
                         // Check if the resident hasn't relocated.
@@ -1667,13 +1964,13 @@ unsafe impl<'a, #[may_dangle] R: Resident> Drop for Hard<'a, R> {
                         // Convert our hard reference into a soft reference to avoid racing with other soft refs.
                         let new_soft_count = old_soft_count.wrapping_add(1);
                         // Check if the incremented soft reference count overflows its bit field.
-                        if new_soft_count > arc::SOFT_COUNT_MAX {
+                        if new_soft_count > L::SOFT_COUNT_MAX {
                             panic!("soft count overflow");
                         }
                         // Clear the soft reference count bit field.
-                        let new_status = new_status & !arc::SOFT_COUNT_MASK;
+                        let new_status = new_status & !L::SOFT_COUNT_MASK;
                         // Splice the incremented soft reference count into the status field.
-                        let new_status = new_status | new_soft_count << arc::SOFT_COUNT_SHIFT;
+                        let new_status = new_status | new_soft_count << L::SOFT_COUNT_SHIFT;
                         // Atomically update the status field, synchronizing with reference acquires and releases.
                         match (*header).status.compare_exchange_weak(old_status, new_status, SeqCst, Relaxed) {
                             // CAS succeeded; the last hard reference has been released.
@@ -1688,22 +1985,24 @@ unsafe impl<'a, #[may_dangle] R: Resident> Drop for Hard<'a, R> {
                                 // Spin until the soft reference has been released.
                                 loop {
                                     // Extract the soft reference count from the status field.
-                                    let old_soft_count = (old_status & arc::SOFT_COUNT_MASK) >> arc::SOFT_COUNT_SHIFT;
+                                    let old_soft_count = (old_status & L::SOFT_COUNT_MASK) >> L::SOFT_COUNT_SHIFT;
                                     // Decrement the soft reference count, checking for underflow.
                                     let new_soft_count = match old_soft_count.checked_sub(1) {
                                         Some(soft_count) => soft_count,
                                         None => panic!("soft count underflow"),
                                     };
                                     // Clear the soft reference count bit field.
-                                    let new_status = old_status & !arc::SOFT_COUNT_MASK;
+                                    let new_status = old_status & !L::SOFT_COUNT_MASK;
                                     // Splice the decremented soft reference count into the status field.
-                                    let new_status = new_status | new_soft_count << arc::SOFT_COUNT_SHIFT;
+                                    let new_status = new_status | new_soft_count << L::SOFT_COUNT_SHIFT;
                                     // Atomically update the status field, synchronizing with reference acquires.
                                     match (*header).status.compare_exchange_weak(old_status, new_status, Release, Relaxed) {
                                         // CAS succeeded.
                                         Ok(_) => {
                                             // Check if all soft references have been released.
                                             if new_soft_count == 0 {
+                                                // Synchronize with the releases of all soft references.
+                                                atomic::fence(Acquire);
                                                 // Shared resident has already been dropped; drop the arc header.
                                                 (*header).drop::<R>(data);
                                                 // Get the block of memory containing the arc structure.
@@ -92,11 +92,11 @@ mod soft;
 
 pub use self::raw::Raw;
 pub use self::ptr::Ptr;
-pub use self::arc::{Arc, ArcHeader, ArcError};
+pub use self::arc::{Arc, ArcHeader, ArcError, CountLayout, DefaultCountLayout};
 pub use self::arc::{HARD_COUNT_MAX, SOFT_COUNT_MAX, REF_COUNT_MAX};
 pub use self::r#mut::Mut;
 pub use self::r#ref::Ref;
-pub use self::hard::Hard;
+pub use self::hard::{Hard, RelocationToken};
 pub use self::soft::Soft;
 
 /// Exclusive reference to a value stored in a `Hold`-allocated memory block,
@@ -7,7 +7,7 @@ use core::ops::{Deref, DerefMut, Index, IndexMut, Add, AddAssign};
 use core::ptr::NonNull;
 use core::sync::atomic::Ordering::{Acquire, Relaxed, Release, SeqCst};
 use crate::block::{Block, Layout};
-use crate::alloc::{AllocTag, Hold, Holder, HoldError};
+use crate::alloc::{AllocTag, Hold, HoldNear, Holder, HoldError};
 use crate::lease::{arc, ArcHeader, ArcError, Lease, DynamicLease, Ref, Hard, Soft};
 use crate::resident::{Resident, ResidentFromValue, ResidentFromClone,
                       ResidentFromCloneUnchecked, ResidentFromCopy,
@@ -15,7 +15,7 @@ use crate::resident::{Resident, ResidentFromValue, ResidentFromClone,
                       ResidentWithCapacity, ResidentUnwrap, ResidentDeref,
                       ResidentDerefMut, ResidentAsRef, ResidentAsMut,
                       ResidentIndex, ResidentIndexMut, ResidentAdd,
-                      ResidentAddAssign, ResidentIntoIterator,
+                      ResidentAddAssign, ResidentExtend, ResidentSwap, ResidentIntoIterator,
                       ResidentIntoRefIterator, ResidentIntoMutIterator,
                       ResidentPartialEq, ResidentEq, ResidentPartialOrd,
                       ResidentOrd, ResidentHash, ResidentDisplay, ResidentDebug};
@@ -253,6 +253,45 @@ impl<'a, R: Resident> Mut<'a, R> {
         Mut::try_hold_cap(hold, cap).unwrap()
     }
 
+    /// Allocates an empty resident in `hold`, and extends it with all elements
+    /// of `iter`; returns a `HoldError` if the resident fails to grow.
+    pub fn try_hold_from_iter<A, I: IntoIterator<Item=A>>(hold: &dyn Hold<'a>, iter: I) -> Result<Mut<'a, R>, HoldError>
+        where R: ResidentFromEmpty<Mut<'a, R>> + ResidentExtend<Mut<'a, R>, A>
+    {
+        let mut lease = Mut::try_hold_empty(hold)?;
+        R::resident_try_extend(&mut lease, iter)?;
+        Ok(lease)
+    }
+
+    /// Allocates an empty resident in `hold`, and extends it with all elements
+    /// of `iter`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the resident fails to grow.
+    pub fn hold_from_iter<A, I: IntoIterator<Item=A>>(hold: &dyn Hold<'a>, iter: I) -> Mut<'a, R>
+        where R: ResidentFromEmpty<Mut<'a, R>> + ResidentExtend<Mut<'a, R>, A>
+    {
+        Mut::try_hold_from_iter(hold, iter).unwrap()
+    }
+
+    /// Allocates a new resident with the given `data` in the same `Hold` as
+    /// `this` lease, preferably near in address to `this` lease's resident,
+    /// so that related structures share locality. Returns a `HoldError` if
+    /// the allocation fails.
+    pub fn try_alloc_sibling<R2: Resident, T>(this: &Mut<'a, R>, data: T) -> Result<Mut<'a, R2>, HoldError>
+        where R2: ResidentFromValue<Mut<'a, R2>, T>
+    {
+        let hold = HoldNear::new(this.holder(), Mut::header(this) as *const u8);
+        Mut::try_hold_new(&hold, data)
+    }
+
+    pub fn alloc_sibling<R2: Resident, T>(this: &Mut<'a, R>, data: T) -> Mut<'a, R2>
+        where R2: ResidentFromValue<Mut<'a, R2>, T>
+    {
+        Mut::try_alloc_sibling(this, data).unwrap()
+    }
+
     #[inline]
     pub fn new<T>(data: T) -> Mut<'a, R>
         where R: ResidentFromValue<Mut<'a, R>, T>
@@ -352,6 +391,25 @@ impl<'a, R: Resident> Mut<'a, R> {
         unsafe { &mut (*Mut::header(this)).meta }
     }
 
+    /// Exchanges the resident of this lease with the resident of lease
+    /// `that`, which may be a different kind of lease, without reallocating.
+    #[inline]
+    pub fn swap_resident<L: Lease>(this: &mut Mut<'a, R>, that: &mut L) where R: ResidentSwap<Mut<'a, R>, L> {
+        R::resident_swap(this, that);
+    }
+
+    /// Moves the resident value out of this lease, leaving its default value
+    /// in place, without reallocating. The default value is constructed
+    /// before the resident is moved, so the lease stays intact if
+    /// `Default::default` panics.
+    #[inline]
+    pub fn take(this: &mut Mut<'a, R>) -> R::Target
+        where R: ResidentDerefMut<Mut<'a, R>>,
+              R::Target: Default + Sized,
+    {
+        mem::take(&mut **this)
+    }
+
     /// Converts this mutable lease into an immutable lease to the shared resident,
     /// returning an error if the incremented reference count overflows `REF_COUNT_MAX`.
     pub fn try_into_ref(this: Mut<'a, R>) -> Result<Ref<'a, R>, ArcError> {
@@ -741,6 +799,30 @@ impl<'a, R: Resident> Mut<'a, R> {
         }
     }
 
+    /// Consumes this mutable lease, and returns the shared resident, if this
+    /// is the only reference of any kind to the resident; returns an `Err`
+    /// containing the original lease if any other hard, immutable, or soft
+    /// reference exists. Unlike `Mut::try_unwrap`, never moves the resident
+    /// out from under outstanding soft references, so the arc block is
+    /// always freed on success.
+    pub fn try_into_inner(this: Mut<'a, R>) -> Result<R::Target, Mut<'a, R>> where R: ResidentUnwrap<Mut<'a, R>> {
+        unsafe {
+            // Load the status field, synchronizing with prior reference releases.
+            let status = (*Mut::header(&this)).status.load(Acquire);
+            // Check if any other hard reference exists.
+            if status & arc::HARD_COUNT_MASK != 1 {
+                return Err(this);
+            }
+            // Check if any soft reference exists.
+            if status & arc::SOFT_COUNT_MASK != 0 {
+                return Err(this);
+            }
+            // We hold the last reference of any kind, so the unwrap moves the
+            // resident out and frees the arc block without contention.
+            Mut::try_unwrap(this)
+        }
+    }
+
     /// Consumes this mutable lease, and returns the shared resident.
     ///
     /// # Panics
@@ -859,6 +941,15 @@ impl<'a, R: ResidentAddAssign<Mut<'a, R>, Rhs>, Rhs> AddAssign<Rhs> for Mut<'a,
     }
 }
 
+impl<'a, R: ResidentExtend<Mut<'a, R>, A>, A> Extend<A> for Mut<'a, R> {
+    /// Extends the resident with the elements of `iter`. Panics if the
+    /// resident fails to grow; use `try_extend` to handle allocation failure.
+    #[inline]
+    fn extend<I: IntoIterator<Item=A>>(&mut self, iter: I) {
+        R::resident_try_extend(self, iter).unwrap();
+    }
+}
+
 impl<'a, R: ResidentIntoIterator<Mut<'a, R>>> IntoIterator for Mut<'a, R> {
     type Item = R::Item;
 
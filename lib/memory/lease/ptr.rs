@@ -14,7 +14,7 @@ use crate::resident::{Resident, ResidentFromValue, ResidentFromClone,
                       ResidentWithCapacity, ResidentUnwrap, ResidentDeref,
                       ResidentDerefMut, ResidentAsRef, ResidentAsMut,
                       ResidentIndex, ResidentIndexMut, ResidentAdd,
-                      ResidentAddAssign, ResidentIntoIterator,
+                      ResidentAddAssign, ResidentExtend, ResidentSwap, ResidentIntoIterator,
                       ResidentIntoRefIterator, ResidentIntoMutIterator,
                       ResidentPartialEq, ResidentEq, ResidentPartialOrd,
                       ResidentOrd, ResidentHash, ResidentDisplay, ResidentDebug,
@@ -52,7 +52,7 @@ impl<'a, R: Resident> Ptr<'a, R> {
                 .extended(R::new_resident_layout(&data, &meta))?;
             // Allocate a block of memory to hold the allocation structure,
             // bailing on failure.
-            let block = hold.alloc(layout)?;
+            let block = hold.alloc_offset(layout, offset)?;
             // Get a pointer to the header field of the new allocation.
             let header = block.as_ptr() as *mut R::Meta;
             // Get a raw pointer to the resident field of the new allocation.
@@ -80,7 +80,7 @@ impl<'a, R: Resident> Ptr<'a, R> {
                 .extended(R::new_resident_layout(data, &meta))?;
             // Allocate a block of memory to hold the allocation structure,
             // bailing on failure.
-            let block = hold.alloc(layout)?;
+            let block = hold.alloc_offset(layout, offset)?;
             // Get a pointer to the header field of the new allocation.
             let header = block.as_ptr() as *mut R::Meta;
             // Get a raw pointer to the resident field of the new allocation.
@@ -107,7 +107,7 @@ impl<'a, R: Resident> Ptr<'a, R> {
             .extended(R::new_resident_layout(data, &meta))?;
         // Allocate a block of memory to hold the allocation structure,
         // bailing on failure.
-        let block = hold.alloc(layout)?;
+        let block = hold.alloc_offset(layout, offset)?;
         // Get a pointer to the header field of the new allocation.
         let header = block.as_ptr() as *mut R::Meta;
         // Get a raw pointer to the resident field of the new allocation.
@@ -134,7 +134,7 @@ impl<'a, R: Resident> Ptr<'a, R> {
                 .extended(R::new_resident_layout(data, &meta))?;
             // Allocate a block of memory to hold the allocation structure,
             // bailing on failure.
-            let block = hold.alloc(layout)?;
+            let block = hold.alloc_offset(layout, offset)?;
             // Get a pointer to the header field of the new allocation.
             let header = block.as_ptr() as *mut R::Meta;
             // Get a raw pointer to the resident field of the new allocation.
@@ -161,7 +161,7 @@ impl<'a, R: Resident> Ptr<'a, R> {
             .extended(R::new_resident_layout(data, &meta))?;
         // Allocate a block of memory to hold the allocation structure,
         // bailing on failure.
-        let block = hold.alloc(layout)?;
+        let block = hold.alloc_offset(layout, offset)?;
         // Get a pointer to the header field of the new allocation.
         let header = block.as_ptr() as *mut R::Meta;
         // Get a raw pointer to the resident field of the new allocation.
@@ -188,7 +188,7 @@ impl<'a, R: Resident> Ptr<'a, R> {
                 .extended(R::new_resident_layout(&meta))?;
             // Allocate a block of memory to hold the allocation structure,
             // bailing on failure.
-            let block = hold.alloc(layout)?;
+            let block = hold.alloc_offset(layout, offset)?;
             // Get a pointer to the header field of the new allocation.
             let header = block.as_ptr() as *mut R::Meta;
             // Get a raw pointer to the resident field of the new arc.
@@ -216,7 +216,7 @@ impl<'a, R: Resident> Ptr<'a, R> {
                 .extended(R::new_resident_layout(cap, &meta)?)?;
             // Allocate a block of memory to hold the allocation structure,
             // bailing on failure.
-            let block = hold.alloc(layout)?;
+            let block = hold.alloc_offset(layout, offset)?;
             // Get a pointer to the header field of the new allocation.
             let header = block.as_ptr() as *mut R::Meta;
             // Get a raw pointer to the resident field of the new allocation.
@@ -330,6 +330,28 @@ impl<'a, R: Resident> Ptr<'a, R> {
         Ptr::try_hold_cap(hold, cap).unwrap()
     }
 
+    /// Allocates an empty resident in `hold`, and extends it with all elements
+    /// of `iter`; returns a `HoldError` if the resident fails to grow.
+    pub fn try_hold_from_iter<A, I: IntoIterator<Item=A>>(hold: &dyn Hold<'a>, iter: I) -> Result<Ptr<'a, R>, HoldError>
+        where R: ResidentFromEmpty<Ptr<'a, R>> + ResidentExtend<Ptr<'a, R>, A>
+    {
+        let mut lease = Ptr::try_hold_empty(hold)?;
+        R::resident_try_extend(&mut lease, iter)?;
+        Ok(lease)
+    }
+
+    /// Allocates an empty resident in `hold`, and extends it with all elements
+    /// of `iter`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the resident fails to grow.
+    pub fn hold_from_iter<A, I: IntoIterator<Item=A>>(hold: &dyn Hold<'a>, iter: I) -> Ptr<'a, R>
+        where R: ResidentFromEmpty<Ptr<'a, R>> + ResidentExtend<Ptr<'a, R>, A>
+    {
+        Ptr::try_hold_from_iter(hold, iter).unwrap()
+    }
+
     #[inline]
     pub fn new<T>(data: T) -> Ptr<'a, R>
         where R: ResidentFromValue<Ptr<'a, R>, T>
@@ -420,6 +442,13 @@ impl<'a, R: Resident> Ptr<'a, R> {
         unsafe { &mut *Ptr::header(this) }
     }
 
+    /// Exchanges the resident of this lease with the resident of lease
+    /// `that`, which may be a different kind of lease, without reallocating.
+    #[inline]
+    pub fn swap_resident<L: Lease>(this: &mut Ptr<'a, R>, that: &mut L) where R: ResidentSwap<Ptr<'a, R>, L> {
+        R::resident_swap(this, that);
+    }
+
     /// Converts this `Ptr` lease into a raw pointer to the owned resident.
     /// Use `Ptr::from_raw` to reconstitute the returned pointer back into
     /// a `Ptr` lease.
@@ -637,6 +666,15 @@ impl<'a, R: ResidentAddAssign<Ptr<'a, R>, Rhs>, Rhs> AddAssign<Rhs> for Ptr<'a,
     }
 }
 
+impl<'a, R: ResidentExtend<Ptr<'a, R>, A>, A> Extend<A> for Ptr<'a, R> {
+    /// Extends the resident with the elements of `iter`. Panics if the
+    /// resident fails to grow; use `try_extend` to handle allocation failure.
+    #[inline]
+    fn extend<I: IntoIterator<Item=A>>(&mut self, iter: I) {
+        R::resident_try_extend(self, iter).unwrap();
+    }
+}
+
 impl<'a, R: ResidentIntoIterator<Ptr<'a, R>>> IntoIterator for Ptr<'a, R> {
     type Item = R::Item;
 
@@ -771,7 +809,7 @@ impl<'a, 'b, R: ResidentClone<Ptr<'b, R>, Ptr<'a, R>>> CloneIntoHold<'a, Ptr<'a,
                 .extended(R::new_resident_layout(self))?;
             // Allocate a block of memory to hold the destination structure,
             // bailing on failure.
-            let dst_block = hold.alloc(dst_layout)?;
+            let dst_block = hold.alloc_offset(dst_layout, offset)?;
             // Get a pointer to the header field of the destination structure.
             let dst_meta = dst_block.as_ptr() as *mut R::Meta;
             // Get a raw pointer to the destination resident.
@@ -810,7 +848,7 @@ impl<'a, 'b, R: ResidentStow<'b, Ptr<'a, R>, Ptr<'b, R>>> Stow<'b, Ptr<'b, R>> f
             .extended(Layout::from_size_align_unchecked(size, mem::align_of_val(&*src_data)))?;
         // Allocate a destination memory block to hold the relocated resident,
         // bailing on failure.
-        let dst_block = hold.alloc(layout)?;
+        let dst_block = hold.alloc_offset(layout, offset)?;
         // Get a pointer to the header field of the destination structure.
         let dst_meta = dst_block.as_ptr() as *mut R::Meta;
         // Get a raw pointer to the destination resident.
@@ -14,7 +14,7 @@ use crate::resident::{Resident, ResidentFromValue, ResidentFromClone,
                       ResidentWithCapacity, ResidentUnwrap, ResidentDeref,
                       ResidentDerefMut, ResidentAsRef, ResidentAsMut,
                       ResidentIndex, ResidentIndexMut, ResidentAdd,
-                      ResidentAddAssign, ResidentIntoIterator,
+                      ResidentAddAssign, ResidentExtend, ResidentSwap, ResidentIntoIterator,
                       ResidentIntoRefIterator, ResidentIntoMutIterator,
                       ResidentPartialEq, ResidentEq, ResidentPartialOrd,
                       ResidentOrd, ResidentHash, ResidentDisplay, ResidentDebug,
@@ -267,6 +267,28 @@ impl<'a, R: Resident> Raw<'a, R> {
         Raw::try_hold_cap(hold, cap).unwrap()
     }
 
+    /// Allocates an empty resident in `hold`, and extends it with all elements
+    /// of `iter`; returns a `HoldError` if the resident fails to grow.
+    pub fn try_hold_from_iter<A, I: IntoIterator<Item=A>>(hold: &dyn Hold<'a>, iter: I) -> Result<Raw<'a, R>, HoldError>
+        where R: ResidentFromEmpty<Raw<'a, R>> + ResidentExtend<Raw<'a, R>, A>
+    {
+        let mut lease = Raw::try_hold_empty(hold)?;
+        R::resident_try_extend(&mut lease, iter)?;
+        Ok(lease)
+    }
+
+    /// Allocates an empty resident in `hold`, and extends it with all elements
+    /// of `iter`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the resident fails to grow.
+    pub fn hold_from_iter<A, I: IntoIterator<Item=A>>(hold: &dyn Hold<'a>, iter: I) -> Raw<'a, R>
+        where R: ResidentFromEmpty<Raw<'a, R>> + ResidentExtend<Raw<'a, R>, A>
+    {
+        Raw::try_hold_from_iter(hold, iter).unwrap()
+    }
+
     #[inline]
     pub fn new<T>(data: T) -> Raw<'a, R>
         where R: ResidentFromValue<Raw<'a, R>, T>
@@ -334,6 +356,13 @@ impl<'a, R: Resident> Raw<'a, R> {
         (data, meta)
     }
 
+    /// Exchanges the resident of this lease with the resident of lease
+    /// `that`, which may be a different kind of lease, without reallocating.
+    #[inline]
+    pub fn swap_resident<L: Lease>(this: &mut Raw<'a, R>, that: &mut L) where R: ResidentSwap<Raw<'a, R>, L> {
+        R::resident_swap(this, that);
+    }
+
     pub fn unwrap(mut this: Raw<'a, R>) -> R::Target where R: ResidentUnwrap<Raw<'a, R>> {
         unsafe {
             let data = this.data.as_ptr();
@@ -481,6 +510,15 @@ impl<'a, R: ResidentAddAssign<Raw<'a, R>, Rhs>, Rhs> AddAssign<Rhs> for Raw<'a,
     }
 }
 
+impl<'a, R: ResidentExtend<Raw<'a, R>, A>, A> Extend<A> for Raw<'a, R> {
+    /// Extends the resident with the elements of `iter`. Panics if the
+    /// resident fails to grow; use `try_extend` to handle allocation failure.
+    #[inline]
+    fn extend<I: IntoIterator<Item=A>>(&mut self, iter: I) {
+        R::resident_try_extend(self, iter).unwrap();
+    }
+}
+
 impl<'a, R: ResidentIntoIterator<Raw<'a, R>>> IntoIterator for Raw<'a, R> {
     type Item = R::Item;
 
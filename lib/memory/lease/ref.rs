@@ -8,13 +8,13 @@ use core::ptr::{self, NonNull};
 use core::sync::atomic::AtomicUsize;
 use core::sync::atomic::Ordering::{Acquire, Relaxed, Release, SeqCst};
 use crate::block::{Block, Layout};
-use crate::alloc::{AllocTag, Hold, Holder, HoldError, TryClone};
+use crate::alloc::{AllocTag, Hold, HoldNear, Holder, HoldError, TryClone};
 use crate::lease::{arc, ArcHeader, ArcError, Lease, Mut, Hard, Soft};
 use crate::resident::{Resident, ResidentFromValue, ResidentFromClone,
                       ResidentFromCloneUnchecked, ResidentFromCopy,
                       ResidentFromCopyUnchecked, ResidentFromEmpty,
                       ResidentWithCapacity, ResidentUnwrap, ResidentDeref,
-                      ResidentAsRef, ResidentIndex, ResidentAdd,
+                      ResidentDerefMut, ResidentAsRef, ResidentIndex, ResidentAdd,
                       ResidentIntoIterator, ResidentIntoRefIterator,
                       ResidentPartialEq, ResidentEq, ResidentPartialOrd,
                       ResidentOrd, ResidentHash, ResidentDisplay, ResidentDebug};
@@ -252,6 +252,22 @@ impl<'a, R: Resident> Ref<'a, R> {
         Ref::try_hold_cap(hold, cap).unwrap()
     }
 
+    /// Allocates a new resident with the given `data` in the same `Hold` as
+    /// `this` lease, preferably near in address to `this` lease's resident.
+    /// Returns a `HoldError` if the allocation fails.
+    pub fn try_alloc_sibling<R2: Resident, T>(this: &Ref<'a, R>, data: T) -> Result<Ref<'a, R2>, HoldError>
+        where R2: ResidentFromValue<Ref<'a, R2>, T>
+    {
+        let hold = HoldNear::new(this.holder(), Ref::header(this) as *const u8);
+        Ref::try_hold_new(&hold, data)
+    }
+
+    pub fn alloc_sibling<R2: Resident, T>(this: &Ref<'a, R>, data: T) -> Ref<'a, R2>
+        where R2: ResidentFromValue<Ref<'a, R2>, T>
+    {
+        Ref::try_alloc_sibling(this, data).unwrap()
+    }
+
     #[inline]
     pub fn new<T>(data: T) -> Ref<'a, R>
         where R: ResidentFromValue<Ref<'a, R>, T>
@@ -368,13 +384,16 @@ impl<'a, R: Resident> Ref<'a, R> {
             // Get a reference to the hold that allocated the original arc.
             let hold = this.holder();
             // Allocate a block of memory to hold the new arc structure, bailing on failure.
-            let new_block = hold.alloc(layout)?;
+            let new_block = hold.alloc_offset(layout, offset)?;
             // Get a pointer to the header field of the new arc.
             let new_header = new_block.as_ptr() as *mut ArcHeader<R::Meta>;
             // Initialize the new relocation address to zero.
             ptr::write(&mut (*new_header).relocation, AtomicUsize::new(0));
             // Initialize the lease status field.
             ptr::write(&mut (*new_header).status, AtomicUsize::new(arc::MUT_STATUS_INIT));
+            // Don't carry the finalizer over to the cloned arc.
+            #[cfg(feature = "finalizers")]
+            ptr::write(&mut (*new_header).finalizer, None);
             // Try to clone the metadata.
             let new_metadata = match (*old_header).meta.try_clone() {
                 // Clone succeeded.
@@ -424,6 +443,135 @@ impl<'a, R: Resident> Ref<'a, R> {
         Ref::try_to_unique(this).unwrap()
     }
 
+    /// Returns an immutable lease to a clone of the shared resident, allocated
+    /// in a new arc in `hold`, returning an error on allocation failure. The
+    /// clone shares no reference counts with this lease's arc.
+    pub fn try_clone_into_hold<'b>(this: &Ref<'a, R>, hold: &dyn Hold<'b>) -> Result<Ref<'b, R>, HoldError>
+        where R::Data: TryClone,
+              R::Meta: TryClone,
+    {
+        unsafe {
+            // Get a pointer to the shared resident.
+            let old_data = this.data.as_ptr();
+            // Get the alignment of the resident.
+            let align = mem::align_of_val(&*old_data);
+            // Get the offset of the resident in the arc structure by rounding up
+            // the size of the arc header to the alignment of the resident.
+            let offset = mem::size_of::<ArcHeader<R::Meta>>()
+                .wrapping_add(align).wrapping_sub(1) & !align.wrapping_sub(1);
+            // Get a pointer to the shared header by subtracting the resident's
+            // offset in the arc structure.
+            let old_header = (old_data as *mut u8).wrapping_sub(offset) as *mut ArcHeader<R::Meta>;
+            // Compute the total size of the arc structure.
+            let size = offset.wrapping_add(R::resident_size(old_data, &mut (*old_header).meta));
+            // Compute the layout of the arc structure.
+            let layout = Layout::from_size_align_unchecked(size, cmp::max(align, mem::align_of::<ArcHeader<R::Meta>>()));
+            // Allocate a block of memory in the target hold to hold the new arc structure, bailing on failure.
+            let new_block = hold.alloc_offset(layout, offset)?;
+            // Get a pointer to the header field of the new arc.
+            let new_header = new_block.as_ptr() as *mut ArcHeader<R::Meta>;
+            // Initialize the new relocation address to zero.
+            ptr::write(&mut (*new_header).relocation, AtomicUsize::new(0));
+            // Initialize the lease status field.
+            ptr::write(&mut (*new_header).status, AtomicUsize::new(arc::REF_STATUS_INIT));
+            // Don't carry the finalizer over to the cloned arc.
+            #[cfg(feature = "finalizers")]
+            ptr::write(&mut (*new_header).finalizer, None);
+            // Try to clone the metadata.
+            let new_metadata = match (*old_header).meta.try_clone() {
+                // Clone succeeded.
+                Ok(metadata) => metadata,
+                // Clone failed.
+                Err(error) => {
+                    // Free the newly allocated arc.
+                    hold.dealloc(new_block);
+                    // Return the allocation error.
+                    return Err(error);
+                },
+            };
+            // Move the cloned metadata into the new arc header.
+            ptr::write(&mut (*new_header).meta, new_metadata);
+            // Get a pointer to the new resident.
+            let new_data = (new_header as *mut u8).wrapping_add(offset) as *mut R::Data;
+            // Try to clone the resident.
+            let new_resident = match (*old_data).try_clone() {
+                // Clone succeeded.
+                Ok(resident) => resident,
+                // Clone failed.
+                Err(error) => {
+                    // Drop the cloned metadata.
+                    ptr::drop_in_place(&mut (*new_header).meta);
+                    // Free the newly allocated arc.
+                    hold.dealloc(new_block);
+                    // Return the allocation error.
+                    return Err(error);
+                },
+            };
+            // Move the cloned resident into the new arc.
+            ptr::write(new_data, new_resident);
+            // Return a new Ref lease with a pointer to the cloned resident.
+            Ok(Ref::from_raw(new_data))
+        }
+    }
+
+    /// Returns an immutable lease to a clone of the shared resident, allocated
+    /// in a new arc in `hold`.
+    ///
+    /// # Panics
+    ///
+    /// Panics on allocation failure.
+    pub fn clone_into_hold<'b>(this: &Ref<'a, R>, hold: &dyn Hold<'b>) -> Ref<'b, R>
+        where R::Data: TryClone,
+              R::Meta: TryClone,
+    {
+        Ref::try_clone_into_hold(this, hold).unwrap()
+    }
+
+    /// Returns a mutable reference to the shared resident, first cloning the
+    /// resident into a new, uniquely held arc if any other leases to the
+    /// resident exist, and updating this lease to point to the clone. Returns
+    /// an error on allocation failure, leaving this lease unchanged.
+    pub fn try_make_mut<'b>(this: &'b mut Ref<'a, R>) -> Result<&'b mut R::Target, ArcError>
+        where R: ResidentDerefMut<Ref<'a, R>>,
+              R::Data: TryClone,
+              R::Meta: TryClone,
+    {
+        unsafe {
+            // Get a pointer to the arc header preceding the shared resident.
+            let header = Ref::header(this);
+            // Synchronously load the status field.
+            let status = (*header).status.load(SeqCst);
+            // Check if this is the only lease of any kind to the shared resident;
+            // no new leases can be acquired while we mutably borrow the only one.
+            if status == arc::REF_STATUS_INIT {
+                // Mutably dereference the resident in place.
+                return Ok(R::resident_deref_mut(this));
+            }
+        }
+        // Clone the aliased resident into a new mutable lease.
+        let unique = Ref::try_to_unique(this)?;
+        // Convert the new mutable lease into an immutable lease, and replace
+        // this lease with it, releasing our reference to the original resident.
+        *this = Mut::try_into_ref(unique)?;
+        // Mutably dereference the cloned resident.
+        Ok(R::resident_deref_mut(this))
+    }
+
+    /// Returns a mutable reference to the shared resident, first cloning the
+    /// resident into a new, uniquely held arc if any other leases to the
+    /// resident exist, and updating this lease to point to the clone.
+    ///
+    /// # Panics
+    ///
+    /// Panics on allocation failure.
+    pub fn make_mut<'b>(this: &'b mut Ref<'a, R>) -> &'b mut R::Target
+        where R: ResidentDerefMut<Ref<'a, R>>,
+              R::Data: TryClone,
+              R::Meta: TryClone,
+    {
+        Ref::try_make_mut(this).unwrap()
+    }
+
     /// Converts this immutable lease into a mutable lease to the shared
     /// resident, cloning the resident if there are any outstanding mutable
     /// or immutable leases, and returning an error on allocation failure.
@@ -478,13 +626,16 @@ impl<'a, R: Resident> Ref<'a, R> {
             // Get a reference to the hold that allocated the original arc.
             let hold = this.holder();
             // Allocate a block of memory to hold the new arc structure, bailing on failure.
-            let new_block = hold.alloc(layout)?;
+            let new_block = hold.alloc_offset(layout, offset)?;
             // Get a pointer to the header field of the new arc.
             let new_header = new_block.as_ptr() as *mut ArcHeader<R::Meta>;
             // Initialize the new relocation address to zero.
             ptr::write(&mut (*new_header).relocation, AtomicUsize::new(0));
             // Initialize the new lease status field.
             ptr::write(&mut (*new_header).status, AtomicUsize::new(arc::MUT_STATUS_INIT));
+            // Don't carry the finalizer over to the cloned arc.
+            #[cfg(feature = "finalizers")]
+            ptr::write(&mut (*new_header).finalizer, None);
             // Try to clone the metadata.
             let new_metadata = match (*old_header).meta.try_clone() {
                 // Clone succeeded.
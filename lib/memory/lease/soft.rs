@@ -894,6 +894,9 @@ unsafe impl<'a, #[may_dangle] R: Resident> Drop for Soft<'a, R> {
                     Ok(_) => {
                         // Check if all references to the shared resident have been released.
                         if new_status & arc::REFERENCED_MASK == 0 {
+                            // Synchronize with the release of the last hard reference,
+                            // which dropped the resident, before freeing its memory.
+                            atomic::fence(Acquire);
                             // Shared resident has already been dropped; drop the arc header.
                             (*header).drop::<R>(data);
                             // Get the block of memory containing the arc structure.
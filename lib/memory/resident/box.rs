@@ -14,7 +14,7 @@ use crate::resident::{Resident, ResidentFromValue, ResidentFromClone,
                       ResidentDerefMut, ResidentAsRef, ResidentAsMut,
                       ResidentPartialEq, ResidentEq, ResidentPartialOrd,
                       ResidentOrd, ResidentHash, ResidentDisplay,
-                      ResidentDebug, ResidentClone, ResidentStow};
+                      ResidentDebug, ResidentClone, ResidentStow, ResidentSwap};
 
 /// A single value, residing in a memory `Lease`. A `Box` is a `Resident`
 /// typeclass; it doesn't store any data in its internal structure. Rather,
@@ -198,6 +198,16 @@ impl<L: Lease> ResidentAsMut<L, L::Data> for Box<L::Data, L::Meta> {
     }
 }
 
+impl<L1: Lease<Data=T, Meta=M>, L2: Lease<Data=T, Meta=M>, T, M> ResidentSwap<L1, L2> for Box<T, M> {
+    #[inline]
+    fn resident_swap(a: &mut L1, b: &mut L2) {
+        unsafe {
+            ptr::swap(a.data(), b.data());
+            ptr::swap(a.meta(), b.meta());
+        }
+    }
+}
+
 impl<L: Lease> ResidentPartialEq<L> for Box<L::Data, L::Meta> where L::Data: PartialEq {
     #[inline]
     fn resident_eq(lease: &L, other: &L) -> bool {
@@ -2,22 +2,23 @@ use core::cmp::Ordering;
 use core::fmt::{self, Debug, Formatter};
 use core::hash::{Hash, Hasher};
 use core::intrinsics::{arith_offset, assume};
-use core::iter::{FusedIterator, TrustedLen};
+use core::iter::{Chain, FusedIterator, TrustedLen};
 use core::marker::PhantomData;
-use core::mem;
+use core::mem::{self, MaybeUninit};
 use core::ops::{Bound, Deref, DerefMut, Index, IndexMut, RangeBounds};
 use core::ptr;
 use core::slice::{self, SliceIndex};
-use crate::block::{Layout, LayoutError, ZSP};
-use crate::alloc::{Hold, Holder, HoldError, TryClone, CloneIntoHold};
-use crate::lease::{Lease, DynamicLease};
+use crate::block::{Layout, LayoutError, Zeroable, ZSP};
+use crate::alloc::{AllocTag, Hold, Holder, HoldError, TryClone, CloneIntoHold};
+use crate::lease::{Lease, DynamicLease, Raw, Ptr};
 use crate::resident::{Resident, ResidentFromClone, ResidentFromCopy,
                       ResidentFromEmpty, ResidentWithCapacity, ResidentDeref,
                       ResidentDerefMut, ResidentAsRef, ResidentAsMut, ResidentIndex,
-                      ResidentIndexMut, ResidentIntoIterator,
+                      ResidentIndexMut, ResidentExtend, ResidentIntoIterator,
                       ResidentIntoRefIterator, ResidentIntoMutIterator,
                       ResidentPartialEq, ResidentEq, ResidentPartialOrd, ResidentOrd,
-                      ResidentHash, ResidentDebug, ResidentClone, ResidentStow};
+                      ResidentHash, ResidentDebug, ResidentClone, ResidentStow,
+                      ResidentSwap};
 
 /// A resizeable array of values, residing in a memory `Lease`. A `Buf` is a
 /// `Resident` typeclass; it doesn't store any data in its internal structure.
@@ -80,12 +81,72 @@ pub struct BufDrain<'a, L: Lease<Data=T, Meta=BufHeader<M>> + 'a, T: 'a, M: 'a =
     lower: usize,
     /// Exclusive upper bound index of the drained slice.
     upper: usize,
+    /// Length of the buf before draining.
+    len: usize,
     /// Pointer to the next element to iterate over.
     head: *const T,
     /// Pointer to the address after the last element to iterate over.
     foot: *const T,
 }
 
+/// Iterator over `n` disjoint mutable chunks of a buf, whose lengths differ
+/// by at most one. Returned by `BufLease::chunks_mut_even`.
+pub struct BufChunksEvenMut<'a, T: 'a> {
+    /// Slice of the elements that remain to be chunked.
+    rest: &'a mut [T],
+    /// Number of chunks that remain to be returned.
+    count: usize,
+    /// Minimum length of each chunk.
+    base: usize,
+    /// Number of remaining chunks that get one extra element.
+    extra: usize,
+}
+
+/// Iterator over the maximal runs of a buf's elements whose adjacent pairs
+/// satisfy a grouping predicate. Returned by `BufLease::chunk_by`.
+pub struct BufChunkBy<'a, T: 'a, F> {
+    /// Slice of the elements that remain to be grouped.
+    rest: &'a [T],
+    /// Returns `true` if two adjacent elements belong to the same group.
+    same_group: F,
+}
+
+/// Iterator over the windows of a buf treated as cyclic, one starting at
+/// each element, with windows that run past the last element wrapping around
+/// to the first. Returned by `BufLease::cyclic_windows`.
+pub struct BufCyclicWindows<'a, T: 'a> {
+    /// Slice of all the buf's elements.
+    slice: &'a [T],
+    /// Number of elements in each window, at most the length of the slice.
+    size: usize,
+    /// Index of the first element of the next window.
+    head: usize,
+    /// Index after the first element of the last window to iterate over.
+    foot: usize,
+}
+
+/// `fmt::Write` sink that appends UTF-8 formatted output to a byte buffer,
+/// retaining the `HoldError` of the last failed write.
+pub struct BufWriter<'b, L: Lease<Data=u8, Meta=BufHeader<M>> + 'b, M: 'b = ()> {
+    buf: &'b mut BufLease<L, u8, M>,
+    error: Option<HoldError>,
+}
+
+/// Panic guard for `BufLease::retain_mut`; shifts any unvisited elements
+/// down over the gap left by removed elements, and restores the buf length.
+struct BufRetain<T, M> {
+    /// Header of the buf being compacted.
+    header: *mut BufHeader<M>,
+    /// Pointer to the first element of the buf.
+    data: *mut T,
+    /// Length of the buf before compaction.
+    len: usize,
+    /// Number of elements visited so far.
+    processed: usize,
+    /// Number of visited elements that have been removed.
+    deleted: usize,
+}
+
 unsafe impl<T: Send, M: Send> Send for Buf<T, M> {
 }
 
@@ -399,6 +460,30 @@ impl<'a, L: Lease<Data=T, Meta=BufHeader<M>>, T: 'a, M> ResidentIntoMutIterator<
     }
 }
 
+impl<'a, L: DynamicLease<'a, Data=T, Meta=BufHeader<M>>, T, M> ResidentExtend<L, T> for Buf<T, M> {
+    #[inline]
+    fn resident_try_extend<I: IntoIterator<Item=T>>(lease: &mut L, iter: I) -> Result<(), HoldError> {
+        <Buf<T, M> as ResidentDerefMut<L>>::resident_deref_mut(lease).try_extend(iter)
+    }
+}
+
+impl<'a, 'b, L: DynamicLease<'a, Data=T, Meta=BufHeader<M>>, T: Copy + 'b, M> ResidentExtend<L, &'b T> for Buf<T, M> {
+    #[inline]
+    fn resident_try_extend<I: IntoIterator<Item=&'b T>>(lease: &mut L, iter: I) -> Result<(), HoldError> {
+        <Buf<T, M> as ResidentDerefMut<L>>::resident_deref_mut(lease).spec_try_extend(iter.into_iter())
+    }
+}
+
+impl<L: Lease<Data=T, Meta=BufHeader<M>>, T, M> ResidentSwap<L, L> for Buf<T, M> {
+    /// Exchanges the memory blocks of two leases of the same kind. The buf
+    /// header travels with its block, whether it's stored in the lease, as
+    /// with a `Raw` lease, or in the allocation, as with `Ptr` and `Mut` leases.
+    #[inline]
+    fn resident_swap(a: &mut L, b: &mut L) {
+        mem::swap(a, b);
+    }
+}
+
 impl<L: Lease<Data=T, Meta=BufHeader<M>>, T: PartialEq, M> ResidentPartialEq<L> for Buf<T, M> {
     #[inline]
     fn resident_eq(lease: &L, other: &L) -> bool {
@@ -485,7 +570,7 @@ impl<L1, L2, T, M> ResidentClone<L1, L2> for Buf<T, M>
             let mut dst_data = dst.data();
             let mut i = 0;
             while i < len {
-                let src_elem = match (*dst_data).try_clone() {
+                let dst_elem = match (*src_data).try_clone() {
                     Ok(elem) => elem,
                     Err(error) => {
                         while i > 0 {
@@ -496,7 +581,7 @@ impl<L1, L2, T, M> ResidentClone<L1, L2> for Buf<T, M>
                         return Err(error);
                     }
                 };
-                ptr::write(dst_data, src_elem);
+                ptr::write(dst_data, dst_elem);
                 src_data = src_data.wrapping_add(1);
                 dst_data = dst_data.wrapping_add(1);
                 i = i.wrapping_add(1);
@@ -545,6 +630,184 @@ impl<M: TryClone> TryClone for BufHeader<M> {
     }
 }
 
+/// Buf capacity reservation error.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TryReserveError {
+    /// Requested capacity overflows the buf's layout.
+    CapacityOverflow,
+    /// Hold failed to allocate the requested capacity.
+    AllocError(HoldError),
+}
+
+/// Error returned by `RawBuf::try_map`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TryMapError<E> {
+    /// The mapping function failed.
+    Map(E),
+    /// Hold failed to allocate the mapped buf.
+    AllocError(HoldError),
+}
+
+impl From<TryReserveError> for HoldError {
+    #[inline]
+    fn from(error: TryReserveError) -> HoldError {
+        match error {
+            TryReserveError::CapacityOverflow => HoldError::Oversized,
+            TryReserveError::AllocError(error) => error,
+        }
+    }
+}
+
+impl<'a, T> Raw<'a, Buf<T>> {
+    /// Decomposes this `RawBuf` into its data pointer, length, and capacity,
+    /// without freeing its memory. Use `RawBuf::from_raw_parts_in` to
+    /// reconstitute the returned parts back into a `RawBuf`. A memory leak
+    /// will occur unless the returned parts are eventually converted back
+    /// into a `RawBuf` and dropped.
+    #[inline]
+    pub fn into_raw_parts(this: Raw<'a, Buf<T>>) -> (*mut T, usize, usize) {
+        unsafe {
+            let (data, header) = Raw::into_raw_meta(this);
+            (data, header.len, header.cap)
+        }
+    }
+
+    /// Consumes `this` buf, passing each element, in order, to `f`, and
+    /// collecting the results into a new buf allocated in `hold`, with
+    /// capacity for exactly as many elements. Stops at the first element
+    /// for which `f` returns an error, dropping the elements not yet passed
+    /// to `f`, and the results already collected, before returning the error.
+    pub fn try_map<'b, U, E, F>(this: Raw<'a, Buf<T>>, mut f: F, hold: &dyn Hold<'b>)
+        -> Result<Raw<'b, Buf<U>>, TryMapError<E>>
+        where F: FnMut(T) -> Result<U, E>
+    {
+        let mut mapped = match Raw::<Buf<U>>::try_hold_cap(hold, this.len()) {
+            Ok(mapped) => mapped,
+            Err(error) => return Err(TryMapError::AllocError(error)),
+        };
+        // Dropping the iterator drops any remaining source elements.
+        for elem in this {
+            match f(elem) {
+                // Can't reallocate, because the buf has capacity for every element.
+                Ok(value) => mapped.push(value),
+                Err(error) => return Err(TryMapError::Map(error)),
+            }
+        }
+        Ok(mapped)
+    }
+
+    /// Reconstitutes a `RawBuf` from the data pointer, length, and capacity
+    /// returned by `RawBuf::into_raw_parts`.
+    ///
+    /// # Safety
+    ///
+    /// `data` must have been allocated by `hold` with room for exactly `cap`
+    /// elements, and must contain `len` initialized elements.
+    #[inline]
+    pub unsafe fn from_raw_parts_in(hold: &dyn Hold<'a>, data: *mut T, len: usize, cap: usize) -> Raw<'a, Buf<T>> {
+        debug_assert!(len <= cap);
+        debug_assert!(AllocTag::from_ptr(data as *mut u8).holder() as *const dyn Hold<'a> as *const u8
+                      == hold as *const dyn Hold<'a> as *const u8);
+        Raw::from_raw_meta(data, BufHeader { len: len, cap: cap, meta: () })
+    }
+}
+
+impl<'a, T: Clone> Raw<'a, Buf<T>> {
+    /// Allocates a new `RawBuf` in `hold` with capacity for exactly `n`
+    /// elements, filled with `n - 1` clones of `value`, followed by `value`
+    /// itself. If a clone panics, the clones already placed are dropped.
+    pub fn try_hold_from_elem(hold: &dyn Hold<'a>, value: T, n: usize) -> Result<Raw<'a, Buf<T>>, HoldError> {
+        let mut buf = Raw::<Buf<T>>::try_hold_cap(hold, n)?;
+        if n != 0 {
+            unsafe {
+                let data = buf.as_mut_ptr();
+                for i in 1..n {
+                    // Place the next clone in the spare capacity.
+                    ptr::write(data.wrapping_add(i - 1), value.clone());
+                    // Expose each placed clone, so that an unwinding clone drops it.
+                    buf.set_len(i);
+                }
+                // Move the value itself into the last slot.
+                ptr::write(data.wrapping_add(n - 1), value);
+                buf.set_len(n);
+            }
+        }
+        Ok(buf)
+    }
+
+    #[inline]
+    pub fn hold_from_elem(hold: &dyn Hold<'a>, value: T, n: usize) -> Raw<'a, Buf<T>> {
+        Raw::<Buf<T>>::try_hold_from_elem(hold, value, n).unwrap()
+    }
+
+    #[inline]
+    pub fn from_elem(value: T, n: usize) -> Raw<'a, Buf<T>> {
+        Raw::<Buf<T>>::hold_from_elem(Hold::global(), value, n)
+    }
+}
+
+impl<'a, T: Zeroable> Raw<'a, Buf<T>> {
+    /// Allocates a new `RawBuf` in `hold` with capacity for `cap` elements,
+    /// and with its length set to `cap`, with every element zeroed in bulk.
+    pub fn try_hold_cap_zeroed(hold: &dyn Hold<'a>, cap: usize) -> Result<Raw<'a, Buf<T>>, HoldError> {
+        let mut buf = Raw::<Buf<T>>::try_hold_cap(hold, cap)?;
+        unsafe {
+            // Zero the requested capacity; the all-zero pattern is a valid T.
+            ptr::write_bytes(buf.as_mut_ptr(), 0, cap);
+            // Expose the zeroed elements.
+            buf.set_len(cap);
+        }
+        Ok(buf)
+    }
+
+    #[inline]
+    pub fn hold_cap_zeroed(hold: &dyn Hold<'a>, cap: usize) -> Raw<'a, Buf<T>> {
+        Raw::<Buf<T>>::try_hold_cap_zeroed(hold, cap).unwrap()
+    }
+
+    #[inline]
+    pub fn with_cap_zeroed(cap: usize) -> Raw<'a, Buf<T>> {
+        Raw::<Buf<T>>::hold_cap_zeroed(Hold::global(), cap)
+    }
+}
+
+impl<'a, T> Ptr<'a, Buf<T>> {
+    /// Decomposes this `PtrBuf` into its data pointer, length, and capacity,
+    /// without freeing its memory. Use `PtrBuf::from_raw_parts_in` to
+    /// reconstitute the returned parts back into a `PtrBuf`. A memory leak
+    /// will occur unless the returned parts are eventually converted back
+    /// into a `PtrBuf` and dropped.
+    #[inline]
+    pub fn into_raw_parts(this: Ptr<'a, Buf<T>>) -> (*mut T, usize, usize) {
+        unsafe {
+            let header = *Ptr::header(&this);
+            (Ptr::into_raw(this), header.len, header.cap)
+        }
+    }
+
+    /// Reconstitutes a `PtrBuf` from the data pointer, length, and capacity
+    /// returned by `PtrBuf::into_raw_parts`, rewriting the `BufHeader` that
+    /// precedes the data in its allocation.
+    ///
+    /// # Safety
+    ///
+    /// `data` must have been returned by `PtrBuf::into_raw_parts` for a buf
+    /// allocated by `hold` with room for exactly `cap` elements, and must
+    /// contain `len` initialized elements.
+    pub unsafe fn from_raw_parts_in(hold: &dyn Hold<'a>, data: *mut T, len: usize, cap: usize) -> Ptr<'a, Buf<T>> {
+        debug_assert!(len <= cap);
+        // Reconstitute the lease around the resident data.
+        let lease = Ptr::from_raw(data);
+        // Get a pointer to the header preceding the resident data.
+        let header = Ptr::header(&lease);
+        debug_assert!(AllocTag::from_ptr(header as *mut u8).holder() as *const dyn Hold<'a> as *const u8
+                      == hold as *const dyn Hold<'a> as *const u8);
+        // Re-establish the buf header.
+        ptr::write(header, BufHeader { len: len, cap: cap, meta: () });
+        lease
+    }
+}
+
 impl<L: Lease<Data=T, Meta=BufHeader<M>>, T, M> BufLease<L, T, M> {
     #[inline]
     fn header(&self) -> &BufHeader<M> {
@@ -566,8 +829,16 @@ impl<L: Lease<Data=T, Meta=BufHeader<M>>, T, M> BufLease<L, T, M> {
         self.header().len
     }
 
+    /// Sets the length of this buf to `new_len`, without dropping or
+    /// initializing any elements.
+    ///
+    /// # Safety
+    ///
+    /// `new_len` must not exceed `cap()`, and the elements in the range
+    /// `[0, new_len)` must be initialized.
     #[inline]
     pub unsafe fn set_len(&mut self, new_len: usize) {
+        debug_assert!(new_len <= self.cap());
         self.header_mut().len = new_len;
     }
 
@@ -610,6 +881,136 @@ impl<L: Lease<Data=T, Meta=BufHeader<M>>, T, M> BufLease<L, T, M> {
         Buf::as_mut_slice(&mut self.lease)
     }
 
+    /// Returns the elements of this buf as a single mutable slice beginning
+    /// at the base of the allocation. A buf's elements always reside
+    /// contiguously from the base of its lease, so this never moves elements;
+    /// it exists so that code written against residents whose logical start
+    /// may wrap or float can treat bufs uniformly.
+    #[inline]
+    pub fn make_contiguous(&mut self) -> &mut [T] {
+        self.as_mut_slice()
+    }
+
+    /// Returns the first element of this buf and the slice of all remaining
+    /// elements, or `None` if the buf is empty.
+    #[inline]
+    pub fn split_first(&self) -> Option<(&T, &[T])> {
+        self.as_slice().split_first()
+    }
+
+    /// Returns the last element of this buf and the slice of all preceding
+    /// elements, or `None` if the buf is empty.
+    #[inline]
+    pub fn split_last(&self) -> Option<(&T, &[T])> {
+        self.as_slice().split_last()
+    }
+
+    /// Returns the elements of this buf reinterpreted as a slice of `U`, or
+    /// `None` if the data pointer isn't aligned for `U`, if the byte length
+    /// of the buf isn't a multiple of `size_of::<U>()`, or if `U` is zero
+    /// sized.
+    ///
+    /// # Safety
+    ///
+    /// Both `T` and `U` must be plain old data: every bit pattern of the
+    /// buf's bytes must be a valid `U`, and neither type may have padding.
+    #[inline]
+    pub unsafe fn as_slice_of<U: Copy>(&self) -> Option<&[U]> where T: Copy {
+        let size = mem::size_of::<U>();
+        if size == 0 {
+            return None;
+        }
+        let data = self.as_ptr();
+        let bytes = self.len().wrapping_mul(mem::size_of::<T>());
+        if data as usize % mem::align_of::<U>() != 0 || bytes % size != 0 {
+            return None;
+        }
+        Some(slice::from_raw_parts(data as *const U, bytes / size))
+    }
+
+    /// Returns an iterator over `n` disjoint mutable sub-slices that
+    /// partition this buf as evenly as possible; earlier chunks get one
+    /// extra element each until the remainder is used up. Returns empty
+    /// chunks if `n` exceeds the length of the buf.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is zero.
+    pub fn chunks_mut_even(&mut self, n: usize) -> BufChunksEvenMut<'_, T> {
+        assert!(n != 0, "zero chunks");
+        let slice = self.as_mut_slice();
+        let len = slice.len();
+        BufChunksEvenMut {
+            rest: slice,
+            count: n,
+            base: len / n,
+            extra: len % n,
+        }
+    }
+
+    /// Returns an iterator over the maximal sub-slices of this buf in which
+    /// every pair of adjacent elements satisfies `same_group`. Yields
+    /// nothing if the buf is empty.
+    #[inline]
+    pub fn chunk_by<F: FnMut(&T, &T) -> bool>(&self, same_group: F) -> BufChunkBy<'_, T, F> {
+        BufChunkBy {
+            rest: self.as_slice(),
+            same_group: same_group,
+        }
+    }
+
+    /// Returns an iterator over the `len` windows of `size` consecutive
+    /// elements of this buf, treated as cyclic: the window starting at each
+    /// element wraps around past the last element to the first. A `size`
+    /// greater than `len` is clamped to `len`, so that no window visits an
+    /// element twice. Yields nothing if the buf is empty. Doesn't allocate.
+    #[inline]
+    pub fn cyclic_windows(&self, size: usize) -> BufCyclicWindows<'_, T> {
+        let slice = self.as_slice();
+        BufCyclicWindows {
+            slice: slice,
+            size: if size < slice.len() { size } else { slice.len() },
+            head: 0,
+            foot: slice.len(),
+        }
+    }
+
+    /// Returns the index of the first element for which `pred` returns
+    /// `false`, assuming the buf is partitioned such that all elements for
+    /// which `pred` returns `true` precede all elements for which it returns
+    /// `false`. Uses a binary search; the result is unspecified if the buf
+    /// is not partitioned by `pred`.
+    #[inline]
+    pub fn partition_point<P: FnMut(&T) -> bool>(&self, pred: P) -> usize {
+        self.as_slice().partition_point(pred)
+    }
+
+    /// Swaps the elements at indexes `a` and `b` of this buf; does nothing
+    /// if `a == b`. Panics if either index is out of bounds.
+    #[inline]
+    pub fn swap(&mut self, a: usize, b: usize) {
+        self.as_mut_slice().swap(a, b);
+    }
+
+    /// Reverses the order of the elements of this buf, in place.
+    #[inline]
+    pub fn reverse(&mut self) {
+        self.as_mut_slice().reverse();
+    }
+
+    /// Returns the uninitialized capacity beyond the length of this buf.
+    /// Call `set_len` after initializing any of the returned elements to
+    /// include them in the buf.
+    pub fn spare_capacity_mut(&mut self) -> &mut [MaybeUninit<T>] {
+        unsafe {
+            let header = self.lease.meta();
+            let len = (*header).len;
+            let cap = self.cap();
+            let data = self.lease.data().wrapping_add(len) as *mut MaybeUninit<T>;
+            slice::from_raw_parts_mut(data, cap.wrapping_sub(len))
+        }
+    }
+
     pub fn pop(&mut self) -> Option<T> {
         unsafe {
             let header = self.lease.meta();
@@ -629,10 +1030,10 @@ impl<L: Lease<Data=T, Meta=BufHeader<M>>, T, M> BufLease<L, T, M> {
         unsafe {
             let header = self.lease.meta();
             let len = (*header).len;
-            assert!(index <= len);
+            assert!(index < len);
             let data = self.lease.data().wrapping_add(index);
             let elem = ptr::read(data);
-            ptr::copy(data.wrapping_add(1), data, len.wrapping_sub(index).wrapping_add(1));
+            ptr::copy(data.wrapping_add(1), data, len.wrapping_sub(index).wrapping_sub(1));
             (*header).len = len.wrapping_sub(1);
             elem
         }
@@ -654,6 +1055,47 @@ impl<L: Lease<Data=T, Meta=BufHeader<M>>, T, M> BufLease<L, T, M> {
         self.truncate(0);
     }
 
+    /// Retains only the elements for which `f` returns `true`, dropping the
+    /// rest, and compacting the kept elements in place, in a single pass.
+    #[inline]
+    pub fn retain<F: FnMut(&T) -> bool>(&mut self, mut f: F) {
+        self.retain_mut(|elem| f(elem));
+    }
+
+    /// Retains only the elements for which `f` returns `true`, dropping the
+    /// rest, and compacting the kept elements in place, in a single pass.
+    /// Each element is passed to `f` mutably, in order, so that kept elements
+    /// can be updated as they're visited. If `f` panics, all elements not yet
+    /// visited are kept, and no element is dropped twice.
+    pub fn retain_mut<F: FnMut(&mut T) -> bool>(&mut self, mut f: F) {
+        unsafe {
+            let header = self.lease.meta();
+            let len = (*header).len;
+            // Hide all elements from the buf until compaction completes.
+            (*header).len = 0;
+            let mut guard = BufRetain {
+                header: header,
+                data: self.lease.data(),
+                len: len,
+                processed: 0,
+                deleted: 0,
+            };
+            while guard.processed < len {
+                let elem = guard.data.wrapping_add(guard.processed);
+                let keep = f(&mut *elem);
+                // Count the element as visited before it can be dropped.
+                guard.processed = guard.processed.wrapping_add(1);
+                if !keep {
+                    guard.deleted = guard.deleted.wrapping_add(1);
+                    ptr::drop_in_place(elem);
+                } else if guard.deleted != 0 {
+                    ptr::copy_nonoverlapping(elem, elem.wrapping_sub(guard.deleted), 1);
+                }
+            }
+            // Guard restores the compacted length on drop.
+        }
+    }
+
     pub fn drain<R: RangeBounds<usize>>(&mut self, range: R) -> BufDrain<L, T, M> {
         let len = self.header().len;
         let lower = match range.start_bound() {
@@ -669,14 +1111,50 @@ impl<L: Lease<Data=T, Meta=BufHeader<M>>, T, M> BufLease<L, T, M> {
         assert!(lower <= upper);
         assert!(upper <= len);
         let data = self.lease.data();
+        // Hide the drained and tail elements from the buf until the drain
+        // drops, so that a leaked drain can't cause a double drop.
+        self.header_mut().len = lower;
         BufDrain {
             buf: self,
             lower: lower,
             upper: upper,
+            len: len,
             head: data.wrapping_add(lower),
             foot: data.wrapping_add(upper),
         }
     }
+
+    /// Removes all but the first of each run of consecutive elements for
+    /// which `same` returns `true`, compacting the retained elements in
+    /// place, and returns a drain that yields the removed elements, in
+    /// unspecified order, instead of dropping them. `same` is passed each
+    /// element, followed by the last retained element before it. Removed
+    /// elements not yielded by the drain are dropped along with the drain;
+    /// leaking the drain leaks them, and leaves the retained elements in the
+    /// buf. If `same` panics, the buf keeps all of its elements, possibly
+    /// reordered.
+    pub fn dedup_drain<F: FnMut(&mut T, &mut T) -> bool>(&mut self, mut same: F) -> BufDrain<L, T, M> {
+        let len = self.header().len;
+        let mut kept: usize = if len != 0 { 1 } else { 0 };
+        unsafe {
+            let data = self.lease.data();
+            let mut index = 1;
+            while index < len {
+                let elem = data.wrapping_add(index);
+                let prev = data.wrapping_add(kept.wrapping_sub(1));
+                if !same(&mut *elem, &mut *prev) {
+                    // Swap the retained element with the first removed
+                    // element, keeping every element in the buf exactly once.
+                    if index != kept {
+                        ptr::swap(elem, data.wrapping_add(kept));
+                    }
+                    kept = kept.wrapping_add(1);
+                }
+                index = index.wrapping_add(1);
+            }
+        }
+        self.drain(kept..)
+    }
 }
 
 impl<'a, L: DynamicLease<'a, Data=T, Meta=BufHeader<M>>, T, M> BufLease<L, T, M> {
@@ -711,7 +1189,7 @@ impl<'a, L: DynamicLease<'a, Data=T, Meta=BufHeader<M>>, T, M> BufLease<L, T, M>
         self.try_reserve(ext).unwrap();
     }
 
-    pub fn try_reserve_exact(&mut self, ext: usize) -> Result<(), HoldError> {
+    pub fn try_reserve_exact(&mut self, ext: usize) -> Result<(), TryReserveError> {
         unsafe {
             let header = self.lease.meta();
             let len = (*header).len;
@@ -721,15 +1199,18 @@ impl<'a, L: DynamicLease<'a, Data=T, Meta=BufHeader<M>>, T, M> BufLease<L, T, M>
             }
             let new_cap = match len.checked_add(ext) {
                 Some(cap) => cap,
-                None => return Err(HoldError::Oversized),
+                None => return Err(TryReserveError::CapacityOverflow),
+            };
+            let new_layout = match Layout::for_array::<T>(new_cap) {
+                Ok(layout) => layout,
+                Err(_) => return Err(TryReserveError::CapacityOverflow),
             };
-            let new_layout = Layout::for_array::<T>(new_cap)?;
             match self.lease.realloc(new_layout) {
-                ok @ Ok(_) => {
+                Ok(_) => {
                     (*header).cap = new_cap;
-                    ok
+                    Ok(())
                 },
-                err @ Err(_) => err,
+                Err(error) => Err(TryReserveError::AllocError(error)),
             }
         }
     }
@@ -810,7 +1291,7 @@ impl<'a, L: DynamicLease<'a, Data=T, Meta=BufHeader<M>>, T, M> BufLease<L, T, M>
             let len = (*header).len;
             assert!(index <= len);
             self.try_reserve(1)?;
-            let data = self.lease.data().wrapping_add(len);
+            let data = self.lease.data().wrapping_add(index);
             ptr::copy(data, data.wrapping_add(1), len.wrapping_sub(index));
             ptr::write(data, elem);
             (*header).len = len.wrapping_add(1);
@@ -822,6 +1303,55 @@ impl<'a, L: DynamicLease<'a, Data=T, Meta=BufHeader<M>>, T, M> BufLease<L, T, M>
         self.try_insert(index, elem).unwrap();
     }
 
+    /// Inserts `elem` into this sorted buf at the position that keeps the buf
+    /// sorted, found by binary search, and returns the index of `elem`.
+    /// Inserts `elem` after any elements equal to it, so that equal elements
+    /// remain in insertion order. Returns a `HoldError`, leaving this buf
+    /// unchanged, if the buf fails to grow. The insertion position is
+    /// unspecified if the buf is not sorted.
+    pub fn try_binary_insert(&mut self, elem: T) -> Result<usize, HoldError> where T: Ord {
+        self.try_binary_insert_by(elem, T::cmp)
+    }
+
+    pub fn binary_insert(&mut self, elem: T) -> usize where T: Ord {
+        self.try_binary_insert(elem).unwrap()
+    }
+
+    /// Inserts `elem` into this buf, sorted by the comparator function
+    /// `compare`, after any elements that compare equal to it; returns the
+    /// index of `elem`.
+    pub fn try_binary_insert_by<F>(&mut self, elem: T, mut compare: F) -> Result<usize, HoldError>
+        where F: FnMut(&T, &T) -> Ordering
+    {
+        let index = self.partition_point(|x| compare(x, &elem) != Ordering::Greater);
+        self.try_insert(index, elem)?;
+        Ok(index)
+    }
+
+    pub fn binary_insert_by<F>(&mut self, elem: T, compare: F) -> usize
+        where F: FnMut(&T, &T) -> Ordering
+    {
+        self.try_binary_insert_by(elem, compare).unwrap()
+    }
+
+    /// Inserts `elem` into this buf, sorted by the key extraction function
+    /// `f`, after any elements with keys equal to its key; returns the index
+    /// of `elem`.
+    pub fn try_binary_insert_by_key<K: Ord, F>(&mut self, elem: T, mut f: F) -> Result<usize, HoldError>
+        where F: FnMut(&T) -> K
+    {
+        let key = f(&elem);
+        let index = self.partition_point(|x| f(x) <= key);
+        self.try_insert(index, elem)?;
+        Ok(index)
+    }
+
+    pub fn binary_insert_by_key<K: Ord, F>(&mut self, elem: T, f: F) -> usize
+        where F: FnMut(&T) -> K
+    {
+        self.try_binary_insert_by_key(elem, f).unwrap()
+    }
+
     #[inline]
     pub fn try_extend<I: IntoIterator<Item=T>>(&mut self, iter: I) -> Result<(), HoldError> {
         <Self as SpecExtend<T, I::IntoIter>>::spec_try_extend(self, iter.into_iter())
@@ -843,6 +1373,69 @@ impl<'a, L: DynamicLease<'a, Data=T, Meta=BufHeader<M>>, T: Clone, M> BufLease<L
     pub fn extend_from_slice(&mut self, slice: &[T]) {
         self.try_extend_from_slice(slice).unwrap();
     }
+
+    /// Inserts clones of all elements of `slice` at position `index`,
+    /// shifting all subsequent elements to the right. Reserves capacity for
+    /// the whole slice before moving any elements, and shifts the tail just
+    /// once. Returns a `HoldError`, leaving this buf unchanged, if the buf
+    /// fails to grow. Panics if `index > len`.
+    pub fn try_insert_slice(&mut self, index: usize, slice: &[T]) -> Result<(), HoldError> {
+        unsafe {
+            let header = self.lease.meta();
+            let len = (*header).len;
+            assert!(index <= len);
+            let n = slice.len();
+            self.try_reserve(n)?;
+            let data = self.lease.data().wrapping_add(index);
+            ptr::copy(data, data.wrapping_add(n), len.wrapping_sub(index));
+            // Leak the shifted tail, rather than double drop it, if a clone panics.
+            (*header).len = index;
+            for i in 0..n {
+                ptr::write(data.wrapping_add(i), slice.get_unchecked(i).clone());
+            }
+            (*header).len = len.wrapping_add(n);
+            Ok(())
+        }
+    }
+
+    pub fn insert_slice(&mut self, index: usize, slice: &[T]) {
+        self.try_insert_slice(index, slice).unwrap();
+    }
+
+    /// Resizes this buf in place to `new_len` elements, dropping the excess
+    /// tail elements when shrinking, and appending clones of `value` when
+    /// growing. Reserves capacity for all new elements before cloning any.
+    /// Returns a `HoldError`, leaving this buf unchanged, if the buf fails
+    /// to grow.
+    pub fn try_resize(&mut self, new_len: usize, value: T) -> Result<(), HoldError> {
+        unsafe {
+            let header = self.lease.meta();
+            let len = (*header).len;
+            if new_len <= len {
+                self.truncate(new_len);
+                return Ok(());
+            }
+            let n = new_len.wrapping_sub(len);
+            self.try_reserve(n)?;
+            let data = self.lease.data().wrapping_add(len);
+            let mut i = 1;
+            while i < n {
+                ptr::write(data.wrapping_add(i.wrapping_sub(1)), value.clone());
+                // Count each clone as it's written, so that a panicking clone
+                // drops only initialized elements.
+                (*header).len = len.wrapping_add(i);
+                i = i.wrapping_add(1);
+            }
+            // Move the fill value itself into the last new slot.
+            ptr::write(data.wrapping_add(n.wrapping_sub(1)), value);
+            (*header).len = new_len;
+            Ok(())
+        }
+    }
+
+    pub fn resize(&mut self, new_len: usize, value: T) {
+        self.try_resize(new_len, value).unwrap();
+    }
 }
 
 impl<'a, L: DynamicLease<'a, Data=T, Meta=BufHeader<M>>, T: TryClone, M> BufLease<L, T, M> {
@@ -867,6 +1460,146 @@ impl<'a, L: DynamicLease<'a, Data=T, Meta=BufHeader<M>>, T: TryClone, M> BufLeas
     }
 }
 
+impl<'a, L: DynamicLease<'a, Data=u8, Meta=BufHeader<M>>, M> BufLease<L, u8, M> {
+    /// Appends all `bytes` to the end of this buffer; returns a `HoldError`
+    /// if the buffer fails to grow.
+    #[inline]
+    pub fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), HoldError> {
+        self.try_extend_from_slice(bytes)
+    }
+
+    /// Returns a `fmt::Write` sink that appends formatted output to this
+    /// buffer, and whose `take_error` recovers the `HoldError` behind any
+    /// `fmt::Error` it returns.
+    #[inline]
+    pub fn writer(&mut self) -> BufWriter<'_, L, M> {
+        BufWriter {
+            buf: self,
+            error: None,
+        }
+    }
+}
+
+impl<'a, L: Lease<Data=T, Meta=BufHeader<M>> + Holder<'a>, T, M> BufLease<L, T, M> {
+    /// Flattens this buf of slices into a new `RawBuf`, allocated in the same
+    /// `Hold` as this buf. Allocates exactly once, sized to the total length
+    /// of all slices; returns a `HoldError` on allocation failure, without
+    /// leaving a partially filled buf behind.
+    pub fn try_concat<U: Clone>(&self) -> Result<Raw<'a, Buf<U>>, HoldError> where T: AsRef<[U]> {
+        self.try_concat_in(self.lease.holder())
+    }
+
+    pub fn concat<U: Clone>(&self) -> Raw<'a, Buf<U>> where T: AsRef<[U]> {
+        self.try_concat().unwrap()
+    }
+
+    /// Flattens this buf of slices into a new `RawBuf` allocated in `hold`,
+    /// which may differ from the `Hold` of this buf. Allocates exactly once,
+    /// sized to the total length of all slices; returns a `HoldError` on
+    /// allocation failure, without leaving a partially filled buf behind.
+    pub fn try_concat_in<'b, U: Clone>(&self, hold: &dyn Hold<'b>) -> Result<Raw<'b, Buf<U>>, HoldError>
+        where T: AsRef<[U]>
+    {
+        let mut len = 0usize;
+        for part in self.as_slice() {
+            len = match len.checked_add(part.as_ref().len()) {
+                Some(len) => len,
+                None => return Err(HoldError::Oversized),
+            };
+        }
+        let mut buf = Raw::<Buf<U>>::try_hold_cap(hold, len)?;
+        for part in self.as_slice() {
+            buf.try_extend_from_slice(part.as_ref())?;
+        }
+        Ok(buf)
+    }
+
+    pub fn concat_in<'b, U: Clone>(&self, hold: &dyn Hold<'b>) -> Raw<'b, Buf<U>> where T: AsRef<[U]> {
+        self.try_concat_in(hold).unwrap()
+    }
+
+    /// Flattens this buf of slices into a new `RawBuf`, allocated in the same
+    /// `Hold` as this buf, interposing `sep` between each slice. Allocates
+    /// exactly once, sized to the total length of all slices and separators;
+    /// returns a `HoldError` on allocation failure, without leaving a
+    /// partially filled buf behind.
+    pub fn try_join<U: Clone>(&self, sep: &[U]) -> Result<Raw<'a, Buf<U>>, HoldError> where T: AsRef<[U]> {
+        self.try_join_in(sep, self.lease.holder())
+    }
+
+    pub fn join<U: Clone>(&self, sep: &[U]) -> Raw<'a, Buf<U>> where T: AsRef<[U]> {
+        self.try_join(sep).unwrap()
+    }
+
+    /// Flattens this buf of slices into a new `RawBuf` allocated in `hold`,
+    /// which may differ from the `Hold` of this buf, interposing `sep`
+    /// between each slice. Allocates exactly once, sized to the total length
+    /// of all slices and separators; returns a `HoldError` on allocation
+    /// failure, without leaving a partially filled buf behind.
+    pub fn try_join_in<'b, U: Clone>(&self, sep: &[U], hold: &dyn Hold<'b>) -> Result<Raw<'b, Buf<U>>, HoldError>
+        where T: AsRef<[U]>
+    {
+        let parts = self.as_slice();
+        let mut len = match sep.len().checked_mul(parts.len().saturating_sub(1)) {
+            Some(len) => len,
+            None => return Err(HoldError::Oversized),
+        };
+        for part in parts {
+            len = match len.checked_add(part.as_ref().len()) {
+                Some(len) => len,
+                None => return Err(HoldError::Oversized),
+            };
+        }
+        let mut buf = Raw::<Buf<U>>::try_hold_cap(hold, len)?;
+        for (i, part) in parts.iter().enumerate() {
+            if i != 0 {
+                buf.try_extend_from_slice(sep)?;
+            }
+            buf.try_extend_from_slice(part.as_ref())?;
+        }
+        Ok(buf)
+    }
+
+    pub fn join_in<'b, U: Clone>(&self, sep: &[U], hold: &dyn Hold<'b>) -> Raw<'b, Buf<U>> where T: AsRef<[U]> {
+        self.try_join_in(sep, hold).unwrap()
+    }
+
+    /// Stably sorts this buf by the key `f` extracts from each element,
+    /// calling `f` exactly once per element. Keys are cached, along with
+    /// their element indexes, in a scratch buf allocated in the same `Hold`
+    /// as this buf; returns a `HoldError`, leaving this buf unchanged, if
+    /// the scratch buf can't be allocated.
+    pub fn try_sort_by_cached_key<K: Ord, F: FnMut(&T) -> K>(&mut self, mut f: F) -> Result<(), HoldError> {
+        let len = self.len();
+        if len < 2 {
+            return Ok(());
+        }
+        let mut keys = Raw::<Buf<(K, usize)>>::try_hold_cap(self.lease.holder(), len)?;
+        for (index, elem) in self.as_slice().iter().enumerate() {
+            // Can't reallocate, because the scratch buf has capacity for every key.
+            keys.push((f(elem), index));
+        }
+        // Indexes make every pair distinct, so an unstable sort is stable.
+        keys.sort_unstable();
+        let slice = self.as_mut_slice();
+        for i in 0..len {
+            // Follow the chain of elements already swapped out of position i.
+            let mut index = keys[i].1;
+            while index < i {
+                index = keys[index].1;
+            }
+            // Record where the element displaced from position i now lives.
+            keys[i].1 = index;
+            slice.swap(i, index);
+        }
+        Ok(())
+    }
+
+    pub fn sort_by_cached_key<K: Ord, F: FnMut(&T) -> K>(&mut self, f: F) {
+        self.try_sort_by_cached_key(f).unwrap()
+    }
+}
+
 impl<L: Lease<Data=T, Meta=BufHeader<M>>, T, M> Deref for BufLease<L, T, M> {
     type Target = [T];
 
@@ -883,6 +1616,37 @@ impl<L: Lease<Data=T, Meta=BufHeader<M>>, T, M> DerefMut for BufLease<L, T, M> {
     }
 }
 
+impl<'a, L: DynamicLease<'a, Data=u8, Meta=BufHeader<M>>, M> fmt::Write for BufLease<L, u8, M> {
+    #[inline]
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        match self.try_extend_from_slice(s.as_bytes()) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(fmt::Error),
+        }
+    }
+}
+
+impl<'a, 'b, L: DynamicLease<'a, Data=u8, Meta=BufHeader<M>>, M> BufWriter<'b, L, M> {
+    /// Returns the `HoldError` of the last failed write, if any, and clears it.
+    #[inline]
+    pub fn take_error(&mut self) -> Option<HoldError> {
+        self.error.take()
+    }
+}
+
+impl<'a, 'b, L: DynamicLease<'a, Data=u8, Meta=BufHeader<M>>, M> fmt::Write for BufWriter<'b, L, M> {
+    #[inline]
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        match self.buf.try_extend_from_slice(s.as_bytes()) {
+            Ok(_) => Ok(()),
+            Err(error) => {
+                self.error = Some(error);
+                Err(fmt::Error)
+            },
+        }
+    }
+}
+
 unsafe impl<L: Lease<Data=T, Meta=BufHeader<M>> + Send, T: Send, M: Send> Send for BufIter<L,T, M> {
 }
 
@@ -1017,6 +1781,149 @@ unsafe impl<'a, L: Lease<Data=T, Meta=BufHeader<M>> + Send, T: Send, M: Send> Se
 unsafe impl<'a, L: Lease<Data=T, Meta=BufHeader<M>> + Sync, T: Sync, M: Sync> Sync for BufDrain<'a, L,T, M> {
 }
 
+impl<'a, T: 'a> Iterator for BufChunksEvenMut<'a, T> {
+    type Item = &'a mut [T];
+
+    #[inline]
+    fn next(&mut self) -> Option<&'a mut [T]> {
+        if self.count == 0 {
+            return None;
+        }
+        // Compute the length of the next chunk.
+        let mut size = self.base;
+        if self.extra != 0 {
+            size = size.wrapping_add(1);
+            self.extra = self.extra.wrapping_sub(1);
+        }
+        self.count = self.count.wrapping_sub(1);
+        // Split the chunk off the front of the remaining elements.
+        let rest = mem::replace(&mut self.rest, &mut []);
+        let (chunk, rest) = rest.split_at_mut(size);
+        self.rest = rest;
+        Some(chunk)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.count, Some(self.count))
+    }
+}
+
+impl<'a, T: 'a> ExactSizeIterator for BufChunksEvenMut<'a, T> {
+}
+
+impl<'a, T: 'a> FusedIterator for BufChunksEvenMut<'a, T> {
+}
+
+impl<'a, T: 'a, F: FnMut(&T, &T) -> bool> Iterator for BufChunkBy<'a, T, F> {
+    type Item = &'a [T];
+
+    #[inline]
+    fn next(&mut self) -> Option<&'a [T]> {
+        if self.rest.is_empty() {
+            return None;
+        }
+        // Find the end of the leading run.
+        let mut size = 1;
+        while size < self.rest.len() && (self.same_group)(&self.rest[size - 1], &self.rest[size]) {
+            size = size.wrapping_add(1);
+        }
+        // Split the run off the front of the remaining elements.
+        let (chunk, rest) = self.rest.split_at(size);
+        self.rest = rest;
+        Some(chunk)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.rest.is_empty() { (0, Some(0)) } else { (1, Some(self.rest.len())) }
+    }
+}
+
+impl<'a, T: 'a, F: FnMut(&T, &T) -> bool> DoubleEndedIterator for BufChunkBy<'a, T, F> {
+    #[inline]
+    fn next_back(&mut self) -> Option<&'a [T]> {
+        if self.rest.is_empty() {
+            return None;
+        }
+        // Find the start of the trailing run.
+        let mut index = self.rest.len() - 1;
+        while index > 0 && (self.same_group)(&self.rest[index - 1], &self.rest[index]) {
+            index = index.wrapping_sub(1);
+        }
+        // Split the run off the back of the remaining elements.
+        let (rest, chunk) = self.rest.split_at(index);
+        self.rest = rest;
+        Some(chunk)
+    }
+}
+
+impl<'a, T: 'a, F: FnMut(&T, &T) -> bool> FusedIterator for BufChunkBy<'a, T, F> {
+}
+
+impl<'a, T: 'a> BufCyclicWindows<'a, T> {
+    /// Returns the window starting at the element at `index`.
+    #[inline]
+    fn window(&self, index: usize) -> Chain<slice::Iter<'a, T>, slice::Iter<'a, T>> {
+        let len = self.slice.len();
+        let end = index.wrapping_add(self.size);
+        if end <= len {
+            self.slice[index..end].iter().chain(self.slice[..0].iter())
+        } else {
+            // Wrap around to the first element.
+            self.slice[index..].iter().chain(self.slice[..end.wrapping_sub(len)].iter())
+        }
+    }
+}
+
+impl<'a, T: 'a> Iterator for BufCyclicWindows<'a, T> {
+    type Item = Chain<slice::Iter<'a, T>, slice::Iter<'a, T>>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.head == self.foot {
+            return None;
+        }
+        let window = self.window(self.head);
+        self.head = self.head.wrapping_add(1);
+        Some(window)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.foot.wrapping_sub(self.head);
+        (len, Some(len))
+    }
+}
+
+impl<'a, T: 'a> DoubleEndedIterator for BufCyclicWindows<'a, T> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.head == self.foot {
+            return None;
+        }
+        self.foot = self.foot.wrapping_sub(1);
+        Some(self.window(self.foot))
+    }
+}
+
+impl<'a, T: 'a> ExactSizeIterator for BufCyclicWindows<'a, T> {
+}
+
+impl<'a, T: 'a> FusedIterator for BufCyclicWindows<'a, T> {
+}
+
+impl<'a, T: 'a> Clone for BufCyclicWindows<'a, T> {
+    fn clone(&self) -> Self {
+        BufCyclicWindows {
+            slice: self.slice,
+            size: self.size,
+            head: self.head,
+            foot: self.foot,
+        }
+    }
+}
+
 impl<'a, L: Lease<Data=T, Meta=BufHeader<M>>, T, M> BufDrain<'a, L, T, M> {
     #[inline]
     pub fn meta(&self) -> &M {
@@ -1124,15 +2031,29 @@ impl<'a, L: Lease<Data=T, Meta=BufHeader<M>>, T, M> Drop for BufDrain<'a, L, T,
     fn drop(&mut self) {
         unsafe {
             ptr::drop_in_place(self.as_mut_slice());
-            let meta = self.buf.lease.meta();
-            let len = (*meta).len;
-            if self.upper < len {
+            let tail = self.len.wrapping_sub(self.upper);
+            if tail != 0 && self.lower != self.upper {
                 let data = self.buf.lease.data();
                 ptr::copy(data.wrapping_add(self.upper),
                           data.wrapping_add(self.lower),
-                          len.wrapping_sub(self.upper));
-                (*meta).len = len.wrapping_sub(self.upper.wrapping_sub(self.lower));
+                          tail);
+            }
+            // Restore the tail elements to the buf.
+            (*self.buf.lease.meta()).len = self.lower.wrapping_add(tail);
+        }
+    }
+}
+
+impl<T, M> Drop for BufRetain<T, M> {
+    fn drop(&mut self) {
+        unsafe {
+            let tail = self.len.wrapping_sub(self.processed);
+            if self.deleted != 0 && tail != 0 {
+                ptr::copy(self.data.wrapping_add(self.processed),
+                          self.data.wrapping_add(self.processed.wrapping_sub(self.deleted)),
+                          tail);
             }
+            (*self.header).len = self.len.wrapping_sub(self.deleted);
         }
     }
 }
@@ -1153,7 +2074,7 @@ impl<'a, L, T, M, I> SpecExtend<T, I> for BufLease<L, T, M>
                     let (lower, _) = iter.size_hint();
                     self.try_reserve(lower.saturating_add(1))?;
                 }
-                ptr::write(self.get_unchecked_mut(len), elem);
+                ptr::write(self.lease.data().wrapping_add(len), elem);
                 self.set_len(len.wrapping_add(1));
             }
             Ok(())
@@ -1171,7 +2092,7 @@ impl<'a, L, T, M, I> SpecExtend<T, I> for BufLease<L, T, M>
             while let Some(elem)  = iter.next() {
                 let len = self.len();
                 debug_assert!(len != self.cap());
-                ptr::write(self.get_unchecked_mut(len), elem);
+                ptr::write(self.lease.data().wrapping_add(len), elem);
                 self.set_len(len.wrapping_add(1));
             }
             Ok(())
@@ -1224,7 +2145,7 @@ impl<'a, L, T, M, I> SpecExtendClone<T, I> for BufLease<L, T, M>
                     let (lower, _) = iter.size_hint();
                     self.try_reserve(lower.saturating_add(1))?;
                 }
-                ptr::write(self.get_unchecked_mut(len), match item.try_clone() {
+                ptr::write(self.lease.data().wrapping_add(len), match item.try_clone() {
                     Ok(elem) => elem,
                     Err(error) => return Err(error),
                 });
@@ -1246,7 +2167,7 @@ impl<'a, L, T, M, I> SpecExtendClone<T, I> for BufLease<L, T, M>
             while let Some(item)  = iter.next() {
                 let len = self.len();
                 debug_assert!(len != self.cap());
-                ptr::write(self.get_unchecked_mut(len), match item.try_clone() {
+                ptr::write(self.lease.data().wrapping_add(len), match item.try_clone() {
                     Ok(elem) => elem,
                     Err(error) => return Err(error),
                 });
@@ -1270,7 +2191,7 @@ impl<'a, 'b, L, T, M, I> SpecExtendClone<&'b T, I> for BufLease<L, T, M>
                     let (lower, _) = iter.size_hint();
                     self.try_reserve(lower.saturating_add(1))?;
                 }
-                ptr::write(self.get_unchecked_mut(len), match item.try_clone() {
+                ptr::write(self.lease.data().wrapping_add(len), match item.try_clone() {
                     Ok(elem) => elem,
                     Err(error) => return Err(error),
                 });
@@ -1292,7 +2213,7 @@ impl<'a, 'b, L, T, M, I> SpecExtendClone<&'b T, I> for BufLease<L, T, M>
             while let Some(item)  = iter.next() {
                 let len = self.len();
                 debug_assert!(len != self.cap());
-                ptr::write(self.get_unchecked_mut(len), match item.try_clone() {
+                ptr::write(self.lease.data().wrapping_add(len), match item.try_clone() {
                     Ok(elem) => elem,
                     Err(error) => return Err(error),
                 });
@@ -48,7 +48,7 @@ mod buf;
 mod string;
 
 pub use self::r#box::Box;
-pub use self::buf::{Buf, BufHeader, BufLease, BufIter, BufDrain};
+pub use self::buf::{Buf, BufHeader, BufLease, BufIter, BufDrain, BufChunksEvenMut, BufChunkBy, BufCyclicWindows, BufWriter, TryReserveError, TryMapError};
 pub use self::string::{String, StringLease};
 
 /// A type that can reside in a raw, unsized memory block. A memory `Resident`
@@ -294,6 +294,20 @@ pub trait ResidentIntoMutIterator<'a, L: Lease>: Resident {
     fn resident_into_iter(lease: &'a mut L) -> Self::IntoIter;
 }
 
+/// An extendable `Resident` of a raw memory `Lease`.
+pub trait ResidentExtend<L: Lease, A>: Resident {
+    /// Appends all elements of `iter` to the resident of the `lease`; returns
+    /// a `HoldError` if the resident fails to grow.
+    fn resident_try_extend<I: IntoIterator<Item=A>>(lease: &mut L, iter: I) -> Result<(), HoldError>;
+}
+
+/// A `Resident` whose contents can be exchanged with those of another `Lease`.
+pub trait ResidentSwap<L1: Lease, L2: Lease>: Resident {
+    /// Exchanges the resident of lease `a` with the resident of lease `b`,
+    /// without reallocating either lease.
+    fn resident_swap(a: &mut L1, b: &mut L2);
+}
+
 /// A partially comparable `Resident` of a raw memory `Lease`.
 pub trait ResidentPartialEq<L: Lease, T: ?Sized = L>: Resident {
     /// Returns `true` if the resident of the `lease` equals some `other` value.
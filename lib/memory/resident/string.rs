@@ -17,7 +17,8 @@ use crate::resident::{Resident, ResidentFromCopy, ResidentFromEmpty,
                       ResidentAsRef, ResidentIndex, ResidentIndexMut, ResidentAdd,
                       ResidentAddAssign, ResidentPartialEq, ResidentEq,
                       ResidentPartialOrd, ResidentOrd, ResidentHash, ResidentDisplay,
-                      ResidentDebug, ResidentClone, ResidentStow, BufHeader, BufLease};
+                      ResidentDebug, ResidentClone, ResidentStow, BufHeader, BufLease,
+                      TryReserveError};
 
 /// A resizeable array of Unicode code points, residing in a memory `Lease`.
 /// A `String` is a `Resident` typeclass; it doesn't store any data in its
@@ -559,7 +560,7 @@ impl<'a, L: DynamicLease<'a, Data=u8, Meta=BufHeader<M>>, M> StringLease<L, M> {
         self.try_reserve(ext).unwrap();
     }
 
-    pub fn try_reserve_exact(&mut self, ext: usize) -> Result<(), HoldError> {
+    pub fn try_reserve_exact(&mut self, ext: usize) -> Result<(), TryReserveError> {
         let buf = unsafe { mem::transmute::<&mut StringLease<L, M>, &mut BufLease<L, u8, M>>(self) };
         buf.try_reserve_exact(ext)
     }
@@ -21,3 +21,12 @@ fn test_addr_space_init() {
     println!("GLOBAL.live: {}", GLOBAL.live());
     println!("GLOBAL.used: {}", GLOBAL.used());
 }
+
+#[test]
+fn test_addr_space_allocation_granularity() {
+    addr_space! {
+        pub heap GLOBAL = [4*4096];
+    }
+    assert_eq!(GLOBAL.allocation_granularity(), 4096);
+    assert_eq!(GLOBAL.min_alignment(), 4096);
+}
@@ -0,0 +1,245 @@
+extern crate tg_mem;
+
+use std::fmt::Write;
+
+use tg_mem::block::Block;
+use tg_mem::alloc::{AlignedHold, HoldError, Pack};
+use tg_mem::lease::{Mut, MutBox, MutBuf, RefBuf};
+
+#[test]
+fn test_mut_buf_index() {
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+
+    let mut xs = MutBuf::<usize>::hold_copy(pack, &[1, 2, 3, 4][..]);
+    assert_eq!(xs[0], 1);
+    assert_eq!(xs[3], 4);
+    assert_eq!(&xs[1..3], &[2, 3]);
+    assert_eq!(&xs[..], &[1, 2, 3, 4]);
+    xs[1] *= 2;
+    xs[2] = 7;
+    xs[..2].copy_from_slice(&[8, 9]);
+    assert_eq!(&xs[..], &[8, 9, 7, 4]);
+}
+
+#[test]
+#[should_panic]
+fn test_mut_buf_index_out_of_bounds() {
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+
+    let mut xs = MutBuf::<usize>::hold_copy(pack, &[1, 2, 3][..]);
+    xs[3] = 4;
+}
+
+#[test]
+fn test_ref_buf_index() {
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+
+    let xs = RefBuf::<usize>::hold_copy(pack, &[1, 2, 3, 4][..]);
+    assert_eq!(xs[0], 1);
+    assert_eq!(xs[3], 4);
+    assert_eq!(&xs[1..3], &[2, 3]);
+    assert_eq!(&xs[2..], &[3, 4]);
+
+    let mut ys = MutBuf::<usize>::hold_copy(pack, &[5, 6][..]);
+    ys[1] *= 2;
+    let ys = Mut::into_ref(ys);
+    assert_eq!(&ys[..], &[5, 12]);
+}
+
+#[test]
+#[should_panic]
+fn test_ref_buf_range_out_of_bounds() {
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+
+    let xs = RefBuf::<usize>::hold_copy(pack, &[1, 2, 3][..]);
+    let _ = &xs[2..4];
+}
+
+#[test]
+fn test_mut_box_swap_resident() {
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+
+    let mut a = MutBox::<i32>::hold_new(pack, 1);
+    let mut b = MutBox::<i32>::hold_new(pack, 2);
+    let used = pack.used();
+    Mut::swap_resident(&mut a, &mut b);
+    assert_eq!(*a, 2);
+    assert_eq!(*b, 1);
+    assert_eq!(pack.used(), used);
+}
+
+#[test]
+fn test_mut_buf_swap_resident() {
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+
+    let mut xs = MutBuf::<u8>::hold_copy(pack, &[1, 2, 3][..]);
+    let mut ys = MutBuf::<u8>::hold_copy(pack, &[4, 5, 6, 7, 8][..]);
+    let used = pack.used();
+    Mut::swap_resident(&mut xs, &mut ys);
+    assert_eq!(xs.len(), 5);
+    assert_eq!(ys.len(), 3);
+    assert_eq!(&xs[..], &[4, 5, 6, 7, 8]);
+    assert_eq!(&ys[..], &[1, 2, 3]);
+    assert_eq!(pack.used(), used);
+}
+
+#[test]
+fn test_mut_buf_chunks_mut_even() {
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+
+    let mut xs = MutBuf::<usize>::hold_copy(pack, &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9][..]);
+    {
+        let chunks = xs.chunks_mut_even(3);
+        assert_eq!(chunks.len(), 3);
+        let lens = chunks.map(|chunk| chunk.len()).collect::<Vec<_>>();
+        assert_eq!(lens, [4, 3, 3]);
+    }
+    for (i, chunk) in xs.chunks_mut_even(3).enumerate() {
+        for x in chunk.iter_mut() {
+            *x += 10 * i;
+        }
+    }
+    assert_eq!(&xs[..], &[0, 1, 2, 3, 14, 15, 16, 27, 28, 29]);
+
+    let chunks = xs.chunks_mut_even(1).collect::<Vec<_>>();
+    assert_eq!(chunks.len(), 1);
+    assert_eq!(chunks[0].len(), 10);
+
+    let lens = xs.chunks_mut_even(12).map(|chunk| chunk.len()).collect::<Vec<_>>();
+    assert_eq!(lens, [1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 0, 0]);
+}
+
+#[test]
+fn test_mut_box_take() {
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering::SeqCst;
+
+    static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+    #[derive(Default)]
+    struct Tracked(usize);
+
+    impl Drop for Tracked {
+        fn drop(&mut self) {
+            DROPS.fetch_add(1, SeqCst);
+        }
+    }
+
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+
+    let mut x = MutBox::<i32>::hold_new(pack, 7);
+    let used = pack.used();
+    assert_eq!(Mut::take(&mut x), 7);
+    assert_eq!(*x, 0);
+    assert_eq!(pack.used(), used);
+
+    let mut y = MutBox::<Tracked>::hold_new(pack, Tracked(5));
+    let old = Mut::take(&mut y);
+    assert_eq!(old.0, 5);
+    assert_eq!(y.0, 0);
+    assert_eq!(DROPS.load(SeqCst), 0);
+    drop(old);
+    assert_eq!(DROPS.load(SeqCst), 1);
+    drop(y);
+    assert_eq!(DROPS.load(SeqCst), 2);
+}
+
+#[test]
+fn test_mut_buf_retain_mut() {
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+
+    let mut ttls = MutBuf::<usize>::hold_copy(pack, &[3, 1, 2, 1, 5][..]);
+    ttls.retain_mut(|ttl| {
+        *ttl -= 1;
+        *ttl != 0
+    });
+    assert_eq!(&ttls[..], &[2, 1, 4][..]);
+    ttls.retain(|&ttl| ttl != 1);
+    assert_eq!(&ttls[..], &[2, 4][..]);
+}
+
+#[test]
+fn test_mut_buf_write() {
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+
+    let mut xs = MutBuf::<u8>::hold_copy(pack, &b"x="[..]);
+    write!(xs, "{}", 42).unwrap();
+    assert_eq!(&xs[..], b"x=42");
+    xs.write_bytes(&[0, 1]).unwrap();
+    assert_eq!(&xs[..], b"x=42\x00\x01");
+
+    // Exhaust the pack, and recover the allocation failure.
+    let mut writer = xs.writer();
+    assert!(write!(writer, "{:>8192}", 7).is_err());
+    assert!(writer.take_error().is_some());
+    assert!(writer.take_error().is_none());
+}
+
+#[test]
+fn test_mut_buf_aligned_hold() {
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    let aligned = AlignedHold::new(pack, 64);
+    {
+        // The resident follows the arc header, and is still aligned.
+        let mut xs = MutBuf::<u8>::hold_cap(&aligned, 1);
+        xs.push(1);
+        assert_eq!(xs.as_ptr() as usize % 64, 0);
+        // Growth resizes through the aligned hold.
+        for x in 2..100 {
+            xs.push(x);
+        }
+        assert_eq!(xs.as_ptr() as usize % 64, 0);
+        assert_eq!(xs.len(), 99);
+
+        let ys = MutBuf::<u8>::hold_copy(pack, &[2][..]);
+        assert_ne!(ys.as_ptr() as usize % 64, 0);
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+#[test]
+fn test_mut_buf_hold_from_iter() {
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let xs = MutBuf::<usize>::hold_from_iter(pack, (1..5).map(|x| x * x));
+        assert_eq!(&xs[..], &[1, 4, 9, 16]);
+        let ys = MutBuf::<usize>::hold_from_iter(pack, &[2, 3]);
+        assert_eq!(&ys[..], &[2, 3]);
+        let zs = MutBuf::<usize>::hold_from_iter(pack, 0..0);
+        assert!(zs.is_empty());
+        // The aligned hold is passed as a trait object.
+        let aligned = AlignedHold::new(pack, 64);
+        let ws = MutBuf::<u8>::hold_from_iter(&aligned, 0..10);
+        assert_eq!(ws.as_ptr() as usize % 64, 0);
+        assert_eq!(ws.len(), 10);
+    }
+    assert_eq!(pack.live(), 0);
+    {
+        // A failure to grow frees the partially built buffer.
+        match MutBuf::<usize>::try_hold_from_iter(pack, 0..1000) {
+            Err(HoldError::OutOfMemory) => (),
+            _ => panic!(),
+        }
+        assert_eq!(pack.live(), 0);
+    }
+}
+
+#[test]
+#[should_panic]
+fn test_mut_buf_hold_from_iter_overflow() {
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    MutBuf::<usize>::hold_from_iter(pack, 0..1000);
+}
@@ -0,0 +1,155 @@
+#![cfg(feature = "failpoints")]
+
+extern crate tg_mem;
+
+use tg_mem::block::{Block, Layout};
+use tg_mem::alloc::{AlignedHold, Heap, HeapError, Hold, HoldError, Pack, Pool, Slab, TryClone};
+use tg_mem::lease::RawBuf;
+
+#[test]
+fn test_pack_fail_next_alloc() {
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        pack.fail_next_alloc().unwrap();
+        match RawBuf::<usize>::try_hold_cap(pack, 2) {
+            Err(HoldError::OutOfMemory) => (),
+            _ => panic!(),
+        }
+        assert_eq!(pack.live(), 0);
+        // Allocations resume once the failpoint trips.
+        let xs = RawBuf::<usize>::try_hold_cap(pack, 2).unwrap();
+        assert_eq!(pack.live(), 1);
+        assert_eq!(xs.cap(), 2);
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+#[test]
+fn test_pack_fail_after() {
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        pack.fail_after(2).unwrap();
+        let _xs = RawBuf::<usize>::try_hold_cap(pack, 2).unwrap();
+        let _ys = RawBuf::<usize>::try_hold_cap(pack, 2).unwrap();
+        assert!(RawBuf::<usize>::try_hold_cap(pack, 2).is_err());
+        let _zs = RawBuf::<usize>::try_hold_cap(pack, 2).unwrap();
+        assert_eq!(pack.live(), 3);
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+#[test]
+fn test_pool_fail_after() {
+    static mut TEST_HUNK: [u8; 4096] = [0; 4096];
+    let slab = Slab::new(unsafe { Block::from_slice(&mut TEST_HUNK) }, 256);
+    let pool = &Pool::new(&slab);
+    {
+        pool.fail_after(1).unwrap();
+        let mut xs = RawBuf::<usize>::try_hold_cap(pool, 2).unwrap();
+        xs.push(1);
+        xs.push(2);
+        // Growing the buf resizes its block in the pool's pack, and trips
+        // the pool's failpoint.
+        assert_eq!(xs.try_push(3), Err(HoldError::OutOfMemory));
+        assert_eq!(&xs[..], &[1, 2]);
+        assert_eq!(pool.live(), 1);
+        xs.push(3);
+        assert_eq!(&xs[..], &[1, 2, 3]);
+    }
+    assert_eq!(pool.live(), 0);
+}
+
+#[test]
+fn test_slab_fail_next_alloc() {
+    static mut TEST_HUNK: [u8; 4096] = [0; 4096];
+    let slab = Slab::new(unsafe { Block::from_slice(&mut TEST_HUNK) }, 256);
+    unsafe {
+        slab.fail_next_alloc().unwrap();
+        assert_eq!(slab.alloc(Layout::for_type::<usize>()), Err(HeapError::OutOfMemory));
+        assert_eq!(slab.live(), 0);
+        let block = slab.alloc(Layout::for_type::<usize>()).unwrap();
+        assert_eq!(slab.live(), 1);
+        slab.dealloc(block);
+    }
+    // A pool fails to grow when its backing slab fails.
+    let pool = &Pool::new(&slab);
+    {
+        slab.fail_next_alloc().unwrap();
+        match RawBuf::<usize>::try_hold_cap(pool, 2) {
+            Err(HoldError::OutOfMemory) => (),
+            _ => panic!(),
+        }
+        let _xs = RawBuf::<usize>::try_hold_cap(pool, 2).unwrap();
+        assert_eq!(slab.live(), 1);
+    }
+}
+
+#[test]
+fn test_aligned_hold_forwards_failpoint() {
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    let hold = AlignedHold::new(pack, 64);
+    {
+        hold.fail_next_alloc().unwrap();
+        assert!(RawBuf::<u8>::try_hold_cap(&hold, 8).is_err());
+        assert!(RawBuf::<u8>::try_hold_cap(&hold, 8).is_ok());
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+#[test]
+fn test_raw_buf_growth_failure() {
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let mut xs = RawBuf::<usize>::hold_cap(pack, 2);
+        xs.push(1);
+        xs.push(2);
+        let used = pack.used();
+        pack.fail_next_alloc().unwrap();
+        assert_eq!(xs.try_push(3), Err(HoldError::OutOfMemory));
+        assert_eq!(xs.len(), 2);
+        assert_eq!(xs.cap(), 2);
+        assert_eq!(&xs[..], &[1, 2]);
+        assert_eq!(pack.used(), used);
+        assert_eq!(pack.live(), 1);
+        xs.push(3);
+        assert_eq!(&xs[..], &[1, 2, 3]);
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+#[test]
+fn test_raw_buf_clone_failure_rollback() {
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let mut xs = RawBuf::<RawBuf<u8>>::hold_cap(pack, 3);
+        for &bytes in [&b"abc"[..], &b"de"[..], &b"f"[..]].iter() {
+            let mut x = RawBuf::<u8>::hold_cap(pack, bytes.len());
+            x.extend_from_slice(bytes);
+            xs.push(x);
+        }
+        let live = pack.live();
+        let used = pack.used();
+        // Fail the third allocation: after the outer buf, and its first element.
+        pack.fail_after(2).unwrap();
+        match xs.try_clone() {
+            Err(HoldError::OutOfMemory) => (),
+            _ => panic!(),
+        }
+        assert_eq!(pack.live(), live);
+        assert_eq!(pack.used(), used);
+        assert_eq!(xs.len(), 3);
+        assert_eq!(&xs[0][..], b"abc");
+        assert_eq!(&xs[1][..], b"de");
+        assert_eq!(&xs[2][..], b"f");
+
+        let ys = xs.try_clone().unwrap();
+        assert_eq!(pack.live(), 2 * live);
+        assert_eq!(&ys[1][..], b"de");
+    }
+    assert_eq!(pack.live(), 0);
+}
@@ -0,0 +1,51 @@
+#![cfg(feature = "finalizers")]
+
+extern crate swim_mem;
+
+use std::mem;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering::SeqCst;
+use swim_mem::block::Block;
+use swim_mem::alloc::Pack;
+use swim_mem::lease::HardBox;
+
+static FINALIZE_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+fn finalize(_meta: *mut ()) {
+    FINALIZE_COUNT.fetch_add(1, SeqCst);
+}
+
+#[test]
+fn test_hard_box_finalizer() {
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+
+    {
+        let x = HardBox::hold_new_with_finalizer(pack, 5usize, finalize);
+        let y = x.to_soft();
+        let z = y.clone();
+        assert_eq!(FINALIZE_COUNT.load(SeqCst), 0);
+
+        mem::drop(x);
+        assert_eq!(pack.live(), 1);
+        assert_eq!(y.hard_count(), 0);
+        assert_eq!(y.soft_count(), 2);
+        assert_eq!(FINALIZE_COUNT.load(SeqCst), 0);
+
+        mem::drop(y);
+        assert_eq!(pack.live(), 1);
+        assert_eq!(FINALIZE_COUNT.load(SeqCst), 0);
+
+        mem::drop(z);
+        assert_eq!(pack.live(), 0);
+        assert_eq!(FINALIZE_COUNT.load(SeqCst), 1);
+    }
+    {
+        let x = HardBox::hold_new_with_finalizer(pack, 5usize, finalize);
+        mem::drop(x);
+        assert_eq!(pack.live(), 0);
+        assert_eq!(FINALIZE_COUNT.load(SeqCst), 2);
+    }
+    assert_eq!(pack.used(), 0);
+    assert_eq!(pack.free(), 4064);
+}
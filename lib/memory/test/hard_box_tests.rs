@@ -1,9 +1,14 @@
 extern crate swim_mem;
 
 use std::mem;
+use std::ptr;
+use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
+use std::thread;
 use swim_mem::block::Block;
-use swim_mem::alloc::{StowInto, Pack};
-use swim_mem::lease::{ArcError, HardBox, SoftBox, RefBox, MutBox};
+use swim_mem::alloc::{Hold, Holder, HoldError, Stow, StowInto, Pack, TryClone};
+use swim_mem::lease::{ArcError, Ref, Hard, HardBox, SoftBox, RefBox, MutBox, HardString, RefString};
+use swim_mem::lease::{CountLayout, DefaultCountLayout, HARD_COUNT_MAX};
+use swim_mem::resident::Box;
 
 #[test]
 fn test_hard_box_hold_new() {
@@ -124,6 +129,57 @@ fn test_hard_box_into_soft() {
     assert_eq!(pack.free(), 4064);
 }
 
+/// Count layout with narrow count bit fields, which overflow quickly.
+struct NarrowLayout;
+
+impl CountLayout for NarrowLayout {
+    const HARD_COUNT_BITS: usize = 4;
+    const SOFT_COUNT_BITS: usize = 4;
+}
+
+/// Narrow count layout that trades soft count bits for hard count bits.
+struct WideHardLayout;
+
+impl CountLayout for WideHardLayout {
+    const HARD_COUNT_BITS: usize = NarrowLayout::HARD_COUNT_BITS + 2;
+    const SOFT_COUNT_BITS: usize = NarrowLayout::SOFT_COUNT_BITS - 2;
+}
+
+/// Clones a new hard lease with count layout `L` until its hard count
+/// overflows; returns the greatest number of simultaneous hard leases.
+fn max_hard_leases<'a, L: CountLayout>(hold: &dyn Hold<'a>) -> usize {
+    let x = Hard::<Box<usize>, L>::hold_new(hold, 5usize);
+    let mut count = 1;
+    while let Ok(y) = x.try_clone() {
+        mem::forget(y);
+        count += 1;
+    }
+    assert_eq!(x.hard_count(), count);
+    for _ in 1..count {
+        mem::drop(unsafe { Hard::<Box<usize>, L>::from_raw(x.as_ptr_unchecked()) });
+    }
+    assert_eq!(x.hard_count(), 1);
+    count
+}
+
+#[test]
+fn test_hard_box_count_layout() {
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+
+    // The default layout keeps its original packing.
+    assert_eq!(HARD_COUNT_MAX, DefaultCountLayout::HARD_COUNT_MAX);
+    #[cfg(target_pointer_width = "64")]
+    assert_eq!(HARD_COUNT_MAX, 0xFFFFFF);
+    #[cfg(target_pointer_width = "32")]
+    assert_eq!(HARD_COUNT_MAX, 0xFFF);
+
+    assert_eq!(max_hard_leases::<NarrowLayout>(pack), NarrowLayout::HARD_COUNT_MAX);
+    assert_eq!(max_hard_leases::<WideHardLayout>(pack), WideHardLayout::HARD_COUNT_MAX);
+    assert!(WideHardLayout::HARD_COUNT_MAX > NarrowLayout::HARD_COUNT_MAX);
+    assert_eq!(pack.live(), 0);
+}
+
 #[test]
 fn test_hard_box_to_ref() {
     static mut TEST_AREA: [u8; 4096] = [0; 4096];
@@ -176,6 +232,32 @@ fn test_hard_box_to_ref() {
     assert_eq!(pack.free(), 4064);
 }
 
+#[test]
+fn test_hard_box_is_exclusive() {
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let cached = HardBox::hold_new(pack, 5usize);
+        assert_eq!(cached.is_exclusive(), true);
+
+        let external = cached.clone();
+        assert_eq!(cached.is_exclusive(), false);
+        assert_eq!(external.is_exclusive(), false);
+        mem::drop(external);
+        assert_eq!(cached.is_exclusive(), true);
+
+        let borrowed = cached.to_ref();
+        assert_eq!(cached.is_exclusive(), false);
+        mem::drop(borrowed);
+        assert_eq!(cached.is_exclusive(), true);
+
+        let weak = cached.to_soft();
+        assert_eq!(cached.is_exclusive(), true);
+        mem::drop(weak);
+    }
+    assert_eq!(pack.live(), 0);
+}
+
 #[test]
 fn test_hard_box_into_ref() {
     static mut TEST_AREA: [u8; 4096] = [0; 4096];
@@ -324,6 +406,68 @@ fn test_hard_box_into_mut() {
     assert_eq!(pack.free(), 4064);
 }
 
+#[test]
+fn test_mut_box_try_into_inner() {
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let x = MutBox::hold_new(pack, String::from("graph"));
+        assert_eq!(pack.live(), 1);
+        assert_eq!(MutBox::try_into_inner(x).ok().unwrap(), "graph");
+        assert_eq!(pack.live(), 0);
+
+        let x = HardBox::hold_new(pack, String::from("node"));
+        let y = x.to_soft();
+        let x = unsafe { x.into_mut() };
+        let x = match MutBox::try_into_inner(x) {
+            Ok(_) => panic!("unwrapped with outstanding soft reference"),
+            Err(x) => x,
+        };
+        assert_eq!(*x, "node");
+        assert_eq!(pack.live(), 1);
+
+        mem::drop(y);
+        assert_eq!(MutBox::try_into_inner(x).ok().unwrap(), "node");
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+#[test]
+fn test_hard_box_custom_relocation() {
+    static mut TEST_AREA0: [u8; 4096] = [0; 4096];
+    let pack0 = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA0) });
+    static mut TEST_AREA1: [u8; 4096] = [0; 4096];
+    let pack1 = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA1) });
+    {
+        let x = HardBox::hold_new(pack0, 5usize);
+        let y = x.clone();
+
+        let token = x.try_begin_relocation().unwrap();
+        assert_eq!(y.is_relocated(), true);
+        assert!(y.try_begin_relocation().is_none());
+        assert_eq!(y.poll_ref().unwrap_err(), ArcError::Relocating);
+        mem::drop(token);
+        assert_eq!(y.is_relocated(), false);
+
+        let borrowed = y.to_ref();
+        assert!(x.try_begin_relocation().is_none());
+        mem::drop(borrowed);
+
+        let token = x.try_begin_relocation().unwrap();
+        let value = unsafe { ptr::read(x.as_ptr_unchecked()) };
+        let z = HardBox::hold_new(pack1, value);
+        unsafe { Hard::complete_relocation(token, z); }
+        assert_eq!(pack0.live(), 1);
+        assert_eq!(pack1.live(), 1);
+        assert_eq!(x.is_relocated(), true);
+        assert_eq!(*x.to_ref(), 5);
+        assert_eq!(*y.to_ref(), 5);
+        assert_eq!(unsafe { x.as_ptr_unchecked() == y.as_ptr_unchecked() }, true);
+    }
+    assert_eq!(pack0.live(), 0);
+    assert_eq!(pack1.live(), 0);
+}
+
 #[test]
 fn test_hard_box_stow_into() {
     static mut TEST_AREA0: [u8; 4096] = [0; 4096];
@@ -409,3 +553,179 @@ fn test_hard_box_stow_into() {
     assert_eq!(pack1.used(), 0);
     assert_eq!(pack1.free(), 4064);
 }
+
+#[test]
+fn test_string_ord() {
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let a = RefString::hold_copy(pack, "apple");
+        let b = RefString::hold_copy(pack, "banana");
+        assert!(a < b);
+        assert_eq!(a.cmp(&b), "apple".cmp("banana"));
+        let x = HardString::hold_copy(pack, "cherry");
+        let y = HardString::hold_copy(pack, "cherries");
+        assert!(x > y);
+        assert_eq!(x.cmp(&y), "cherry".cmp("cherries"));
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+#[test]
+fn test_ref_box_make_mut() {
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let mut x = RefBox::hold_new(pack, 5usize);
+        let addr = &*x as *const usize;
+        *Ref::make_mut(&mut x) = 6;
+        assert_eq!(&*x as *const usize, addr);
+        assert_eq!(*x, 6);
+        assert_eq!(pack.live(), 1);
+
+        let y = x.clone();
+        *Ref::make_mut(&mut x) = 7;
+        assert_ne!(&*x as *const usize, addr);
+        assert_eq!(&*y as *const usize, addr);
+        assert_eq!(*x, 7);
+        assert_eq!(*y, 6);
+        assert_eq!(pack.live(), 2);
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+#[test]
+fn test_ref_box_clone_into_hold() {
+    static mut TEST_AREA0: [u8; 4096] = [0; 4096];
+    static mut TEST_AREA1: [u8; 4096] = [0; 4096];
+    let pack0 = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA0) });
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA1) });
+    {
+        let x = RefBox::hold_new(pack0, 5i32);
+        let mut y = Ref::clone_into_hold(&x, pack);
+        assert_eq!(pack.live(), 1);
+        assert_eq!(y.holder() as *const dyn Hold as *const u8, pack as *const Pack as *const u8);
+        assert_ne!(&*x as *const i32, &*y as *const i32);
+        assert_eq!(Ref::hard_count(&x), 1);
+        assert_eq!(Ref::hard_count(&y), 1);
+        assert_eq!(*y, 5);
+
+        *Ref::make_mut(&mut y) = 6;
+        assert_eq!(pack.live(), 1);
+        assert_eq!(*x, 5);
+        assert_eq!(*y, 6);
+
+        let h = HardBox::hold_new(pack0, 7i32);
+        let k = h.clone_into_hold(pack);
+        assert_eq!(pack.live(), 2);
+        assert_eq!(h.hard_count(), 1);
+        assert_eq!(k.hard_count(), 1);
+        assert_eq!(k.ref_count(), 0);
+        assert_eq!(*k.to_ref(), 7);
+    }
+    assert_eq!(pack0.live(), 0);
+    assert_eq!(pack.live(), 0);
+}
+
+struct Node<'a> {
+    value: usize,
+    this: SoftBox<'a, Node<'a>>,
+}
+
+#[test]
+fn test_hard_box_hold_new_cyclic() {
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+
+    assert_eq!(pack.live(), 0);
+    {
+        let x = HardBox::hold_new_cyclic(pack, |this: &SoftBox<Node>| {
+            assert_eq!(this.hard_count(), 0);
+            assert_eq!(this.soft_count(), 1);
+            assert_eq!(this.try_to_hard().err(), Some(ArcError::Cleared));
+            Node { value: 5, this: this.clone() }
+        });
+        assert_eq!(pack.live(), 1);
+        assert_eq!(x.hard_count(), 1);
+        assert_eq!(x.soft_count(), 1);
+
+        let y = x.to_ref();
+        assert_eq!(y.value, 5);
+        let z = y.this.to_hard();
+        assert_eq!(x.hard_count(), 3);
+        assert_eq!(z.to_ref().value, 5);
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+/// Shares a pack between test threads; packs allocate and free atomically.
+struct SharedPack(&'static Pack<'static>);
+
+unsafe impl Sync for SharedPack {
+}
+
+/// Resident that counts its drops in a per-test counter.
+struct Tally<'c>(usize, &'c AtomicUsize);
+
+impl<'b, 'c> Stow<'b> for Tally<'c> {
+    unsafe fn stow(src: *mut Tally<'c>, dst: *mut Tally<'c>, _hold: &dyn Hold<'b>) -> Result<(), HoldError> {
+        ptr::copy_nonoverlapping(src, dst, 1);
+        Ok(())
+    }
+
+    unsafe fn unstow(_src: *mut Tally<'c>, _dst: *mut Tally<'c>) {
+        // nop
+    }
+}
+
+impl<'c> Drop for Tally<'c> {
+    fn drop(&mut self) {
+        self.1.fetch_add(1, SeqCst);
+    }
+}
+
+#[test]
+fn test_hard_box_concurrent_relocate_drop_upgrade() {
+    static mut TEST_AREA0: [u8; 16384] = [0; 16384];
+    let pack0 = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA0) });
+    static mut TEST_AREA1: [u8; 16384] = [0; 16384];
+    let pack1 = &SharedPack(Pack::new(unsafe { Block::from_slice(&mut TEST_AREA1) }));
+    let drops = &AtomicUsize::new(0);
+
+    for round in 0..256 {
+        // Race a relocation, two hard drops, and two soft upgrades of each
+        // of two residents, on ten threads.
+        let xs = [HardBox::hold_new(pack0, Tally(2 * round, drops)),
+                  HardBox::hold_new(pack0, Tally(2 * round + 1, drops))];
+        thread::scope(|scope| {
+            for x in xs {
+                let value = x.to_ref().0;
+                let relocating = x.clone();
+                let upgrading = [x.to_soft(), x.to_soft()];
+                let dropping = [x.clone(), x];
+                scope.spawn(move || {
+                    let y: HardBox<Tally> = relocating.stow_into(pack1.0);
+                    assert_eq!(y.to_ref().0, value);
+                });
+                for x in dropping {
+                    scope.spawn(move || {
+                        drop(x);
+                    });
+                }
+                for x in upgrading {
+                    scope.spawn(move || {
+                        if let Ok(z) = x.try_to_hard() {
+                            assert_eq!(z.to_ref().0, value);
+                            // Possibly releases the last hard reference to a relocated resident.
+                            drop(z.into_soft());
+                        }
+                    });
+                }
+            }
+        });
+        // Every resident dropped exactly once, and all memory got released.
+        assert_eq!(drops.load(SeqCst), 2 * round + 2);
+        assert_eq!(pack0.live(), 0);
+        assert_eq!(pack1.0.live(), 0);
+    }
+}
@@ -1,9 +1,14 @@
 #[macro_use]
 extern crate tg_mem;
 
+use std::mem;
 use tg_mem::block::Block;
-use tg_mem::alloc::{Hold, Pack};
+use tg_mem::alloc::{Hold, LocalHold, Pack};
+#[cfg(feature = "trace")]
+use tg_mem::alloc::HoldTrace;
 use tg_mem::lease::RawBox;
+#[cfg(feature = "trace")]
+use tg_mem::lease::RawBuf;
 
 #[test]
 fn test_local_hold_alloc_dealloc_boxes() {
@@ -39,3 +44,78 @@ fn test_local_hold_alloc_dealloc_boxes() {
     assert_eq!(pack.used(), 0);
     assert_eq!(pack.free(), 4064);
 }
+
+#[test]
+fn test_local_pinned_hold_alloc_dealloc_boxes() {
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    LocalHold::pinned(pack, 1024, |local| {
+        // The parent pack only accounts for the leased local pack.
+        assert_eq!(pack.live(), 1);
+        assert_eq!(pack.used(), 1024);
+        assert_eq!(local.live(), 0);
+        {
+            let x = RawBox::hold_new(unsafe { Hold::local() }, 5usize);
+            let y = RawBox::hold_new(local, 9usize);
+            // Local allocations never touch the parent's atomic counters.
+            assert_eq!(pack.live(), 1);
+            assert_eq!(pack.used(), 1024);
+            assert_eq!(local.live(), 2);
+            assert_eq!(local.used(), 16);
+            assert_eq!(*x, 5);
+            assert_eq!(*y, 9);
+        }
+        assert_eq!(local.live(), 0);
+        assert_eq!(local.used(), 0);
+    });
+    // Exiting the scope returns the local pack to the parent.
+    assert_eq!(pack.live(), 0);
+    assert_eq!(pack.used(), 0);
+    assert_eq!(pack.free(), 4064);
+}
+
+#[cfg(feature = "trace")]
+#[test]
+fn test_local_pinned_hold_performs_no_atomics() {
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    let start = HoldTrace::get();
+    LocalHold::pinned(pack, 2048, |local| {
+        // Leasing the local pack allocates once from the parent.
+        assert_eq!(HoldTrace::since(start), HoldTrace { allocs: 1, deallocs: 0, resizes: 0, atomic_ops: 1, alloc_bytes: 2048, dealloc_bytes: 0 });
+        let start = HoldTrace::get();
+        for i in 0..100 {
+            let mut xs = RawBuf::<usize>::hold_cap(unsafe { Hold::local() }, 1);
+            xs.push(i);
+            xs.push(i);
+            let x = RawBox::hold_new(local, i);
+            assert_eq!(*x, xs[1]);
+        }
+        let trace = HoldTrace::since(start);
+        assert_eq!(trace.allocs, 200);
+        assert_eq!(trace.deallocs, 200);
+        assert_eq!(trace.resizes, 100);
+        assert_eq!(trace.atomic_ops, 0);
+        assert_eq!(trace.alloc_bytes, 3200);
+        assert_eq!(trace.dealloc_bytes, 3200);
+        assert_eq!(local.live(), 0);
+        // The parent's accounting is untouched by local allocations.
+        assert_eq!(pack.live(), 1);
+        assert_eq!(pack.used(), 2048);
+    });
+    // Returning the local pack deallocates once from the parent.
+    assert_eq!(HoldTrace::since(start), HoldTrace { allocs: 201, deallocs: 201, resizes: 100, atomic_ops: 2, alloc_bytes: 5248, dealloc_bytes: 5248 });
+    assert_eq!(pack.live(), 0);
+    assert_eq!(pack.used(), 0);
+    assert_eq!(pack.free(), 4064);
+}
+
+#[test]
+#[should_panic(expected = "leaky local pack")]
+fn test_local_pinned_hold_leak_fails() {
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    LocalHold::pinned(pack, 1024, |local| {
+        mem::forget(RawBox::hold_new(local, 5usize));
+    });
+}
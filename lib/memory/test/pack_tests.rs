@@ -1,7 +1,7 @@
 extern crate tg_mem;
 
-use tg_mem::block::Block;
-use tg_mem::alloc::Pack;
+use tg_mem::block::{Block, Layout};
+use tg_mem::alloc::{Hold, Pack};
 use tg_mem::lease::{RawBox, RawBuf};
 
 #[test]
@@ -82,3 +82,29 @@ pub fn test_pack_alloc_dealloc_bufs() {
     assert_eq!(pack.live(), 0);
     assert_eq!(pack.used(), 0);
 }
+
+#[test]
+#[should_panic(expected = "dealloc size mismatch")]
+#[cfg(debug_assertions)]
+fn test_pack_dealloc_size_mismatch() {
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    unsafe {
+        let x = pack.alloc(Layout::from_size_align_unchecked(16, 8)).unwrap();
+        pack.dealloc(Block::from_raw_parts(x.as_ptr(), 64));
+    }
+}
+
+#[test]
+#[should_panic(expected = "dealloc size mismatch")]
+#[cfg(debug_assertions)]
+fn test_pack_dealloc_interior_size_mismatch() {
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    unsafe {
+        // A block followed by another allocation, freed with a smaller size.
+        let x = pack.alloc(Layout::from_size_align_unchecked(64, 8)).unwrap();
+        let _y = pack.alloc(Layout::from_size_align_unchecked(16, 8)).unwrap();
+        pack.dealloc(Block::from_raw_parts(x.as_ptr(), 16));
+    }
+}
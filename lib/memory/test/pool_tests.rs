@@ -1,8 +1,11 @@
+#[macro_use]
 extern crate swim_mem;
 
-use swim_mem::block::Block;
-use swim_mem::alloc::{Slab, Pool};
-use swim_mem::lease::{RawBox, RawBuf};
+use std::mem;
+
+use swim_mem::block::{Block, Layout};
+use swim_mem::alloc::{AllocTag, Hold, HoldError, Restow, Slab, Pool};
+use swim_mem::lease::{RawBox, RawBuf, Mut, MutBox, MutBuf};
 
 #[test]
 fn test_pool_alloc_dealloc_boxes() {
@@ -79,3 +82,133 @@ pub fn test_pool_alloc_dealloc_bufs() {
     assert_eq!(pool.live(), 0);
     assert_eq!(pool.used(), 0);
 }
+
+#[test]
+fn test_pool_alloc_near() {
+    static mut TEST_HUNK: [u8; 4096] = [0; 4096];
+    let hunk_addr = unsafe { TEST_HUNK.as_ptr() as usize };
+    let slab = Slab::new(unsafe { Block::from_slice(&mut TEST_HUNK) }, 256);
+    let pool = &Pool::new(&slab);
+    let hunk_index = |block: &Block| (block.as_ptr() as usize - hunk_addr) / 256;
+
+    unsafe {
+        let layout = Layout::for_type::<usize>();
+        let first = pool.alloc(layout).unwrap();
+        // Allocate a block too large for the first hunk's remaining space,
+        // forcing the pool to push a new head hunk.
+        let filler = pool.alloc(Layout::from_size_align(208, 8).unwrap()).unwrap();
+        assert_ne!(hunk_index(&filler), hunk_index(&first));
+
+        let mut nodes = Vec::new();
+        for _ in 0..4 {
+            let node = pool.alloc_near(layout, first.as_ptr()).unwrap();
+            assert_eq!(hunk_index(&node), hunk_index(&first));
+            nodes.push(node);
+        }
+        assert_eq!(pool.live(), 6);
+
+        while let Some(node) = nodes.pop() {
+            AllocTag::from_ptr(node.as_ptr()).dealloc(node);
+        }
+        AllocTag::from_ptr(filler.as_ptr()).dealloc(filler);
+        AllocTag::from_ptr(first.as_ptr()).dealloc(first);
+    }
+    assert_eq!(pool.live(), 0);
+}
+
+#[test]
+fn test_pool_alloc_sibling() {
+    static mut TEST_HUNK: [u8; 4096] = [0; 4096];
+    let hunk_addr = unsafe { TEST_HUNK.as_ptr() as usize };
+    let slab = Slab::new(unsafe { Block::from_slice(&mut TEST_HUNK) }, 256);
+    let pool = &Pool::new(&slab);
+    let hunk_index = |ptr: *const u8| (ptr as usize - hunk_addr) / 256;
+
+    let xs = MutBuf::<usize>::hold_copy(pool, &[1, 2][..]);
+    // Allocate a block too large for the first hunk's remaining space,
+    // forcing the pool to push a new head hunk.
+    let filler = unsafe { pool.alloc(Layout::from_size_align(208, 8).unwrap()).unwrap() };
+    assert_ne!(hunk_index(filler.as_ptr()), hunk_index(xs.as_ptr() as *const u8));
+
+    let x: MutBox<usize> = Mut::alloc_sibling(&xs, 3);
+    assert_eq!(*x, 3);
+    assert_eq!(hunk_index(&*x as *const usize as *const u8), hunk_index(xs.as_ptr() as *const u8));
+    assert_eq!(pool.live(), 3);
+
+    unsafe { AllocTag::from_ptr(filler.as_ptr()).dealloc(filler); }
+    drop(x);
+    drop(xs);
+    assert_eq!(pool.live(), 0);
+}
+
+#[test]
+fn test_pool_defragment() {
+    static mut TEST_HUNK: [u8; 4096] = [0; 4096];
+    let slab = Slab::new(unsafe { Block::from_slice(&mut TEST_HUNK) }, 256);
+    let pool = &Pool::new(&slab);
+
+    // Fragment the pool by dropping every other box.
+    let xs = (0..32).map(|i| RawBox::hold_new(pool, i)).collect::<Vec<_>>();
+    let mut xs = xs.into_iter().filter(|x| **x % 2 == 0).collect::<Vec<_>>();
+    assert_eq!(pool.live(), 16);
+    assert_eq!(pool.used(), 128);
+    // Allocate a large block that doesn't fit in any fragment.
+    let mut ys = RawBuf::<usize>::hold_copy(pool, &[7; 16][..]);
+    assert_eq!(pool.live(), 17);
+    assert_eq!(pool.used(), 256);
+    let size = pool.size();
+
+    {
+        let mut live = xs.iter_mut().map(|x| x as &mut dyn Restow).collect::<Vec<_>>();
+        live.push(&mut ys);
+        pool.defragment(&mut live[..]).unwrap();
+    }
+    assert_eq!(pool.live(), 17);
+    assert_eq!(pool.used(), 256);
+    assert!(pool.size() < size);
+    for (i, x) in xs.iter().enumerate() {
+        assert_eq!(**x, 2 * i);
+    }
+    assert_eq!(&ys[..], &[7; 16]);
+
+    drop(xs);
+    drop(ys);
+    assert_eq!(pool.live(), 0);
+    assert_eq!(pool.used(), 0);
+}
+
+#[test]
+fn test_pool_backing_addr_space_exhausted() {
+    addr_space! {
+        pub heap HEAP = [4*4096];
+    }
+    let pool = &Pool::with_heap(&HEAP);
+    assert_eq!(pool.backing_heap() as *const _ as *const u8, &HEAP as *const _ as *const u8);
+
+    let mut bufs = Vec::new();
+    let error = loop {
+        match RawBuf::<u8>::try_hold_cap(pool, 2048) {
+            Ok(buf) => bufs.push(buf),
+            Err(error) => break error,
+        }
+        assert!(bufs.len() <= 8);
+    };
+    assert_eq!(error, HoldError::OutOfMemory);
+    assert!(!bufs.is_empty());
+    assert!(pool.size() <= 4*4096);
+}
+
+#[test]
+#[should_panic(expected = "dealloc size mismatch")]
+#[cfg(debug_assertions)]
+fn test_pool_dealloc_size_mismatch() {
+    static mut TEST_HUNK: [u8; 4096] = [0; 4096];
+    let slab = Slab::new(unsafe { Block::from_slice(&mut TEST_HUNK) }, 256);
+    // Don't drop the leaky pool while unwinding.
+    let pool = &mem::ManuallyDrop::new(Pool::new(&slab));
+    unsafe {
+        let x = pool.alloc(Layout::from_size_align_unchecked(32, 8)).unwrap();
+        let _y = pool.alloc(Layout::from_size_align_unchecked(16, 8)).unwrap();
+        AllocTag::from_ptr(x.as_ptr()).dealloc(Block::from_raw_parts(x.as_ptr(), 8));
+    }
+}
@@ -78,3 +78,21 @@ fn test_raw_box_hold_copy_str() {
     assert_eq!(pack.used(), 0);
     assert_eq!(pack.free(), 4064);
 }
+
+#[test]
+fn test_raw_box_ord() {
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let mut xs = [RawBox::hold_new(pack, 3i32),
+                      RawBox::hold_new(pack, -1i32),
+                      RawBox::hold_new(pack, 2i32)];
+        assert!(xs[1] < xs[2]);
+        assert!(xs[0] > xs[2]);
+        xs.sort();
+        assert_eq!(*xs[0], -1);
+        assert_eq!(*xs[1], 2);
+        assert_eq!(*xs[2], 3);
+    }
+    assert_eq!(pack.live(), 0);
+}
@@ -1,8 +1,12 @@
 extern crate tg_mem;
 
-use tg_mem::block::Block;
-use tg_mem::alloc::Pack;
-use tg_mem::lease::RawBuf;
+use core::cell::Cell;
+use core::mem::MaybeUninit;
+use std::panic::{self, AssertUnwindSafe};
+use tg_mem::block::{Block, Layout};
+use tg_mem::alloc::{AlignedHold, AllocTag, Hold, HoldError, Holder, Pack};
+use tg_mem::lease::{PtrBuf, RawBuf};
+use tg_mem::resident::{TryMapError, TryReserveError};
 
 #[test]
 fn test_raw_buf_hold_cap() {
@@ -41,3 +45,694 @@ fn test_raw_buf_hold_cap() {
     assert_eq!(pack.used(), 0);
     assert_eq!(pack.free(), 4064);
 }
+
+#[test]
+fn test_raw_buf_spare_capacity_set_len() {
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let mut xs = RawBuf::<usize>::hold_cap(pack, 4);
+        xs.push(1);
+        {
+            let spare = xs.spare_capacity_mut();
+            assert_eq!(spare.len(), 3);
+            spare[0] = MaybeUninit::new(2);
+            spare[1] = MaybeUninit::new(3);
+        }
+        unsafe { xs.set_len(3); }
+        assert_eq!(xs.len(), 3);
+        assert_eq!(xs.cap(), 4);
+        assert_eq!(xs[0], 1);
+        assert_eq!(xs[1], 2);
+        assert_eq!(xs[2], 3);
+        assert_eq!(xs.spare_capacity_mut().len(), 1);
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+#[test]
+#[should_panic]
+#[cfg(debug_assertions)]
+fn test_raw_buf_set_len_beyond_cap() {
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    let mut xs = RawBuf::<usize>::hold_cap(pack, 2);
+    unsafe { xs.set_len(3); }
+}
+
+#[test]
+fn test_raw_buf_swap_reverse() {
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let mut xs = RawBuf::<usize>::hold_cap(pack, 8);
+        xs.reverse();
+        xs.extend_from_slice(&[1, 2, 3, 4, 5]);
+        xs.swap(0, 3);
+        assert_eq!(xs.as_slice(), &[4, 2, 3, 1, 5]);
+        xs.swap(2, 2);
+        assert_eq!(xs.as_slice(), &[4, 2, 3, 1, 5]);
+        xs.reverse();
+        assert_eq!(xs.as_slice(), &[5, 1, 3, 2, 4]);
+        xs.push(6);
+        xs.reverse();
+        assert_eq!(xs.as_slice(), &[6, 4, 2, 3, 1, 5]);
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+#[test]
+#[should_panic]
+fn test_raw_buf_swap_out_of_bounds() {
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    let mut xs = RawBuf::<usize>::hold_cap(pack, 4);
+    xs.extend_from_slice(&[1, 2]);
+    xs.swap(0, 2);
+}
+
+#[test]
+fn test_raw_buf_remove() {
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        // A full buf, so that shifting past the end would overrun the block.
+        let mut xs = RawBuf::<usize>::hold_cap(pack, 4);
+        xs.extend_from_slice(&[1, 2, 3, 4]);
+        let ys = RawBuf::<usize>::hold_copy(pack, &[9, 9][..]);
+        assert_eq!(xs.remove(1), 2);
+        assert_eq!(xs.as_slice(), &[1, 3, 4]);
+        assert_eq!(xs.remove(2), 4);
+        assert_eq!(xs.as_slice(), &[1, 3]);
+        assert_eq!(xs.remove(0), 1);
+        assert_eq!(xs.as_slice(), &[3]);
+        assert_eq!(ys.as_slice(), &[9, 9]);
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+#[test]
+#[should_panic]
+fn test_raw_buf_remove_out_of_bounds() {
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    let mut xs = RawBuf::<usize>::hold_cap(pack, 4);
+    xs.extend_from_slice(&[1, 2]);
+    xs.remove(2);
+}
+
+#[test]
+fn test_raw_buf_binary_insert() {
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let mut xs = RawBuf::<usize>::hold_cap(pack, 2);
+        assert_eq!(xs.binary_insert(5), 0);
+        assert_eq!(xs.binary_insert(2), 0);
+        assert_eq!(xs.binary_insert(9), 2);
+        assert_eq!(xs.binary_insert(7), 2);
+        assert_eq!(xs.as_slice(), &[2, 5, 7, 9]);
+
+        let mut edges = RawBuf::<(u32, u32)>::hold_cap(pack, 4);
+        edges.extend_from_slice(&[(1, 0), (2, 0), (3, 0)]);
+        // Equal keys insert after existing equals.
+        assert_eq!(edges.binary_insert_by_key((2, 1), |e| e.0), 2);
+        assert_eq!(edges.binary_insert_by((2, 2), |a, b| a.0.cmp(&b.0)), 3);
+        assert_eq!(edges.binary_insert_by_key((0, 0), |e| e.0), 0);
+        assert_eq!(edges.as_slice(), &[(0, 0), (1, 0), (2, 0), (2, 1), (2, 2), (3, 0)]);
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+#[test]
+fn test_raw_buf_partition_point() {
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let mut xs = RawBuf::<usize>::hold_cap(pack, 8);
+        assert_eq!(xs.partition_point(|&x| x < 5), 0);
+        xs.extend_from_slice(&[1, 2, 3, 5, 8, 13, 21]);
+        assert_eq!(xs.partition_point(|&x| x < 5), 3);
+        assert_eq!(xs.partition_point(|&x| x < 1), 0);
+        assert_eq!(xs.partition_point(|&x| x < 100), 7);
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+#[test]
+fn test_raw_buf_chunk_by() {
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let mut edges = RawBuf::<(u32, u32)>::hold_cap(pack, 8);
+        assert_eq!(edges.chunk_by(|a, b| a.0 == b.0).next(), None);
+        edges.extend_from_slice(&[(1, 2), (1, 3), (2, 1), (4, 1), (4, 2), (4, 3), (5, 0)]);
+        let mut groups = edges.chunk_by(|a, b| a.0 == b.0);
+        assert_eq!(groups.next(), Some(&[(1, 2), (1, 3)][..]));
+        assert_eq!(groups.next(), Some(&[(2, 1)][..]));
+        assert_eq!(groups.next(), Some(&[(4, 1), (4, 2), (4, 3)][..]));
+        assert_eq!(groups.next(), Some(&[(5, 0)][..]));
+        assert_eq!(groups.next(), None);
+
+        let mut groups = edges.chunk_by(|a, b| a.0 == b.0);
+        assert_eq!(groups.next_back(), Some(&[(5, 0)][..]));
+        assert_eq!(groups.next_back(), Some(&[(4, 1), (4, 2), (4, 3)][..]));
+        assert_eq!(groups.next(), Some(&[(1, 2), (1, 3)][..]));
+        assert_eq!(groups.next(), Some(&[(2, 1)][..]));
+        assert_eq!(groups.next_back(), None);
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+#[test]
+fn test_raw_buf_cyclic_windows() {
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let mut xs = RawBuf::<u32>::hold_cap(pack, 4);
+        assert_eq!(xs.cyclic_windows(2).next().is_none(), true);
+        xs.extend_from_slice(&[1, 2, 3, 4]);
+        let edges = xs.cyclic_windows(2).map(|w| w.cloned().collect::<Vec<_>>()).collect::<Vec<_>>();
+        assert_eq!(edges, [[1, 2], [2, 3], [3, 4], [4, 1]]);
+        assert_eq!(xs.cyclic_windows(2).len(), 4);
+
+        let mut windows = xs.cyclic_windows(3);
+        assert_eq!(windows.next_back().unwrap().cloned().collect::<Vec<_>>(), [4, 1, 2]);
+        assert_eq!(windows.next().unwrap().cloned().collect::<Vec<_>>(), [1, 2, 3]);
+        assert_eq!(windows.len(), 2);
+
+        // Windows larger than the buf are clamped to its length.
+        let windows = xs.cyclic_windows(6).map(|w| w.cloned().collect::<Vec<_>>()).collect::<Vec<_>>();
+        assert_eq!(windows, [[1, 2, 3, 4], [2, 3, 4, 1], [3, 4, 1, 2], [4, 1, 2, 3]]);
+        assert!(xs.cyclic_windows(0).all(|mut w| w.next().is_none()));
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+#[test]
+fn test_raw_buf_make_contiguous() {
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let mut xs = RawBuf::<usize>::hold_cap(pack, 4);
+        assert_eq!(xs.make_contiguous(), &mut [][..]);
+        xs.extend_from_slice(&[3, 1, 2]);
+        let base = xs.as_ptr();
+        let slice = xs.make_contiguous();
+        assert_eq!(slice.as_ptr(), base);
+        slice.sort();
+        assert_eq!(xs.as_slice(), &[1, 2, 3]);
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+#[test]
+fn test_raw_buf_sort_by_cached_key() {
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let mut edges = RawBuf::<(u32, u32)>::hold_cap(pack, 8);
+        edges.extend_from_slice(&[(3, 0), (1, 1), (2, 2), (1, 3), (5, 4), (2, 5), (0, 6)]);
+        let calls = Cell::new(0);
+        edges.sort_by_cached_key(|edge| {
+            calls.set(calls.get() + 1);
+            edge.0
+        });
+        assert_eq!(calls.get(), 7);
+        assert_eq!(&edges[..], &[(0, 6), (1, 1), (1, 3), (2, 2), (2, 5), (3, 0), (5, 4)][..]);
+        assert_eq!(pack.live(), 1);
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+/// Clonable value that counts its clones and drops, and panics on its
+/// `panic_at`-th clone.
+struct Counted<'c> {
+    clones: &'c Cell<usize>,
+    drops: &'c Cell<usize>,
+    panic_at: usize,
+}
+
+impl<'c> Clone for Counted<'c> {
+    fn clone(&self) -> Counted<'c> {
+        self.clones.set(self.clones.get() + 1);
+        if self.clones.get() == self.panic_at {
+            panic!("clone {}", self.panic_at);
+        }
+        Counted { clones: self.clones, drops: self.drops, panic_at: self.panic_at }
+    }
+}
+
+impl<'c> Drop for Counted<'c> {
+    fn drop(&mut self) {
+        self.drops.set(self.drops.get() + 1);
+    }
+}
+
+#[test]
+fn test_raw_buf_hold_from_elem() {
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    let clones = Cell::new(0);
+    let drops = Cell::new(0);
+    {
+        let value = Counted { clones: &clones, drops: &drops, panic_at: 0 };
+        let xs = RawBuf::hold_from_elem(pack, value, 100);
+        assert_eq!(xs.len(), 100);
+        assert_eq!(xs.cap(), 100);
+        assert_eq!(clones.get(), 99);
+        assert_eq!(drops.get(), 0);
+    }
+    assert_eq!(drops.get(), 100);
+    assert_eq!(pack.live(), 0);
+
+    drops.set(0);
+    {
+        let value = Counted { clones: &clones, drops: &drops, panic_at: 0 };
+        let xs = RawBuf::hold_from_elem(pack, value, 0);
+        assert_eq!(xs.len(), 0);
+        assert_eq!(drops.get(), 1);
+    }
+    assert_eq!(pack.live(), 0);
+
+    clones.set(0);
+    drops.set(0);
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        let value = Counted { clones: &clones, drops: &drops, panic_at: 50 };
+        RawBuf::hold_from_elem(pack, value, 100)
+    }));
+    assert!(result.is_err());
+    // The 49 placed clones, and the original value, were dropped.
+    assert_eq!(drops.get(), 50);
+    assert_eq!(pack.live(), 0);
+}
+
+#[test]
+fn test_raw_buf_split_first_last() {
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let mut xs = RawBuf::<usize>::hold_cap(pack, 4);
+        assert_eq!(xs.split_first(), None);
+        assert_eq!(xs.split_last(), None);
+        xs.push(5);
+        assert_eq!(xs.split_first(), Some((&5, &[][..])));
+        xs.push(9);
+        xs.push(11);
+        assert_eq!(xs.split_first(), Some((&5, &[9, 11][..])));
+        assert_eq!(xs.split_last(), Some((&11, &[5, 9][..])));
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+#[test]
+fn test_raw_buf_try_reserve_exact_errors() {
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let mut xs = RawBuf::<usize>::hold_cap(pack, 2);
+        xs.push(5);
+        let used = pack.used();
+        assert_eq!(xs.try_reserve_exact(usize::MAX), Err(TryReserveError::CapacityOverflow));
+        assert_eq!(xs.try_reserve_exact(usize::MAX / 4), Err(TryReserveError::CapacityOverflow));
+        assert_eq!(pack.used(), used);
+        assert_eq!(xs.try_reserve_exact(1024), Err(TryReserveError::AllocError(HoldError::OutOfMemory)));
+        assert_eq!(xs.cap(), 2);
+        assert_eq!(xs[0], 5);
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+#[test]
+fn test_raw_buf_raw_parts_round_trip() {
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let mut xs = RawBuf::<usize>::hold_cap(pack, 4);
+        xs.push(3);
+        xs.push(7);
+        let (data, len, cap) = RawBuf::into_raw_parts(xs);
+        assert_eq!(len, 2);
+        assert_eq!(cap, 4);
+        assert_eq!(pack.live(), 1);
+
+        let ys = unsafe { RawBuf::<usize>::from_raw_parts_in(pack, data, len, cap) };
+        assert_eq!(pack.live(), 1);
+        assert_eq!(ys.len(), 2);
+        assert_eq!(ys.cap(), 4);
+        assert_eq!(ys[0], 3);
+        assert_eq!(ys[1], 7);
+    }
+    assert_eq!(pack.live(), 0);
+    assert_eq!(pack.used(), 0);
+}
+
+#[test]
+fn test_raw_buf_concat_join() {
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let mut parts = RawBuf::<RawBuf<u8>>::hold_cap(pack, 3);
+        parts.push(RawBuf::hold_copy(pack, &b"ab"[..]));
+        parts.push(RawBuf::hold_copy(pack, &b""[..]));
+        parts.push(RawBuf::hold_copy(pack, &b"cde"[..]));
+
+        let concat = parts.concat();
+        assert_eq!(concat.len(), 5);
+        assert_eq!(concat.cap(), 5);
+        assert_eq!(&concat[..], b"abcde");
+
+        let joined = parts.join(b", ");
+        assert_eq!(joined.len(), 9);
+        assert_eq!(joined.cap(), 9);
+        assert_eq!(&joined[..], b"ab, , cde");
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+#[test]
+fn test_raw_buf_concat_join_in() {
+    static mut SCRATCH_AREA: [u8; 4096] = [0; 4096];
+    static mut RESULT_AREA: [u8; 4096] = [0; 4096];
+    let scratch = Pack::new(unsafe { Block::from_slice(&mut SCRATCH_AREA) });
+    let result = Pack::new(unsafe { Block::from_slice(&mut RESULT_AREA) });
+    {
+        let mut parts = RawBuf::<RawBuf<u8>>::hold_cap(scratch, 3);
+        parts.push(RawBuf::hold_copy(scratch, &b"ab"[..]));
+        parts.push(RawBuf::hold_copy(scratch, &b""[..]));
+        parts.push(RawBuf::hold_copy(scratch, &b"cde"[..]));
+        assert_eq!(scratch.live(), 4);
+
+        let concat = parts.concat_in(result);
+        assert_eq!(&concat[..], b"abcde");
+        assert_eq!(concat.cap(), 5);
+        assert_eq!(concat.holder() as *const dyn Hold as *const u8, result as *const Pack as *const u8);
+
+        let joined = parts.join_in(b", ", result);
+        assert_eq!(&joined[..], b"ab, , cde");
+        assert_eq!(joined.cap(), 9);
+        assert_eq!(joined.holder() as *const dyn Hold as *const u8, result as *const Pack as *const u8);
+
+        assert_eq!(scratch.live(), 4);
+        assert_eq!(result.live(), 2);
+        drop(parts);
+        assert_eq!(scratch.live(), 0);
+        assert_eq!(&concat[..], b"abcde");
+    }
+    assert_eq!(result.live(), 0);
+}
+
+#[test]
+fn test_raw_buf_insert_slice() {
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let mut xs = RawBuf::<usize>::hold_cap(pack, 4);
+        xs.extend_from_slice(&[1, 2, 6, 7]);
+        assert_eq!(xs.cap(), 4);
+
+        xs.insert_slice(2, &[3, 4, 5]);
+        assert_eq!(xs.cap(), 8);
+        assert_eq!(&xs[..], &[1, 2, 3, 4, 5, 6, 7]);
+
+        xs.insert_slice(0, &[0]);
+        assert_eq!(&xs[..], &[0, 1, 2, 3, 4, 5, 6, 7]);
+
+        xs.insert_slice(8, &[8, 9]);
+        assert_eq!(&xs[..], &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9]);
+
+        xs.insert_slice(5, &[]);
+        assert_eq!(xs.len(), 10);
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+#[test]
+fn test_raw_buf_resize() {
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    let drops = Cell::new(0);
+    {
+        let mut xs = RawBuf::hold_cap(pack, 2);
+        xs.push(Tracked { value: 1, drops: &drops });
+
+        xs.resize(5, Tracked { value: 7, drops: &drops });
+        assert_eq!(xs.cap(), 8);
+        assert_eq!(xs.iter().map(|x| x.value).collect::<Vec<_>>(), [1, 7, 7, 7, 7]);
+        assert_eq!(drops.get(), 0);
+
+        // Shrinking drops the tail, and the unused fill value.
+        xs.resize(2, Tracked { value: 9, drops: &drops });
+        assert_eq!(xs.iter().map(|x| x.value).collect::<Vec<_>>(), [1, 7]);
+        assert_eq!(xs.cap(), 8);
+        assert_eq!(drops.get(), 4);
+
+        // A failed grow leaves the buf unchanged.
+        assert!(xs.try_resize(1000, Tracked { value: 0, drops: &drops }).is_err());
+        assert_eq!(xs.iter().map(|x| x.value).collect::<Vec<_>>(), [1, 7]);
+        assert_eq!(xs.cap(), 8);
+        assert_eq!(drops.get(), 5);
+        assert_eq!(pack.live(), 1);
+    }
+    assert_eq!(drops.get(), 7);
+    assert_eq!(pack.live(), 0);
+}
+
+#[test]
+fn test_raw_buf_extend() {
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let mut xs = RawBuf::<usize>::hold_empty(pack);
+        xs.extend((0..100).filter(|_| true));
+        assert_eq!(xs.len(), 100);
+        assert_eq!(xs.cap(), 128);
+        assert!(xs.iter().cloned().eq(0..100));
+
+        xs.truncate(2);
+        xs.extend(&[7, 8]);
+        xs.extend([9usize].iter());
+        assert_eq!(&xs[..], &[0, 1, 7, 8, 9]);
+
+        assert_eq!(xs.try_extend(0..1000), Err(HoldError::OutOfMemory));
+        assert_eq!(&xs[..], &[0, 1, 7, 8, 9]);
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+#[test]
+fn test_raw_buf_hold_from_iter() {
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let xs = RawBuf::<usize>::hold_from_iter(pack, (1..4).map(|x| x * x));
+        assert_eq!(&xs[..], &[1, 4, 9]);
+        let ys = RawBuf::<usize>::hold_from_iter(pack, &[2, 3]);
+        assert_eq!(&ys[..], &[2, 3]);
+        assert!(RawBuf::<usize>::try_hold_from_iter(pack, 0..1000).is_err());
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+#[test]
+fn test_raw_buf_as_slice_of() {
+    #[repr(align(16))]
+    #[derive(Clone, Copy)]
+    struct Quad([u32; 4]);
+
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+
+    let xs = RawBuf::<u8>::hold_copy(pack, &[1u8, 0, 0, 0, 2, 0, 0, 0, 3, 0, 0, 0, 4, 0, 0, 0][..]);
+    let words = unsafe { xs.as_slice_of::<u32>() }.unwrap();
+    assert_eq!(words.len(), 4);
+    assert_eq!(u32::from_le(words[3]), 4);
+
+    // Consecutive allocations can't both be 16 byte aligned.
+    let zs = RawBuf::<u8>::hold_copy(pack, &[0u8; 16][..]);
+    let xs_quads = unsafe { xs.as_slice_of::<Quad>() };
+    let zs_quads = unsafe { zs.as_slice_of::<Quad>() };
+    assert_eq!(xs_quads.is_some(), xs.as_ptr() as usize % 16 == 0);
+    assert_eq!(zs_quads.is_some(), zs.as_ptr() as usize % 16 == 0);
+    assert!(xs_quads.is_none() || zs_quads.is_none());
+    if let Some(quads) = xs_quads {
+        assert_eq!(u32::from_le(quads[0].0[3]), 4);
+    }
+
+    let ys = RawBuf::<u8>::hold_copy(pack, &[0u8; 15][..]);
+    assert!(unsafe { ys.as_slice_of::<u32>() }.is_none());
+}
+
+#[test]
+fn test_raw_buf_hold_cap_zeroed() {
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+
+    // Dirty the memory that the zeroed buf will reuse.
+    let xs = RawBuf::<u32>::hold_copy(pack, &[0xFFFFFFFF; 100][..]);
+    let xs_ptr = xs.as_ptr();
+    drop(xs);
+
+    let ys = RawBuf::<u32>::hold_cap_zeroed(pack, 100);
+    assert_eq!(ys.as_ptr(), xs_ptr);
+    assert_eq!(ys.len(), 100);
+    assert!(ys.iter().all(|&y| y == 0));
+    drop(ys);
+
+    assert_eq!(RawBuf::<u32>::try_hold_cap_zeroed(pack, 4096).unwrap_err(), HoldError::OutOfMemory);
+    assert_eq!(pack.live(), 0);
+}
+
+#[test]
+fn test_raw_buf_aligned_hold() {
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    let aligned = AlignedHold::new(pack, 64);
+    assert_eq!(aligned.min_alignment(), 64);
+    {
+        let mut xs = RawBuf::<u8>::hold_cap(&aligned, 1);
+        xs.push(1);
+        assert_eq!(&xs[0] as *const u8 as usize % 64, 0);
+
+        // The next block directly follows the aligned block in the pack.
+        let mut ys = RawBuf::<u8>::hold_cap(pack, 1);
+        ys.push(2);
+        assert_ne!(&ys[0] as *const u8 as usize % 64, 0);
+
+        let mut zs = RawBuf::<u8>::hold_cap(&aligned, 1);
+        zs.push(3);
+        assert_eq!(&zs[0] as *const u8 as usize % 64, 0);
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+#[test]
+fn test_aligned_hold_realloc_keeps_alignment() {
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    let aligned = AlignedHold::new(pack, 64);
+    unsafe {
+        // Align the address 24 bytes into the block, as for a resident after a header.
+        let block = aligned.alloc_offset(Layout::from_size_align(32, 8).unwrap(), 24).unwrap();
+        assert_eq!((block.as_ptr() as usize + 24) % 64, 0);
+        // Block in place resizing, so that the realloc moves the block.
+        let filler = pack.alloc(Layout::from_size_align(8, 8).unwrap()).unwrap();
+        let hold = AllocTag::from_ptr(block.as_ptr()).holder();
+        let block = hold.realloc(block, Layout::from_size_align(256, 8).unwrap()).unwrap();
+        assert_eq!((block.as_ptr() as usize + 24) % 64, 0);
+        assert_eq!(AllocTag::from_ptr(block.as_ptr()).holder().min_alignment(), 64);
+        AllocTag::from_ptr(filler.as_ptr()).dealloc(filler);
+        AllocTag::from_ptr(block.as_ptr()).dealloc(block);
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+struct Tracked<'c, T> {
+    value: T,
+    drops: &'c Cell<usize>,
+}
+
+impl<'c, T> Drop for Tracked<'c, T> {
+    fn drop(&mut self) {
+        self.drops.set(self.drops.get() + 1);
+    }
+}
+
+impl<'c, T: Clone> Clone for Tracked<'c, T> {
+    fn clone(&self) -> Self {
+        Tracked { value: self.value.clone(), drops: self.drops }
+    }
+}
+
+#[test]
+fn test_raw_buf_try_map() {
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    let src_drops = Cell::new(0);
+    let dst_drops = Cell::new(0);
+    {
+        let mut xs = RawBuf::hold_cap(pack, 5);
+        for &s in ["1", "2", "3"].iter() {
+            xs.push(Tracked { value: s, drops: &src_drops });
+        }
+        let ys = RawBuf::try_map(xs, |x| x.value.parse::<u32>().map(|n| Tracked { value: n, drops: &dst_drops }), pack).unwrap();
+        assert_eq!(src_drops.get(), 3);
+        assert_eq!(ys.iter().map(|y| y.value).collect::<Vec<_>>(), [1, 2, 3]);
+    }
+    assert_eq!(dst_drops.get(), 3);
+    assert_eq!(pack.live(), 0);
+
+    src_drops.set(0);
+    dst_drops.set(0);
+    {
+        let mut xs = RawBuf::hold_cap(pack, 5);
+        for &s in ["1", "2", "x", "4", "5"].iter() {
+            xs.push(Tracked { value: s, drops: &src_drops });
+        }
+        match RawBuf::try_map(xs, |x| x.value.parse::<u32>().map(|n| Tracked { value: n, drops: &dst_drops }), pack) {
+            Err(TryMapError::Map(_)) => (),
+            _ => panic!(),
+        }
+        assert_eq!(src_drops.get(), 5);
+        assert_eq!(dst_drops.get(), 2);
+    }
+    assert_eq!(pack.live(), 0);
+}
+
+#[test]
+fn test_raw_buf_dedup_drain() {
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    let drops = Cell::new(0);
+    {
+        let mut xs = RawBuf::hold_cap(pack, 10);
+        for &(key, id) in [(1, 0), (1, 1), (2, 2), (3, 3), (3, 4), (3, 5), (1, 6), (4, 7), (4, 8), (4, 9)].iter() {
+            xs.push(Tracked { value: (key, id), drops: &drops });
+        }
+        let mut removed = xs.dedup_drain(|a, b| a.value.0 == b.value.0).map(|x| x.value.1).collect::<Vec<_>>();
+        removed.sort();
+        assert_eq!(removed, [1, 4, 5, 8, 9]);
+        assert_eq!(drops.get(), 5);
+        assert_eq!(xs.iter().map(|x| x.value).collect::<Vec<_>>(), [(1, 0), (2, 2), (3, 3), (1, 6), (4, 7)]);
+
+        // Dropping the drain drops the removed elements it didn't yield.
+        xs.push(Tracked { value: (4, 10), drops: &drops });
+        xs.push(Tracked { value: (4, 11), drops: &drops });
+        let mut drain = xs.dedup_drain(|a, b| a.value.0 == b.value.0);
+        assert_eq!(drain.len(), 2);
+        drain.next().unwrap();
+        drop(drain);
+        assert_eq!(drops.get(), 7);
+        assert_eq!(xs.len(), 5);
+
+        // Leaking the drain leaks the removed elements, but keeps the rest.
+        xs.push(Tracked { value: (4, 12), drops: &drops });
+        core::mem::forget(xs.dedup_drain(|a, b| a.value.0 == b.value.0));
+        assert_eq!(xs.len(), 5);
+        assert_eq!(xs.iter().map(|x| x.value.1).collect::<Vec<_>>(), [0, 2, 3, 6, 7]);
+
+        let mut ys = RawBuf::<Tracked<(i32, i32)>>::hold_cap(pack, 1);
+        assert_eq!(ys.dedup_drain(|a, b| a.value == b.value).len(), 0);
+        assert_eq!(ys.len(), 0);
+    }
+    assert_eq!(drops.get(), 12);
+    assert_eq!(pack.live(), 0);
+}
+
+#[test]
+fn test_ptr_buf_hold_from_iter() {
+    static mut TEST_AREA: [u8; 4096] = [0; 4096];
+    let pack = Pack::new(unsafe { Block::from_slice(&mut TEST_AREA) });
+    {
+        let mut xs = PtrBuf::<usize>::hold_from_iter(pack, (1..4).map(|x| x * 10));
+        assert_eq!(&xs[..], &[10, 20, 30]);
+        xs.extend(&[40]);
+        assert_eq!(&xs[..], &[10, 20, 30, 40]);
+        let ys = PtrBuf::<usize>::hold_from_iter(pack, &[2, 3]);
+        assert_eq!(&ys[..], &[2, 3]);
+    }
+    assert_eq!(pack.live(), 0);
+    // A failure to grow frees the partially built buffer.
+    assert_eq!(PtrBuf::<usize>::try_hold_from_iter(pack, 0..1000).err(), Some(HoldError::OutOfMemory));
+    assert_eq!(pack.live(), 0);
+}
@@ -29,3 +29,89 @@ fn test_slab_alloc_dealloc() {
         assert_eq!(slab.dead(), 16);
     }
 }
+
+#[test]
+#[should_panic(expected = "double dealloc")]
+#[cfg(debug_assertions)]
+fn test_slab_double_dealloc() {
+    static mut TEST_HUNK: [u8; 4096] = [0; 4096];
+    unsafe {
+        let slab = Slab::new(Block::from_slice(&mut TEST_HUNK), 256);
+        let x = slab.alloc(Layout::from_size_align_unchecked(256, 1)).unwrap();
+        slab.dealloc(x);
+        slab.dealloc(x);
+    }
+}
+
+#[test]
+#[should_panic(expected = "dealloc size mismatch")]
+#[cfg(debug_assertions)]
+fn test_slab_dealloc_size_mismatch() {
+    static mut TEST_HUNK: [u8; 4096] = [0; 4096];
+    unsafe {
+        let slab = Slab::new(Block::from_slice(&mut TEST_HUNK), 256);
+        let x = slab.alloc(Layout::from_size_align_unchecked(256, 1)).unwrap();
+        slab.dealloc(Block::from_raw_parts(x.as_ptr(), 512));
+    }
+}
+
+#[test]
+fn test_slab_min_alignment() {
+    #[repr(align(4096))]
+    struct Hunk([u8; 4096]);
+    static mut TEST_HUNK: Hunk = Hunk([0; 4096]);
+    unsafe {
+        let slab = Slab::new(Block::from_slice(&mut TEST_HUNK.0), 256);
+        assert_eq!(slab.min_alignment(), 256);
+        assert_eq!(slab.allocation_granularity(), 256);
+    }
+    unsafe {
+        let slab = Slab::new(Block::from_slice(&mut TEST_HUNK.0), 96);
+        assert_eq!(slab.min_alignment(), 32);
+        assert_eq!(slab.allocation_granularity(), 96);
+    }
+}
+
+#[test]
+fn test_slab_add_region() {
+    static mut TEST_HUNK: [u8; 512] = [0; 512];
+    static mut TEST_REGION: [u8; 512] = [0; 512];
+    unsafe {
+        let slab = Slab::new(Block::from_slice(&mut TEST_HUNK), 256);
+        let layout = Layout::from_size_align_unchecked(256, 1);
+
+        let x = slab.alloc(layout).unwrap();
+        let y = slab.alloc(layout).unwrap();
+        assert!(slab.alloc(layout).is_err());
+        assert_eq!(slab.live(), 2);
+        assert_eq!(slab.dead(), 0);
+
+        slab.add_region(Block::from_slice(&mut TEST_REGION));
+        assert_eq!(slab.size(), 1024);
+        assert_eq!(slab.block_count(), 4);
+        assert_eq!(slab.dead(), 2);
+
+        let region = TEST_REGION.as_ptr() as usize;
+        let z = slab.alloc(layout).unwrap();
+        let w = slab.alloc(layout).unwrap();
+        assert!(z.as_ptr() as usize >= region && (z.as_ptr() as usize) < region + 512);
+        assert!(w.as_ptr() as usize >= region && (w.as_ptr() as usize) < region + 512);
+        assert!(slab.alloc(layout).is_err());
+        assert_eq!(slab.live(), 4);
+
+        slab.dealloc(x);
+        slab.dealloc(z);
+        assert_eq!(slab.live(), 2);
+        assert_eq!(slab.dead(), 2);
+        let u = slab.alloc(layout).unwrap();
+        let v = slab.alloc(layout).unwrap();
+        assert!(slab.alloc(layout).is_err());
+
+        slab.dealloc(u);
+        slab.dealloc(v);
+        slab.dealloc(w);
+        slab.dealloc(y);
+        assert_eq!(slab.live(), 0);
+        assert_eq!(slab.dead(), 4);
+    }
+}